@@ -1,12 +1,42 @@
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Functions missing from older upstream releases, with the cfg set when the
+/// installed header doesn't declare them.
+const OPTIONAL_SYMBOLS: [(&str, &str); 3] = [
+    ("ggwave_ndecode", "ggwave_no_ndecode"),
+    ("ggwave_rxDurationFrames", "ggwave_no_rx_duration_frames"),
+    ("ggwave_rxProtocolSetFreqStart", "ggwave_no_freq_start"),
+];
 
 fn main() {
+    for (_, cfg) in OPTIONAL_SYMBOLS {
+        println!("cargo::rustc-check-cfg=cfg({cfg})");
+    }
+    println!("cargo:rerun-if-env-changed=GGWAVE_NO_NDECODE");
+
     let vendored = env::var_os("CARGO_FEATURE_VENDORED").is_some();
     let system = env::var_os("CARGO_FEATURE_SYSTEM").is_some();
 
+    if env::var_os("CARGO_FEATURE_PURE_RUST").is_some() {
+        // Nothing to build or link; the C API is implemented in Rust.
+        let needs_cpp = ["CARGO_FEATURE_BINDGEN", "CARGO_FEATURE_CXX"];
+        if needs_cpp.iter().any(|feature| env::var_os(feature).is_some()) {
+            panic!("The 'bindgen' and 'cxx' features need the C++ library, not 'pure-rust'");
+        }
+        return;
+    }
+
+    // There's no C++ standard library to build upstream against.
+    let wasm_unknown = env::var("CARGO_CFG_TARGET_ARCH").as_deref() == Ok("wasm32")
+        && env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("unknown");
+    if wasm_unknown {
+        panic!("wasm32-unknown-unknown needs the 'wasm' or 'pure-rust' feature");
+    }
+
     if !vendored && !system {
-        panic!("Either the 'vendored' or 'system' feature must be enabled");
+        panic!("Either the 'vendored', 'system' or 'pure-rust' feature must be enabled");
     }
 
     if system {
@@ -15,9 +45,27 @@ fn main() {
                 "cargo:warning=Both 'vendored' and 'system' features enabled; using system library."
             );
         }
-        pkg_config::Config::new()
+        let library = pkg_config::Config::new()
             .probe("ggwave")
             .expect("Failed to find system ggwave via pkg-config");
+        println!("cargo:rustc-env=GGWAVE_BACKEND_VERSION={}", library.version);
+
+        // Without a header to inspect, the library is assumed to be recent.
+        let header_path = find_header(&library.include_paths);
+        let header = header_path.as_ref().and_then(|h| fs::read_to_string(h).ok());
+        for (symbol, cfg) in OPTIONAL_SYMBOLS {
+            let missing = header.as_deref().is_some_and(|h| !h.contains(symbol));
+            let forced = cfg == "ggwave_no_ndecode" && env::var_os("GGWAVE_NO_NDECODE").is_some();
+            if missing || forced {
+                println!("cargo:rustc-cfg={cfg}");
+            }
+        }
+        #[cfg(feature = "bindgen")]
+        generate_bindings(
+            &header_path.expect("The bindgen feature needs the system ggwave/ggwave.h"),
+        );
+        #[cfg(feature = "cxx")]
+        build_cxx_bridge(&library.include_paths);
         return;
     }
 
@@ -27,6 +75,10 @@ fn main() {
     let src_dir = ggwave_dir.join("src");
     let include_dir = ggwave_dir.join("include");
 
+    if let Some(version) = vendored_version(&ggwave_dir.join("CMakeLists.txt")) {
+        println!("cargo:rustc-env=GGWAVE_BACKEND_VERSION={version}");
+    }
+
     println!("cargo:rerun-if-changed={}", src_dir.join("ggwave.cpp").display());
     println!(
         "cargo:rerun-if-changed={}",
@@ -38,6 +90,9 @@ fn main() {
         src_dir.join("reed-solomon/rs.hpp").display()
     );
 
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&include_dir.join("ggwave/ggwave.h"));
+
     let mut build = cc::Build::new();
     build
         .cpp(true)
@@ -49,6 +104,9 @@ fn main() {
         .warnings(false)
         .compile("ggwave");
 
+    #[cfg(feature = "cxx")]
+    build_cxx_bridge(&[src_dir, include_dir]);
+
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
     let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
     if target_os == "macos" || target_os == "ios" {
@@ -58,3 +116,59 @@ fn main() {
     }
 }
 
+/// Finds the installed `ggwave.h`, searching the pkg-config include paths and
+/// the default system locations.
+fn find_header(include_paths: &[PathBuf]) -> Option<PathBuf> {
+    let default_paths = [
+        PathBuf::from("/usr/include"),
+        PathBuf::from("/usr/local/include"),
+    ];
+    include_paths
+        .iter()
+        .chain(&default_paths)
+        .map(|dir| dir.join("ggwave/ggwave.h"))
+        .find(|header| header.is_file())
+}
+
+/// Writes bindings for `header` to `$OUT_DIR/bindings.rs`, where `ffi`
+/// checks its hand-written declarations against them.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(header: &Path) {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR missing"));
+    println!("cargo:rerun-if-changed={}", header.display());
+    bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .allowlist_function("ggwave_.*")
+        .allowlist_type("ggwave_.*")
+        .allowlist_var("GGWAVE_.*")
+        .newtype_enum("ggwave_.*")
+        .generate()
+        .expect("Failed to generate bindings from ggwave.h")
+        .write_to_file(out_dir.join("bindings.rs"))
+        .expect("Failed to write bindings");
+}
+
+/// Compiles the C++ side of the `cpp` module's bridge against the headers
+/// in `include_dirs`.
+#[cfg(feature = "cxx")]
+fn build_cxx_bridge(include_dirs: &[PathBuf]) {
+    println!("cargo:rerun-if-changed=src/cpp.rs");
+    println!("cargo:rerun-if-changed=src/cpp/bridge.h");
+    println!("cargo:rerun-if-changed=src/cpp/bridge.cc");
+    cxx_build::bridge("src/cpp.rs")
+        .file("src/cpp/bridge.cc")
+        .includes(include_dirs)
+        .flag_if_supported("-std=c++14")
+        .warnings(false)
+        .compile("ggwave-cxx");
+}
+
+/// Extracts the version from the vendored `project(ggwave VERSION x.y.z)`.
+fn vendored_version(cmake_lists: &Path) -> Option<String> {
+    let contents = fs::read_to_string(cmake_lists).ok()?;
+    let start = contents.find("project(")?;
+    let project = &contents[start..start + contents[start..].find(')')?];
+    let mut words = project.split_whitespace();
+    words.find(|w| *w == "VERSION")?;
+    words.next().map(str::to_string)
+}
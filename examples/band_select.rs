@@ -0,0 +1,36 @@
+//! Choosing the band of each message without touching the C library's global
+//! protocol tables, e.g. for several tenants sharing one process.
+//!
+//! The sender shifts its waveform with `EncodeOptions::freq_shift`; the
+//! receiver shifts the capture back by the same amount before decoding.
+
+use ggwave_rs::{default_parameters, EncodeOptions, GgWave, ProtocolId, Waveform};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let params = default_parameters();
+    let rate = params.sampleRateOut as u32;
+    let tx = GgWave::new(params)?;
+
+    for (tenant, shift) in [
+        ("tenant-a", 0.0),
+        ("tenant-b", 2_500.0),
+        ("tenant-c", 5_000.0),
+    ] {
+        let options = EncodeOptions::new().freq_shift(shift);
+        let encoded = tx.encode_with(
+            tenant.as_bytes(),
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+            &options,
+        )?;
+
+        let capture = Waveform::from_f32_bytes(&encoded, rate).freq_shifted(-shift);
+        let rx = GgWave::new(params)?;
+        let decoded = rx.decode(&capture.to_f32_bytes())?;
+        println!(
+            "{tenant} shifted by {shift} Hz: {}",
+            decoded.map_or("nothing decoded".into(), |p| String::from_utf8_lossy(&p)
+                .into_owned())
+        );
+    }
+    Ok(())
+}
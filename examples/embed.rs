@@ -0,0 +1,34 @@
+//! Embedding a message in program audio and finding it again.
+//!
+//! A quiet low tone stands in for the program; the message is mixed in at
+//! 1.5 s and `scan` reports where it was found.
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use ggwave_rs::scan;
+use ggwave_rs::{default_parameters, GgWave, ProtocolId, Waveform};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let params = default_parameters();
+    let rate = params.sampleRateOut as u32;
+
+    let mut program: Vec<f32> = (0..rate as usize * 5)
+        .map(|i| 0.05 * (2.0 * PI * 220.0 * i as f32 / rate as f32).sin())
+        .collect();
+
+    let encoded =
+        GgWave::new(params)?.encode(b"cue-1", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)?;
+    let message = Waveform::from_f32_bytes(&encoded, rate);
+    message.insert_into(&mut program, Duration::from_millis(1500), 0.5)?;
+
+    for detection in scan::scan(params, &program)? {
+        println!(
+            "{:.2}-{:.2} s: {}",
+            detection.segment.start_secs(rate as f32),
+            detection.segment.end_secs(rate as f32),
+            String::from_utf8_lossy(&detection.payload)
+        );
+    }
+    Ok(())
+}
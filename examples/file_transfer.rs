@@ -0,0 +1,101 @@
+//! Sending a file larger than one transmission: split it into wire format
+//! chunk frames, encode each, and reassemble on the receiving side. The
+//! transfer checks a cancellation token between chunks, the way a GUI's
+//! cancel button would stop it; here pressing Enter cancels.
+//!
+//! Usage: `cargo run --example file_transfer [FILE]` (defaults to this
+//! crate's Cargo.toml).
+
+use std::collections::BTreeMap;
+
+use ggwave_rs::cancel::{CancellationToken, Outcome, Progress};
+use ggwave_rs::wire::{Frame, FrameKind};
+use ggwave_rs::{default_parameters, GgWave, ProtocolId};
+
+/// Body bytes per frame, leaving room for the header and chunk fields within
+/// upstream's 140-byte variable-length limit.
+const CHUNK_BODY: usize = 128;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Cargo.toml".into());
+    let contents = std::fs::read(&path)?;
+
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    std::thread::spawn(move || {
+        // Enter, not the end of a redirected stdin.
+        if let Ok(1..) = std::io::stdin().read_line(&mut String::new()) {
+            cancel.cancel();
+        }
+    });
+
+    let received = match transfer(&contents, &token)? {
+        Outcome::Complete(received) => received,
+        Outcome::Cancelled { partial, progress } => {
+            println!(
+                "{path}: cancelled after {} of {} chunks",
+                progress.done, progress.total
+            );
+            partial
+        }
+    };
+
+    let count = contents.chunks(CHUNK_BODY).count();
+    if received.len() == count {
+        let reassembled: Vec<u8> = received.into_values().flatten().collect();
+        assert_eq!(reassembled, contents);
+        println!("{path}: {} bytes in {count} frames", reassembled.len());
+    } else {
+        println!("{path}: {} of {count} frames received", received.len());
+    }
+    Ok(())
+}
+
+/// Chunk bodies received, by index.
+type Received = BTreeMap<u8, Vec<u8>>;
+
+/// Sends `contents` chunk by chunk, stopping at the next chunk boundary once
+/// `token` is cancelled.
+fn transfer(
+    contents: &[u8],
+    token: &CancellationToken,
+) -> Result<Outcome<Received>, Box<dyn std::error::Error>> {
+    let chunks: Vec<&[u8]> = contents.chunks(CHUNK_BODY).collect();
+    let count = u8::try_from(chunks.len()).map_err(|_| "file too large for 255 chunks")?;
+
+    let params = default_parameters();
+    let tx = GgWave::new(params)?;
+    let rx = GgWave::new(params)?;
+
+    let mut received = BTreeMap::new();
+    let mut progress = Progress {
+        done: 0,
+        total: chunks.len(),
+    };
+    for (index, chunk) in chunks.iter().enumerate() {
+        if token.is_cancelled() {
+            return Ok(Outcome::Cancelled {
+                partial: received,
+                progress,
+            });
+        }
+
+        let frame = Frame::chunk(index as u8, count, *chunk).encode()?;
+        let waveform = tx.encode(&frame, ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST, 25)?;
+
+        // On a real link the waveform goes through the air here, and frames
+        // may arrive out of order or more than once.
+        progress.done += 1;
+        let Some(payload) = rx.decode(&waveform)? else {
+            eprintln!("chunk {index} lost");
+            continue;
+        };
+        let frame = Frame::decode(&payload)?;
+        if let FrameKind::Chunk { index, .. } = frame.kind {
+            received.insert(index, frame.body);
+        }
+    }
+    Ok(Outcome::Complete(received))
+}
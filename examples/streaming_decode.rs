@@ -0,0 +1,35 @@
+//! Decoding audio as it arrives, the way a microphone capture callback
+//! delivers it: small chunks fed to one long-lived `Receiver`.
+//!
+//! This crate has no sound device support, so the "capture" here is an
+//! encoded message surrounded by silence, handed over 1024 samples at a time.
+//! With a real input device, call `receiver.decode` from its callback.
+
+use ggwave_rs::receiver::Receiver;
+use ggwave_rs::{default_parameters, GgWave, ProtocolId, Waveform};
+
+const CHUNK: usize = 1024;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let params = default_parameters();
+    let rate = params.sampleRateOut as u32;
+
+    let encoded =
+        GgWave::new(params)?.encode(b"streamed", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)?;
+    let silence = vec![0.0; rate as usize / 2];
+    let mut capture = silence.clone();
+    capture.extend(Waveform::from_f32_bytes(&encoded, rate).samples);
+    capture.extend(&silence);
+
+    let mut receiver = Receiver::new(params)?;
+    for (i, chunk) in capture.chunks(CHUNK).enumerate() {
+        if let Some(payload) = receiver.decode(chunk)? {
+            let at = (i * CHUNK) as f32 / rate as f32;
+            println!("{at:.2} s: {}", String::from_utf8_lossy(&payload));
+        }
+    }
+    if let Some(warning) = receiver.warning() {
+        eprintln!("warning: {warning:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+//! One-time password tokens sent over sound (needs the `totp` feature):
+//! `cargo run --example totp_token --features totp`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggwave_rs::totp::{TotpConfig, TotpSender, TotpVerifier};
+use ggwave_rs::{default_parameters, GgWave, ProtocolId};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = TotpConfig::new("door-1", b"shared secret".to_vec())?;
+    let sender = TotpSender::new(config.clone());
+    let mut verifier = TotpVerifier::new(config, 1);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let params = default_parameters();
+    let waveform = GgWave::new(params)?.encode(
+        &sender.token_at(now),
+        ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+        25,
+    )?;
+
+    let payload = GgWave::new(params)?
+        .decode(&waveform)?
+        .ok_or("nothing decoded")?;
+    println!("first use: {:?}", verifier.verify(&payload, now));
+    println!("replayed:  {:?}", verifier.verify(&payload, now));
+    Ok(())
+}
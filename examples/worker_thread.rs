@@ -0,0 +1,42 @@
+//! Using ggwave from several threads, or from async code.
+//!
+//! `GgWave` is `!Send` because the C library's instance table isn't
+//! thread-safe. `GgWaveHandle` keeps the instance on a worker thread of its
+//! own and can be shared freely; in an async runtime, make its blocking calls
+//! from the runtime's blocking pool.
+
+use std::sync::Arc;
+use std::thread;
+
+use ggwave_rs::{default_parameters, GgWaveHandle, ProtocolId};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let params = default_parameters();
+    let tx = Arc::new(GgWaveHandle::new(params)?);
+
+    let clients: Vec<_> = (0..3)
+        .map(|n| {
+            let tx = Arc::clone(&tx);
+            thread::spawn(move || {
+                let payload = format!("client {n}");
+                let waveform = tx.encode(
+                    payload.as_bytes(),
+                    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+                    25,
+                )?;
+                // Each transmission gets a fresh receiver so no decoder state
+                // carries over between clients.
+                GgWaveHandle::new(params)?.decode(&waveform)
+            })
+        })
+        .collect();
+
+    for client in clients {
+        match client.join().expect("client thread panicked") {
+            Ok(Some(payload)) => println!("round trip: {}", String::from_utf8_lossy(&payload)),
+            Ok(None) => eprintln!("nothing decoded"),
+            Err(e) => eprintln!("failed: {e}"),
+        }
+    }
+    Ok(())
+}
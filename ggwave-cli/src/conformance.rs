@@ -0,0 +1,232 @@
+use std::path::Path;
+
+use ggwave_rs::wire::{Frame, WIRE_VERSION};
+use ggwave_rs::{GgWave, ProtocolId};
+use serde::Deserialize;
+
+use crate::{f32_parameters, hex, wav};
+
+#[derive(Debug, Deserialize)]
+pub struct Suite {
+    pub format: String,
+    pub version: u8,
+    pub vectors: Vec<WireVector>,
+    #[serde(default)]
+    pub audio: Vec<AudioVector>,
+}
+
+/// A frame together with either its expected parse or the expected error code.
+#[derive(Debug, Deserialize)]
+pub struct WireVector {
+    pub name: String,
+    pub frame: String,
+    #[serde(default)]
+    pub expect: Option<ExpectedFrame>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExpectedFrame {
+    pub kind: String,
+    #[serde(default)]
+    pub chunk: Option<[u8; 2]>,
+    #[serde(default)]
+    pub address: Option<[u8; 2]>,
+    #[serde(default)]
+    pub tag: Option<u16>,
+    pub body: String,
+}
+
+/// A WAV file (relative to the suite) that must decode to `frame`.
+#[derive(Debug, Deserialize)]
+pub struct AudioVector {
+    pub name: String,
+    pub wav: String,
+    pub protocol: String,
+    #[serde(default = "default_volume")]
+    pub volume: i32,
+    pub frame: String,
+}
+
+fn default_volume() -> i32 {
+    25
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+}
+
+pub fn load(path: &Path) -> Result<Suite, Box<dyn std::error::Error>> {
+    let suite: Suite = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if suite.format != "ggwave-wire" {
+        return Err(format!("unexpected vector format '{}'", suite.format).into());
+    }
+    if suite.version != WIRE_VERSION {
+        return Err(format!(
+            "vectors are for wire format version {}, this build implements {WIRE_VERSION}",
+            suite.version
+        )
+        .into());
+    }
+    Ok(suite)
+}
+
+pub fn check_wire(vector: &WireVector) -> Outcome {
+    let bytes = match hex::parse(&vector.frame) {
+        Ok(bytes) => bytes,
+        Err(e) => return Outcome::Fail(e),
+    };
+
+    match (Frame::decode(&bytes), &vector.expect, &vector.error) {
+        (Ok(frame), Some(expect), None) => {
+            let expected = match expected_frame(expect) {
+                Ok(frame) => frame,
+                Err(e) => return Outcome::Fail(e),
+            };
+            if frame != expected {
+                return Outcome::Fail(format!("parsed {frame:?}, expected {expected:?}"));
+            }
+            match frame.encode() {
+                Ok(encoded) if encoded == bytes => Outcome::Pass,
+                Ok(encoded) => Outcome::Fail(format!(
+                    "re-encoded as {}, expected {}",
+                    hex::encode(&encoded),
+                    vector.frame
+                )),
+                Err(e) => Outcome::Fail(format!("re-encoding failed: {e}")),
+            }
+        }
+        (Err(e), None, Some(code)) if e.code() == code => Outcome::Pass,
+        (Err(e), None, Some(code)) => {
+            Outcome::Fail(format!("failed with '{}', expected '{code}'", e.code()))
+        }
+        (Ok(frame), None, Some(code)) => {
+            Outcome::Fail(format!("parsed {frame:?}, expected error '{code}'"))
+        }
+        (Err(e), Some(_), None) => Outcome::Fail(format!("failed with '{}'", e.code())),
+        _ => Outcome::Fail("vector must set exactly one of 'expect' and 'error'".into()),
+    }
+}
+
+pub fn check_audio(vector: &AudioVector, base: &Path) -> Outcome {
+    let path = base.join(&vector.wav);
+    // A missing file fails rather than skips, so a checkout without the
+    // fixtures can't pass the audio vectors unchecked.
+    if !path.exists() {
+        return Outcome::Fail(format!(
+            "{} not found; write it with --generate-audio",
+            path.display()
+        ));
+    }
+    let expected = match hex::parse(&vector.frame) {
+        Ok(bytes) => bytes,
+        Err(e) => return Outcome::Fail(e),
+    };
+
+    let decoded = wav::read_f32(&path).and_then(|(waveform, sample_rate)| {
+        let ggwave = GgWave::new(f32_parameters(Some(sample_rate)))?;
+        Ok(ggwave.decode(&waveform)?)
+    });
+    match decoded {
+        Ok(Some(payload)) if payload == expected => Outcome::Pass,
+        Ok(Some(payload)) => Outcome::Fail(format!(
+            "decoded {}, expected {}",
+            hex::encode(&payload),
+            vector.frame
+        )),
+        Ok(None) => Outcome::Fail("no payload decoded".into()),
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+/// Encodes an audio vector with this implementation and writes its WAV file.
+pub fn generate_audio(vector: &AudioVector, base: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let protocol = ProtocolId::from_id(&vector.protocol)
+        .ok_or_else(|| format!("unknown protocol: {}", vector.protocol))?;
+    let frame = hex::parse(&vector.frame)?;
+
+    let params = f32_parameters(None);
+    let ggwave = GgWave::new(params)?;
+    let waveform = ggwave.encode(&frame, protocol, vector.volume)?;
+
+    let path = base.join(&vector.wav);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    wav::write_f32(&path, &waveform, params.sampleRateOut as u32)
+}
+
+fn expected_frame(expect: &ExpectedFrame) -> Result<Frame, String> {
+    let body = hex::parse(&expect.body)?;
+    let mut frame = match (expect.kind.as_str(), expect.chunk) {
+        ("message", None) => Frame::message(body),
+        ("chunk", Some([index, count])) => Frame::chunk(index, count, body),
+        (kind, _) => return Err(format!("invalid expectation for kind '{kind}'")),
+    };
+    if let Some([source, destination]) = expect.address {
+        frame = frame.with_address(source, destination);
+    }
+    if let Some(tag) = expect.tag {
+        frame = frame.with_tag(tag);
+    }
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn suite_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../conformance/wire-v1.json")
+    }
+
+    #[test]
+    fn test_published_vectors_pass() {
+        let path = suite_path();
+        let suite = load(&path).expect("load vectors failed");
+        assert_eq!(suite.version, WIRE_VERSION);
+        assert!(!suite.vectors.is_empty());
+        for vector in &suite.vectors {
+            assert_eq!(check_wire(vector), Outcome::Pass, "vector {}", vector.name);
+        }
+        assert!(!suite.audio.is_empty());
+        for vector in &suite.audio {
+            let outcome = check_audio(vector, path.parent().unwrap());
+            assert_eq!(outcome, Outcome::Pass, "vector {}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_expectation_fails() {
+        let vector = WireVector {
+            name: "wrong-body".into(),
+            frame: "01000061".into(),
+            expect: Some(ExpectedFrame {
+                kind: "message".into(),
+                chunk: None,
+                address: None,
+                tag: None,
+                body: "62".into(),
+            }),
+            error: None,
+        };
+        assert!(matches!(check_wire(&vector), Outcome::Fail(_)));
+    }
+
+    #[test]
+    fn test_missing_audio_fails() {
+        let vector = AudioVector {
+            name: "missing".into(),
+            wav: "audio/does-not-exist.wav".into(),
+            protocol: "audible.fast".into(),
+            volume: 25,
+            frame: "01000061".into(),
+        };
+        let outcome = check_audio(&vector, &std::env::temp_dir());
+        assert!(matches!(outcome, Outcome::Fail(e) if e.contains("not found")));
+    }
+}
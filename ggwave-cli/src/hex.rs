@@ -0,0 +1,36 @@
+pub fn parse(hex: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in '{hex}'"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid hex in '{hex}'"))
+        })
+        .collect()
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse("00ff 10"), Ok(vec![0x00, 0xff, 0x10]));
+        assert!(parse("abc").is_err());
+        assert!(parse("zz").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        assert_eq!(parse(&encode(&[0, 1, 0xab])), Ok(vec![0, 1, 0xab]));
+    }
+}
@@ -0,0 +1,428 @@
+//! Compile-time message tables for user-facing CLI output.
+//!
+//! Every message is matched exhaustively per language, so adding a message
+//! without translating it is a compile error.
+
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+
+use crate::plan::{Audibility, Plan};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// English
+    En,
+    /// Español
+    Es,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+impl Lang {
+    /// Picks the language from `LC_ALL`, `LC_MESSAGES` or `LANG`, falling back
+    /// to English.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| Self::from_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+
+    /// Parses a POSIX locale name such as `es_ES.UTF-8`.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let language = locale
+            .split(['_', '.', '@', '-'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            _ => None,
+        }
+    }
+}
+
+/// Sets the language used by [`tr`]. Only the first call has an effect.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+fn lang() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
+}
+
+pub enum Msg<'a> {
+    Error(&'a dyn Display),
+    Encoded {
+        bytes: usize,
+        path: &'a Path,
+        samples: usize,
+        sample_rate: u32,
+    },
+    Embedded {
+        bytes: usize,
+        path: &'a Path,
+        at_secs: f64,
+    },
+    Rendered {
+        messages: usize,
+        path: &'a Path,
+        secs: f64,
+    },
+    NoPayload,
+    NoPayloadShort,
+    Levels {
+        peak_dbfs: f32,
+        rms_dbfs: f32,
+    },
+    ClipWarning,
+    InputClipping,
+    Rejected(&'a str),
+    Verified {
+        path: &'a Path,
+        bytes: usize,
+    },
+    VerifyMismatch {
+        path: &'a Path,
+        expected: &'a str,
+        got: Option<&'a str>,
+    },
+    NoAudioSupport(&'a str),
+    HeadroomWarning,
+    NotMono(u16),
+    UnsupportedSampleFormat(&'a dyn Display),
+    Wrote(&'a Path),
+    ConformanceFailed(usize),
+    StatsTotal {
+        messages: usize,
+        failures: usize,
+    },
+    StatsSenders(usize),
+    StatsHourly,
+    StatsTopPayloads,
+    TryHeader,
+    TrySummary {
+        succeeded: usize,
+        total: usize,
+    },
+    Onboarding {
+        frames: usize,
+        repeat: usize,
+        path: &'a Path,
+    },
+    OnboardingCleartext,
+    Plan(&'a Plan),
+    Served {
+        requests: usize,
+        failed: usize,
+    },
+    Sent {
+        bytes: usize,
+        secs: f64,
+    },
+    Listening,
+    Listened(usize),
+}
+
+impl Msg<'_> {
+    pub fn text(&self, lang: Lang) -> String {
+        match lang {
+            Lang::En => self.en(),
+            Lang::Es => self.es(),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Msg::Error(e) => format!("Error: {e}"),
+            Msg::Encoded {
+                bytes,
+                path,
+                samples,
+                sample_rate,
+            } => format!(
+                "Encoded {bytes} bytes into {} ({samples} samples, {sample_rate} Hz)",
+                path.display()
+            ),
+            Msg::Embedded {
+                bytes,
+                path,
+                at_secs,
+            } => format!(
+                "Embedded {bytes} bytes into {} at {at_secs:.3} s",
+                path.display()
+            ),
+            Msg::Rendered {
+                messages,
+                path,
+                secs,
+            } => format!(
+                "Rendered {messages} message(s) into {} ({secs:.1} s)",
+                path.display()
+            ),
+            Msg::NoPayload => "No payload decoded.".into(),
+            Msg::NoPayloadShort => "(no payload)".into(),
+            Msg::Levels {
+                peak_dbfs,
+                rms_dbfs,
+            } => format!("Peak {peak_dbfs:.1} dBFS, RMS {rms_dbfs:.1} dBFS"),
+            Msg::ClipWarning => {
+                "Warning: the waveform reaches full scale and will clip in 16-bit output; \
+                 lower --volume"
+                    .into()
+            }
+            Msg::NoAudioSupport(command) => format!(
+                "`ggwave {command}` needs sound device support, which this build doesn't include. \
+                 Use `ggwave encode` to write a WAV file and play it with any player, or record \
+                 with any recorder and run `ggwave decode`."
+            ),
+            Msg::InputClipping => {
+                "Hint: the recording clips, which often prevents decoding; reduce mic gain".into()
+            }
+            Msg::Rejected(reason) => format!("payload rejected: {reason}"),
+            Msg::Served { requests, failed } => {
+                format!("Handled {requests} request(s), {failed} failed")
+            }
+            Msg::Sent { bytes, secs } => format!("Played {bytes} bytes ({secs:.2} s)"),
+            Msg::Listening => "Listening; press Enter to stop".into(),
+            Msg::Listened(payloads) => format!("Heard {payloads} message(s)"),
+            Msg::Verified { path, bytes } => {
+                format!(
+                    "Verified: {} decodes to the {bytes}-byte message",
+                    path.display()
+                )
+            }
+            Msg::VerifyMismatch {
+                path,
+                expected,
+                got,
+            } => match got {
+                Some(got) => format!(
+                    "verification failed: {} decodes to \"{got}\", expected \"{expected}\"",
+                    path.display()
+                ),
+                None => format!(
+                    "verification failed: nothing decodes from {}, expected \"{expected}\"",
+                    path.display()
+                ),
+            },
+            Msg::HeadroomWarning => {
+                "Warning: less than 1 dB of headroom; mixing with other audio may clip".into()
+            }
+            Msg::NotMono(channels) => format!("expected mono audio, got {channels} channels"),
+            Msg::UnsupportedSampleFormat(format) => {
+                format!("unsupported sample format: {format}")
+            }
+            Msg::Wrote(path) => format!("wrote {}", path.display()),
+            Msg::ConformanceFailed(n) => format!("{n} conformance vector(s) failed"),
+            Msg::StatsTotal { messages, failures } => {
+                format!("{messages} message(s), {failures} failed decode(s)")
+            }
+            Msg::StatsSenders(n) => format!("{n} unique sender(s)"),
+            Msg::StatsHourly => "Per hour (messages, failure ratio):".into(),
+            Msg::StatsTopPayloads => "Most common payloads:".into(),
+            Msg::TryHeader => "    rate  threshold  protocols   result".into(),
+            Msg::TrySummary { succeeded, total } => {
+                format!("{succeeded} of {total} combination(s) decoded a payload")
+            }
+            Msg::Onboarding {
+                frames,
+                repeat,
+                path,
+            } => format!(
+                "Wrote {frames} frame(s), repeated {repeat} time(s), to {}",
+                path.display()
+            ),
+            Msg::OnboardingCleartext => {
+                "Warning: the passphrase is sent unencrypted; anyone nearby can record it".into()
+            }
+            Msg::Plan(plan) => format!(
+                "Duration:   {:.2} s\n\
+                 Band:       {:.0}-{:.0} Hz ({})\n\
+                 Payload:    {} bytes + {} framing + {} error correction ({:.0}% overhead)",
+                plan.duration.as_secs_f64(),
+                plan.band.0,
+                plan.band.1,
+                match plan.audibility {
+                    Audibility::Audible => "audible",
+                    Audibility::NearUltrasonic => "near-ultrasonic, inaudible to most adults",
+                },
+                plan.message_len,
+                plan.framing,
+                plan.error_correction,
+                plan.overhead_ratio() * 100.0
+            ),
+        }
+    }
+
+    fn es(&self) -> String {
+        match self {
+            Msg::Error(e) => format!("Error: {e}"),
+            Msg::Encoded {
+                bytes,
+                path,
+                samples,
+                sample_rate,
+            } => format!(
+                "Codificados {bytes} bytes en {} ({samples} muestras, {sample_rate} Hz)",
+                path.display()
+            ),
+            Msg::Embedded {
+                bytes,
+                path,
+                at_secs,
+            } => format!(
+                "Insertados {bytes} bytes en {} a los {at_secs:.3} s",
+                path.display()
+            ),
+            Msg::Rendered {
+                messages,
+                path,
+                secs,
+            } => format!(
+                "Generados {messages} mensaje(s) en {} ({secs:.1} s)",
+                path.display()
+            ),
+            Msg::NoPayload => "No se decodificó ningún mensaje.".into(),
+            Msg::NoPayloadShort => "(sin mensaje)".into(),
+            Msg::Levels {
+                peak_dbfs,
+                rms_dbfs,
+            } => format!("Pico {peak_dbfs:.1} dBFS, RMS {rms_dbfs:.1} dBFS"),
+            Msg::ClipWarning => {
+                "Aviso: la señal alcanza el fondo de escala y se saturará en salida de 16 bits; \
+                 reduzca --volume"
+                    .into()
+            }
+            Msg::NoAudioSupport(command) => format!(
+                "`ggwave {command}` necesita soporte de dispositivos de sonido, que esta versión no \
+                 incluye. Use `ggwave encode` para escribir un WAV y reprodúzcalo con cualquier \
+                 reproductor, o grabe con cualquier grabadora y ejecute `ggwave decode`."
+            ),
+            Msg::InputClipping => {
+                "Sugerencia: la grabación está saturada, lo que suele impedir la decodificación; \
+                 reduzca la ganancia del micrófono"
+                    .into()
+            }
+            Msg::Rejected(reason) => format!("mensaje rechazado: {reason}"),
+            Msg::Served { requests, failed } => {
+                format!("{requests} solicitud(es) atendida(s), {failed} fallida(s)")
+            }
+            Msg::Sent { bytes, secs } => format!("Reproducidos {bytes} bytes ({secs:.2} s)"),
+            Msg::Listening => "Escuchando; pulse Intro para detener".into(),
+            Msg::Listened(payloads) => format!("{payloads} mensaje(s) recibido(s)"),
+            Msg::Verified { path, bytes } => format!(
+                "Verificado: {} se decodifica como el mensaje de {bytes} bytes",
+                path.display()
+            ),
+            Msg::VerifyMismatch {
+                path,
+                expected,
+                got,
+            } => match got {
+                Some(got) => format!(
+                    "verificación fallida: {} se decodifica como \"{got}\", se esperaba \"{expected}\"",
+                    path.display()
+                ),
+                None => format!(
+                    "verificación fallida: no se decodifica nada de {}, se esperaba \"{expected}\"",
+                    path.display()
+                ),
+            },
+            Msg::HeadroomWarning => {
+                "Aviso: queda menos de 1 dB de margen; mezclar con otro audio puede saturar".into()
+            }
+            Msg::NotMono(channels) => {
+                format!("se esperaba audio mono, el archivo tiene {channels} canales")
+            }
+            Msg::UnsupportedSampleFormat(format) => {
+                format!("formato de muestra no soportado: {format}")
+            }
+            Msg::Wrote(path) => format!("escrito {}", path.display()),
+            Msg::ConformanceFailed(n) => format!("fallaron {n} vector(es) de conformidad"),
+            Msg::StatsTotal { messages, failures } => {
+                format!("{messages} mensaje(s), {failures} decodificación(es) fallida(s)")
+            }
+            Msg::StatsSenders(n) => format!("{n} emisor(es) distinto(s)"),
+            Msg::StatsHourly => "Por hora (mensajes, tasa de fallos):".into(),
+            Msg::StatsTopPayloads => "Mensajes más frecuentes:".into(),
+            Msg::TryHeader => "    tasa     umbral  protocolos  resultado".into(),
+            Msg::TrySummary { succeeded, total } => {
+                format!("{succeeded} de {total} combinación(es) decodificaron un mensaje")
+            }
+            Msg::Onboarding {
+                frames,
+                repeat,
+                path,
+            } => format!(
+                "Escritas {frames} trama(s), repetidas {repeat} vez/veces, en {}",
+                path.display()
+            ),
+            Msg::OnboardingCleartext => {
+                "Aviso: la contraseña se envía sin cifrar; cualquiera cerca puede grabarla".into()
+            }
+            Msg::Plan(plan) => format!(
+                "Duración:   {:.2} s\n\
+                 Banda:      {:.0}-{:.0} Hz ({})\n\
+                 Carga:      {} bytes + {} de trama + {} de corrección de errores \
+                 ({:.0}% de sobrecarga)",
+                plan.duration.as_secs_f64(),
+                plan.band.0,
+                plan.band.1,
+                match plan.audibility {
+                    Audibility::Audible => "audible",
+                    Audibility::NearUltrasonic => "casi ultrasónica, inaudible para la mayoría de adultos",
+                },
+                plan.message_len,
+                plan.framing,
+                plan.error_correction,
+                plan.overhead_ratio() * 100.0
+            ),
+        }
+    }
+}
+
+/// Renders a message in the configured language.
+pub fn tr(msg: Msg<'_>) -> String {
+    msg.text(lang())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_locale() {
+        assert_eq!(Lang::from_locale("es_ES.UTF-8"), Some(Lang::Es));
+        assert_eq!(Lang::from_locale("es"), Some(Lang::Es));
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::from_locale("C"), Some(Lang::En));
+        assert_eq!(Lang::from_locale("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_messages_are_translated() {
+        let path = Path::new("out.wav");
+        let msg = Msg::Encoded {
+            bytes: 5,
+            path,
+            samples: 100,
+            sample_rate: 48000,
+        };
+        assert_eq!(
+            msg.text(Lang::En),
+            "Encoded 5 bytes into out.wav (100 samples, 48000 Hz)"
+        );
+        assert_eq!(
+            msg.text(Lang::Es),
+            "Codificados 5 bytes en out.wav (100 muestras, 48000 Hz)"
+        );
+        assert_ne!(Msg::NoPayload.text(Lang::En), Msg::NoPayload.text(Lang::Es));
+    }
+}
@@ -0,0 +1,1193 @@
+//! The `ggwave` command line tool as a library.
+//!
+//! [`run`] executes a [`Command`] exactly as the binary does, including the
+//! files it writes, but returns what happened as an [`Output`] instead of
+//! printing it. GUI wrappers and tests can drive the CLI this way without
+//! spawning a process and parsing its stdout; the binary is [`run`] plus
+//! presentation.
+//!
+//! ```no_run
+//! use ggwave_cli::{run, Command, Output, Protocol};
+//!
+//! let output = run(Command::Plan {
+//!     message: "hello".into(),
+//!     protocol: Protocol::UltrasoundFast,
+//!     framed: false,
+//! })?;
+//! if let Output::Planned(plan) = output {
+//!     println!("{:.2} s", plan.duration.as_secs_f64());
+//! }
+//! # Ok::<(), ggwave_cli::Error>(())
+//! ```
+
+pub mod conformance;
+mod hex;
+pub mod i18n;
+pub mod markers;
+pub mod matrix;
+pub mod meter;
+pub mod plan;
+pub mod preview;
+pub mod serve;
+pub mod split;
+pub mod stats;
+pub mod storage;
+mod timecode;
+mod timeline;
+pub mod validate;
+mod wav;
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Subcommand, ValueEnum};
+use ggwave_rs::audio;
+use ggwave_rs::onboarding::{self, Delivery, DeviceKey, WifiCredentials};
+use ggwave_rs::receiver::{ClipDetector, Validator};
+use ggwave_rs::scan;
+use ggwave_rs::{default_parameters, GgWave, Parameters, ProtocolId, SampleFormat, Waveform};
+use i18n::{tr, Msg};
+use markers::{Marker, MarkerFormat};
+use matrix::{Combination, ProtocolSet};
+use meter::Levels;
+use plan::Plan;
+use stats::Stats;
+use storage::{Query, Record};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Encode a message into a WAV file
+    Encode {
+        /// Message to encode
+        message: String,
+        /// Output WAV file path
+        output: PathBuf,
+        /// Volume (0-100)
+        #[arg(short, long, default_value = "25")]
+        volume: i32,
+        /// Protocol to use
+        #[arg(short, long, default_value = "audible-fast", value_parser = parse_protocol)]
+        protocol: Protocol,
+        /// Print peak/RMS levels and warn about clipping
+        #[arg(long)]
+        meter: bool,
+        /// Append a JSON line describing what was written to this log
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+        /// Decode the written file again and fail unless it gives back the message
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Check that a WAV file decodes to the expected message
+    Verify {
+        /// Input WAV file path
+        input: PathBuf,
+        /// Message the file must decode to
+        #[arg(long)]
+        expect: String,
+    },
+    /// Mix a message into existing audio at a given position
+    Embed {
+        /// Message to embed
+        message: String,
+        /// Program audio to embed into (mono WAV)
+        carrier: PathBuf,
+        /// Output WAV file path
+        output: PathBuf,
+        /// Position of the message: HH:MM:SS.mmm, MM:SS.mmm or seconds
+        #[arg(long, default_value = "0", value_parser = timecode::parse)]
+        at: std::time::Duration,
+        /// Linear gain applied to the message before mixing
+        #[arg(long, default_value_t = 1.0)]
+        gain: f32,
+        /// Volume (0-100)
+        #[arg(short, long, default_value = "25")]
+        volume: i32,
+        /// Protocol to use
+        #[arg(short, long, default_value = "audible-fast", value_parser = parse_protocol)]
+        protocol: Protocol,
+    },
+    /// Render a timeline of messages (TOML or JSON) into a program-length WAV
+    Render {
+        /// Timeline file (.toml, or .json)
+        timeline: PathBuf,
+        /// Output WAV file path
+        output: PathBuf,
+    },
+    /// Estimate duration, frequency band and overhead without writing audio
+    Plan {
+        /// Message to send
+        message: String,
+        /// Protocol to use
+        #[arg(short, long, default_value = "audible-fast", value_parser = parse_protocol)]
+        protocol: Protocol,
+        /// Account for wrapping the message in a wire format frame
+        #[arg(long)]
+        framed: bool,
+    },
+    /// Play a message through the default output device
+    Send {
+        /// Message to send
+        message: String,
+        /// Volume (0-100)
+        #[arg(short, long, default_value = "25")]
+        volume: i32,
+        /// Protocol to use
+        #[arg(short, long, default_value = "audible-fast", value_parser = parse_protocol)]
+        protocol: Protocol,
+    },
+    /// Print messages heard on the default input device
+    Listen,
+    /// Decode a message from a WAV file
+    Decode {
+        /// Input WAV file path
+        input: PathBuf,
+        /// Write the exact decoded bytes to a file ("-" for stdout) instead of a preview
+        #[arg(long, value_name = "PATH")]
+        raw_output: Option<PathBuf>,
+        /// Append the result to a message store (.jsonl, or .db with the sqlite feature)
+        #[arg(long, value_name = "PATH")]
+        store: Option<PathBuf>,
+        /// Reject payloads that fail a check (repeatable)
+        #[arg(long = "validate", value_enum, value_name = "CHECK")]
+        checks: Vec<validate::Check>,
+        /// Reject payloads that don't match a JSON Schema (needs the jsonschema feature)
+        #[arg(long, value_name = "PATH")]
+        schema: Option<PathBuf>,
+    },
+    /// Report which decoding parameters work for a WAV file
+    Try {
+        /// Input WAV file path
+        input: PathBuf,
+        /// Try every combination of sample rate, marker threshold and protocol set
+        #[arg(long)]
+        matrix: bool,
+    },
+    /// List every transmission in a recording with its position
+    Scan {
+        /// Input WAV file path
+        input: PathBuf,
+        /// Write a marker file instead of a listing
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        export_markers: Option<MarkerFormat>,
+        /// Marker file path (defaults to stdout)
+        #[arg(short, long, requires = "export_markers")]
+        output: Option<PathBuf>,
+    },
+    /// Cut a long recording into one WAV per decoded transmission
+    Split {
+        /// Input WAV file path
+        input: PathBuf,
+        /// Directory for the parts and manifest.json
+        #[arg(long, default_value = "parts")]
+        out_dir: PathBuf,
+    },
+    /// List messages recorded with `decode --store`
+    History {
+        /// Message store (.jsonl, or .db with the sqlite feature)
+        store: PathBuf,
+        /// Only messages received at or after this Unix time
+        #[arg(long)]
+        since: Option<u64>,
+        /// Only the most recent N messages
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Print aggregate statistics instead of individual messages
+        #[arg(long)]
+        stats: bool,
+        /// Number of most common payloads to show with --stats
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+    /// Write Wi-Fi credentials as a WAV for provisioning a headless device
+    Onboard {
+        /// Network name
+        #[arg(long)]
+        ssid: String,
+        /// Passphrase (omit for open networks)
+        #[arg(long)]
+        psk: Option<String>,
+        /// Network security
+        #[arg(long, value_enum, default_value = "wpa")]
+        security: Security,
+        /// The network doesn't broadcast its SSID
+        #[arg(long)]
+        hidden: bool,
+        /// Device key (64 hex digits) to encrypt the credentials with
+        #[arg(long, value_parser = parse_device_key)]
+        key: Option<DeviceKey>,
+        /// Output WAV file path
+        output: PathBuf,
+        /// Protocol to use
+        #[arg(short, long, default_value = "audible-fast", value_parser = parse_protocol)]
+        protocol: Protocol,
+        /// Volume (0-100)
+        #[arg(short, long, default_value = "25")]
+        volume: i32,
+        /// Times to repeat the whole sequence, so a missed frame is caught on a later pass
+        #[arg(long, default_value_t = 3)]
+        repeat: usize,
+    },
+    /// Handle many encode/decode requests in one process, keeping instances warm
+    Serve {
+        /// Read JSON line requests from stdin and answer on stdout
+        #[arg(long)]
+        stdin_jsonl: bool,
+    },
+    /// Validate this implementation against wire format conformance vectors
+    Conformance {
+        /// Conformance vector file (JSON)
+        #[arg(default_value = "conformance/wire-v1.json")]
+        vectors: PathBuf,
+        /// (Re)generate the suite's WAV vectors instead of checking them
+        #[arg(long)]
+        generate_audio: bool,
+    },
+}
+
+/// CLI names for the standard protocols. Arguments are parsed with
+/// [`parse_protocol`], so the stable identifiers (`audible.fast`) and the
+/// shorthands `ProtocolId::from_str` knows (`u-fast`, `[U] Fast`) work too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Protocol {
+    #[value(alias = "audible.normal")]
+    AudibleNormal,
+    #[value(alias = "audible.fast")]
+    AudibleFast,
+    #[value(alias = "audible.fastest")]
+    AudibleFastest,
+    #[value(alias = "ultrasound.normal")]
+    UltrasoundNormal,
+    #[value(alias = "ultrasound.fast")]
+    UltrasoundFast,
+    #[value(alias = "ultrasound.fastest")]
+    UltrasoundFastest,
+    #[value(alias = "dt.normal")]
+    DtNormal,
+    #[value(alias = "dt.fast")]
+    DtFast,
+    #[value(alias = "dt.fastest")]
+    DtFastest,
+    #[value(alias = "mt.normal")]
+    MtNormal,
+    #[value(alias = "mt.fast")]
+    MtFast,
+    #[value(alias = "mt.fastest")]
+    MtFastest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Security {
+    Wpa,
+    Wep,
+    Open,
+}
+
+impl From<Security> for onboarding::Security {
+    fn from(s: Security) -> Self {
+        match s {
+            Security::Wpa => onboarding::Security::Wpa,
+            Security::Wep => onboarding::Security::Wep,
+            Security::Open => onboarding::Security::Open,
+        }
+    }
+}
+
+impl From<Protocol> for ProtocolId {
+    fn from(p: Protocol) -> Self {
+        match p {
+            Protocol::AudibleNormal => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
+            Protocol::AudibleFast => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+            Protocol::AudibleFastest => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST,
+            Protocol::UltrasoundNormal => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL,
+            Protocol::UltrasoundFast => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST,
+            Protocol::UltrasoundFastest => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST,
+            Protocol::DtNormal => ProtocolId::GGWAVE_PROTOCOL_DT_NORMAL,
+            Protocol::DtFast => ProtocolId::GGWAVE_PROTOCOL_DT_FAST,
+            Protocol::DtFastest => ProtocolId::GGWAVE_PROTOCOL_DT_FASTEST,
+            Protocol::MtNormal => ProtocolId::GGWAVE_PROTOCOL_MT_NORMAL,
+            Protocol::MtFast => ProtocolId::GGWAVE_PROTOCOL_MT_FAST,
+            Protocol::MtFastest => ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST,
+        }
+    }
+}
+
+impl TryFrom<ProtocolId> for Protocol {
+    type Error = ggwave_rs::Error;
+
+    fn try_from(id: ProtocolId) -> std::result::Result<Self, Self::Error> {
+        Protocol::value_variants()
+            .iter()
+            .copied()
+            .find(|&p| ProtocolId::from(p) == id)
+            .ok_or(ggwave_rs::Error::InvalidInput("not a standard protocol"))
+    }
+}
+
+fn parse_device_key(s: &str) -> std::result::Result<DeviceKey, String> {
+    let bytes = hex::parse(s)?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("device key is {} bytes; expected 32", b.len()))?;
+    Ok(DeviceKey::new(bytes))
+}
+
+fn parse_protocol(s: &str) -> std::result::Result<Protocol, String> {
+    s.parse::<ProtocolId>()
+        .and_then(Protocol::try_from)
+        .map_err(|_| {
+            let names: Vec<_> = Protocol::value_variants()
+                .iter()
+                .filter_map(|p| p.to_possible_value())
+                .map(|v| v.get_name().to_string())
+                .collect();
+            format!(
+                "unknown protocol '{s}'; expected one of {} or a shorthand such as u-fast",
+                names.join(", ")
+            )
+        })
+}
+
+/// Where `decode --raw-output` and `scan --export-markers` send their result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sink {
+    /// Left to the caller (`-` on the command line).
+    Stdout,
+    /// Already written to this file.
+    File(PathBuf),
+}
+
+impl Sink {
+    fn new(path: &Path) -> Self {
+        if path == Path::new("-") {
+            Sink::Stdout
+        } else {
+            Sink::File(path.to_path_buf())
+        }
+    }
+}
+
+/// One row of `ggwave try`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    pub combination: Combination,
+    /// The payload, `None` if nothing decoded, or the library's error.
+    pub result: std::result::Result<Option<Vec<u8>>, String>,
+}
+
+/// What a command did. Files the command writes are already written.
+#[derive(Debug)]
+pub enum Output {
+    Encoded {
+        bytes: usize,
+        path: PathBuf,
+        samples: usize,
+        sample_rate: u32,
+        /// With `--meter`.
+        levels: Option<Levels>,
+        /// With `--verify`; a failed verification is an error instead.
+        verified: bool,
+    },
+    Verified {
+        path: PathBuf,
+        bytes: usize,
+    },
+    Embedded {
+        bytes: usize,
+        path: PathBuf,
+        at_secs: f64,
+    },
+    Rendered {
+        messages: usize,
+        path: PathBuf,
+        secs: f64,
+    },
+    Planned(Plan),
+    Decoded {
+        payload: Option<Vec<u8>>,
+        /// The recording clips, which often prevents decoding.
+        clipping: bool,
+        raw_output: Option<Sink>,
+    },
+    Tried(Vec<Attempt>),
+    Scanned {
+        markers: Vec<Marker>,
+        /// Rendered marker file, with `--export-markers`.
+        export: Option<(String, Sink)>,
+    },
+    Split {
+        /// Each part with the payload decoded from it.
+        parts: Vec<(split::Part, Vec<u8>)>,
+        manifest: PathBuf,
+    },
+    History(Vec<Record>),
+    Stats(Stats),
+    Onboarded {
+        frames: usize,
+        repeat: usize,
+        path: PathBuf,
+        /// A passphrase was sent in the clear.
+        cleartext: bool,
+    },
+    /// Results by vector name; the command failed if any is a failure.
+    Conformance(Vec<(String, conformance::Outcome)>),
+    /// Audio vectors written by `conformance --generate-audio`.
+    Generated(Vec<PathBuf>),
+    /// A `serve` session ended; its responses went to stdout as it ran.
+    Served(serve::Summary),
+    Sent {
+        bytes: usize,
+        secs: f64,
+    },
+    /// `listen` stopped; the payloads went to stdout as they arrived.
+    Listened {
+        payloads: usize,
+    },
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The command needs sound device support, which this build lacks.
+    NoAudioSupport(&'static str),
+    Failed(Box<dyn std::error::Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoAudioSupport(command) => f.write_str(&tr(Msg::NoAudioSupport(command))),
+            Error::Failed(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Error::Failed(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+type CommandResult = std::result::Result<Output, Box<dyn std::error::Error>>;
+
+/// Runs a command the way the `ggwave` binary does.
+pub fn run(command: Command) -> Result<Output> {
+    let output = match command {
+        Command::Encode {
+            message,
+            output,
+            volume,
+            protocol,
+            meter,
+            report,
+            verify,
+        } => encode(
+            &message,
+            &output,
+            volume,
+            protocol,
+            meter,
+            report.as_deref(),
+            verify,
+        ),
+        Command::Verify { input, expect } => {
+            verify_file(&input, expect.as_bytes()).map(|()| Output::Verified {
+                path: input,
+                bytes: expect.len(),
+            })
+        }
+        Command::Embed {
+            message,
+            carrier,
+            output,
+            at,
+            gain,
+            volume,
+            protocol,
+        } => {
+            let placement = Placement {
+                at,
+                gain,
+                volume,
+                protocol,
+            };
+            embed(&message, &carrier, &output, placement)
+        }
+        Command::Render { timeline, output } => render(&timeline, &output),
+        Command::Plan {
+            message,
+            protocol,
+            framed,
+        } => plan::plan(message.as_bytes(), protocol.into(), framed).map(Output::Planned),
+        #[cfg(feature = "cpal")]
+        Command::Send {
+            message,
+            volume,
+            protocol,
+        } => send(&message, volume, protocol),
+        #[cfg(not(feature = "cpal"))]
+        Command::Send { .. } => return Err(audio_unavailable("send")),
+        #[cfg(feature = "cpal")]
+        Command::Listen => listen(),
+        #[cfg(not(feature = "cpal"))]
+        Command::Listen => return Err(audio_unavailable("listen")),
+        Command::Decode {
+            input,
+            raw_output,
+            store,
+            checks,
+            schema,
+        } => validators(&checks, schema.as_deref()).and_then(|validators| {
+            decode(&input, raw_output.as_deref(), store.as_deref(), &validators)
+        }),
+        Command::Try { input, matrix } => try_decode(&input, matrix),
+        Command::Scan {
+            input,
+            export_markers,
+            output,
+        } => scan_recording(&input, export_markers, output.as_deref()),
+        Command::Split { input, out_dir } => split(&input, &out_dir),
+        Command::History {
+            store,
+            since,
+            limit,
+            stats,
+            top,
+        } => {
+            let query = Query { since, limit };
+            if stats {
+                history_stats(&store, query, top)
+            } else {
+                history(&store, query)
+            }
+        }
+        Command::Onboard {
+            ssid,
+            psk,
+            security,
+            hidden,
+            key,
+            output,
+            protocol,
+            volume,
+            repeat,
+        } => {
+            let credentials = WifiCredentials {
+                ssid,
+                security: security.into(),
+                psk,
+                hidden,
+            };
+            onboard(&credentials, key.as_ref(), &output, protocol, volume, repeat)
+        }
+        Command::Conformance {
+            vectors,
+            generate_audio,
+        } => conformance(&vectors, generate_audio),
+        Command::Serve { stdin_jsonl } => {
+            if !stdin_jsonl {
+                return Err(Error::Failed(
+                    "serve needs a transport; only --stdin-jsonl is available".into(),
+                ));
+            }
+            serve::serve(std::io::stdin().lock(), std::io::stdout().lock()).map(Output::Served)
+        }
+    };
+    Ok(output?)
+}
+
+/// `send` and `listen` stay available in every build, so that scripts get an
+/// explanation and a dedicated exit code instead of a usage error.
+#[cfg(not(feature = "cpal"))]
+fn audio_unavailable(command: &'static str) -> Error {
+    Error::NoAudioSupport(command)
+}
+
+#[cfg(feature = "cpal")]
+fn send(message: &str, volume: i32, protocol: Protocol) -> CommandResult {
+    let report = audio::play(message.as_bytes(), protocol.into(), volume)?;
+    Ok(Output::Sent {
+        bytes: message.len(),
+        secs: report.duration.as_secs_f64(),
+    })
+}
+
+/// Prints every payload heard as it arrives, until a line is entered or
+/// stdin is closed.
+#[cfg(feature = "cpal")]
+fn listen() -> CommandResult {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let heard = Arc::new(AtomicUsize::new(0));
+    let counter = heard.clone();
+    let listener = audio::listen(move |payload| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        println!("{}", preview::render(&payload));
+    })?;
+    eprintln!("{}", tr(Msg::Listening));
+    std::io::stdin().read_line(&mut String::new())?;
+    listener.stop()?;
+    Ok(Output::Listened {
+        payloads: heard.load(Ordering::Relaxed),
+    })
+}
+
+/// Default parameters with F32 input/output, optionally overriding the input
+/// sample rate with the rate of a WAV file being decoded.
+pub(crate) fn f32_parameters(sample_rate_inp: Option<u32>) -> Parameters {
+    let mut params = default_parameters();
+    params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+    params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+    if let Some(rate) = sample_rate_inp {
+        params.sampleRateInp = rate as f32;
+    }
+    params
+}
+
+fn encode(
+    message: &str,
+    output: &Path,
+    volume: i32,
+    protocol: Protocol,
+    meter: bool,
+    report: Option<&Path>,
+    verify: bool,
+) -> CommandResult {
+    let params = f32_parameters(None);
+
+    let ggwave = GgWave::new(params)?;
+    let waveform = ggwave.encode(message.as_bytes(), protocol.into(), volume)?;
+
+    let sample_rate = params.sampleRateOut as u32;
+    let tx_report = audio::transmit(
+        &wav::to_samples(&waveform),
+        sample_rate,
+        protocol.into(),
+        |_| wav::write_f32(output, &waveform, sample_rate),
+    )?;
+    if let Some(path) = report {
+        append_tx_report(path, output, &tx_report)?;
+    }
+    if verify {
+        verify_file(output, message.as_bytes())?;
+    }
+
+    Ok(Output::Encoded {
+        bytes: message.len(),
+        path: output.to_path_buf(),
+        samples: waveform.len() / 4,
+        sample_rate,
+        levels: meter.then(|| Levels::measure(&wav::to_samples(&waveform))),
+        verified: verify,
+    })
+}
+
+/// Decodes `path` with a fresh instance, failing unless it yields `expected`.
+fn verify_file(
+    path: &Path,
+    expected: &[u8],
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (waveform, sample_rate) = wav::read_f32(path)?;
+    let decoded = GgWave::new(f32_parameters(Some(sample_rate)))?.decode(&waveform)?;
+    if decoded.as_deref() == Some(expected) {
+        return Ok(());
+    }
+    let got = decoded.as_deref().map(preview::inline);
+    Err(tr(Msg::VerifyMismatch {
+        path,
+        expected: &preview::inline(expected),
+        got: got.as_deref(),
+    })
+    .into())
+}
+
+/// Where and how loud a message goes into program audio.
+struct Placement {
+    at: std::time::Duration,
+    gain: f32,
+    volume: i32,
+    protocol: Protocol,
+}
+
+fn embed(message: &str, carrier: &Path, output: &Path, placement: Placement) -> CommandResult {
+    let (audio, sample_rate) = wav::read_f32(carrier)?;
+    let mut audio = wav::to_samples(&audio);
+
+    let mut params = f32_parameters(None);
+    params.sampleRateOut = sample_rate as f32;
+    let encoded = GgWave::new(params)?.encode(
+        message.as_bytes(),
+        placement.protocol.into(),
+        placement.volume,
+    )?;
+    let waveform = Waveform::from_f32_bytes(&encoded, sample_rate);
+    waveform.insert_into(&mut audio, placement.at, placement.gain)?;
+
+    wav::write_f32(output, &wav::to_bytes(&audio), sample_rate)?;
+    Ok(Output::Embedded {
+        bytes: message.len(),
+        path: output.to_path_buf(),
+        at_secs: waveform.offset_of(placement.at) as f64 / sample_rate as f64,
+    })
+}
+
+fn render(path: &Path, output: &Path) -> CommandResult {
+    let timeline = timeline::load(path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let (track, sample_rate) = timeline::render(&timeline, base)?;
+    wav::write_f32(output, &wav::to_bytes(&track), sample_rate)?;
+    Ok(Output::Rendered {
+        messages: timeline.message.len(),
+        path: output.to_path_buf(),
+        secs: track.len() as f64 / sample_rate as f64,
+    })
+}
+
+/// Appends `report` to a JSON Lines log.
+fn append_tx_report(
+    log: &Path,
+    output: &Path,
+    report: &audio::TxReport,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let started_at = report
+        .started_at
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let line = serde_json::json!({
+        "started_at": started_at.as_secs_f64(),
+        "duration": report.duration.as_secs_f64(),
+        "samples": report.samples,
+        "protocol": report.protocol.id(),
+        "peak_level": report.peak_level,
+        "output": output.display().to_string(),
+    });
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)?;
+    writeln!(file, "{line}")?;
+    Ok(())
+}
+
+fn decode(
+    input: &Path,
+    raw_output: Option<&Path>,
+    store: Option<&Path>,
+    validators: &[Validator],
+) -> CommandResult {
+    let (waveform, sample_rate) = wav::read_f32(input)?;
+
+    let ggwave = GgWave::new(f32_parameters(Some(sample_rate)))?;
+    let decoded = ggwave.decode(&waveform)?;
+    let clipping = ClipDetector::new(sample_rate).feed_any(&wav::to_samples(&waveform));
+
+    // Rejected payloads are neither printed nor stored.
+    if let Some(payload) = &decoded {
+        for validator in validators {
+            if let Err(reason) = validator(payload) {
+                return Err(tr(Msg::Rejected(&reason)).into());
+            }
+        }
+    }
+
+    if let Some(path) = store {
+        let record = Record::now(input.display().to_string(), decoded.clone());
+        storage::open(path)?.append(&record)?;
+    }
+
+    let raw_output = raw_output.map(Sink::new);
+    if let (Some(payload), Some(Sink::File(path))) = (&decoded, &raw_output) {
+        std::fs::write(path, payload)?;
+    }
+
+    Ok(Output::Decoded {
+        payload: decoded,
+        clipping,
+        raw_output,
+    })
+}
+
+fn validators(
+    checks: &[validate::Check],
+    schema: Option<&Path>,
+) -> std::result::Result<Vec<Validator>, Box<dyn std::error::Error>> {
+    let mut validators: Vec<Validator> = checks
+        .iter()
+        .map(|&check| Box::new(move |payload: &[u8]| check.run(payload)) as Validator)
+        .collect();
+    if let Some(path) = schema {
+        validators.push(validate::schema(path)?);
+    }
+    Ok(validators)
+}
+
+fn try_decode(input: &Path, matrix: bool) -> CommandResult {
+    let (waveform, file_rate) = wav::read_f32(input)?;
+    let defaults = f32_parameters(Some(file_rate));
+
+    let combinations = if matrix {
+        matrix::combinations(file_rate, defaults.soundMarkerThreshold)
+    } else {
+        vec![Combination {
+            sample_rate: file_rate,
+            marker_threshold: defaults.soundMarkerThreshold,
+            protocols: ProtocolSet::All,
+        }]
+    };
+
+    let mut attempts = Vec::with_capacity(combinations.len());
+    for combination in combinations {
+        let mut params = defaults;
+        combination.apply(&mut params);
+
+        let result = combination
+            .protocols
+            .restrict()
+            .and_then(|_guard| GgWave::new(params))
+            .and_then(|ggwave| ggwave.decode(&waveform))
+            .map_err(|e| e.to_string());
+        attempts.push(Attempt {
+            combination,
+            result,
+        });
+    }
+
+    Ok(Output::Tried(attempts))
+}
+
+fn scan_recording(
+    input: &Path,
+    export_markers: Option<MarkerFormat>,
+    output: Option<&Path>,
+) -> CommandResult {
+    let (waveform, sample_rate) = wav::read_f32(input)?;
+    let detections = scan::scan(
+        f32_parameters(Some(sample_rate)),
+        &wav::to_samples(&waveform),
+    )?;
+
+    let rate = sample_rate as f32;
+    let markers: Vec<Marker> = detections
+        .iter()
+        .map(|d| Marker {
+            start: d.segment.start_secs(rate),
+            end: d.segment.end_secs(rate),
+            label: preview::inline(&d.payload),
+        })
+        .collect();
+
+    let Some(format) = export_markers else {
+        return Ok(Output::Scanned {
+            markers,
+            export: None,
+        });
+    };
+
+    let source = input
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let rendered = markers::render(format, &source, &markers);
+    let sink = match output {
+        Some(path) => {
+            std::fs::write(path, &rendered)?;
+            Sink::File(path.to_path_buf())
+        }
+        None => Sink::Stdout,
+    };
+    Ok(Output::Scanned {
+        markers,
+        export: Some((rendered, sink)),
+    })
+}
+
+fn split(input: &Path, out_dir: &Path) -> CommandResult {
+    let (waveform, sample_rate) = wav::read_f32(input)?;
+    let samples = wav::to_samples(&waveform);
+
+    let detections = scan::scan(f32_parameters(Some(sample_rate)), &samples)?;
+    let manifest = split::write_parts(input, &samples, sample_rate, &detections, out_dir)?;
+
+    Ok(Output::Split {
+        parts: manifest
+            .parts
+            .into_iter()
+            .zip(detections.into_iter().map(|d| d.payload))
+            .collect(),
+        manifest: out_dir.join(split::MANIFEST),
+    })
+}
+
+fn history(store: &Path, query: Query) -> CommandResult {
+    Ok(Output::History(storage::open(store)?.query(&query)?))
+}
+
+fn history_stats(store: &Path, query: Query, top: usize) -> CommandResult {
+    let records = storage::open(store)?.query(&query)?;
+    Ok(Output::Stats(Stats::compute(&records, top)))
+}
+
+fn onboard(
+    credentials: &WifiCredentials,
+    key: Option<&DeviceKey>,
+    output: &Path,
+    protocol: Protocol,
+    volume: i32,
+    repeat: usize,
+) -> CommandResult {
+    /// Silence between frames, so each one is decoded on its own.
+    const GAP_SECS: f32 = 0.5;
+
+    let params = f32_parameters(None);
+    let ggwave = GgWave::new(params)?;
+    let delivery = match key {
+        Some(key) => Delivery::sealed(credentials, key)?,
+        None => Delivery::new(credentials)?,
+    };
+    let frames = delivery.frames();
+
+    let gap = vec![0.0; (params.sampleRateOut * GAP_SECS) as usize];
+    let mut sequence = Vec::new();
+    for frame in frames {
+        let waveform = ggwave.encode(frame, protocol.into(), volume)?;
+        sequence.extend(wav::to_samples(&waveform));
+        sequence.extend_from_slice(&gap);
+    }
+    let samples = sequence.repeat(repeat.max(1));
+
+    wav::write_f32(
+        output,
+        &wav::to_bytes(&samples),
+        params.sampleRateOut as u32,
+    )?;
+
+    Ok(Output::Onboarded {
+        frames: frames.len(),
+        repeat: repeat.max(1),
+        path: output.to_path_buf(),
+        cleartext: key.is_none()
+            && credentials.psk.is_some()
+            && credentials.security != onboarding::Security::Open,
+    })
+}
+
+fn conformance(vectors: &Path, generate_audio: bool) -> CommandResult {
+    let suite = conformance::load(vectors)?;
+    let base = vectors.parent().unwrap_or(Path::new("."));
+
+    if generate_audio {
+        let mut written = Vec::with_capacity(suite.audio.len());
+        for vector in &suite.audio {
+            conformance::generate_audio(vector, base)?;
+            written.push(base.join(&vector.wav));
+        }
+        return Ok(Output::Generated(written));
+    }
+
+    let wire = suite
+        .vectors
+        .iter()
+        .map(|v| (v.name.clone(), conformance::check_wire(v)));
+    let audio = suite
+        .audio
+        .iter()
+        .map(|v| (v.name.clone(), conformance::check_audio(v, base)));
+    Ok(Output::Conformance(wire.chain(audio).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::WavReader;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn test_protocol_aliases() {
+        for alias in [
+            "ultrasound-fast",
+            "ultrasound.fast",
+            "u-fast",
+            "[U] Fast",
+            "[U1]",
+        ] {
+            assert!(matches!(
+                parse_protocol(alias),
+                Ok(Protocol::UltrasoundFast)
+            ));
+        }
+        assert!(matches!(
+            parse_protocol("audible"),
+            Ok(Protocol::AudibleNormal)
+        ));
+        assert!(parse_protocol("custom.0").is_err());
+        assert!(matches!(parse_protocol("loud"), Err(e) if e.contains("audible-fast")));
+    }
+
+    fn temp_wav_path() -> PathBuf {
+        let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut path = std::env::temp_dir();
+        path.push(format!("ggwave_cli_test_{}_{}.wav", std::process::id(), id));
+        path
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn test_encode_decode_wav_roundtrip() {
+        let message = "hello";
+        let wav_path = temp_wav_path();
+
+        // Encode
+        encode(
+            &message,
+            &wav_path,
+            25,
+            Protocol::AudibleFast,
+            false,
+            None,
+            false,
+        )
+        .expect("encode failed");
+
+        // Verify file exists
+        assert!(wav_path.exists(), "WAV file should exist");
+
+        // Decode by reading the file and checking output
+        let mut reader = WavReader::open(&wav_path).expect("open wav failed");
+        let spec = reader.spec();
+        assert_eq!(spec.channels, 1);
+        assert_eq!(spec.bits_per_sample, 32);
+
+        let waveform: Vec<u8> = reader
+            .samples::<f32>()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .expect("read samples failed")
+            .into_iter()
+            .flat_map(|s| s.to_ne_bytes())
+            .collect();
+
+        let mut params = default_parameters();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        params.sampleRateInp = spec.sample_rate as f32;
+
+        let ggwave = GgWave::new(params).expect("ggwave init failed");
+        let decoded = ggwave.decode(&waveform).expect("decode failed");
+        let decoded = decoded.expect("no payload decoded");
+
+        assert_eq!(decoded, b"hello");
+
+        // Cleanup
+        let _ = std::fs::remove_file(&wav_path);
+    }
+
+    #[test]
+    fn test_read_rejects_stereo_and_8_bit_wav() {
+        let wav_path = temp_wav_path();
+        let write = |channels, bits_per_sample| {
+            let spec = hound::WavSpec {
+                channels,
+                sample_rate: 48000,
+                bits_per_sample,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut writer = hound::WavWriter::create(&wav_path, spec).unwrap();
+            for _ in 0..channels {
+                writer.write_sample(0i8).unwrap();
+            }
+            writer.finalize().unwrap();
+        };
+
+        write(2, 16);
+        let err = wav::read_f32(&wav_path).unwrap_err().to_string();
+        assert_eq!(err, tr(Msg::NotMono(2)));
+
+        write(1, 8);
+        let err = wav::read_f32(&wav_path).unwrap_err().to_string();
+        assert_eq!(err, tr(Msg::UnsupportedSampleFormat(&"Int 8bit")));
+
+        let _ = std::fs::remove_file(&wav_path);
+    }
+
+    #[test]
+    fn test_tx_report_log_appends_lines() {
+        let log = temp_wav_path().with_extension("jsonl");
+        let report = audio::TxReport::measure(
+            &[0.0, 0.5],
+            48000,
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000),
+        );
+        append_tx_report(&log, Path::new("out.wav"), &report).unwrap();
+        append_tx_report(&log, Path::new("out.wav"), &report).unwrap();
+
+        let contents = std::fs::read_to_string(&log).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["protocol"], "audible.fast");
+        assert_eq!(lines[0]["samples"], 2);
+        assert_eq!(lines[0]["started_at"], 1_700_000_000.0);
+        assert_eq!(lines[0]["peak_level"], 0.5);
+        let _ = std::fs::remove_file(&log);
+    }
+
+    #[test]
+    fn test_run_reports_missing_audio_support() {
+        #[cfg(not(feature = "cpal"))]
+        assert!(matches!(
+            run(Command::Listen),
+            Err(Error::NoAudioSupport("listen"))
+        ));
+        assert!(matches!(
+            run(Command::Decode {
+                input: PathBuf::from("/nonexistent/path.wav"),
+                raw_output: None,
+                store: None,
+                checks: Vec::new(),
+                schema: None,
+            }),
+            Err(Error::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_fails_loudly_on_mismatch() {
+        let wav_path = temp_wav_path();
+        let silence = vec![0u8; 4 * 48000];
+        wav::write_f32(&wav_path, &silence, 48000).unwrap();
+
+        let err = verify_file(&wav_path, b"hello").unwrap_err().to_string();
+        assert!(err.contains("verification failed"), "{err}");
+        assert!(err.contains("hello"), "{err}");
+        let _ = std::fs::remove_file(&wav_path);
+    }
+
+    #[test]
+    fn test_decode_nonexistent_file() {
+        let result = decode(&PathBuf::from("/nonexistent/path.wav"), None, None, &[]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_decode_reports_clipping_before_a_quiet_tail() {
+        let wav_path = temp_wav_path();
+        let sample_rate = 48000;
+        let half = sample_rate as usize / 2;
+        // Clips for half a second in the middle, then a second of silence.
+        let audio = [vec![0.0; half], vec![1.0; half], vec![0.0; 2 * half]].concat();
+        wav::write_f32(&wav_path, &wav::to_bytes(&audio), sample_rate).unwrap();
+
+        let output = decode(&wav_path, None, None, &[]).expect("decode failed");
+        let _ = std::fs::remove_file(&wav_path);
+        assert!(matches!(output, Output::Decoded { clipping: true, .. }));
+    }
+}
@@ -1,254 +1,284 @@
-use std::path::PathBuf;
+use std::io::Write;
 
-use clap::{Parser, Subcommand, ValueEnum};
-use ggwave_rs::{default_parameters, GgWave, ProtocolId, SampleFormat};
-use hound::{SampleFormat as HoundSampleFormat, WavReader, WavSpec, WavWriter};
+use clap::Parser;
+use ggwave_cli::i18n::{self, tr, Lang, Msg};
+use ggwave_cli::meter::Warning;
+use ggwave_cli::{conformance, preview, storage, Command, Error, Output, Sink};
 
 #[derive(Parser)]
 #[command(name = "ggwave", about = "Encode/decode data via audio waveforms")]
 struct Cli {
+    /// Language for messages (defaults to the LANG environment variable)
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
     #[command(subcommand)]
     command: Command,
 }
 
-#[derive(Subcommand)]
-enum Command {
-    /// Encode a message into a WAV file
-    Encode {
-        /// Message to encode
-        message: String,
-        /// Output WAV file path
-        output: PathBuf,
-        /// Volume (0-100)
-        #[arg(short, long, default_value = "25")]
-        volume: i32,
-        /// Protocol to use
-        #[arg(short, long, default_value = "audible-fast")]
-        protocol: Protocol,
-    },
-    /// Decode a message from a WAV file
-    Decode {
-        /// Input WAV file path
-        input: PathBuf,
-    },
-}
+/// Exit code of commands that need sound device support this build lacks.
+const EXIT_NO_AUDIO: i32 = 3;
 
-#[derive(Clone, Copy, ValueEnum)]
-enum Protocol {
-    AudibleNormal,
-    AudibleFast,
-    AudibleFastest,
-    UltrasoundNormal,
-    UltrasoundFast,
-    UltrasoundFastest,
-    DtNormal,
-    DtFast,
-    DtFastest,
-    MtNormal,
-    MtFast,
-    MtFastest,
-}
+fn main() {
+    let cli = Cli::parse();
+    if let Some(lang) = cli.lang {
+        i18n::set_lang(lang);
+    }
 
-impl From<Protocol> for ProtocolId {
-    fn from(p: Protocol) -> Self {
-        match p {
-            Protocol::AudibleNormal => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
-            Protocol::AudibleFast => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
-            Protocol::AudibleFastest => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST,
-            Protocol::UltrasoundNormal => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL,
-            Protocol::UltrasoundFast => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST,
-            Protocol::UltrasoundFastest => ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST,
-            Protocol::DtNormal => ProtocolId::GGWAVE_PROTOCOL_DT_NORMAL,
-            Protocol::DtFast => ProtocolId::GGWAVE_PROTOCOL_DT_FAST,
-            Protocol::DtFastest => ProtocolId::GGWAVE_PROTOCOL_DT_FASTEST,
-            Protocol::MtNormal => ProtocolId::GGWAVE_PROTOCOL_MT_NORMAL,
-            Protocol::MtFast => ProtocolId::GGWAVE_PROTOCOL_MT_FAST,
-            Protocol::MtFastest => ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST,
+    let result = ggwave_cli::run(cli.command).and_then(|output| Ok(print(&output)?));
+    match result {
+        Ok(()) => {}
+        Err(Error::NoAudioSupport(command)) => {
+            eprintln!("{}", tr(Msg::NoAudioSupport(command)));
+            std::process::exit(EXIT_NO_AUDIO);
+        }
+        Err(Error::Failed(e)) => {
+            eprintln!("{}", tr(Msg::Error(&e)));
+            std::process::exit(1);
         }
     }
 }
 
-fn main() {
-    let cli = Cli::parse();
-
-    match cli.command {
-        Command::Encode {
-            message,
-            output,
-            volume,
-            protocol,
+/// Presents a command's output the way the CLI always has.
+fn print(output: &Output) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        Output::Encoded {
+            bytes,
+            path,
+            samples,
+            sample_rate,
+            levels,
+            verified,
         } => {
-            if let Err(e) = encode(&message, &output, volume, protocol) {
-                eprintln!("Error: {e}");
-                std::process::exit(1);
+            println!(
+                "{}",
+                tr(Msg::Encoded {
+                    bytes: *bytes,
+                    path,
+                    samples: *samples,
+                    sample_rate: *sample_rate,
+                })
+            );
+            if let Some(levels) = levels {
+                println!(
+                    "{}",
+                    tr(Msg::Levels {
+                        peak_dbfs: levels.peak_dbfs(),
+                        rms_dbfs: levels.rms_dbfs(),
+                    })
+                );
+                match levels.warning() {
+                    Some(Warning::Clips) => eprintln!("{}", tr(Msg::ClipWarning)),
+                    Some(Warning::LowHeadroom) => eprintln!("{}", tr(Msg::HeadroomWarning)),
+                    None => {}
+                }
+            }
+            if *verified {
+                println!(
+                    "{}",
+                    tr(Msg::Verified {
+                        path,
+                        bytes: *bytes,
+                    })
+                );
             }
         }
-        Command::Decode { input } => {
-            if let Err(e) = decode(&input) {
-                eprintln!("Error: {e}");
-                std::process::exit(1);
+        Output::Verified { path, bytes } => println!(
+            "{}",
+            tr(Msg::Verified {
+                path,
+                bytes: *bytes,
+            })
+        ),
+        Output::Embedded {
+            bytes,
+            path,
+            at_secs,
+        } => println!(
+            "{}",
+            tr(Msg::Embedded {
+                bytes: *bytes,
+                path,
+                at_secs: *at_secs,
+            })
+        ),
+        Output::Rendered {
+            messages,
+            path,
+            secs,
+        } => println!(
+            "{}",
+            tr(Msg::Rendered {
+                messages: *messages,
+                path,
+                secs: *secs,
+            })
+        ),
+        Output::Planned(plan) => println!("{}", tr(Msg::Plan(plan))),
+        Output::Decoded {
+            payload,
+            clipping,
+            raw_output,
+        } => {
+            if *clipping {
+                eprintln!("{}", tr(Msg::InputClipping));
+            }
+            match (payload, raw_output) {
+                (Some(payload), Some(Sink::Stdout)) => {
+                    let mut stdout = std::io::stdout().lock();
+                    stdout.write_all(payload)?;
+                    stdout.flush()?;
+                }
+                (Some(_), Some(Sink::File(_))) => {}
+                (Some(payload), None) => println!("{}", preview::render(payload)),
+                (None, Some(_)) => eprintln!("{}", tr(Msg::NoPayload)),
+                (None, None) => println!("{}", tr(Msg::NoPayload)),
             }
         }
-    }
-}
-
-fn encode(
-    message: &str,
-    output: &PathBuf,
-    volume: i32,
-    protocol: Protocol,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut params = default_parameters();
-    params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-    params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-
-    let ggwave = GgWave::new(params)?;
-    let waveform = ggwave.encode(message.as_bytes(), protocol.into(), volume)?;
-
-    let sample_rate = params.sampleRateOut as u32;
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate,
-        bits_per_sample: 32,
-        sample_format: HoundSampleFormat::Float,
-    };
-
-    let mut writer = WavWriter::create(output, spec)?;
-
-    // Convert raw bytes to f32 samples and write
-    for chunk in waveform.chunks_exact(4) {
-        let sample = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
-        writer.write_sample(sample)?;
-    }
-    writer.finalize()?;
-
-    println!(
-        "Encoded {} bytes into {} ({} samples, {} Hz)",
-        message.len(),
-        output.display(),
-        waveform.len() / 4,
-        sample_rate
-    );
-
-    Ok(())
-}
-
-fn decode(input: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let mut reader = WavReader::open(input)?;
-    let spec = reader.spec();
-
-    if spec.channels != 1 {
-        return Err(format!("expected mono audio, got {} channels", spec.channels).into());
-    }
-
-    // Read samples and convert to raw F32 bytes
-    let waveform: Vec<u8> = match (spec.sample_format, spec.bits_per_sample) {
-        (HoundSampleFormat::Float, 32) => reader
-            .samples::<f32>()
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .flat_map(|s| s.to_le_bytes())
-            .collect(),
-        (HoundSampleFormat::Int, 16) => reader
-            .samples::<i16>()
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .map(|s| s as f32 / i16::MAX as f32)
-            .flat_map(|s| s.to_le_bytes())
-            .collect(),
-        (HoundSampleFormat::Int, 32) => reader
-            .samples::<i32>()
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .map(|s| s as f32 / i32::MAX as f32)
-            .flat_map(|s| s.to_le_bytes())
-            .collect(),
-        _ => {
-            return Err(format!(
-                "unsupported sample format: {:?} {}bit",
-                spec.sample_format, spec.bits_per_sample
-            )
-            .into())
+        Output::Tried(attempts) => {
+            println!("{}", tr(Msg::TryHeader));
+            let mut succeeded = 0;
+            for attempt in attempts {
+                let result = match &attempt.result {
+                    Ok(Some(payload)) => {
+                        succeeded += 1;
+                        preview::inline(payload)
+                    }
+                    Ok(None) => "-".to_string(),
+                    Err(e) => e.clone(),
+                };
+                println!(
+                    "{:>8}  {:>9.1}  {:<10}  {result}",
+                    attempt.combination.sample_rate,
+                    attempt.combination.marker_threshold,
+                    attempt.combination.protocols.name()
+                );
+            }
+            println!(
+                "{}",
+                tr(Msg::TrySummary {
+                    succeeded,
+                    total: attempts.len(),
+                })
+            );
         }
-    };
-
-    let mut params = default_parameters();
-    params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-    params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-    params.sampleRateInp = spec.sample_rate as f32;
-
-    let ggwave = GgWave::new(params)?;
+        Output::Scanned { markers, export } => match export {
+            None => {
+                for marker in markers {
+                    println!(
+                        "{:>8.2}s  {:>8.2}s  {}",
+                        marker.start, marker.end, marker.label
+                    );
+                }
+            }
+            Some((rendered, Sink::Stdout)) => print!("{rendered}"),
+            Some((_, Sink::File(path))) => eprintln!("{}", tr(Msg::Wrote(path))),
+        },
+        Output::Split { parts, manifest } => {
+            for (part, payload) in parts {
+                println!(
+                    "{}  {:>8.2}s  {:>8.2}s  {}",
+                    part.file,
+                    part.start_secs,
+                    part.end_secs,
+                    preview::inline(payload)
+                );
+            }
+            println!("{}", tr(Msg::Wrote(manifest)));
+        }
+        Output::History(records) => {
+            for record in records {
+                let payload = match &record.payload {
+                    Some(payload) => preview::inline(payload),
+                    None => tr(Msg::NoPayloadShort),
+                };
+                println!(
+                    "{}  {}  {payload}",
+                    storage::format_timestamp(record.received_at),
+                    record.source
+                );
+            }
+        }
+        Output::Stats(stats) => {
+            println!(
+                "{}",
+                tr(Msg::StatsTotal {
+                    messages: stats.total.messages,
+                    failures: stats.total.failures,
+                })
+            );
+            println!("{}", tr(Msg::StatsSenders(stats.senders.len())));
+
+            println!("\n{}", tr(Msg::StatsHourly));
+            for (hour, bucket) in &stats.hourly {
+                println!(
+                    "  {}  {:>6}  {:>5.1}%",
+                    storage::format_timestamp(*hour),
+                    bucket.messages,
+                    bucket.failure_ratio() * 100.0
+                );
+            }
 
-    match ggwave.decode(&waveform)? {
-        Some(payload) => {
-            let text = String::from_utf8_lossy(&payload);
-            println!("{text}");
+            if !stats.top_payloads.is_empty() {
+                println!("\n{}", tr(Msg::StatsTopPayloads));
+                for (payload, count) in &stats.top_payloads {
+                    println!("  {count:>6}  {}", preview::inline(payload));
+                }
+            }
+        }
+        Output::Onboarded {
+            frames,
+            repeat,
+            path,
+            cleartext,
+        } => {
+            if *cleartext {
+                eprintln!("{}", tr(Msg::OnboardingCleartext));
+            }
+            println!(
+                "{}",
+                tr(Msg::Onboarding {
+                    frames: *frames,
+                    repeat: *repeat,
+                    path,
+                })
+            );
+        }
+        Output::Conformance(results) => {
+            let mut failed = 0;
+            for (name, outcome) in results {
+                match outcome {
+                    conformance::Outcome::Pass => println!("ok      {name}"),
+                    conformance::Outcome::Fail(reason) => {
+                        failed += 1;
+                        println!("FAILED  {name}: {reason}");
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(tr(Msg::ConformanceFailed(failed)).into());
+            }
         }
-        None => {
-            println!("No payload decoded.");
+        // stdout carries the responses, so the summary goes to stderr.
+        Output::Served(summary) => eprintln!(
+            "{}",
+            tr(Msg::Served {
+                requests: summary.requests,
+                failed: summary.failed,
+            })
+        ),
+        Output::Sent { bytes, secs } => println!(
+            "{}",
+            tr(Msg::Sent {
+                bytes: *bytes,
+                secs: *secs,
+            })
+        ),
+        Output::Listened { payloads } => eprintln!("{}", tr(Msg::Listened(*payloads))),
+        Output::Generated(paths) => {
+            for path in paths {
+                println!("{}", tr(Msg::Wrote(path)));
+            }
         }
     }
-
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-
-    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
-
-    fn temp_wav_path() -> PathBuf {
-        let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
-        let mut path = std::env::temp_dir();
-        path.push(format!("ggwave_cli_test_{}_{}.wav", std::process::id(), id));
-        path
-    }
-
-    #[test]
-    fn test_encode_decode_wav_roundtrip() {
-        let message = "hello";
-        let wav_path = temp_wav_path();
-
-        // Encode
-        encode(&message, &wav_path, 25, Protocol::AudibleFast).expect("encode failed");
-
-        // Verify file exists
-        assert!(wav_path.exists(), "WAV file should exist");
-
-        // Decode by reading the file and checking output
-        let mut reader = WavReader::open(&wav_path).expect("open wav failed");
-        let spec = reader.spec();
-        assert_eq!(spec.channels, 1);
-        assert_eq!(spec.bits_per_sample, 32);
-
-        let waveform: Vec<u8> = reader
-            .samples::<f32>()
-            .collect::<Result<Vec<_>, _>>()
-            .expect("read samples failed")
-            .into_iter()
-            .flat_map(|s| s.to_le_bytes())
-            .collect();
-
-        let mut params = default_parameters();
-        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32;
-        params.sampleRateInp = spec.sample_rate as f32;
-
-        let ggwave = GgWave::new(params).expect("ggwave init failed");
-        let decoded = ggwave.decode(&waveform).expect("decode failed");
-        let decoded = decoded.expect("no payload decoded");
-
-        assert_eq!(decoded, b"hello");
-
-        // Cleanup
-        let _ = std::fs::remove_file(&wav_path);
-    }
-
-    #[test]
-    fn test_decode_nonexistent_file() {
-        let result = decode(&PathBuf::from("/nonexistent/path.wav"));
-        assert!(result.is_err());
-    }
-}
@@ -0,0 +1,136 @@
+//! Marker files locating decoded messages in a recording, for audio and video
+//! editors.
+
+use std::fmt::Write;
+
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MarkerFormat {
+    /// SubRip subtitles
+    Srt,
+    /// CD cue sheet
+    Cue,
+    /// Audacity label track
+    Audacity,
+}
+
+/// A labelled time range, in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub start: f64,
+    pub end: f64,
+    pub label: String,
+}
+
+/// Renders `markers` for a recording whose file name is `source`.
+pub fn render(format: MarkerFormat, source: &str, markers: &[Marker]) -> String {
+    let mut out = String::new();
+    match format {
+        MarkerFormat::Srt => {
+            for (i, marker) in markers.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "{}\n{} --> {}\n{}\n",
+                    i + 1,
+                    srt_time(marker.start),
+                    srt_time(marker.end),
+                    marker.label
+                );
+            }
+        }
+        MarkerFormat::Cue => {
+            let _ = writeln!(out, "FILE \"{}\" WAVE", cue_escape(source));
+            for (i, marker) in markers.iter().enumerate() {
+                let _ = writeln!(out, "  TRACK {:02} AUDIO", i + 1);
+                let _ = writeln!(out, "    TITLE \"{}\"", cue_escape(&marker.label));
+                let _ = writeln!(out, "    INDEX 01 {}", cue_time(marker.start));
+            }
+        }
+        MarkerFormat::Audacity => {
+            for marker in markers {
+                let _ = writeln!(
+                    out,
+                    "{:.6}\t{:.6}\t{}",
+                    marker.start, marker.end, marker.label
+                );
+            }
+        }
+    }
+    out
+}
+
+/// `HH:MM:SS,mmm`
+fn srt_time(secs: f64) -> String {
+    let millis = (secs * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// `MM:SS:FF` with 75 frames per second.
+fn cue_time(secs: f64) -> String {
+    let frames = (secs * 75.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / 75 / 60,
+        frames / 75 % 60,
+        frames % 75
+    )
+}
+
+/// Cue sheets have no escape sequences, so double quotes are replaced.
+fn cue_escape(text: &str) -> String {
+    text.replace('"', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers() -> Vec<Marker> {
+        vec![
+            Marker {
+                start: 1.5,
+                end: 3.25,
+                label: "hello".into(),
+            },
+            Marker {
+                start: 3725.0,
+                end: 3726.0,
+                label: "say \"hi\"".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_srt() {
+        assert_eq!(
+            render(MarkerFormat::Srt, "in.wav", &markers()),
+            "1\n00:00:01,500 --> 00:00:03,250\nhello\n\n\
+             2\n01:02:05,000 --> 01:02:06,000\nsay \"hi\"\n\n"
+        );
+    }
+
+    #[test]
+    fn test_cue() {
+        assert_eq!(
+            render(MarkerFormat::Cue, "in.wav", &markers()),
+            "FILE \"in.wav\" WAVE\n\
+             \x20 TRACK 01 AUDIO\n    TITLE \"hello\"\n    INDEX 01 00:01:38\n\
+             \x20 TRACK 02 AUDIO\n    TITLE \"say 'hi'\"\n    INDEX 01 62:05:00\n"
+        );
+    }
+
+    #[test]
+    fn test_audacity() {
+        assert_eq!(
+            render(MarkerFormat::Audacity, "in.wav", &markers()[..1]),
+            "1.500000\t3.250000\thello\n"
+        );
+    }
+}
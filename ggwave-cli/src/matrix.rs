@@ -0,0 +1,182 @@
+//! Parameter combinations tried by `ggwave try --matrix`.
+
+use ggwave_rs::toggles::RxProtocolGuard;
+use ggwave_rs::{Error, Parameters, ProtocolId};
+
+/// Input sample rates worth trying besides the one in the WAV header, which is
+/// sometimes wrong after resampling or re-encoding.
+const SAMPLE_RATES: [u32; 6] = [8000, 16000, 22050, 32000, 44100, 48000];
+
+const MARKER_THRESHOLDS: [f32; 4] = [2.0, 3.0, 4.0, 5.0];
+
+const AUDIBLE: [ProtocolId; 3] = [
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST,
+];
+const ULTRASOUND: [ProtocolId; 3] = [
+    ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL,
+    ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST,
+    ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST,
+];
+const DT: [ProtocolId; 3] = [
+    ProtocolId::GGWAVE_PROTOCOL_DT_NORMAL,
+    ProtocolId::GGWAVE_PROTOCOL_DT_FAST,
+    ProtocolId::GGWAVE_PROTOCOL_DT_FASTEST,
+];
+const MT: [ProtocolId; 3] = [
+    ProtocolId::GGWAVE_PROTOCOL_MT_NORMAL,
+    ProtocolId::GGWAVE_PROTOCOL_MT_FAST,
+    ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolSet {
+    All,
+    Audible,
+    Ultrasound,
+    Dt,
+    Mt,
+}
+
+impl ProtocolSet {
+    pub const ALL: [ProtocolSet; 5] = [
+        ProtocolSet::All,
+        ProtocolSet::Audible,
+        ProtocolSet::Ultrasound,
+        ProtocolSet::Dt,
+        ProtocolSet::Mt,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ProtocolSet::All => "all",
+            ProtocolSet::Audible => "audible",
+            ProtocolSet::Ultrasound => "ultrasound",
+            ProtocolSet::Dt => "dt",
+            ProtocolSet::Mt => "mt",
+        }
+    }
+
+    fn contains(self, protocol: ProtocolId) -> bool {
+        match self {
+            ProtocolSet::All => true,
+            ProtocolSet::Audible => AUDIBLE.contains(&protocol),
+            ProtocolSet::Ultrasound => ULTRASOUND.contains(&protocol),
+            ProtocolSet::Dt => DT.contains(&protocol),
+            ProtocolSet::Mt => MT.contains(&protocol),
+        }
+    }
+
+    /// Disables the standard protocols outside this set for reception in
+    /// instances created while the guard lives. `All` leaves the toggles
+    /// alone, so it also works in `strict` builds.
+    pub fn restrict(self) -> Result<Option<RxProtocolGuard>, Error> {
+        if self == ProtocolSet::All {
+            return Ok(None);
+        }
+        let excluded: Vec<ProtocolId> = ProtocolId::all()
+            .filter(|&p| !p.is_custom() && !self.contains(p))
+            .collect();
+        RxProtocolGuard::new(&excluded, false).map(Some)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Combination {
+    pub sample_rate: u32,
+    pub marker_threshold: f32,
+    pub protocols: ProtocolSet,
+}
+
+impl Combination {
+    pub fn apply(&self, params: &mut Parameters) {
+        params.sampleRateInp = self.sample_rate as f32;
+        params.soundMarkerThreshold = self.marker_threshold;
+    }
+}
+
+/// Every combination to try, starting with the file's own sample rate and the
+/// default marker threshold so the plain `decode` setup comes first.
+pub fn combinations(file_rate: u32, default_threshold: f32) -> Vec<Combination> {
+    let mut rates = vec![file_rate];
+    rates.extend(SAMPLE_RATES.iter().filter(|&&r| r != file_rate));
+    let mut thresholds = vec![default_threshold];
+    thresholds.extend(
+        MARKER_THRESHOLDS
+            .iter()
+            .filter(|&&t| t != default_threshold),
+    );
+
+    let mut combinations = Vec::new();
+    for &sample_rate in &rates {
+        for &marker_threshold in &thresholds {
+            for protocols in ProtocolSet::ALL {
+                combinations.push(Combination {
+                    sample_rate,
+                    marker_threshold,
+                    protocols,
+                });
+            }
+        }
+    }
+    combinations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combinations_start_with_defaults() {
+        let combinations = combinations(48000, 3.0);
+        assert_eq!(
+            combinations[0],
+            Combination {
+                sample_rate: 48000,
+                marker_threshold: 3.0,
+                protocols: ProtocolSet::All,
+            }
+        );
+        // 48000 and 3.0 are already in the tables and are not repeated.
+        assert_eq!(combinations.len(), 6 * 4 * 5);
+    }
+
+    #[test]
+    fn test_combinations_include_unusual_rate() {
+        let combinations = combinations(11025, 2.5);
+        assert_eq!(combinations.len(), 7 * 5 * 5);
+        assert_eq!(combinations[0].sample_rate, 11025);
+        assert_eq!(combinations[0].marker_threshold, 2.5);
+    }
+
+    #[test]
+    fn test_protocol_sets_are_disjoint() {
+        for protocol in ProtocolId::all().filter(|p| !p.is_custom()) {
+            let sets = ProtocolSet::ALL[1..]
+                .iter()
+                .filter(|set| set.contains(protocol))
+                .count();
+            assert_eq!(sets, 1);
+        }
+    }
+
+    #[test]
+    fn test_restrict_puts_toggles_back() {
+        use ggwave_rs::toggles::ProtocolToggles;
+
+        let before = ProtocolToggles::snapshot();
+        assert!(ProtocolSet::All.restrict().unwrap().is_none());
+        if ggwave_rs::is_strict() {
+            assert!(ProtocolSet::Audible.restrict().is_err());
+            return;
+        }
+        {
+            let _guard = ProtocolSet::Audible.restrict().unwrap();
+            let during = ProtocolToggles::snapshot();
+            assert!(during.rx_enabled(AUDIBLE[0]));
+            assert!(!during.rx_enabled(ULTRASOUND[0]));
+        }
+        assert_eq!(ProtocolToggles::snapshot(), before);
+    }
+}
@@ -0,0 +1,86 @@
+/// Peak above which mixing the waveform with other audio is likely to clip.
+const HEADROOM_PEAK: f32 = 0.891; // -1 dBFS
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// Samples reach full scale and will clip when converted to 16-bit.
+    Clips,
+    /// Less than 1 dB of headroom is left for mixing.
+    LowHeadroom,
+}
+
+impl Levels {
+    pub fn measure(samples: &[f32]) -> Self {
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let rms = if samples.is_empty() {
+            0.0
+        } else {
+            let sum: f64 = samples.iter().map(|&s| s as f64 * s as f64).sum();
+            (sum / samples.len() as f64).sqrt() as f32
+        };
+        Self { peak, rms }
+    }
+
+    pub fn peak_dbfs(&self) -> f32 {
+        to_dbfs(self.peak)
+    }
+
+    pub fn rms_dbfs(&self) -> f32 {
+        to_dbfs(self.rms)
+    }
+
+    pub fn warning(&self) -> Option<Warning> {
+        if self.peak >= 1.0 {
+            Some(Warning::Clips)
+        } else if self.peak > HEADROOM_PEAK {
+            Some(Warning::LowHeadroom)
+        } else {
+            None
+        }
+    }
+}
+
+fn to_dbfs(level: f32) -> f32 {
+    if level > 0.0 {
+        20.0 * level.log10()
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measure_levels() {
+        let levels = Levels::measure(&[0.5, -0.5, 0.5, -0.5]);
+        assert_eq!(levels.peak, 0.5);
+        assert!((levels.rms - 0.5).abs() < 1e-6);
+        assert!((levels.peak_dbfs() + 6.0206).abs() < 1e-3);
+        assert_eq!(levels.warning(), None);
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert_eq!(Levels::measure(&[1.0]).warning(), Some(Warning::Clips));
+        assert_eq!(Levels::measure(&[-1.2]).warning(), Some(Warning::Clips));
+        assert_eq!(
+            Levels::measure(&[0.95]).warning(),
+            Some(Warning::LowHeadroom)
+        );
+    }
+
+    #[test]
+    fn test_silence() {
+        let levels = Levels::measure(&[]);
+        assert_eq!(levels.peak_dbfs(), f32::NEG_INFINITY);
+        assert_eq!(levels.warning(), None);
+    }
+}
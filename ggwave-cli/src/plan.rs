@@ -0,0 +1,110 @@
+//! Estimates for a planned transmission: how long it takes, which band it
+//! occupies and how much of it is overhead.
+//!
+//! The band comes from the protocol's metadata and the duration from the
+//! library's encoded size, which is cheap and exact.
+
+use std::error::Error;
+use std::time::Duration;
+
+use ggwave_rs::wire::Frame;
+use ggwave_rs::{GgWave, ProtocolId};
+
+use crate::f32_parameters;
+
+/// Tones above this are inaudible to most adults.
+const AUDIBLE_LIMIT_HZ: f32 = 15_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audibility {
+    /// Clearly audible chirping.
+    Audible,
+    /// Above most adults' hearing; children and pets may still hear it, and
+    /// many phone speakers and microphones roll off in this range.
+    NearUltrasonic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub duration: Duration,
+    /// Lowest and highest tone frequency in Hz.
+    pub band: (f32, f32),
+    pub audibility: Audibility,
+    /// Message bytes as given.
+    pub message_len: usize,
+    /// Wire format header bytes, when the message is sent as a frame.
+    pub framing: usize,
+    /// Reed-Solomon bytes ggwave adds to the (framed) payload.
+    pub error_correction: usize,
+}
+
+impl Plan {
+    /// Share of the transmitted bytes that isn't message.
+    pub fn overhead_ratio(&self) -> f32 {
+        let overhead = self.framing + self.error_correction;
+        overhead as f32 / (self.message_len + overhead) as f32
+    }
+}
+
+/// Plans sending `message`, optionally wrapped in a wire format frame.
+pub fn plan(message: &[u8], protocol: ProtocolId, framed: bool) -> Result<Plan, Box<dyn Error>> {
+    let params = f32_parameters(None);
+    let band = protocol
+        .freq_range_hz(&params)
+        .ok_or("custom protocols have no known band")?;
+    let framing = if framed {
+        Frame::message(message).overhead()
+    } else {
+        0
+    };
+    let payload_len = message.len() + framing;
+
+    let duration = GgWave::new(params)?.tx_duration(payload_len, protocol)?;
+    Ok(Plan {
+        duration,
+        band,
+        audibility: audibility(band),
+        message_len: message.len(),
+        framing,
+        error_correction: ecc_len(payload_len),
+    })
+}
+
+pub fn audibility((low, _): (f32, f32)) -> Audibility {
+    if low >= AUDIBLE_LIMIT_HZ {
+        Audibility::NearUltrasonic
+    } else {
+        Audibility::Audible
+    }
+}
+
+/// Error-correction bytes upstream adds to a payload of `len` bytes.
+fn ecc_len(len: usize) -> usize {
+    if len < 4 {
+        2
+    } else {
+        (2 * (len / 5)).max(4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audibility() {
+        assert_eq!(audibility((1875.0, 6328.125)), Audibility::Audible);
+        assert_eq!(
+            audibility((15_000.0, 19_453.125)),
+            Audibility::NearUltrasonic
+        );
+    }
+
+    #[test]
+    fn test_ecc_len() {
+        assert_eq!(ecc_len(1), 2);
+        assert_eq!(ecc_len(4), 4);
+        assert_eq!(ecc_len(25), 10);
+        assert_eq!(ecc_len(140), 56);
+    }
+}
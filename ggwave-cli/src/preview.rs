@@ -0,0 +1,92 @@
+/// Renders a decoded payload for display on a terminal.
+///
+/// Valid UTF-8 without control characters is shown as-is (so non-Latin text
+/// survives intact); anything else is shown as a hex dump with an ASCII gutter.
+pub fn render(payload: &[u8]) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(text) if is_printable(text) => text.to_string(),
+        _ => hex_dump(payload),
+    }
+}
+
+/// Single-line variant of [`render`]: binary payloads are shown as plain hex.
+pub fn inline(payload: &[u8]) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(text) if !text.chars().any(char::is_control) => text.to_string(),
+        _ => format!("0x{}", crate::hex::encode(payload)),
+    }
+}
+
+fn is_printable(text: &str) -> bool {
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// Formats bytes in the style of `hexdump -C`.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, line) in bytes.chunks(16).enumerate() {
+        out.push_str(&format!("{:08x} ", i * 16));
+        for j in 0..16 {
+            if j == 8 {
+                out.push(' ');
+            }
+            match line.get(j) {
+                Some(b) => out.push_str(&format!(" {b:02x}")),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("  |");
+        out.extend(line.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    out.push_str(&format!("{:08x}", bytes.len()));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_utf8_text() {
+        assert_eq!(render("hello".as_bytes()), "hello");
+        assert_eq!(render("привет, 世界".as_bytes()), "привет, 世界");
+    }
+
+    #[test]
+    fn test_render_binary_as_hex() {
+        let rendered = render(&[0x00, 0x01, b'a', 0xff]);
+        assert_eq!(
+            rendered,
+            "00000000  00 01 61 ff                                       |..a.|\n00000004"
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(inline(b"hi there"), "hi there");
+        assert_eq!(inline(b"two\nlines"), "0x74776f0a6c696e6573");
+        assert_eq!(inline(&[0xff]), "0xff");
+    }
+
+    #[test]
+    fn test_hex_dump_multiline() {
+        let bytes: Vec<u8> = (0x41..0x41 + 18).collect();
+        let dump = hex_dump(&bytes);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|"
+        );
+        assert!(lines[1].ends_with("|QR|"));
+        assert_eq!(lines[2], "00000012");
+    }
+}
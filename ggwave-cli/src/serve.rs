@@ -0,0 +1,283 @@
+//! `ggwave serve --stdin-jsonl`: many encode/decode requests in one process.
+//!
+//! Each input line is a request, answered by one output line in the same
+//! order:
+//!
+//! ```text
+//! {"id": 1, "op": "encode", "message": "hi", "output": "out/1.wav", "protocol": "u-fast", "volume": 25}
+//! {"id": 1, "ok": true, "samples": 71680}
+//! {"id": 2, "op": "decode", "input": "out/1.wav"}
+//! {"id": 2, "ok": true, "text": "hi", "payload": "6869"}
+//! {"id": 3, "op": "decode", "input": "missing.wav"}
+//! {"id": 3, "ok": false, "error": "No such file or directory (os error 2)"}
+//! ```
+//!
+//! `id` is optional and echoed back as given. The encoder, and a decoder
+//! for each input sample rate, are created on first use and kept for the
+//! rest of the session, so a pipeline handling thousands of files pays their
+//! initialization cost once. After each file the decoder is fed silence
+//! until it's listening again, so a transmission the file ends in the middle
+//! of can't run into the next file; a decoder that can't be reset that way
+//! is dropped instead.
+
+use std::error::Error;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use ggwave_rs::{GgWave, ProtocolId};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{f32_parameters, hex, wav};
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    #[serde(flatten)]
+    op: Op,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+enum Op {
+    Encode {
+        message: String,
+        output: PathBuf,
+        protocol: Option<String>,
+        volume: Option<i32>,
+    },
+    Decode {
+        input: PathBuf,
+    },
+}
+
+/// Requests handled and how many of them failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub requests: usize,
+    pub failed: usize,
+}
+
+/// Decoders kept warm, most recently used last. With the encoder they stay
+/// below the library's 4-instance cap.
+const MAX_DECODERS: usize = 2;
+
+#[derive(Default)]
+struct Server {
+    encoder: Option<GgWave>,
+    /// By input sample rate.
+    decoders: Vec<(u32, GgWave)>,
+}
+
+impl Server {
+    fn handle(&mut self, op: Op) -> Result<Value, Box<dyn Error>> {
+        match op {
+            Op::Encode {
+                message,
+                output,
+                protocol,
+                volume,
+            } => {
+                let protocol = match protocol {
+                    Some(name) => name
+                        .parse::<ProtocolId>()
+                        .map_err(|_| format!("unknown protocol '{name}'"))?,
+                    None => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+                };
+                let params = f32_parameters(None);
+                let encoder = match &mut self.encoder {
+                    Some(encoder) => encoder,
+                    encoder => encoder.insert(GgWave::new(params)?),
+                };
+                let waveform =
+                    encoder.encode(message.as_bytes(), protocol, volume.unwrap_or(25))?;
+                wav::write_f32(&output, &waveform, params.sampleRateOut as u32)?;
+                Ok(json!({ "samples": waveform.len() / 4 }))
+            }
+            Op::Decode { input } => {
+                let (waveform, sample_rate) = wav::read_f32(&input)?;
+                let decoder = self.decoder(sample_rate)?;
+                let decoded = decoder.decode(&waveform);
+                if decoded.is_err() || !flush(decoder).unwrap_or(false) {
+                    self.decoders.pop();
+                }
+                match decoded? {
+                    Some(payload) => Ok(json!({
+                        "text": std::str::from_utf8(&payload).ok(),
+                        "payload": hex::encode(&payload),
+                    })),
+                    None => Ok(json!({ "text": null, "payload": null })),
+                }
+            }
+        }
+    }
+}
+
+impl Server {
+    fn decoder(&mut self, sample_rate: u32) -> Result<&GgWave, Box<dyn Error>> {
+        let decoder = match self
+            .decoders
+            .iter()
+            .position(|(rate, _)| *rate == sample_rate)
+        {
+            Some(i) => self.decoders.remove(i).1,
+            None => {
+                if self.decoders.len() == MAX_DECODERS {
+                    self.decoders.remove(0);
+                }
+                GgWave::new(f32_parameters(Some(sample_rate)))?
+            }
+        };
+        self.decoders.push((sample_rate, decoder));
+        Ok(&self.decoders[self.decoders.len() - 1].1)
+    }
+}
+
+/// Longest silence fed to a decoder to end a transmission in progress; well
+/// past the longest one ggwave sends.
+const MAX_FLUSH_SECS: f32 = 30.0;
+
+/// Feeds F32 silence until `decoder` is listening again. `false` if it's
+/// still receiving, or the library can't report reception state.
+fn flush(decoder: &GgWave) -> Result<bool, ggwave_rs::Error> {
+    let params = decoder.parameters();
+    let frame_samples = params.samplesPerFrame as f32 * params.sampleRateInp / params.sampleRate;
+    let mut fed = 0.0;
+    loop {
+        // The decoder gives up on a missing end marker only after the
+        // longest possible transmission, so this may take a few rounds.
+        let frames = decoder.rx_duration_frames();
+        if frames <= 0 || fed >= MAX_FLUSH_SECS * params.sampleRateInp {
+            return Ok(frames == 0);
+        }
+        let samples = ((frames + 2) as f32 * frame_samples).ceil() as usize;
+        decoder.decode(&vec![0; samples * 4])?;
+        fed += samples as f32;
+    }
+}
+
+/// Answers requests from `input` on `output` until `input` ends.
+pub fn serve(input: impl BufRead, mut output: impl Write) -> Result<Summary, Box<dyn Error>> {
+    let mut server = Server::default();
+    let mut summary = Summary::default();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        summary.requests += 1;
+
+        let (id, result) = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => (request.id, server.handle(request.op)),
+            Err(e) => (Value::Null, Err(e.into())),
+        };
+        let mut response = match result {
+            Ok(Value::Object(fields)) => {
+                let mut response = json!({ "ok": true });
+                response.as_object_mut().unwrap().extend(fields);
+                response
+            }
+            Ok(_) => unreachable!("handlers return objects"),
+            Err(e) => {
+                summary.failed += 1;
+                json!({ "ok": false, "error": e.to_string() })
+            }
+        };
+        if !id.is_null() {
+            response["id"] = id;
+        }
+        writeln!(output, "{response}")?;
+        output.flush()?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requests() {
+        let request: Request = serde_json::from_str(
+            r#"{"id": 7, "op": "encode", "message": "hi", "output": "a.wav", "protocol": "u-fast"}"#,
+        )
+        .unwrap();
+        assert_eq!(request.id, json!(7));
+        assert!(matches!(request.op, Op::Encode { volume: None, .. }));
+
+        let request: Request =
+            serde_json::from_str(r#"{"op": "decode", "input": "a.wav"}"#).unwrap();
+        assert!(request.id.is_null());
+        assert!(matches!(request.op, Op::Decode { .. }));
+
+        assert!(serde_json::from_str::<Request>(r#"{"op": "play"}"#).is_err());
+    }
+
+    #[test]
+    fn test_errors_are_answered_in_order() {
+        let input = concat!(
+            "{\"id\": \"a\", \"op\": \"decode\", \"input\": \"/nonexistent/a.wav\"}\n",
+            "\n",
+            "not json\n",
+            "{\"id\": 3, \"op\": \"encode\", \"message\": \"x\", \"output\": \"x.wav\", \"protocol\": \"loud\"}\n",
+        );
+        let mut output = Vec::new();
+        let summary = serve(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                requests: 3,
+                failed: 3
+            }
+        );
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], "a");
+        assert_eq!(responses[0]["ok"], false);
+        assert!(responses[1].get("id").is_none());
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["error"], "unknown protocol 'loud'");
+    }
+
+    #[test]
+    fn test_instances_are_reused_between_files() {
+        let dir = std::env::temp_dir().join(format!("ggwave_serve_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let encode = |message: &str| Op::Encode {
+            message: message.into(),
+            output: dir.join(format!("{message}.wav")),
+            protocol: None,
+            volume: None,
+        };
+        let decode = |name: &str| Op::Decode {
+            input: dir.join(format!("{name}.wav")),
+        };
+
+        let mut server = Server::default();
+        server.handle(encode("one")).unwrap();
+        let encoder = server.encoder.as_ref().unwrap().as_raw();
+        server.handle(encode("two")).unwrap();
+        assert_eq!(server.encoder.as_ref().unwrap().as_raw(), encoder);
+
+        assert_eq!(server.handle(decode("one")).unwrap()["text"], "one");
+        let decoder = server.decoders[0].1.as_raw();
+        // A file cut off mid-transmission doesn't spill into the next one.
+        let (waveform, rate) = wav::read_f32(&dir.join("two.wav")).unwrap();
+        let cut = &waveform[..waveform.len() / 8 * 4];
+        wav::write_f32(&dir.join("cut.wav"), cut, rate).unwrap();
+        assert_eq!(server.handle(decode("cut")).unwrap()["text"], Value::Null);
+        assert_eq!(server.handle(decode("one")).unwrap()["text"], "one");
+
+        assert_eq!(server.decoders.len(), 1);
+        if ggwave_rs::backend_capabilities().rx_duration_frames {
+            assert_eq!(server.decoders[0].1.as_raw(), decoder);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
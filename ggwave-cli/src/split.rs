@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use ggwave_rs::scan::Detection;
+use serde::Serialize;
+
+use crate::{hex, wav};
+
+pub const MANIFEST: &str = "manifest.json";
+
+/// Index of the parts cut from a recording, written next to them.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub source: String,
+    pub sample_rate: u32,
+    pub parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Part {
+    pub file: String,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    /// Payload as text, when it is valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub payload: String,
+}
+
+impl Part {
+    pub fn new(index: usize, detection: &Detection, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+        Self {
+            file: format!("part-{:03}.wav", index + 1),
+            start_secs: detection.segment.start_secs(rate),
+            end_secs: detection.segment.end_secs(rate),
+            text: String::from_utf8(detection.payload.clone()).ok(),
+            payload: hex::encode(&detection.payload),
+        }
+    }
+}
+
+/// Writes one WAV per detection plus the manifest into `out_dir`.
+pub fn write_parts(
+    source: &Path,
+    samples: &[f32],
+    sample_rate: u32,
+    detections: &[Detection],
+    out_dir: &Path,
+) -> Result<Manifest, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut parts = Vec::with_capacity(detections.len());
+    for (index, detection) in detections.iter().enumerate() {
+        let part = Part::new(index, detection, sample_rate);
+        let segment = &samples[detection.segment.start..detection.segment.end];
+        wav::write_f32(
+            &out_dir.join(&part.file),
+            &wav::to_bytes(segment),
+            sample_rate,
+        )?;
+        parts.push(part);
+    }
+
+    let manifest = Manifest {
+        source: source.display().to_string(),
+        sample_rate,
+        parts,
+    };
+    std::fs::write(
+        out_dir.join(MANIFEST),
+        serde_json::to_string_pretty(&manifest)? + "\n",
+    )?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ggwave_rs::scan::Segment;
+
+    #[test]
+    fn test_write_parts() {
+        let dir = std::env::temp_dir().join(format!("ggwave_split_{}", std::process::id()));
+        let samples = vec![0.25f32; 4800];
+        let detections = [
+            Detection {
+                segment: Segment { start: 0, end: 480 },
+                payload: b"one".to_vec(),
+            },
+            Detection {
+                segment: Segment {
+                    start: 2400,
+                    end: 4800,
+                },
+                payload: vec![0xff],
+            },
+        ];
+
+        let manifest = write_parts(Path::new("in.wav"), &samples, 4800, &detections, &dir).unwrap();
+        assert_eq!(manifest.parts.len(), 2);
+        assert_eq!(manifest.parts[0].text.as_deref(), Some("one"));
+        assert_eq!(manifest.parts[1].text, None);
+        assert_eq!(manifest.parts[1].payload, "ff");
+        assert_eq!(manifest.parts[1].start_secs, 0.5);
+
+        let (part, rate) = wav::read_f32(&dir.join("part-002.wav")).unwrap();
+        assert_eq!(rate, 4800);
+        assert_eq!(part.len(), 2400 * 4);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join(MANIFEST)).unwrap()).unwrap();
+        assert_eq!(json["parts"][0]["file"], "part-001.wav");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
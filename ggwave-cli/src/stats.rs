@@ -0,0 +1,126 @@
+//! Aggregate statistics over stored decode results.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ggwave_rs::wire::Frame;
+
+use crate::storage::Record;
+
+const HOUR: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bucket {
+    pub messages: usize,
+    pub failures: usize,
+}
+
+impl Bucket {
+    /// Fraction of decode attempts in this bucket that yielded no payload.
+    pub fn failure_ratio(&self) -> f64 {
+        if self.messages == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.messages as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub total: Bucket,
+    /// Per-hour buckets keyed by the Unix time the hour starts at.
+    pub hourly: BTreeMap<u64, Bucket>,
+    /// Distinct source addresses seen in addressed wire frames.
+    pub senders: BTreeSet<u8>,
+    /// Most frequent payloads, most common first.
+    pub top_payloads: Vec<(Vec<u8>, usize)>,
+}
+
+impl Stats {
+    /// Computes statistics over `records`, keeping the `top` most common payloads.
+    pub fn compute(records: &[Record], top: usize) -> Self {
+        let mut stats = Stats::default();
+        let mut counts: HashMap<&[u8], usize> = HashMap::new();
+
+        for record in records {
+            let hour = record.received_at - record.received_at % HOUR;
+            let bucket = stats.hourly.entry(hour).or_default();
+            bucket.messages += 1;
+            stats.total.messages += 1;
+
+            let Some(payload) = &record.payload else {
+                bucket.failures += 1;
+                stats.total.failures += 1;
+                continue;
+            };
+            *counts.entry(payload).or_default() += 1;
+            if let Some(address) = Frame::decode(payload).ok().and_then(|f| f.address) {
+                stats.senders.insert(address.source);
+            }
+        }
+
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        // Ties are broken by payload so the output is stable.
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        stats.top_payloads = counts
+            .into_iter()
+            .take(top)
+            .map(|(payload, count)| (payload.to_vec(), count))
+            .collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(received_at: u64, payload: Option<Vec<u8>>) -> Record {
+        Record {
+            received_at,
+            source: "test.wav".into(),
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_compute() {
+        let from = |source| {
+            Frame::message(b"hi".to_vec())
+                .with_address(source, 0)
+                .encode()
+                .unwrap()
+        };
+        let records = [
+            record(10, Some(b"beacon".to_vec())),
+            record(20, None),
+            record(3700, Some(b"beacon".to_vec())),
+            record(3800, Some(from(7))),
+            record(3900, Some(from(9))),
+            record(4000, Some(from(7))),
+        ];
+
+        let stats = Stats::compute(&records, 2);
+        assert_eq!(
+            stats.total,
+            Bucket {
+                messages: 6,
+                failures: 1
+            }
+        );
+        assert_eq!(stats.hourly.len(), 2);
+        assert_eq!(stats.hourly[&0].failure_ratio(), 0.5);
+        assert_eq!(stats.hourly[&3600].failure_ratio(), 0.0);
+        assert_eq!(stats.senders, BTreeSet::from([7, 9]));
+        assert_eq!(stats.top_payloads.len(), 2);
+        assert_eq!(stats.top_payloads[0].1, 2);
+        assert_eq!(stats.top_payloads[1].1, 2);
+    }
+
+    #[test]
+    fn test_empty() {
+        let stats = Stats::compute(&[], 5);
+        assert_eq!(stats.total.failure_ratio(), 0.0);
+        assert!(stats.top_payloads.is_empty());
+    }
+}
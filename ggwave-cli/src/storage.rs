@@ -0,0 +1,299 @@
+//! Durable log of received messages.
+//!
+//! Stores are selected by file extension: `.db`/`.sqlite` use SQLite (with the
+//! `sqlite` feature), anything else is an append-only JSON Lines file.
+
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::hex;
+
+/// One decode attempt. `payload` is `None` when nothing could be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Unix time in seconds.
+    pub received_at: u64,
+    pub source: String,
+    pub payload: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query {
+    /// Only records received at or after this Unix time.
+    pub since: Option<u64>,
+    /// Only the most recent `limit` matching records.
+    pub limit: Option<usize>,
+}
+
+pub trait MessageStore {
+    fn append(&mut self, record: &Record) -> Result<(), Box<dyn Error>>;
+    /// Returns matching records, oldest first.
+    fn query(&self, query: &Query) -> Result<Vec<Record>, Box<dyn Error>>;
+}
+
+impl Record {
+    pub fn now(source: impl Into<String>, payload: Option<Vec<u8>>) -> Self {
+        let received_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            received_at,
+            source: source.into(),
+            payload,
+        }
+    }
+}
+
+pub fn open(path: &Path) -> Result<Box<dyn MessageStore>, Box<dyn Error>> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension {
+        "db" | "sqlite" | "sqlite3" => open_sqlite(path),
+        _ => Ok(Box::new(JsonlStore::new(path))),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(path: &Path) -> Result<Box<dyn MessageStore>, Box<dyn Error>> {
+    Ok(Box::new(SqliteStore::open(path)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(path: &Path) -> Result<Box<dyn MessageStore>, Box<dyn Error>> {
+    Err(format!(
+        "{} looks like an SQLite database, but ggwave was built without the 'sqlite' feature",
+        path.display()
+    )
+    .into())
+}
+
+fn apply_query(mut records: Vec<Record>, query: &Query) -> Vec<Record> {
+    if let Some(since) = query.since {
+        records.retain(|r| r.received_at >= since);
+    }
+    if let Some(limit) = query.limit {
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+    }
+    records
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonlRecord {
+    received_at: u64,
+    source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+}
+
+pub struct JsonlStore {
+    path: PathBuf,
+}
+
+impl JsonlStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl MessageStore for JsonlStore {
+    fn append(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        let line = serde_json::to_string(&JsonlRecord {
+            received_at: record.received_at,
+            source: record.source.clone(),
+            payload: record.payload.as_deref().map(hex::encode),
+        })?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<Record>, Box<dyn Error>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records = Vec::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let raw: JsonlRecord = serde_json::from_str(&line)
+                .map_err(|e| format!("{}:{}: {e}", self.path.display(), n + 1))?;
+            records.push(Record {
+                received_at: raw.received_at,
+                source: raw.source,
+                payload: raw.payload.as_deref().map(hex::parse).transpose()?,
+            });
+        }
+        Ok(apply_query(records, query))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    conn: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                 id INTEGER PRIMARY KEY,
+                 received_at INTEGER NOT NULL,
+                 source TEXT NOT NULL,
+                 payload BLOB
+             );
+             CREATE INDEX IF NOT EXISTS messages_received_at ON messages (received_at);",
+        )?;
+        Ok(Self { conn })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl MessageStore for SqliteStore {
+    fn append(&mut self, record: &Record) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT INTO messages (received_at, source, payload) VALUES (?1, ?2, ?3)",
+            rusqlite::params![record.received_at as i64, record.source, record.payload],
+        )?;
+        Ok(())
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<Record>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT received_at, source, payload FROM messages
+             WHERE received_at >= ?1 ORDER BY received_at, id",
+        )?;
+        let since = query.since.unwrap_or(0) as i64;
+        let records = stmt
+            .query_map([since], |row| {
+                Ok(Record {
+                    received_at: row.get::<_, i64>(0)? as u64,
+                    source: row.get(1)?,
+                    payload: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(apply_query(records, query))
+    }
+}
+
+/// Formats a Unix timestamp as an RFC 3339 UTC date-time.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_path(extension: &str) -> PathBuf {
+        let id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "ggwave_cli_store_{}_{}.{extension}",
+            std::process::id(),
+            id
+        ))
+    }
+
+    fn sample_records() -> Vec<Record> {
+        vec![
+            Record {
+                received_at: 100,
+                source: "a.wav".into(),
+                payload: Some(b"one".to_vec()),
+            },
+            Record {
+                received_at: 200,
+                source: "b.wav".into(),
+                payload: None,
+            },
+            Record {
+                received_at: 300,
+                source: "c.wav".into(),
+                payload: Some(vec![0, 0xff]),
+            },
+        ]
+    }
+
+    fn exercise(store: &mut dyn MessageStore) {
+        let records = sample_records();
+        for record in &records {
+            store.append(record).unwrap();
+        }
+        assert_eq!(store.query(&Query::default()).unwrap(), records);
+        let recent = store
+            .query(&Query {
+                since: Some(150),
+                limit: None,
+            })
+            .unwrap();
+        assert_eq!(recent, records[1..]);
+        let last = store
+            .query(&Query {
+                since: None,
+                limit: Some(1),
+            })
+            .unwrap();
+        assert_eq!(last, records[2..]);
+    }
+
+    #[test]
+    fn test_jsonl_store() {
+        let path = temp_path("jsonl");
+        let mut store = JsonlStore::new(&path);
+        assert!(store.query(&Query::default()).unwrap().is_empty());
+        exercise(&mut store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        let path = temp_path("db");
+        let mut store = SqliteStore::open(&path).unwrap();
+        exercise(&mut store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,41 @@
+//! Parsing of positions in program material, e.g. `--at 00:01:23.500`.
+
+use std::time::Duration;
+
+/// Parses `HH:MM:SS.mmm`, `MM:SS.mmm` or plain seconds (`83.5`).
+pub fn parse(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid time '{text}', expected HH:MM:SS.mmm, MM:SS.mmm or seconds");
+
+    let mut parts = text.rsplit(':');
+    let seconds: f64 = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+        .ok_or_else(invalid)?;
+    let mut total = seconds;
+    for (part, scale) in parts.zip([60.0, 3600.0]) {
+        let value: u32 = part.parse().map_err(|_| invalid())?;
+        total += value as f64 * scale;
+    }
+    if text.matches(':').count() > 2 || (text.contains(':') && seconds >= 60.0) {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs_f64(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("00:01:23.500"), Ok(Duration::from_millis(83_500)));
+        assert_eq!(parse("1:23.5"), Ok(Duration::from_millis(83_500)));
+        assert_eq!(parse("83.5"), Ok(Duration::from_millis(83_500)));
+        assert_eq!(parse("2:00:00"), Ok(Duration::from_secs(7200)));
+        assert!(parse("1:75").is_err());
+        assert!(parse("1:2:3:4").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse("abc").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! Program-length tracks with many messages, described in a TOML or JSON
+//! timeline file:
+//!
+//! ```toml
+//! carrier = "programme.wav"  # optional; silence otherwise
+//! sample_rate = 48000        # without a carrier; defaults to 48000
+//! duration = "00:05:00"      # without a carrier; defaults to 1 s after the last message
+//!
+//! [[message]]
+//! text = "cue-1"
+//! at = "00:01:23.500"        # or seconds, e.g. 83.5
+//! protocol = "audible.fast"  # optional
+//! gain = 0.5                 # optional, defaults to 1.0
+//! volume = 25                # optional
+//! ```
+//!
+//! In JSON the messages are a `"message"` array of the same objects.
+
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use ggwave_rs::{GgWave, ProtocolId, Waveform};
+use serde::Deserialize;
+
+use crate::{f32_parameters, timecode, wav};
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+/// Silence after the last message when the timeline has no duration.
+const TAIL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Timeline {
+    /// Relative to the timeline file.
+    pub carrier: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub duration: Option<Time>,
+    #[serde(default)]
+    pub message: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    pub text: String,
+    pub at: Time,
+    pub protocol: Option<String>,
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+    #[serde(default = "default_volume")]
+    pub volume: i32,
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+fn default_volume() -> i32 {
+    25
+}
+
+/// A position given as seconds or as a timecode string.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Time {
+    Seconds(f64),
+    Timecode(String),
+}
+
+impl Time {
+    fn to_duration(&self) -> Result<Duration, String> {
+        match self {
+            Time::Seconds(secs) => {
+                Duration::try_from_secs_f64(*secs).map_err(|_| format!("invalid time {secs}"))
+            }
+            Time::Timecode(text) => timecode::parse(text),
+        }
+    }
+}
+
+/// Reads a timeline, as JSON if the file name ends in `.json` and as TOML
+/// otherwise.
+pub fn load(path: &Path) -> Result<Timeline, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    if path.extension().is_some_and(|e| e == "json") {
+        Ok(serde_json::from_str(&text)?)
+    } else {
+        Ok(toml::from_str(&text)?)
+    }
+}
+
+/// Renders the track; `base` is the directory the carrier path is relative to.
+/// Returns the samples and their sample rate.
+pub fn render(timeline: &Timeline, base: &Path) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+    let (carrier, sample_rate) = match &timeline.carrier {
+        Some(carrier) => {
+            let (audio, rate) = wav::read_f32(&base.join(carrier))?;
+            (Some(wav::to_samples(&audio)), rate)
+        }
+        None => (None, timeline.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE)),
+    };
+
+    let mut params = f32_parameters(None);
+    params.sampleRateOut = sample_rate as f32;
+    let ggwave = GgWave::new(params)?;
+
+    let mut placed = Vec::with_capacity(timeline.message.len());
+    for (i, entry) in timeline.message.iter().enumerate() {
+        let protocol = match &entry.protocol {
+            Some(id) => id
+                .parse::<ProtocolId>()
+                .map_err(|_| format!("message {}: unknown protocol '{id}'", i + 1))?,
+            None => ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+        };
+        let at = entry
+            .at
+            .to_duration()
+            .map_err(|e| format!("message {}: {e}", i + 1))?;
+        let encoded = ggwave.encode(entry.text.as_bytes(), protocol, entry.volume)?;
+        placed.push((i, at, Waveform::from_f32_bytes(&encoded, sample_rate)));
+    }
+
+    // Overlapping messages garble each other, so refuse to mix them.
+    placed.sort_by_key(|(_, at, _)| *at);
+    for pair in placed.windows(2) {
+        let (first, at, waveform) = &pair[0];
+        let (second, next_at, _) = &pair[1];
+        if *at + waveform.duration() > *next_at {
+            return Err(format!("messages {} and {} overlap", first + 1, second + 1).into());
+        }
+    }
+
+    let mut track = match carrier {
+        Some(samples) => samples,
+        None => {
+            let end = match &timeline.duration {
+                Some(duration) => duration.to_duration()?,
+                None => {
+                    placed
+                        .iter()
+                        .map(|(_, at, waveform)| *at + waveform.duration())
+                        .max()
+                        .unwrap_or_default()
+                        + TAIL
+                }
+            };
+            vec![0.0; (end.as_secs_f64() * sample_rate as f64).round() as usize]
+        }
+    };
+
+    for (i, at, waveform) in &placed {
+        let gain = timeline.message[*i].gain;
+        waveform
+            .insert_into(&mut track, *at, gain)
+            .map_err(|_| format!("message {} runs past the end of the track", i + 1))?;
+    }
+    Ok((track, sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml_and_json() {
+        let toml: Timeline = toml::from_str(
+            r#"
+            sample_rate = 44100
+            duration = "1:00"
+
+            [[message]]
+            text = "cue-1"
+            at = "00:00:05.250"
+            protocol = "ultrasound.fast"
+            gain = 0.5
+
+            [[message]]
+            text = "cue-2"
+            at = 30
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml.sample_rate, Some(44100));
+        assert_eq!(toml.message.len(), 2);
+        assert_eq!(
+            toml.message[0].at.to_duration(),
+            Ok(Duration::from_millis(5250))
+        );
+        assert_eq!(
+            toml.message[1].at.to_duration(),
+            Ok(Duration::from_secs(30))
+        );
+        assert_eq!(toml.message[1].gain, 1.0);
+        assert_eq!(toml.message[1].volume, 25);
+
+        let json: Timeline =
+            serde_json::from_str(r#"{"message": [{"text": "hi", "at": 1.5}]}"#).unwrap();
+        assert_eq!(json.message[0].text, "hi");
+        assert!(serde_json::from_str::<Timeline>(r#"{"mesage": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_empty_timeline_renders_silence() {
+        let timeline: Timeline = toml::from_str("sample_rate = 8000\nduration = 2").unwrap();
+        let (track, rate) = render(&timeline, Path::new(".")).unwrap();
+        assert_eq!(rate, 8000);
+        assert_eq!(track.len(), 16000);
+        assert!(track.iter().all(|&s| s == 0.0));
+    }
+}
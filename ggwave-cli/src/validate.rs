@@ -0,0 +1,89 @@
+//! Checks decoded payloads must pass before they are printed or stored.
+
+use std::error::Error;
+use std::path::Path;
+
+use clap::ValueEnum;
+use ggwave_rs::receiver::Validator;
+use ggwave_rs::wire::Frame;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Check {
+    /// Valid UTF-8 text
+    Utf8,
+    /// A JSON document
+    Json,
+    /// A well-formed wire format frame
+    Wire,
+}
+
+impl Check {
+    pub fn run(self, payload: &[u8]) -> Result<(), String> {
+        match self {
+            Check::Utf8 => std::str::from_utf8(payload)
+                .map(|_| ())
+                .map_err(|e| format!("not UTF-8: {e}")),
+            Check::Json => serde_json::from_slice::<serde_json::Value>(payload)
+                .map(|_| ())
+                .map_err(|e| format!("not JSON: {e}")),
+            Check::Wire => Frame::decode(payload)
+                .map(|_| ())
+                .map_err(|e| format!("not a wire frame: {e}")),
+        }
+    }
+}
+
+/// Loads a JSON Schema that payloads (parsed as JSON) must satisfy.
+#[cfg(feature = "jsonschema")]
+pub fn schema(path: &Path) -> Result<Validator, Box<dyn Error>> {
+    let schema: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let validator = jsonschema::validator_for(&schema).map_err(|e| e.to_string())?;
+    Ok(Box::new(move |payload: &[u8]| {
+        let document: serde_json::Value =
+            serde_json::from_slice(payload).map_err(|e| format!("not JSON: {e}"))?;
+        validator
+            .validate(&document)
+            .map_err(|e| format!("schema violation: {e}"))
+    }))
+}
+
+#[cfg(not(feature = "jsonschema"))]
+pub fn schema(path: &Path) -> Result<Validator, Box<dyn Error>> {
+    Err(format!(
+        "can't check {} against a JSON Schema: ggwave was built without the 'jsonschema' feature",
+        path.display()
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checks() {
+        assert!(Check::Utf8.run("héllo".as_bytes()).is_ok());
+        assert!(Check::Utf8.run(&[0xff]).is_err());
+        assert!(Check::Json.run(br#"{"a": 1}"#).is_ok());
+        assert!(Check::Json.run(b"{").is_err());
+        let frame = Frame::message(b"hi".to_vec()).encode().unwrap();
+        assert!(Check::Wire.run(&frame).is_ok());
+        assert!(Check::Wire.run(b"hi").is_err());
+    }
+
+    #[cfg(feature = "jsonschema")]
+    #[test]
+    fn test_schema() {
+        let path = std::env::temp_dir().join(format!("ggwave_schema_{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}"#,
+        )
+        .unwrap();
+        let check = schema(&path).unwrap();
+        assert!(check(br#"{"id": 3}"#).is_ok());
+        assert!(check(br#"{"id": "x"}"#).is_err());
+        assert!(check(b"plain").is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
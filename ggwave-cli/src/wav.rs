@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use ggwave_rs::Waveform;
+use hound::{SampleFormat as HoundSampleFormat, WavReader};
+
+use crate::i18n::{tr, Msg};
+
+/// Writes a raw F32 waveform (as produced by `GgWave::encode`) to a mono WAV file.
+pub fn write_f32(
+    path: &Path,
+    waveform: &[u8],
+    sample_rate: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Waveform::from_f32_bytes(waveform, sample_rate).to_wav(path)?;
+    Ok(())
+}
+
+/// Reads a mono WAV file and returns its samples as raw F32 bytes together
+/// with the file's sample rate.
+pub fn read_f32(path: &Path) -> Result<(Vec<u8>, u32), Box<dyn std::error::Error>> {
+    let spec = WavReader::open(path)?.spec();
+    if spec.channels != 1 {
+        return Err(tr(Msg::NotMono(spec.channels)).into());
+    }
+    if !matches!(
+        (spec.sample_format, spec.bits_per_sample),
+        (HoundSampleFormat::Float, 32) | (HoundSampleFormat::Int, 16 | 32)
+    ) {
+        let format = format!("{:?} {}bit", spec.sample_format, spec.bits_per_sample);
+        return Err(tr(Msg::UnsupportedSampleFormat(&format)).into());
+    }
+
+    let waveform = Waveform::from_wav(path)?;
+    Ok((waveform.to_f32_bytes(), waveform.sample_rate))
+}
+
+/// Converts raw F32 bytes (as used by `GgWave`) to samples.
+pub fn to_samples(waveform: &[u8]) -> Vec<f32> {
+    waveform
+        .chunks_exact(4)
+        .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Converts samples to raw F32 bytes.
+pub fn to_bytes(samples: &[f32]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_ne_bytes()).collect()
+}
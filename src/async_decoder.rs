@@ -0,0 +1,260 @@
+//! Decoding audio from async sources (`tokio` feature).
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::cancel::CancellationToken;
+use crate::{expect_format, Decoder, Error, Parameters, SampleFormat};
+
+/// Size of the reads from an [`AsyncRead`] source.
+const READ_CHUNK: usize = 4096;
+/// Chunks and payloads queued between the tasks.
+const QUEUE: usize = 16;
+
+enum Chunk {
+    Bytes(Vec<u8>),
+    Samples(Vec<f32>),
+}
+
+/// A [`Stream`] of payloads decoded from an async audio source.
+///
+/// The [`Decoder`] behind it can't leave the thread that created it, so it
+/// lives on a blocking task of its own; audio is handed to it and payloads
+/// come back through channels. The stream ends when the source does, when
+/// the token given to a `_cancellable` constructor is cancelled, or at the
+/// first error, which [`take_error`](Self::take_error) then returns.
+///
+/// The constructors must be called from within a Tokio runtime. Dropping
+/// the stream stops the background tasks.
+pub struct AsyncDecoder {
+    payloads: mpsc::Receiver<Result<Vec<u8>, Error>>,
+    reader: Option<JoinHandle<()>>,
+    error: Option<Error>,
+}
+
+impl AsyncDecoder {
+    /// Decodes PCM read from `reader`, in the instance's input format.
+    pub async fn from_reader<R>(parameters: Parameters, reader: R) -> Result<Self, Error>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        Self::from_reader_cancellable(parameters, reader, &CancellationToken::new()).await
+    }
+
+    /// Like [`from_reader`](Self::from_reader), but checks `token` before
+    /// each read and each chunk decoded; once it is cancelled the stream
+    /// ends as if the source had.
+    pub async fn from_reader_cancellable<R>(
+        parameters: Parameters,
+        mut reader: R,
+        token: &CancellationToken,
+    ) -> Result<Self, Error>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let (chunks, queue) = mpsc::channel(QUEUE);
+        let mut decoder = Self::start(parameters, queue, token.clone()).await?;
+        let token = token.clone();
+        decoder.reader = Some(tokio::spawn(async move {
+            let mut buf = vec![0u8; READ_CHUNK];
+            while !token.is_cancelled() {
+                let chunk = match reader.read(&mut buf).await {
+                    Ok(0) => return,
+                    Ok(n) => Ok(Chunk::Bytes(buf[..n].to_vec())),
+                    Err(_) => Err(Error::InvalidInput("reading audio for the decoder failed")),
+                };
+                let failed = chunk.is_err();
+                if chunks.send(chunk).await.is_err() || failed {
+                    return;
+                }
+            }
+        }));
+        Ok(decoder)
+    }
+
+    /// Decodes chunks of F32 samples received from `samples`, e.g. sent by
+    /// a capture callback. The instance's input format must be F32.
+    pub async fn from_channel(
+        parameters: Parameters,
+        samples: mpsc::Receiver<Vec<f32>>,
+    ) -> Result<Self, Error> {
+        Self::from_channel_cancellable(parameters, samples, &CancellationToken::new()).await
+    }
+
+    /// Like [`from_channel`](Self::from_channel), but checks `token` before
+    /// each chunk; once it is cancelled the stream ends as if the channel
+    /// had closed.
+    pub async fn from_channel_cancellable(
+        parameters: Parameters,
+        mut samples: mpsc::Receiver<Vec<f32>>,
+        token: &CancellationToken,
+    ) -> Result<Self, Error> {
+        expect_format(
+            parameters.sampleFormatInp,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            "input format is not F32",
+        )?;
+        let (chunks, queue) = mpsc::channel(QUEUE);
+        let mut decoder = Self::start(parameters, queue, token.clone()).await?;
+        let token = token.clone();
+        // Forwarded by a task rather than read by the worker, so closing
+        // the stream also lets go of the caller's channel.
+        decoder.reader = Some(tokio::spawn(async move {
+            while let Some(chunk) = samples.recv().await {
+                if token.is_cancelled() || chunks.send(Ok(Chunk::Samples(chunk))).await.is_err() {
+                    return;
+                }
+            }
+        }));
+        Ok(decoder)
+    }
+
+    /// Starts the worker and creates the decoder on it.
+    async fn start(
+        parameters: Parameters,
+        mut chunks: mpsc::Receiver<Result<Chunk, Error>>,
+        token: CancellationToken,
+    ) -> Result<Self, Error> {
+        let (payloads, results) = mpsc::channel(QUEUE);
+        let (ready, created) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let mut decoder = match Decoder::new(parameters) {
+                Ok(decoder) => {
+                    let _ = ready.send(Ok(()));
+                    decoder
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            while let Some(chunk) = chunks.blocking_recv() {
+                if token.is_cancelled() {
+                    return;
+                }
+                let decoded = chunk.and_then(|chunk| match chunk {
+                    Chunk::Bytes(bytes) => decoder.feed(&bytes),
+                    Chunk::Samples(samples) => decoder.feed_f32(&samples),
+                });
+                let failed = decoded.is_err();
+                if let Some(result) = decoded.transpose() {
+                    if payloads.blocking_send(result).is_err() || failed {
+                        return;
+                    }
+                }
+            }
+        });
+
+        created.await.map_err(|_| Error::WorkerStopped)??;
+        Ok(Self {
+            payloads: results,
+            reader: None,
+            error: None,
+        })
+    }
+
+    /// The error that ended the stream, if any.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl Stream for AsyncDecoder {
+    type Item = Vec<u8>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        match self.payloads.poll_recv(cx) {
+            Poll::Ready(Some(Ok(payload))) => Poll::Ready(Some(payload)),
+            Poll::Ready(Some(Err(e))) => {
+                self.error = Some(e);
+                self.payloads.close();
+                Poll::Ready(None)
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for AsyncDecoder {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, GgWave, ProtocolId};
+
+    async fn next(decoder: &mut AsyncDecoder) -> Option<Vec<u8>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *decoder).poll_next(cx)).await
+    }
+
+    // Each test runs on a runtime of its own, so holding the lock across
+    // awaits only blocks other tests.
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn decodes_from_a_reader_and_a_channel() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let waveform = GgWave::new(params)
+            .expect("tx init failed")
+            .encode_f32(b"async", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+        let silence = vec![0.0f32; params.samplesPerFrame as usize * 8];
+        let audio = [waveform, silence].concat();
+
+        let bytes: Vec<u8> = audio.iter().flat_map(|s| s.to_ne_bytes()).collect();
+        let mut decoder = AsyncDecoder::from_reader(params, std::io::Cursor::new(bytes))
+            .await
+            .unwrap();
+        assert_eq!(next(&mut decoder).await.as_deref(), Some(&b"async"[..]));
+        assert_eq!(next(&mut decoder).await, None);
+        assert!(decoder.take_error().is_none());
+
+        let (tx, rx) = mpsc::channel(4);
+        let mut decoder = AsyncDecoder::from_channel(params, rx).await.unwrap();
+        tokio::spawn(async move {
+            for chunk in audio.chunks(1000) {
+                tx.send(chunk.to_vec()).await.unwrap();
+            }
+        });
+        assert_eq!(next(&mut decoder).await.as_deref(), Some(&b"async"[..]));
+        assert_eq!(next(&mut decoder).await, None);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::await_holding_lock)]
+    async fn cancelled_decoder_ends() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let waveform = GgWave::new(params)
+            .expect("tx init failed")
+            .encode(b"async", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let reader = std::io::Cursor::new(waveform);
+        let mut decoder = AsyncDecoder::from_reader_cancellable(params, reader, &token)
+            .await
+            .unwrap();
+        assert_eq!(next(&mut decoder).await, None);
+        assert!(decoder.take_error().is_none());
+
+        let (tx, rx) = mpsc::channel(4);
+        let mut decoder = AsyncDecoder::from_channel_cancellable(params, rx, &token)
+            .await
+            .unwrap();
+        tx.send(vec![0.0; 1024]).await.unwrap();
+        assert_eq!(next(&mut decoder).await, None);
+    }
+}
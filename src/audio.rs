@@ -0,0 +1,363 @@
+//! Helpers for applications that play or capture ggwave audio.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::ProtocolId;
+
+#[cfg(feature = "cpal")]
+mod device;
+#[cfg(feature = "cpal")]
+pub use device::{listen, play, play_ducked, Listener};
+
+/// Lowers or pauses other application audio while a transmission plays.
+///
+/// Implemented by the host application (e.g. against its own mixer), since
+/// program audio playing at the same time can mask the payload.
+pub trait Ducker {
+    /// Called right before the transmission starts.
+    fn duck(&mut self);
+    /// Called once the transmission has finished, including on failure.
+    fn restore(&mut self);
+}
+
+/// Keeps other audio ducked for as long as the guard is alive.
+pub struct DuckGuard<'a> {
+    ducker: &'a mut dyn Ducker,
+}
+
+impl<'a> DuckGuard<'a> {
+    pub fn new(ducker: &'a mut dyn Ducker) -> Self {
+        ducker.duck();
+        Self { ducker }
+    }
+}
+
+impl Drop for DuckGuard<'_> {
+    fn drop(&mut self) {
+        self.ducker.restore();
+    }
+}
+
+/// Runs `transmit` with other audio ducked, restoring it afterwards.
+pub fn with_ducking<R>(ducker: &mut dyn Ducker, transmit: impl FnOnce() -> R) -> R {
+    let _guard = DuckGuard::new(ducker);
+    transmit()
+}
+
+/// Record of what was physically emitted for one message, e.g. for
+/// compliance logs of installations in public spaces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxReport {
+    /// When playback (or the file write) started.
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    /// Samples emitted, per channel.
+    pub samples: usize,
+    pub protocol: ProtocolId,
+    /// Absolute peak of the emitted samples, 1.0 being full scale.
+    pub peak_level: f32,
+}
+
+impl TxReport {
+    /// Describes `samples` emitted at `sample_rate` starting at `started_at`.
+    pub fn measure(
+        samples: &[f32],
+        sample_rate: u32,
+        protocol: ProtocolId,
+        started_at: SystemTime,
+    ) -> Self {
+        Self {
+            started_at,
+            duration: Duration::from_secs_f64(samples.len() as f64 / sample_rate as f64),
+            samples: samples.len(),
+            protocol,
+            peak_level: samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs())),
+        }
+    }
+}
+
+/// Hands `samples` to `emit` (the host's playback or file writer) and
+/// returns a report once it has completed. Nothing is reported if `emit`
+/// fails.
+pub fn transmit<E>(
+    samples: &[f32],
+    sample_rate: u32,
+    protocol: ProtocolId,
+    emit: impl FnOnce(&[f32]) -> Result<(), E>,
+) -> Result<TxReport, E> {
+    let started_at = SystemTime::now();
+    emit(samples)?;
+    Ok(TxReport::measure(
+        samples,
+        sample_rate,
+        protocol,
+        started_at,
+    ))
+}
+
+/// Fans one capture stream out to several independent consumers.
+///
+/// `GgWave` is `!Send`, so each consumer usually owns its own instance (with
+/// its own parameters and protocol set) on its own thread, and receives the
+/// same captured chunks through the bus instead of opening the device again.
+pub struct SampleBus<T = f32> {
+    subscribers: Vec<Sender<Arc<[T]>>>,
+}
+
+impl<T> SampleBus<T> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<Arc<[T]>> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.len()
+    }
+}
+
+impl<T: Copy> SampleBus<T> {
+    /// Sends a chunk of samples to every subscriber.
+    ///
+    /// The chunk is copied once and shared; subscribers whose receiver has
+    /// been dropped are removed.
+    pub fn publish(&mut self, samples: &[T]) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let chunk: Arc<[T]> = Arc::from(samples);
+        self.subscribers
+            .retain(|subscriber| subscriber.send(Arc::clone(&chunk)).is_ok());
+    }
+}
+
+impl<T> Default for SampleBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An interval the capture stream didn't cover, e.g. while the device was
+/// being reopened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// Clock position at which the stream stopped.
+    pub at: Duration,
+    pub len: Duration,
+}
+
+/// Monotonic timeline for captured audio that survives device restarts.
+///
+/// Timestamps are derived from sample counts rather than the wall clock, so
+/// they don't jitter with buffer delivery. When capture is restarted (possibly
+/// at a different sample rate), [`restart`](Self::restart) records how long
+/// the stream was down and the timeline continues after the gap, so
+/// timestamps from a long-running receiver stay comparable.
+#[derive(Debug, Clone)]
+pub struct SampleClock {
+    sample_rate: u32,
+    /// Position at which the current capture segment started.
+    origin: Duration,
+    /// Samples counted in the current segment.
+    samples: u64,
+    gaps: Vec<Gap>,
+}
+
+impl SampleClock {
+    pub fn new(sample_rate: u32) -> Self {
+        assert!(sample_rate > 0, "sample rate must be positive");
+        Self {
+            sample_rate,
+            origin: Duration::ZERO,
+            samples: 0,
+            gaps: Vec::new(),
+        }
+    }
+
+    /// Counts a captured chunk of `frames` samples per channel, returning the
+    /// timestamp of its first sample.
+    pub fn advance(&mut self, frames: usize) -> Duration {
+        let start = self.position();
+        self.samples += frames as u64;
+        start
+    }
+
+    /// Timestamp of the next sample to be captured.
+    pub fn position(&self) -> Duration {
+        let nanos = self.samples as u128 * 1_000_000_000 / self.sample_rate as u128;
+        self.origin + Duration::from_nanos(nanos as u64)
+    }
+
+    /// Timestamp of sample `offset` within the chunk starting at `chunk_start`.
+    pub fn at(&self, chunk_start: Duration, offset: usize) -> Duration {
+        chunk_start + Duration::from_nanos(offset as u64 * 1_000_000_000 / self.sample_rate as u64)
+    }
+
+    /// Continues the timeline after the capture stream was reopened at
+    /// `sample_rate`, `dropped` (typically measured with `Instant`) after the
+    /// last counted sample.
+    pub fn restart(&mut self, sample_rate: u32, dropped: Duration) {
+        assert!(sample_rate > 0, "sample rate must be positive");
+        let at = self.position();
+        if !dropped.is_zero() {
+            self.gaps.push(Gap { at, len: dropped });
+        }
+        self.origin = at + dropped;
+        self.samples = 0;
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Intervals lost to restarts, oldest first.
+    pub fn gaps(&self) -> &[Gap] {
+        &self.gaps
+    }
+
+    /// Total time lost to restarts.
+    pub fn dropped(&self) -> Duration {
+        self.gaps.iter().map(|gap| gap.len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<&'static str>,
+    }
+
+    impl Ducker for Recorder {
+        fn duck(&mut self) {
+            self.events.push("duck");
+        }
+
+        fn restore(&mut self) {
+            self.events.push("restore");
+        }
+    }
+
+    #[test]
+    fn ducks_around_transmission() {
+        let mut recorder = Recorder::default();
+        let result = with_ducking(&mut recorder, || 42);
+        assert_eq!(result, 42);
+        assert_eq!(recorder.events, ["duck", "restore"]);
+    }
+
+    #[test]
+    fn restores_on_failure() {
+        let mut recorder = Recorder::default();
+        let result: Result<(), &str> = with_ducking(&mut recorder, || Err("playback failed"));
+        assert!(result.is_err());
+        assert_eq!(recorder.events, ["duck", "restore"]);
+    }
+
+    #[test]
+    fn transmit_reports_what_was_emitted() {
+        let samples = [0.0, 0.5, -0.75, 0.25];
+        let mut played = Vec::new();
+        let report = transmit(
+            &samples,
+            8,
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+            |s| -> Result<(), ()> {
+                played.extend_from_slice(s);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(played, samples);
+        assert_eq!(report.samples, 4);
+        assert_eq!(report.duration, Duration::from_millis(500));
+        assert_eq!(report.peak_level, 0.75);
+        assert_eq!(report.protocol, ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST);
+
+        let failed = transmit(&samples, 8, report.protocol, |_| Err("device busy"));
+        assert_eq!(failed, Err("device busy"));
+    }
+
+    #[test]
+    fn clock_continues_across_restarts() {
+        let mut clock = SampleClock::new(48_000);
+        assert_eq!(clock.advance(48_000), Duration::ZERO);
+        assert_eq!(clock.advance(24_000), Duration::from_secs(1));
+        assert_eq!(clock.position(), Duration::from_millis(1500));
+
+        clock.restart(44_100, Duration::from_millis(250));
+        let chunk = clock.advance(4410);
+        assert_eq!(chunk, Duration::from_millis(1750));
+        assert_eq!(clock.at(chunk, 441), Duration::from_millis(1760));
+        assert_eq!(clock.position(), Duration::from_millis(1850));
+        assert_eq!(
+            clock.gaps(),
+            [Gap {
+                at: Duration::from_millis(1500),
+                len: Duration::from_millis(250),
+            }]
+        );
+
+        clock.restart(44_100, Duration::ZERO);
+        assert_eq!(clock.gaps().len(), 1);
+        assert_eq!(clock.dropped(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn bus_delivers_every_chunk_to_every_subscriber() {
+        let mut bus = SampleBus::new();
+        let a = bus.subscribe();
+        let b = bus.subscribe();
+
+        bus.publish(&[0.1, 0.2]);
+        bus.publish(&[0.3]);
+
+        for rx in [a, b] {
+            let chunks: Vec<Vec<f32>> = rx.try_iter().map(|c| c.to_vec()).collect();
+            assert_eq!(chunks, [vec![0.1, 0.2], vec![0.3]]);
+        }
+    }
+
+    #[test]
+    fn bus_drops_disconnected_subscribers() {
+        let mut bus = SampleBus::<i16>::new();
+        let kept = bus.subscribe();
+        drop(bus.subscribe());
+        assert_eq!(bus.subscriber_count(), 2);
+
+        bus.publish(&[1, 2, 3]);
+        assert_eq!(bus.subscriber_count(), 1);
+        assert_eq!(&*kept.recv().unwrap(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn bus_feeds_consumer_threads() {
+        let mut bus = SampleBus::new();
+        let workers: Vec<_> = (0..3)
+            .map(|_| {
+                let rx = bus.subscribe();
+                std::thread::spawn(move || rx.iter().map(|c| c.len()).sum::<usize>())
+            })
+            .collect();
+
+        for _ in 0..10 {
+            bus.publish(&[0.0f32; 64]);
+        }
+        drop(bus);
+
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), 640);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Playing and capturing through the default sound devices (`cpal`
+//! feature).
+//!
+//! Devices pick their own sample rate, sample format and channel count.
+//! Instances are created at the device's rate; samples are converted to and
+//! from F32 and mixed down to, or copied across, the channels.
+//!
+//! The capture callback hands its audio to the decode thread through a
+//! [`ring`](crate::ring), so it neither allocates nor blocks.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use super::{DuckGuard, Ducker, TxReport};
+use crate::ring::{self, RtConsumer, RtProducer};
+use crate::{
+    default_parameters, Decoder, Error, GgWave, Parameters, ProtocolId, SampleFormat as Format,
+};
+
+/// Silence played after the waveform, so the device's buffers drain before
+/// the stream is closed.
+const TAIL_SECONDS: f32 = 0.2;
+
+/// Captured frames mixed down at a time, in a buffer on the callback's
+/// stack.
+const MIX_FRAMES: usize = 256;
+
+/// Encodes `payload` and plays it on the default output device, returning
+/// once it has been played, or with the error that stopped the device.
+pub fn play(payload: &[u8], protocol: ProtocolId, volume: i32) -> Result<TxReport, Error> {
+    play_with(payload, protocol, volume, None)
+}
+
+/// [`play`] with other application audio ducked from the moment the stream
+/// starts until it has finished or failed.
+pub fn play_ducked(
+    payload: &[u8],
+    protocol: ProtocolId,
+    volume: i32,
+    ducker: &mut dyn Ducker,
+) -> Result<TxReport, Error> {
+    play_with(payload, protocol, volume, Some(ducker))
+}
+
+fn play_with(
+    payload: &[u8],
+    protocol: ProtocolId,
+    volume: i32,
+    ducker: Option<&mut dyn Ducker>,
+) -> Result<TxReport, Error> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| Error::AudioDevice("no output device".into()))?;
+    let supported = device.default_output_config().map_err(device_error)?;
+    let sample_rate = supported.sample_rate();
+
+    let mut parameters = default_parameters();
+    parameters.sampleRateOut = sample_rate as f32;
+    let mut samples = GgWave::new(parameters)?.encode_f32(payload, protocol, volume)?;
+    let waveform_len = samples.len();
+    samples.resize(
+        waveform_len + (TAIL_SECONDS * sample_rate as f32) as usize,
+        0.0,
+    );
+    let samples: Arc<[f32]> = samples.into();
+
+    let config = supported.config();
+    let (done, finished) = mpsc::channel();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => output_stream::<f32>(&device, &config, samples.clone(), done),
+        SampleFormat::F64 => output_stream::<f64>(&device, &config, samples.clone(), done),
+        SampleFormat::I16 => output_stream::<i16>(&device, &config, samples.clone(), done),
+        SampleFormat::I32 => output_stream::<i32>(&device, &config, samples.clone(), done),
+        SampleFormat::U16 => output_stream::<u16>(&device, &config, samples.clone(), done),
+        SampleFormat::I8 => output_stream::<i8>(&device, &config, samples.clone(), done),
+        SampleFormat::U8 => output_stream::<u8>(&device, &config, samples.clone(), done),
+        format => return Err(unsupported(format)),
+    }?;
+
+    let _ducked = ducker.map(DuckGuard::new);
+    let started_at = SystemTime::now();
+    stream.play().map_err(device_error)?;
+    finished
+        .recv()
+        .map_err(|_| Error::AudioDevice("output stream stopped".into()))??;
+    Ok(TxReport::measure(
+        &samples[..waveform_len],
+        sample_rate,
+        protocol,
+        started_at,
+    ))
+}
+
+fn output_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    samples: Arc<[f32]>,
+    done: mpsc::Sender<Result<(), Error>>,
+) -> Result<Stream, Error>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = usize::from(config.channels);
+    let mut position = 0;
+    let failed = done.clone();
+    device
+        .build_output_stream::<T, _, _>(
+            config,
+            move |out, _| {
+                for frame in out.chunks_mut(channels) {
+                    let sample = samples.get(position).copied().unwrap_or(0.0);
+                    frame.fill(T::from_sample(sample));
+                    position += 1;
+                }
+                if position >= samples.len() {
+                    // Only the first send is received; later ones fail.
+                    let _ = done.send(Ok(()));
+                }
+            },
+            move |e| {
+                let _ = failed.send(Err(device_error(e)));
+            },
+            None,
+        )
+        .map_err(device_error)
+}
+
+/// Listens on the default input device, calling `on_payload` with every
+/// payload received, until the returned [`Listener`] is stopped or dropped.
+///
+/// `on_payload` runs on a thread of its own, not the audio callback's. A
+/// device error stops the listener; [`Listener::stop`] returns it.
+pub fn listen<F>(on_payload: F) -> Result<Listener, Error>
+where
+    F: FnMut(Vec<u8>) + Send + 'static,
+{
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| Error::AudioDevice("no input device".into()))?;
+    let supported = device.default_input_config().map_err(device_error)?;
+    let sample_rate = supported.sample_rate();
+
+    let mut parameters = default_parameters();
+    parameters.sampleRateInp = sample_rate as f32;
+    parameters.sampleFormatInp = Format::GGWAVE_SAMPLE_FORMAT_F32.into();
+    // A second of audio, far more than the decode thread lags behind.
+    let (producer, consumer) = ring::channel(sample_rate as usize);
+    let (failed, errors) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let worker = spawn_decoder(parameters, consumer, errors, stop.clone(), on_payload)?;
+
+    let config = supported.config();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => input_stream::<f32>(&device, &config, producer, failed),
+        SampleFormat::F64 => input_stream::<f64>(&device, &config, producer, failed),
+        SampleFormat::I16 => input_stream::<i16>(&device, &config, producer, failed),
+        SampleFormat::I32 => input_stream::<i32>(&device, &config, producer, failed),
+        SampleFormat::U16 => input_stream::<u16>(&device, &config, producer, failed),
+        SampleFormat::I8 => input_stream::<i8>(&device, &config, producer, failed),
+        SampleFormat::U8 => input_stream::<u8>(&device, &config, producer, failed),
+        format => Err(unsupported(format)),
+    };
+    // Dropping the listener on an error below stops the worker.
+    let mut listener = Listener {
+        stream: None,
+        stop,
+        worker: Some(worker),
+    };
+    let stream = stream?;
+    stream.play().map_err(device_error)?;
+    listener.stream = Some(stream);
+    Ok(listener)
+}
+
+/// Creates the decoder on a worker thread, which drains the ring into it
+/// every frame's duration until stopped, the device fails or decoding
+/// fails.
+fn spawn_decoder<F>(
+    parameters: Parameters,
+    mut consumer: RtConsumer,
+    errors: mpsc::Receiver<Error>,
+    stop: Arc<AtomicBool>,
+    mut on_payload: F,
+) -> Result<JoinHandle<Result<(), Error>>, Error>
+where
+    F: FnMut(Vec<u8>) + Send + 'static,
+{
+    let poll =
+        Duration::from_secs_f32(parameters.samplesPerFrame as f32 / parameters.sampleRateInp);
+    let (ready, created) = mpsc::channel();
+    let worker = thread::Builder::new()
+        .name("ggwave-listen".into())
+        .spawn(move || {
+            let mut decoder = match Decoder::new(parameters) {
+                Ok(decoder) => {
+                    let _ = ready.send(Ok(()));
+                    decoder
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return Ok(());
+                }
+            };
+            loop {
+                let stopping = stop.load(Ordering::Acquire);
+                consumer.drain_into(&mut decoder, &mut on_payload)?;
+                if let Ok(e) = errors.try_recv() {
+                    return Err(e);
+                }
+                if stopping {
+                    return Ok(());
+                }
+                thread::sleep(poll);
+            }
+        })
+        .map_err(|_| Error::WorkerStopped)?;
+    created.recv().map_err(|_| Error::WorkerStopped)??;
+    Ok(worker)
+}
+
+fn input_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut producer: RtProducer,
+    failed: mpsc::Sender<Error>,
+) -> Result<Stream, Error>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = usize::from(config.channels);
+    device
+        .build_input_stream::<T, _, _>(
+            config,
+            move |data, _| {
+                let mut mono = [0.0f32; MIX_FRAMES];
+                for frames in data.chunks(MIX_FRAMES * channels) {
+                    let len = frames.len() / channels;
+                    for (frame, out) in frames.chunks(channels).zip(&mut mono) {
+                        *out = frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>()
+                            / channels as f32;
+                    }
+                    // What doesn't fit is dropped and counted by the ring.
+                    producer.push(&mono[..len]);
+                }
+            },
+            move |e| {
+                // Fails only once the worker has stopped.
+                let _ = failed.send(device_error(e));
+            },
+            None,
+        )
+        .map_err(device_error)
+}
+
+/// Capture started by [`listen`].
+pub struct Listener {
+    stream: Option<Stream>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl Listener {
+    /// Stops capturing and waits for the payloads in flight to be handed
+    /// over. Returns the error that stopped decoding early, if any.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<(), Error> {
+        // The worker drains what the stream captured before it closed.
+        drop(self.stream.take());
+        self.stop.store(true, Ordering::Release);
+        match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::WorkerStopped),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+fn device_error(e: impl std::fmt::Display) -> Error {
+    Error::AudioDevice(e.to_string())
+}
+
+fn unsupported(format: SampleFormat) -> Error {
+    Error::AudioDevice(format!("unsupported sample format {format}"))
+}
@@ -0,0 +1,134 @@
+//! What the linked C library supports.
+//!
+//! With the `system` feature the build script inspects the installed
+//! `ggwave.h` and compiles out bindings the library doesn't have; this module
+//! reports the result so callers can degrade gracefully.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackendCapabilities {
+    /// Upstream version, if it was known at build time.
+    pub version: Option<&'static str>,
+    /// Whether the library was built from the vendored sources.
+    pub vendored: bool,
+    /// Whether the C API is the crate's own Rust implementation
+    /// (`pure-rust` feature) rather than upstream's library.
+    pub pure_rust: bool,
+    /// `ggwave_ndecode`. Without it decoding goes through `ggwave_decode`.
+    pub ndecode: bool,
+    /// `ggwave_rxDurationFrames`. Without it `rx_duration_frames` returns -1.
+    pub rx_duration_frames: bool,
+    /// `ggwave_rxProtocolSetFreqStart` and `ggwave_txProtocolSetFreqStart`.
+    pub freq_start: bool,
+}
+
+/// Capabilities of the C library this crate was built against.
+pub fn backend_capabilities() -> BackendCapabilities {
+    BackendCapabilities {
+        version: option_env!("GGWAVE_BACKEND_VERSION"),
+        vendored: !cfg!(feature = "system") && !cfg!(feature = "pure-rust"),
+        pure_rust: cfg!(feature = "pure-rust"),
+        ndecode: !cfg!(ggwave_no_ndecode),
+        rx_duration_frames: !cfg!(ggwave_no_rx_duration_frames),
+        freq_start: !cfg!(ggwave_no_freq_start),
+    }
+}
+
+/// Optional subsystems compiled into this build of the crate. Fields are
+/// added as features are, so it can't be built or matched exhaustively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    pub backend: BackendCapabilities,
+    /// Playing through sound devices (`cpal` or `rodio` feature). Without
+    /// it, audio has to go through files or the host application's own
+    /// audio stack.
+    pub audio_io: bool,
+    /// Listening on the microphone as well (`cpal` feature).
+    pub audio_capture: bool,
+    /// The C API implemented in Rust (`pure-rust` feature); the same as
+    /// `backend.pure_rust`.
+    pub pure_rust: bool,
+    /// One-time password tokens (`totp` feature).
+    pub totp: bool,
+    /// Sealed Wi-Fi onboarding (`encryption` feature).
+    pub encryption: bool,
+    /// Forwarding the C library's log to the `log` crate (`log` feature).
+    pub log: bool,
+    /// Global state changes are forbidden (`strict` feature).
+    pub strict: bool,
+    /// Calls into the C library are serialized and `GgWave` is `Send`
+    /// (`send` feature).
+    pub send: bool,
+    /// The C++ class's spectrum, amplitude and receive state (`cxx` feature).
+    pub cpp: bool,
+    /// `AsyncDecoder` on Tokio (`tokio` feature).
+    pub tokio: bool,
+    /// Runtime-agnostic `Stream`/`Sink` adapters (`futures` feature).
+    pub futures: bool,
+    /// The tokio-util PCM codec (`codec` feature).
+    pub codec: bool,
+    /// Reading and writing WAV files (`wav` feature).
+    pub wav: bool,
+    /// Decoding audio at any sample rate (`rubato` feature).
+    pub resampling: bool,
+    /// Serde support for parameters and enums (`serde` feature).
+    pub serde: bool,
+    /// Allocation-free encoding and decoding (`heapless` feature).
+    pub heapless: bool,
+    /// The lock-free capture ring (`ringbuf` feature).
+    pub ringbuf: bool,
+}
+
+/// Reports which optional subsystems this build includes, so applications
+/// can hide or explain features that were compiled out.
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        backend: backend_capabilities(),
+        audio_io: cfg!(any(feature = "cpal", feature = "rodio")),
+        audio_capture: cfg!(feature = "cpal"),
+        pure_rust: cfg!(feature = "pure-rust"),
+        totp: cfg!(feature = "totp"),
+        encryption: cfg!(feature = "encryption"),
+        log: cfg!(feature = "log"),
+        strict: cfg!(feature = "strict"),
+        send: cfg!(feature = "send"),
+        cpp: cfg!(feature = "cxx"),
+        tokio: cfg!(feature = "tokio"),
+        futures: cfg!(feature = "futures"),
+        codec: cfg!(feature = "codec"),
+        wav: cfg!(feature = "wav"),
+        resampling: cfg!(feature = "rubato"),
+        serde: cfg!(feature = "serde"),
+        heapless: cfg!(feature = "heapless"),
+        ringbuf: cfg!(feature = "ringbuf"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendored_builds_have_everything() {
+        let _serial = crate::serial();
+        let caps = backend_capabilities();
+        if caps.vendored {
+            assert!(caps.ndecode && caps.rx_duration_frames && caps.freq_start);
+        }
+    }
+
+    #[test]
+    fn capabilities_match_features() {
+        let caps = capabilities();
+        assert_eq!(caps.backend, backend_capabilities());
+        assert_eq!(
+            caps.audio_io,
+            cfg!(any(feature = "cpal", feature = "rodio"))
+        );
+        assert!(caps.audio_io || !caps.audio_capture);
+        assert_eq!(caps.pure_rust, caps.backend.pure_rust);
+        assert_eq!(caps.totp, cfg!(feature = "totp"));
+        assert_eq!(caps.wav, cfg!(feature = "wav"));
+        assert_eq!(caps.strict, crate::is_strict());
+    }
+}
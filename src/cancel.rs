@@ -0,0 +1,88 @@
+//! Cooperative cancellation of long-running operations.
+//!
+//! Operations that accept a [`CancellationToken`] check it at chunk
+//! boundaries and, once it is cancelled, stop and return what they have done
+//! so far as [`Outcome::Cancelled`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag requesting that an operation stop; clones share the flag, so
+/// one can be handed to the worker and another kept by e.g. a cancel button.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How far an operation got, in operation-specific units (e.g. segments).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// Completed share in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+/// Result of an operation that may have been cancelled part-way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<T> {
+    Complete(T),
+    Cancelled { partial: T, progress: Progress },
+}
+
+impl<T> Outcome<T> {
+    /// The full or partial result.
+    pub fn into_inner(self) -> T {
+        match self {
+            Outcome::Complete(value) => value,
+            Outcome::Cancelled { partial, .. } => partial,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Outcome::Cancelled { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let token = CancellationToken::new();
+        let worker = token.clone();
+        assert!(!worker.is_cancelled());
+        token.cancel();
+        assert!(worker.is_cancelled());
+    }
+
+    #[test]
+    fn progress_fraction() {
+        assert_eq!(Progress { done: 1, total: 4 }.fraction(), 0.25);
+        assert_eq!(Progress { done: 0, total: 0 }.fraction(), 1.0);
+    }
+}
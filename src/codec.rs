@@ -0,0 +1,111 @@
+//! A tokio-util codec over PCM byte streams (`codec` feature).
+
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec;
+
+use crate::{Decoder, Error, Parameters, ProtocolId};
+
+/// Decodes payloads from, and encodes payloads into, PCM in the instance's
+/// input and output formats.
+///
+/// Wrapped around a PCM transport with `tokio_util::codec::Framed` (or
+/// `FramedRead`/`FramedWrite`), it gives a `Stream` of decoded payloads and
+/// a `Sink` of payloads to send:
+///
+/// ```no_run
+/// use ggwave_rs::{default_parameters, GgWaveCodec, ProtocolId};
+/// use tokio_util::codec::FramedRead;
+///
+/// # fn run<T>(pipe: T) -> Result<(), ggwave_rs::Error> {
+/// let codec = GgWaveCodec::new(default_parameters(), ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)?;
+/// let payloads = FramedRead::new(pipe, codec);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Like [`GgWave`](crate::GgWave), the codec is only `Send` with the `send`
+/// feature; without it, keep the framed transport on one task.
+pub struct GgWaveCodec {
+    decoder: Decoder,
+    protocol: ProtocolId,
+    volume: i32,
+}
+
+impl GgWaveCodec {
+    /// A codec that sends with `protocol` at `volume` (0 to 100).
+    pub fn new(parameters: Parameters, protocol: ProtocolId, volume: i32) -> Result<Self, Error> {
+        Ok(Self {
+            decoder: Decoder::new(parameters)?,
+            protocol,
+            volume,
+        })
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+}
+
+impl codec::Decoder for GgWaveCodec {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, Error> {
+        // The decoder holds back partial frames, and the audio after a
+        // payload, itself; it's drained by the calls on an empty buffer that
+        // follow every payload.
+        let chunk = src.split();
+        Ok(self.decoder.feed(&chunk)?.map(Bytes::from))
+    }
+}
+
+impl<T: AsRef<[u8]>> codec::Encoder<T> for GgWaveCodec {
+    type Error = Error;
+
+    fn encode(&mut self, payload: T, dst: &mut BytesMut) -> Result<(), Error> {
+        let ggwave = self.decoder.ggwave();
+        let payload = payload.as_ref();
+        let start = dst.len();
+        dst.resize(
+            start + ggwave.encoded_size(payload.len(), self.protocol)?,
+            0,
+        );
+        let written = ggwave.encode_into(payload, self.protocol, self.volume, &mut dst[start..]);
+        // Nothing is left behind when encoding fails.
+        dst.truncate(start + *written.as_ref().unwrap_or(&0));
+        written.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+    use tokio_util::codec::{Decoder as _, Encoder as _};
+
+    #[test]
+    fn encodes_and_decodes_pcm_frames() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatInp = params.sampleFormatOut;
+        let mut codec =
+            GgWaveCodec::new(params, ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25).unwrap();
+
+        let mut pcm = BytesMut::new();
+        codec.encode(&b"one"[..], &mut pcm).unwrap();
+        codec.encode(Bytes::from_static(b"two"), &mut pcm).unwrap();
+        pcm.resize(pcm.len() + 64 * 1024, 0);
+
+        // As a framed reader would: whatever arrived, then an empty buffer
+        // after each payload.
+        let mut payloads = Vec::new();
+        for arrived in pcm.chunks(4000) {
+            let mut src = BytesMut::from(arrived);
+            while let Some(payload) = codec.decode(&mut src).unwrap() {
+                payloads.push(payload);
+            }
+            assert!(src.is_empty());
+        }
+        assert_eq!(payloads, [&b"one"[..], &b"two"[..]]);
+    }
+}
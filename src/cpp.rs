@@ -0,0 +1,180 @@
+//! The C++ `GGWave` class through a `cxx` bridge (`cxx` feature).
+//!
+//! The C API only covers encoding and decoding. The class behind it also
+//! exposes the receiver's spectrum and amplitude, its receive state, and
+//! protocol toggles that belong to one instance instead of the whole
+//! process. [`CppGgWave`] wraps those as safe methods.
+//!
+//! A `CppGgWave` is its own instance: it doesn't use the C API's instance
+//! table and doesn't count towards [`runtime::live_instances`](crate::runtime::live_instances).
+
+use cxx::UniquePtr;
+
+use crate::{
+    check_volume, require_mode, Error, OperatingMode, Parameters, ProtocolId, MAX_DATA_SIZE,
+};
+
+#[cxx::bridge(namespace = "ggwave_rs")]
+mod bridge {
+    /// [`Parameters`](crate::Parameters) with the enums as plain integers.
+    struct Params {
+        payload_length: i32,
+        sample_rate_inp: f32,
+        sample_rate_out: f32,
+        sample_rate: f32,
+        samples_per_frame: i32,
+        sound_marker_threshold: f32,
+        sample_format_inp: i32,
+        sample_format_out: i32,
+        operating_mode: i32,
+    }
+
+    unsafe extern "C++" {
+        include!("ggwave-rs/src/cpp/bridge.h");
+
+        type Instance;
+
+        fn new_instance(params: &Params) -> UniquePtr<Instance>;
+
+        fn encode(
+            self: Pin<&mut Instance>,
+            payload: &[u8],
+            protocol: i32,
+            volume: i32,
+            out: &mut Vec<u8>,
+        ) -> bool;
+        fn decode(self: Pin<&mut Instance>, waveform: &[u8], payload: &mut Vec<u8>) -> i32;
+
+        fn take_spectrum(self: Pin<&mut Instance>, out: &mut Vec<f32>) -> bool;
+        fn take_amplitude(self: Pin<&mut Instance>, out: &mut Vec<f32>) -> bool;
+
+        fn is_receiving(&self) -> bool;
+        fn is_analyzing(&self) -> bool;
+        fn frames_left_to_record(&self) -> i32;
+        fn stop_receiving(self: Pin<&mut Instance>) -> bool;
+
+        fn toggle_rx_protocol(self: Pin<&mut Instance>, protocol: i32, enabled: bool);
+        fn toggle_tx_protocol(self: Pin<&mut Instance>, protocol: i32, enabled: bool);
+    }
+}
+
+/// A `GGWave` C++ object, created and owned through the bridge.
+pub struct CppGgWave {
+    inner: UniquePtr<bridge::Instance>,
+    parameters: Parameters,
+}
+
+impl CppGgWave {
+    pub fn new(parameters: Parameters) -> Result<Self, Error> {
+        let params = bridge::Params {
+            payload_length: parameters.payloadLength,
+            sample_rate_inp: parameters.sampleRateInp,
+            sample_rate_out: parameters.sampleRateOut,
+            sample_rate: parameters.sampleRate,
+            samples_per_frame: parameters.samplesPerFrame,
+            sound_marker_threshold: parameters.soundMarkerThreshold,
+            sample_format_inp: parameters.sampleFormatInp.0,
+            sample_format_out: parameters.sampleFormatOut.0,
+            operating_mode: parameters.operatingMode,
+        };
+        let inner = bridge::new_instance(&params);
+        if inner.is_null() {
+            return Err(Error::InitFailed {
+                code: -1,
+                parameters,
+            });
+        }
+        Ok(Self { inner, parameters })
+    }
+
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    pub fn encode(
+        &mut self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Vec<u8>, Error> {
+        require_mode(&self.parameters, OperatingMode::TX)?;
+        check_volume(volume)?;
+        let mut waveform = Vec::new();
+        if !self
+            .inner
+            .pin_mut()
+            .encode(payload, protocol.into(), volume, &mut waveform)
+        {
+            return Err(Error::EncodeFailed {
+                code: 0,
+                protocol,
+                payload_len: payload.len(),
+            });
+        }
+        Ok(waveform)
+    }
+
+    /// Feeds a chunk of audio to the receiver, returning a payload once one
+    /// is complete.
+    pub fn decode(&mut self, waveform: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        require_mode(&self.parameters, OperatingMode::RX)?;
+        let mut payload = Vec::with_capacity(MAX_DATA_SIZE);
+        match self.inner.pin_mut().decode(waveform, &mut payload) {
+            code if code < 0 => Err(Error::DecodeFailed { code }),
+            0 => Ok(None),
+            _ => Ok(Some(payload)),
+        }
+    }
+
+    /// The spectrum of the last analysed frame, `samplesPerFrame` values,
+    /// or `None` if no new frame has been analysed since the last call.
+    pub fn take_spectrum(&mut self) -> Option<Vec<f32>> {
+        let mut out = Vec::new();
+        self.inner.pin_mut().take_spectrum(&mut out).then_some(out)
+    }
+
+    /// The samples of the last analysed frame, as for
+    /// [`take_spectrum`](Self::take_spectrum).
+    pub fn take_amplitude(&mut self) -> Option<Vec<f32>> {
+        let mut out = Vec::new();
+        self.inner.pin_mut().take_amplitude(&mut out).then_some(out)
+    }
+
+    /// Whether a transmission's start marker has been heard and its data
+    /// is being recorded.
+    pub fn is_receiving(&self) -> bool {
+        self.inner.is_receiving()
+    }
+
+    /// Whether a recorded transmission is being decoded.
+    pub fn is_analyzing(&self) -> bool {
+        self.inner.is_analyzing()
+    }
+
+    /// Frames still to record for the transmission being received, or 0
+    /// when not receiving.
+    pub fn frames_left_to_record(&self) -> u32 {
+        self.inner.frames_left_to_record().max(0) as u32
+    }
+
+    /// Abandons the transmission being received. Returns `false` if nothing
+    /// was being received.
+    pub fn stop_receiving(&mut self) -> bool {
+        self.inner.pin_mut().stop_receiving()
+    }
+
+    /// Enables or disables a protocol for this instance's receiver only;
+    /// unlike [`toggles`](crate::toggles), no other instance is affected.
+    pub fn set_rx_protocol_enabled(&mut self, protocol: ProtocolId, enabled: bool) {
+        self.inner
+            .pin_mut()
+            .toggle_rx_protocol(protocol.into(), enabled);
+    }
+
+    /// Enables or disables a protocol for this instance's transmitter only.
+    pub fn set_tx_protocol_enabled(&mut self, protocol: ProtocolId, enabled: bool) {
+        self.inner
+            .pin_mut()
+            .toggle_tx_protocol(protocol.into(), enabled);
+    }
+}
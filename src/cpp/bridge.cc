@@ -0,0 +1,106 @@
+#include "ggwave-rs/src/cpp/bridge.h"
+#include "ggwave-rs/src/cpp.rs.h"
+
+namespace ggwave_rs {
+
+namespace {
+
+ggwave_Parameters to_parameters(const Params & p) {
+    ggwave_Parameters parameters = GGWave::getDefaultParameters();
+    parameters.payloadLength = p.payload_length;
+    parameters.sampleRateInp = p.sample_rate_inp;
+    parameters.sampleRateOut = p.sample_rate_out;
+    parameters.sampleRate = p.sample_rate;
+    parameters.samplesPerFrame = p.samples_per_frame;
+    parameters.soundMarkerThreshold = p.sound_marker_threshold;
+    parameters.sampleFormatInp = static_cast<ggwave_SampleFormat>(p.sample_format_inp);
+    parameters.sampleFormatOut = static_cast<ggwave_SampleFormat>(p.sample_format_out);
+    parameters.operatingMode = p.operating_mode;
+    return parameters;
+}
+
+// Upstream's buffers are non-owning views; these copy the Rust side's
+// storage in and out of them.
+template <typename T>
+bool take(rust::Vec<T> & out, int32_t len, bool (GGWave::*method)(ggvector<T> &), GGWave & ggwave) {
+    out.clear();
+    out.reserve(len);
+    for (int32_t i = 0; i < len; ++i) {
+        out.push_back(T());
+    }
+    ggvector<T> view(out.data(), len);
+    if (!(ggwave.*method)(view)) {
+        out.clear();
+        return false;
+    }
+    return true;
+}
+
+} // namespace
+
+Instance::Instance(const ggwave_Parameters & parameters)
+    : m_ggwave(parameters), m_samplesPerFrame(parameters.samplesPerFrame) {}
+
+bool Instance::encode(rust::Slice<const uint8_t> payload, int32_t protocol, int32_t volume,
+                      rust::Vec<uint8_t> & out) {
+    if (!m_ggwave.init(static_cast<int>(payload.size()),
+                       reinterpret_cast<const char *>(payload.data()),
+                       static_cast<GGWave::TxProtocolId>(protocol), volume)) {
+        return false;
+    }
+    const uint32_t size = m_ggwave.encode();
+    if (size == 0) {
+        return false;
+    }
+    const auto * waveform = static_cast<const uint8_t *>(m_ggwave.txWaveform());
+    out.clear();
+    out.reserve(m_ggwave.encodeSize_bytes());
+    for (uint32_t i = 0; i < m_ggwave.encodeSize_bytes(); ++i) {
+        out.push_back(waveform[i]);
+    }
+    return true;
+}
+
+int32_t Instance::decode(rust::Slice<const uint8_t> waveform, rust::Vec<uint8_t> & payload) {
+    if (!m_ggwave.decode(waveform.data(), static_cast<uint32_t>(waveform.size()))) {
+        return -1;
+    }
+    uint8_t buffer[GGWave::kMaxDataSize];
+    GGWave::TxRxData view(buffer, GGWave::kMaxDataSize);
+    const int n = m_ggwave.rxTakeData(view);
+    payload.clear();
+    for (int i = 0; i < n; ++i) {
+        payload.push_back(buffer[i]);
+    }
+    return n;
+}
+
+bool Instance::take_spectrum(rust::Vec<float> & out) {
+    return take(out, m_samplesPerFrame, &GGWave::rxTakeSpectrum, m_ggwave);
+}
+
+bool Instance::take_amplitude(rust::Vec<float> & out) {
+    return take(out, m_samplesPerFrame, &GGWave::rxTakeAmplitude, m_ggwave);
+}
+
+bool Instance::is_receiving() const { return m_ggwave.rxReceiving(); }
+
+bool Instance::is_analyzing() const { return m_ggwave.rxAnalyzing(); }
+
+int32_t Instance::frames_left_to_record() const { return m_ggwave.rxFramesLeftToRecord(); }
+
+bool Instance::stop_receiving() { return m_ggwave.rxStopReceiving(); }
+
+void Instance::toggle_rx_protocol(int32_t protocol, bool enabled) {
+    m_ggwave.rxProtocols().toggle(static_cast<GGWave::RxProtocolId>(protocol), enabled);
+}
+
+void Instance::toggle_tx_protocol(int32_t protocol, bool enabled) {
+    m_ggwave.txProtocols().toggle(static_cast<GGWave::TxProtocolId>(protocol), enabled);
+}
+
+std::unique_ptr<Instance> new_instance(const Params & params) {
+    return std::unique_ptr<Instance>(new Instance(to_parameters(params)));
+}
+
+} // namespace ggwave_rs
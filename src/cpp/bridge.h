@@ -0,0 +1,39 @@
+// Shim between the cxx bridge in src/cpp.rs and upstream's GGWave class.
+#pragma once
+
+#include "ggwave/ggwave.h"
+#include "rust/cxx.h"
+
+#include <memory>
+
+namespace ggwave_rs {
+
+struct Params;
+
+class Instance {
+public:
+    explicit Instance(const ggwave_Parameters & parameters);
+
+    bool encode(rust::Slice<const uint8_t> payload, int32_t protocol, int32_t volume,
+                rust::Vec<uint8_t> & out);
+    int32_t decode(rust::Slice<const uint8_t> waveform, rust::Vec<uint8_t> & payload);
+
+    bool take_spectrum(rust::Vec<float> & out);
+    bool take_amplitude(rust::Vec<float> & out);
+
+    bool is_receiving() const;
+    bool is_analyzing() const;
+    int32_t frames_left_to_record() const;
+    bool stop_receiving();
+
+    void toggle_rx_protocol(int32_t protocol, bool enabled);
+    void toggle_tx_protocol(int32_t protocol, bool enabled);
+
+private:
+    GGWave m_ggwave;
+    int32_t m_samplesPerFrame;
+};
+
+std::unique_ptr<Instance> new_instance(const Params & params);
+
+} // namespace ggwave_rs
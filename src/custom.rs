@@ -0,0 +1,148 @@
+//! Custom protocol slots, `GGWAVE_PROTOCOL_CUSTOM_0` to `_9`.
+//!
+//! Upstream defines every slot like the audible normal protocol (three bytes
+//! per transmission over 96 tone bins) and leaves it disabled. Through the C
+//! API a slot can only be moved to another start frequency and enabled, which
+//! is what [`CustomProtocol`] does. Both are process-wide settings, picked up
+//! by instances created afterwards.
+
+use crate::{check_global_state, set_rx_protocol_enabled, set_tx_protocol_enabled};
+use crate::{set_rx_protocol_freq_start, set_tx_protocol_freq_start, Error, ProtocolId};
+
+/// Number of custom slots.
+pub const SLOTS: u8 = 10;
+
+/// Configures a custom protocol slot.
+///
+/// ```no_run
+/// use ggwave_rs::custom::CustomProtocol;
+///
+/// // Three bytes per transmission starting at bin 200 (about 9.4 kHz).
+/// let protocol = CustomProtocol::new(0)?.freq_start(200).register()?;
+/// # Ok::<(), ggwave_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomProtocol {
+    id: ProtocolId,
+    freq_start: Option<i32>,
+    rx: bool,
+    tx: bool,
+}
+
+impl CustomProtocol {
+    /// Slot `0..SLOTS`, enabled for reception and transmission.
+    pub fn new(slot: u8) -> Result<Self, Error> {
+        if slot >= SLOTS {
+            return Err(Error::InvalidInput("custom protocol slot must be 0 to 9"));
+        }
+        let raw = ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0.as_raw() + slot as i32;
+        Ok(Self {
+            id: ProtocolId::from_raw(raw).expect("custom slots are known protocols"),
+            freq_start: None,
+            rx: true,
+            tx: true,
+        })
+    }
+
+    /// First tone bin; see [`hz_to_bin`](crate::hz_to_bin), with the
+    /// parameters the instances are created with. Keeps
+    /// upstream's start (bin 40) when not set.
+    pub fn freq_start(mut self, bin: i32) -> Self {
+        self.freq_start = Some(bin);
+        self
+    }
+
+    pub fn rx(mut self, enabled: bool) -> Self {
+        self.rx = enabled;
+        self
+    }
+
+    pub fn tx(mut self, enabled: bool) -> Self {
+        self.tx = enabled;
+        self
+    }
+
+    pub fn id(&self) -> ProtocolId {
+        self.id
+    }
+
+    /// Applies the configuration, returning the protocol to encode with.
+    ///
+    /// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+    pub fn register(self) -> Result<ProtocolId, Error> {
+        check_global_state()?;
+        if let Some(bin) = self.freq_start {
+            if self.rx {
+                set_rx_protocol_freq_start(self.id, bin)?;
+            }
+            if self.tx {
+                set_tx_protocol_freq_start(self.id, bin)?;
+            }
+        }
+        set_rx_protocol_enabled(self.id, self.rx)?;
+        set_tx_protocol_enabled(self.id, self.tx)?;
+        Ok(self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toggles::ProtocolToggles;
+    use crate::{default_parameters, is_strict, GgWave};
+
+    #[test]
+    fn slots_map_to_custom_protocols() {
+        assert_eq!(
+            CustomProtocol::new(0).unwrap().id(),
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0
+        );
+        assert_eq!(
+            CustomProtocol::new(9).unwrap().id(),
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_9
+        );
+        assert!(CustomProtocol::new(SLOTS).is_err());
+    }
+
+    #[test]
+    fn register_validates_and_enables() {
+        let slot = CustomProtocol::new(8).unwrap();
+        if is_strict() {
+            assert!(matches!(slot.register(), Err(Error::GlobalStateForbidden)));
+            return;
+        }
+
+        assert!(slot.freq_start(417).register().is_err());
+        assert!(slot.freq_start(0).register().is_err());
+
+        let before = ProtocolToggles::snapshot();
+        let protocol = slot.tx(false).register().unwrap();
+        let toggles = ProtocolToggles::snapshot();
+        assert!(toggles.rx_enabled(protocol) && !toggles.tx_enabled(protocol));
+        before.restore().unwrap();
+    }
+
+    #[test]
+    fn moved_custom_protocol_roundtrips() {
+        let _serial = crate::serial();
+        if is_strict() {
+            return;
+        }
+        let before = ProtocolToggles::snapshot();
+        let protocol = CustomProtocol::new(7)
+            .unwrap()
+            .freq_start(200)
+            .register()
+            .unwrap();
+
+        let params = default_parameters();
+        let roundtrip = || -> Result<_, Error> {
+            let waveform = GgWave::new(params)?.encode(b"custom", protocol, 25)?;
+            GgWave::new(params)?.decode(&waveform)
+        };
+        let decoded = roundtrip();
+        before.restore().unwrap();
+        let decoded = decoded.expect("roundtrip failed");
+        assert_eq!(decoded.as_deref(), Some(&b"custom"[..]));
+    }
+}
@@ -0,0 +1,201 @@
+//! Incremental decoding of live audio.
+
+use alloc::vec::Vec;
+
+use crate::{expect_format, Error, GgWave, Parameters, SampleFormat};
+
+/// Decodes audio fed in chunks of any size.
+///
+/// The C library analyses whole frames of `samplesPerFrame` samples; the
+/// decoder holds back a partial frame until the rest of it arrives, so
+/// capture callbacks can hand over whatever their device delivers.
+pub struct Decoder {
+    ggwave: GgWave,
+    frame_bytes: usize,
+    pending: Vec<u8>,
+    /// Frames fed since the library locked onto a start marker.
+    frames_received: u32,
+}
+
+/// Reception state of a [`Decoder`], for "receiving…" indicators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxStatus {
+    /// Waiting for a start marker.
+    Listening,
+    /// Locked onto a start marker; the payload is due in about
+    /// `frames_remaining` more frames.
+    Receiving { frames_remaining: u32 },
+    /// The linked library can't report reception state; see
+    /// [`BackendCapabilities::rx_duration_frames`](crate::BackendCapabilities).
+    Unknown,
+}
+
+impl RxStatus {
+    /// From [`GgWave::rx_duration_frames`] and the frames fed since it
+    /// became nonzero.
+    fn new(duration_frames: i32, frames_received: u32) -> Self {
+        match duration_frames {
+            d if d < 0 => RxStatus::Unknown,
+            0 => RxStatus::Listening,
+            d => RxStatus::Receiving {
+                frames_remaining: (d as u32).saturating_sub(frames_received),
+            },
+        }
+    }
+}
+
+impl Decoder {
+    pub fn new(parameters: Parameters) -> Result<Self, Error> {
+        let sample_bytes = SampleFormat::try_from(parameters.sampleFormatInp)?.bytes_per_sample();
+        let samples_per_frame = usize::try_from(parameters.samplesPerFrame).unwrap_or(0);
+        if sample_bytes == 0 || samples_per_frame == 0 {
+            return Err(Error::InvalidInput(
+                "decoder needs an input format and samplesPerFrame",
+            ));
+        }
+        let frame_bytes = sample_bytes * samples_per_frame;
+        Ok(Self {
+            ggwave: GgWave::new(parameters)?,
+            frame_bytes,
+            pending: Vec::with_capacity(frame_bytes),
+            frames_received: 0,
+        })
+    }
+
+    /// Feeds captured audio in the instance's input format, returning a
+    /// payload as soon as one is complete.
+    ///
+    /// Audio after the end of a payload, or after a frame the library
+    /// returned an error for, stays buffered and is decoded on the next
+    /// call, so no message is lost when two arrive in one chunk.
+    pub fn feed(&mut self, samples: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.pending.extend_from_slice(samples);
+        let mut consumed = 0;
+        let mut decoded = Ok(None);
+        while matches!(decoded, Ok(None)) && self.pending.len() - consumed >= self.frame_bytes {
+            decoded = self
+                .ggwave
+                .decode(&self.pending[consumed..consumed + self.frame_bytes]);
+            consumed += self.frame_bytes;
+            if self.ggwave.rx_duration_frames() > 0 {
+                self.frames_received += 1;
+            } else {
+                self.frames_received = 0;
+            }
+        }
+        if let Ok(Some(_)) = decoded {
+            self.frames_received = 0;
+        }
+        self.pending.drain(..consumed);
+        decoded
+    }
+
+    /// [`feed`](Self::feed) for a decoder whose input format is F32.
+    pub fn feed_f32(&mut self, samples: &[f32]) -> Result<Option<Vec<u8>>, Error> {
+        expect_format(
+            self.ggwave.parameters().sampleFormatInp,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            "input format is not F32",
+        )?;
+        // Native-endian, as the library reads it.
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                samples.as_ptr() as *const u8,
+                core::mem::size_of_val(samples),
+            )
+        };
+        self.feed(bytes)
+    }
+
+    /// Whether a transmission is being received, as of the last whole frame.
+    pub fn status(&self) -> RxStatus {
+        RxStatus::new(self.ggwave.rx_duration_frames(), self.frames_received)
+    }
+
+    pub fn is_receiving(&self) -> bool {
+        matches!(self.status(), RxStatus::Receiving { .. })
+    }
+
+    /// Bytes held back until their frame is complete.
+    pub fn buffered(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn ggwave(&self) -> &GgWave {
+        &self.ggwave
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, ProtocolId};
+
+    #[test]
+    fn odd_sized_chunks_decode() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let waveform = GgWave::new(params)
+            .expect("tx init failed")
+            .encode(b"chunked", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+
+        let mut decoder = Decoder::new(params).unwrap();
+        let mut decoded = Vec::new();
+        let silence = vec![0u8; params.samplesPerFrame as usize * 4 * 8];
+        for chunk in waveform.chunks(1000).chain(silence.chunks(1000)) {
+            decoded.extend(decoder.feed(chunk).unwrap());
+            assert!(decoder.buffered() < params.samplesPerFrame as usize * 4);
+        }
+        assert_eq!(decoded, [b"chunked".to_vec()]);
+    }
+
+    #[test]
+    fn keeps_audio_after_a_payload() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let ggwave = GgWave::new(params).expect("tx init failed");
+        let frame_bytes = params.samplesPerFrame as usize * 4;
+        let mut audio = Vec::new();
+        for payload in [&b"first"[..], b"second"] {
+            audio.extend(
+                ggwave
+                    .encode(payload, ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+                    .expect("encode failed"),
+            );
+            audio.resize(audio.len() + 8 * frame_bytes, 0);
+        }
+
+        let mut decoder = Decoder::new(params).unwrap();
+        assert_eq!(
+            decoder.feed(&audio).unwrap().as_deref(),
+            Some(&b"first"[..])
+        );
+        assert!(decoder.buffered() > frame_bytes);
+        assert_eq!(decoder.feed(&[]).unwrap().as_deref(), Some(&b"second"[..]));
+        assert_eq!(decoder.feed(&[]).unwrap(), None);
+        assert!(decoder.buffered() < frame_bytes);
+    }
+
+    #[test]
+    fn rx_status() {
+        let _serial = crate::serial();
+        assert_eq!(RxStatus::new(-1, 0), RxStatus::Unknown);
+        assert_eq!(RxStatus::new(0, 0), RxStatus::Listening);
+        assert_eq!(
+            RxStatus::new(40, 15),
+            RxStatus::Receiving {
+                frames_remaining: 25
+            }
+        );
+        assert_eq!(
+            RxStatus::new(40, 50),
+            RxStatus::Receiving {
+                frames_remaining: 0
+            }
+        );
+
+        let decoder = Decoder::new(default_parameters()).unwrap();
+        assert!(!decoder.is_receiving());
+    }
+}
@@ -0,0 +1,187 @@
+//! Small signal-processing helpers shared by the pre-processing stages.
+
+use std::f32::consts::PI;
+
+use crate::ffi::ggwave_SampleFormat;
+
+/// In-place iterative radix-2 FFT. `re` and `im` must have the same
+/// power-of-two length. The inverse transform is scaled by `1 / len`.
+pub(crate) fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+    assert_eq!(n, im.len());
+    assert!(n.is_power_of_two(), "FFT length must be a power of two");
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f32;
+        re.iter_mut().chain(im.iter_mut()).for_each(|x| *x *= scale);
+    }
+}
+
+/// Square root of a periodic Hann window. Applied on both analysis and
+/// synthesis, frames overlapping by half add back up to unity gain.
+pub(crate) fn sqrt_hann(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| (0.5 - 0.5 * (2.0 * PI * n as f32 / len as f32).cos()).sqrt())
+        .collect()
+}
+
+/// Shifts every frequency component of `samples` by `hz` (down if negative).
+///
+/// Works on the analytic signal, so unlike mixing with a carrier it produces
+/// no mirror image. The input is zero-padded to twice its length so the
+/// circular transform doesn't wrap its ends into each other.
+pub(crate) fn freq_shift(samples: &[f32], hz: f32, sample_rate: f32) -> Vec<f32> {
+    if samples.is_empty() || hz == 0.0 {
+        return samples.to_vec();
+    }
+
+    let n = (samples.len() * 2).next_power_of_two();
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    re[..samples.len()].copy_from_slice(samples);
+
+    fft(&mut re, &mut im, false);
+    for k in 1..n / 2 {
+        re[k] *= 2.0;
+        im[k] *= 2.0;
+    }
+    re[n / 2 + 1..].fill(0.0);
+    im[n / 2 + 1..].fill(0.0);
+    fft(&mut re, &mut im, true);
+
+    let cycles_per_sample = hz as f64 / sample_rate as f64;
+    (0..samples.len())
+        .map(|i| {
+            // Reduce in f64 so the phase stays exact over long waveforms.
+            let phase = 2.0 * std::f64::consts::PI * (i as f64 * cycles_per_sample).fract();
+            let (sin, cos) = phase.sin_cos();
+            re[i] * cos as f32 - im[i] * sin as f32
+        })
+        .collect()
+}
+
+/// Converts native-endian samples in `format` to floats.
+pub(crate) fn from_bytes(bytes: &[u8], format: ggwave_SampleFormat) -> Vec<f32> {
+    use ggwave_SampleFormat as F;
+    match format {
+        F::GGWAVE_SAMPLE_FORMAT_U8 => bytes.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        F::GGWAVE_SAMPLE_FORMAT_I8 => bytes.iter().map(|&b| b as i8 as f32 / 128.0).collect(),
+        F::GGWAVE_SAMPLE_FORMAT_U16 => bytes
+            .chunks_exact(2)
+            .map(|c| (u16::from_ne_bytes([c[0], c[1]]) as f32 - 32768.0) / 32768.0)
+            .collect(),
+        F::GGWAVE_SAMPLE_FORMAT_I16 => bytes
+            .chunks_exact(2)
+            .map(|c| i16::from_ne_bytes([c[0], c[1]]) as f32 / 32768.0)
+            .collect(),
+        _ => bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    }
+}
+
+/// Converts floats to native-endian samples in `format`.
+pub(crate) fn to_bytes(samples: &[f32], format: ggwave_SampleFormat) -> Vec<u8> {
+    use ggwave_SampleFormat as F;
+    let scaled = |s: f32, max: f32| (s.clamp(-1.0, 1.0) * max).round();
+    match format {
+        F::GGWAVE_SAMPLE_FORMAT_U8 => samples
+            .iter()
+            .map(|&s| (scaled(s, 127.0) + 128.0) as u8)
+            .collect(),
+        F::GGWAVE_SAMPLE_FORMAT_I8 => samples
+            .iter()
+            .map(|&s| scaled(s, 127.0) as i8 as u8)
+            .collect(),
+        F::GGWAVE_SAMPLE_FORMAT_U16 => samples
+            .iter()
+            .flat_map(|&s| ((scaled(s, 32767.0) + 32768.0) as u16).to_ne_bytes())
+            .collect(),
+        F::GGWAVE_SAMPLE_FORMAT_I16 => samples
+            .iter()
+            .flat_map(|&s| (scaled(s, 32767.0) as i16).to_ne_bytes())
+            .collect(),
+        _ => samples.iter().flat_map(|s| s.to_ne_bytes()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_roundtrip_and_peak() {
+        let n = 64;
+        let signal: Vec<f32> = (0..n)
+            .map(|i| (2.0 * PI * 5.0 * i as f32 / n as f32).cos())
+            .collect();
+        let mut re = signal.clone();
+        let mut im = vec![0.0; n];
+
+        fft(&mut re, &mut im, false);
+        let magnitude = |k: usize| re[k].hypot(im[k]);
+        assert!((magnitude(5) - n as f32 / 2.0).abs() < 1e-3);
+        assert!(magnitude(6) < 1e-3);
+
+        fft(&mut re, &mut im, true);
+        for (a, b) in re.iter().zip(&signal) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn freq_shift_moves_tone_without_mirror() {
+        let rate = 8000.0;
+        let tone = |hz: f32, i: usize| (2.0 * PI * hz * i as f32 / rate).cos();
+        let signal: Vec<f32> = (0..4000).map(|i| tone(1000.0, i)).collect();
+        let shifted = freq_shift(&signal, 500.0, rate);
+
+        // Correlate with reference tones away from the edges.
+        let level = |hz: f32| {
+            let (c, s) = (1000..3000).fold((0.0, 0.0), |(c, s), i| {
+                let phase = 2.0 * PI * hz * i as f32 / rate;
+                (c + shifted[i] * phase.cos(), s + shifted[i] * phase.sin())
+            });
+            f32::hypot(c, s) / 1000.0
+        };
+        assert!((level(1500.0) - 1.0).abs() < 0.01, "{}", level(1500.0));
+        assert!(level(500.0) < 0.01);
+        assert!(level(1000.0) < 0.01);
+        assert_eq!(freq_shift(&signal, 0.0, rate), signal);
+    }
+}
@@ -2,55 +2,68 @@
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
-use libc::{c_float, c_int, c_void};
+use core::ffi::{c_float, c_int};
+#[cfg(not(feature = "pure-rust"))]
+use core::ffi::c_void;
 
 pub const GGWAVE_MAX_INSTANCES: c_int = 4;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ggwave_SampleFormat {
-    GGWAVE_SAMPLE_FORMAT_UNDEFINED = 0,
-    GGWAVE_SAMPLE_FORMAT_U8 = 1,
-    GGWAVE_SAMPLE_FORMAT_I8 = 2,
-    GGWAVE_SAMPLE_FORMAT_U16 = 3,
-    GGWAVE_SAMPLE_FORMAT_I16 = 4,
-    GGWAVE_SAMPLE_FORMAT_F32 = 5,
+// C enums are mirrored as `c_int` newtypes rather than Rust enums: a value the
+// library hands back that this crate doesn't know about (e.g. from a newer
+// system libggwave) is then just an unknown integer instead of undefined
+// behavior. The safe layer converts them with `from_raw`.
+
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ggwave_SampleFormat(pub c_int);
+
+impl ggwave_SampleFormat {
+    pub const GGWAVE_SAMPLE_FORMAT_UNDEFINED: Self = Self(0);
+    pub const GGWAVE_SAMPLE_FORMAT_U8: Self = Self(1);
+    pub const GGWAVE_SAMPLE_FORMAT_I8: Self = Self(2);
+    pub const GGWAVE_SAMPLE_FORMAT_U16: Self = Self(3);
+    pub const GGWAVE_SAMPLE_FORMAT_I16: Self = Self(4);
+    pub const GGWAVE_SAMPLE_FORMAT_F32: Self = Self(5);
 }
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ggwave_ProtocolId {
-    GGWAVE_PROTOCOL_AUDIBLE_NORMAL = 0,
-    GGWAVE_PROTOCOL_AUDIBLE_FAST = 1,
-    GGWAVE_PROTOCOL_AUDIBLE_FASTEST = 2,
-    GGWAVE_PROTOCOL_ULTRASOUND_NORMAL = 3,
-    GGWAVE_PROTOCOL_ULTRASOUND_FAST = 4,
-    GGWAVE_PROTOCOL_ULTRASOUND_FASTEST = 5,
-    GGWAVE_PROTOCOL_DT_NORMAL = 6,
-    GGWAVE_PROTOCOL_DT_FAST = 7,
-    GGWAVE_PROTOCOL_DT_FASTEST = 8,
-    GGWAVE_PROTOCOL_MT_NORMAL = 9,
-    GGWAVE_PROTOCOL_MT_FAST = 10,
-    GGWAVE_PROTOCOL_MT_FASTEST = 11,
-    GGWAVE_PROTOCOL_CUSTOM_0 = 12,
-    GGWAVE_PROTOCOL_CUSTOM_1 = 13,
-    GGWAVE_PROTOCOL_CUSTOM_2 = 14,
-    GGWAVE_PROTOCOL_CUSTOM_3 = 15,
-    GGWAVE_PROTOCOL_CUSTOM_4 = 16,
-    GGWAVE_PROTOCOL_CUSTOM_5 = 17,
-    GGWAVE_PROTOCOL_CUSTOM_6 = 18,
-    GGWAVE_PROTOCOL_CUSTOM_7 = 19,
-    GGWAVE_PROTOCOL_CUSTOM_8 = 20,
-    GGWAVE_PROTOCOL_CUSTOM_9 = 21,
-    GGWAVE_PROTOCOL_COUNT = 22,
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ggwave_ProtocolId(pub c_int);
+
+impl ggwave_ProtocolId {
+    pub const GGWAVE_PROTOCOL_AUDIBLE_NORMAL: Self = Self(0);
+    pub const GGWAVE_PROTOCOL_AUDIBLE_FAST: Self = Self(1);
+    pub const GGWAVE_PROTOCOL_AUDIBLE_FASTEST: Self = Self(2);
+    pub const GGWAVE_PROTOCOL_ULTRASOUND_NORMAL: Self = Self(3);
+    pub const GGWAVE_PROTOCOL_ULTRASOUND_FAST: Self = Self(4);
+    pub const GGWAVE_PROTOCOL_ULTRASOUND_FASTEST: Self = Self(5);
+    pub const GGWAVE_PROTOCOL_DT_NORMAL: Self = Self(6);
+    pub const GGWAVE_PROTOCOL_DT_FAST: Self = Self(7);
+    pub const GGWAVE_PROTOCOL_DT_FASTEST: Self = Self(8);
+    pub const GGWAVE_PROTOCOL_MT_NORMAL: Self = Self(9);
+    pub const GGWAVE_PROTOCOL_MT_FAST: Self = Self(10);
+    pub const GGWAVE_PROTOCOL_MT_FASTEST: Self = Self(11);
+    pub const GGWAVE_PROTOCOL_CUSTOM_0: Self = Self(12);
+    pub const GGWAVE_PROTOCOL_CUSTOM_1: Self = Self(13);
+    pub const GGWAVE_PROTOCOL_CUSTOM_2: Self = Self(14);
+    pub const GGWAVE_PROTOCOL_CUSTOM_3: Self = Self(15);
+    pub const GGWAVE_PROTOCOL_CUSTOM_4: Self = Self(16);
+    pub const GGWAVE_PROTOCOL_CUSTOM_5: Self = Self(17);
+    pub const GGWAVE_PROTOCOL_CUSTOM_6: Self = Self(18);
+    pub const GGWAVE_PROTOCOL_CUSTOM_7: Self = Self(19);
+    pub const GGWAVE_PROTOCOL_CUSTOM_8: Self = Self(20);
+    pub const GGWAVE_PROTOCOL_CUSTOM_9: Self = Self(21);
+    pub const GGWAVE_PROTOCOL_COUNT: Self = Self(22);
 }
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ggwave_Filter {
-    GGWAVE_FILTER_HANN = 0,
-    GGWAVE_FILTER_HAMMING = 1,
-    GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS = 2,
+#[repr(transparent)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ggwave_Filter(pub c_int);
+
+impl ggwave_Filter {
+    pub const GGWAVE_FILTER_HANN: Self = Self(0);
+    pub const GGWAVE_FILTER_HAMMING: Self = Self(1);
+    pub const GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS: Self = Self(2);
 }
 
 pub const GGWAVE_OPERATING_MODE_RX: c_int = 1 << 1;
@@ -76,6 +89,7 @@ pub struct ggwave_Parameters {
 
 pub type ggwave_Instance = c_int;
 
+#[cfg(not(feature = "pure-rust"))]
 extern "C" {
     pub fn ggwave_setLogFile(fptr: *mut c_void);
     pub fn ggwave_getDefaultParameters() -> ggwave_Parameters;
@@ -96,6 +110,7 @@ extern "C" {
         waveformSize: c_int,
         payloadBuffer: *mut c_void,
     ) -> c_int;
+    #[cfg(not(ggwave_no_ndecode))]
     pub fn ggwave_ndecode(
         instance: ggwave_Instance,
         waveformBuffer: *const c_void,
@@ -105,8 +120,69 @@ extern "C" {
     ) -> c_int;
     pub fn ggwave_rxToggleProtocol(protocolId: ggwave_ProtocolId, state: c_int);
     pub fn ggwave_txToggleProtocol(protocolId: ggwave_ProtocolId, state: c_int);
+    #[cfg(not(ggwave_no_freq_start))]
     pub fn ggwave_rxProtocolSetFreqStart(protocolId: ggwave_ProtocolId, freqStart: c_int);
+    #[cfg(not(ggwave_no_freq_start))]
     pub fn ggwave_txProtocolSetFreqStart(protocolId: ggwave_ProtocolId, freqStart: c_int);
+    #[cfg(not(ggwave_no_rx_duration_frames))]
     pub fn ggwave_rxDurationFrames(instance: ggwave_Instance) -> c_int;
 }
 
+/// With `pure-rust` the same functions are implemented in Rust.
+#[cfg(feature = "pure-rust")]
+pub use crate::modem::{
+    ggwave_decode, ggwave_encode, ggwave_free, ggwave_getDefaultParameters, ggwave_init,
+    ggwave_ndecode, ggwave_rxDurationFrames, ggwave_rxProtocolSetFreqStart,
+    ggwave_rxToggleProtocol, ggwave_setLogFile, ggwave_txProtocolSetFreqStart,
+    ggwave_txToggleProtocol,
+};
+
+
+/// Bindings generated from the `ggwave.h` being built against (`bindgen`
+/// feature). The declarations above stay the ones the crate uses; the
+/// checks below compare them with the header so drift fails the build.
+#[cfg(feature = "bindgen")]
+#[allow(clashing_extern_declarations, dead_code, clippy::all)]
+pub mod generated {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+#[cfg(feature = "bindgen")]
+const _: () = {
+    use core::mem::{align_of, offset_of, size_of};
+
+    type Generated = generated::ggwave_Parameters;
+    assert!(size_of::<ggwave_Parameters>() == size_of::<Generated>());
+    assert!(align_of::<ggwave_Parameters>() == align_of::<Generated>());
+    macro_rules! same_offsets {
+        ($($field:ident),*) => {
+            $(assert!(
+                offset_of!(ggwave_Parameters, $field) == offset_of!(Generated, $field)
+            );)*
+        };
+    }
+    same_offsets!(
+        payloadLength,
+        sampleRateInp,
+        sampleRateOut,
+        sampleRate,
+        samplesPerFrame,
+        soundMarkerThreshold,
+        sampleFormatInp,
+        sampleFormatOut,
+        operatingMode
+    );
+
+    assert!(
+        generated::ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.0 as c_int
+            == ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.0
+    );
+    assert!(
+        generated::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 as c_int
+            == ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0
+    );
+    assert!(
+        generated::ggwave_Filter::GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS.0 as c_int
+            == ggwave_Filter::GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS.0
+    );
+};
@@ -0,0 +1,139 @@
+//! Encoding and decoding into fixed-size buffers (`heapless` feature).
+//!
+//! For firmware without a heap, or that can't allocate in its audio path:
+//! the waveform goes to a `heapless::Vec` whose capacity is chosen at
+//! compile time with [`waveform_capacity`], and payloads come back as a
+//! [`Payload`] of [`MAX_DATA_SIZE`] bytes. Neither allocates on the Rust
+//! side; the C++ library allocates its buffers when the instance is
+//! created.
+//!
+//! ```no_run
+//! use ggwave_rs::fixed::waveform_capacity;
+//! use ggwave_rs::{default_parameters, GgWave, ProtocolId};
+//!
+//! // Up to 16-byte payloads, 1024 I16 samples per frame.
+//! const WAVEFORM: usize = waveform_capacity(1024, 2, 16);
+//!
+//! let ggwave = GgWave::new(default_parameters())?;
+//! let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+//! let waveform = ggwave.encode_heapless::<WAVEFORM>(b"ping", protocol, 25)?;
+//! # Ok::<(), ggwave_rs::Error>(())
+//! ```
+
+use crate::{Error, GgWave, ProtocolId, MAX_DATA_SIZE};
+
+/// A decoded payload, in a buffer that holds the largest one.
+pub type Payload = heapless::Vec<u8, MAX_DATA_SIZE>;
+
+/// Frames of the start and end markers together.
+const MARKER_FRAMES: usize = 2 * 16;
+/// The length byte and its two parity bytes, before the payload.
+const LENGTH_BYTES: usize = 3;
+/// Slowest ratio of frames to bytes among the audible and ultrasound
+/// protocols: the normal ones send 3 bytes every 9 frames.
+const FRAMES_PER_TX: usize = 9;
+const BYTES_PER_TX: usize = 3;
+
+/// Parity bytes protecting a `len`-byte payload.
+const fn ecc_len(len: usize) -> usize {
+    if len < 4 {
+        2
+    } else if len / 5 < 2 {
+        4
+    } else {
+        2 * (len / 5)
+    }
+}
+
+/// Bytes needed for the waveform of a variable-length payload of up to
+/// `max_payload_len` bytes, with frames of `samples_per_frame` samples of
+/// `sample_size` bytes (see
+/// [`SampleFormat::bytes_per_sample`](crate::SampleFormat::bytes_per_sample)).
+///
+/// The bound holds for the audible and ultrasound protocols when the output
+/// sample rate is at most the internal one, as with the defaults. The DT and
+/// MT protocols and a higher output rate need more; encoding then fails with
+/// [`Error::BufferTooSmall`] carrying the size needed.
+pub const fn waveform_capacity(
+    samples_per_frame: usize,
+    sample_size: usize,
+    max_payload_len: usize,
+) -> usize {
+    let encoded = LENGTH_BYTES + max_payload_len + ecc_len(max_payload_len);
+    let frames = MARKER_FRAMES + encoded.div_ceil(BYTES_PER_TX) * FRAMES_PER_TX;
+    frames * samples_per_frame * sample_size
+}
+
+impl GgWave {
+    /// [`encode`](Self::encode) into a `heapless::Vec` of capacity `N`.
+    ///
+    /// Fails with [`Error::BufferTooSmall`], carrying the size needed, when
+    /// the waveform doesn't fit.
+    pub fn encode_heapless<const N: usize>(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<heapless::Vec<u8, N>, Error> {
+        let mut waveform = heapless::Vec::new();
+        // Can't fail: the length is the capacity.
+        let _ = waveform.resize(N, 0);
+        let written = self.encode_into(payload, protocol, volume, &mut waveform)?;
+        waveform.truncate(written);
+        Ok(waveform)
+    }
+
+    /// [`decode`](Self::decode) into a [`Payload`].
+    pub fn decode_heapless(&self, waveform: &[u8]) -> Result<Option<Payload>, Error> {
+        let mut payload = Payload::new();
+        let _ = payload.resize(MAX_DATA_SIZE, 0);
+        Ok(self.decode_into(waveform, &mut payload)?.map(|len| {
+            payload.truncate(len);
+            payload
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, SampleFormat};
+
+    #[test]
+    fn roundtrip_through_fixed_buffers() {
+        let _serial = crate::serial();
+        const MAX_PAYLOAD: usize = 8;
+        const CAPACITY: usize = waveform_capacity(1024, 2, MAX_PAYLOAD);
+        let mut params = default_parameters();
+        assert_eq!(params.samplesPerFrame, 1024);
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_I16.into();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_I16.into();
+        let ggwave = GgWave::new(params).expect("init failed");
+
+        for protocol in [
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
+            ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST,
+        ] {
+            for len in [1, 4, MAX_PAYLOAD] {
+                let needed = ggwave.encoded_size(len, protocol).unwrap();
+                assert!(needed <= CAPACITY, "{protocol:?}, {len} bytes: {needed}");
+            }
+        }
+
+        let waveform = ggwave
+            .encode_heapless::<CAPACITY>(b"fixed", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+        let payload = ggwave.decode_heapless(&waveform).unwrap();
+        assert_eq!(payload.as_deref(), Some(&b"fixed"[..]));
+
+        let too_small =
+            ggwave.encode_heapless::<1024>(b"fixed", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25);
+        assert!(matches!(
+            too_small,
+            Err(Error::BufferTooSmall {
+                needed_hint: Some(_),
+                provided: 1024
+            })
+        ));
+    }
+}
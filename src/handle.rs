@@ -0,0 +1,153 @@
+//! A `Send + Sync` handle to an instance living on its own thread.
+
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+use crate::{Error, GgWave, Parameters, ProtocolId};
+
+type Reply<T> = mpsc::Sender<Result<T, Error>>;
+
+enum Job {
+    Encode {
+        payload: Vec<u8>,
+        protocol: ProtocolId,
+        volume: i32,
+        reply: Reply<Vec<u8>>,
+    },
+    Decode {
+        waveform: Vec<u8>,
+        reply: Reply<Option<Vec<u8>>>,
+    },
+}
+
+/// Owns a [`GgWave`] on a dedicated worker thread and forwards calls to it.
+///
+/// `GgWave` can't leave the thread that created it; the handle can, and can
+/// be shared between threads. Calls are served one at a time in the order
+/// they arrive, and block until the worker has answered. Dropping the handle
+/// stops the worker and frees the instance.
+pub struct GgWaveHandle {
+    jobs: Option<mpsc::Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+    parameters: Parameters,
+}
+
+impl GgWaveHandle {
+    /// Starts the worker and creates the instance on it.
+    pub fn new(parameters: Parameters) -> Result<Self, Error> {
+        let (jobs, queue) = mpsc::channel();
+        let (ready, created) = mpsc::channel();
+        let worker = thread::Builder::new()
+            .name("ggwave".into())
+            .spawn(move || {
+                let ggwave = match GgWave::new(parameters) {
+                    Ok(ggwave) => {
+                        let _ = ready.send(Ok(()));
+                        ggwave
+                    }
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                // A caller that went away doesn't need its reply.
+                for job in queue {
+                    match job {
+                        Job::Encode {
+                            payload,
+                            protocol,
+                            volume,
+                            reply,
+                        } => {
+                            let _ = reply.send(ggwave.encode(&payload, protocol, volume));
+                        }
+                        Job::Decode { waveform, reply } => {
+                            let _ = reply.send(ggwave.decode(&waveform));
+                        }
+                    }
+                }
+            })
+            .map_err(|_| Error::WorkerStopped)?;
+
+        created.recv().map_err(|_| Error::WorkerStopped)??;
+        Ok(Self {
+            jobs: Some(jobs),
+            worker: Some(worker),
+            parameters,
+        })
+    }
+
+    pub fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    /// [`GgWave::encode`] on the worker.
+    pub fn encode(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Vec<u8>, Error> {
+        self.call(|reply| Job::Encode {
+            payload: payload.to_vec(),
+            protocol,
+            volume,
+            reply,
+        })
+    }
+
+    /// [`GgWave::decode`] on the worker. Decoder state carries over between
+    /// calls, as it does on the instance itself.
+    pub fn decode(&self, waveform: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        self.call(|reply| Job::Decode {
+            waveform: waveform.to_vec(),
+            reply,
+        })
+    }
+
+    fn call<T>(&self, job: impl FnOnce(Reply<T>) -> Job) -> Result<T, Error> {
+        let (reply, answer) = mpsc::channel();
+        self.jobs
+            .as_ref()
+            .ok_or(Error::WorkerStopped)?
+            .send(job(reply))
+            .map_err(|_| Error::WorkerStopped)?;
+        answer.recv().map_err(|_| Error::WorkerStopped)?
+    }
+}
+
+impl Drop for GgWaveHandle {
+    fn drop(&mut self) {
+        // Closing the queue ends the worker's loop.
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn handle_is_shareable_across_threads() {
+        let _serial = crate::serial();
+        assert_send_sync::<GgWaveHandle>();
+
+        let params = default_parameters();
+        let handle = GgWaveHandle::new(params).expect("worker init failed");
+        let waveform = thread::scope(|s| {
+            s.spawn(|| handle.encode(b"ping", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25))
+                .join()
+                .unwrap()
+        })
+        .expect("encode failed");
+
+        let rx = GgWaveHandle::new(params).expect("worker init failed");
+        assert_eq!(rx.decode(&waveform).unwrap().as_deref(), Some(&b"ping"[..]));
+    }
+}
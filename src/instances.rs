@@ -0,0 +1,18 @@
+//! Count of live `GgWave` instances, reported by
+//! `runtime::live_instances`.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn live() -> usize {
+    LIVE.load(Ordering::SeqCst)
+}
+
+pub(crate) fn register() {
+    LIVE.fetch_add(1, Ordering::SeqCst);
+}
+
+pub(crate) fn unregister() {
+    LIVE.fetch_sub(1, Ordering::SeqCst);
+}
@@ -1,41 +1,304 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod audio;
+#[cfg(feature = "tokio")]
+mod async_decoder;
+mod backend;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(feature = "cxx")]
+pub mod cpp;
+#[cfg(feature = "std")]
+pub mod custom;
+pub mod decoder;
+#[cfg(feature = "std")]
+mod dsp;
 pub mod ffi;
+#[cfg(feature = "heapless")]
+pub mod fixed;
+#[cfg(feature = "std")]
+mod handle;
+mod instances;
+#[cfg(feature = "pure-rust")]
+mod modem;
+#[cfg(feature = "std")]
+pub mod noise;
+#[cfg(feature = "std")]
+pub mod onboarding;
+#[cfg(feature = "std")]
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod receiver;
+#[cfg(feature = "rubato")]
+mod resample;
+#[cfg(feature = "ringbuf")]
+pub mod ring;
+#[cfg(feature = "std")]
+pub mod router;
+mod rt_encoder;
+mod rx_session;
+#[cfg(feature = "std")]
+pub mod runtime;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(feature = "futures")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod toggles;
+#[cfg(feature = "std")]
+pub mod tones;
+#[cfg(feature = "totp")]
+pub mod totp;
+mod tx_session;
+mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wav")]
+mod wav;
+#[cfg(feature = "std")]
+pub mod waveform;
+#[cfg(feature = "std")]
+pub mod wire;
 
-use libc::{c_int, c_void};
-use std::marker::PhantomData;
-use std::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::ffi::{c_int, c_void};
+use core::marker::PhantomData;
 
 pub use ffi::{
-    ggwave_Parameters as Parameters, ggwave_ProtocolId as ProtocolId,
-    ggwave_SampleFormat as SampleFormat, GGWAVE_OPERATING_MODE_RX,
+    ggwave_Parameters as Parameters, GGWAVE_OPERATING_MODE_RX,
     GGWAVE_OPERATING_MODE_RX_AND_TX, GGWAVE_OPERATING_MODE_TX,
     GGWAVE_OPERATING_MODE_TX_ONLY_TONES, GGWAVE_OPERATING_MODE_USE_DSS,
 };
+#[cfg(feature = "tokio")]
+pub use async_decoder::AsyncDecoder;
+#[cfg(feature = "codec")]
+pub use codec::GgWaveCodec;
+pub use decoder::{Decoder, RxStatus};
+pub use rt_encoder::RtEncoder;
+pub use rx_session::RxSession;
+pub use tx_session::TxSession;
+#[cfg(feature = "std")]
+pub use handle::GgWaveHandle;
+#[cfg(feature = "std")]
+pub use runtime::{disable_logging, set_log_output};
+#[cfg(all(feature = "log", unix))]
+pub use runtime::forward_log;
+pub use backend::{backend_capabilities, capabilities, BackendCapabilities, Capabilities};
+pub use types::{Filter, OperatingMode, ProtocolId, SampleFormat, Speed};
+#[cfg(feature = "std")]
+pub use waveform::Waveform;
 
 pub const MAX_DATA_SIZE: usize = 256;
+/// Longest payload upstream encodes in variable-length mode.
+pub const MAX_VARIABLE_PAYLOAD_LEN: usize = 140;
+/// Largest `Parameters::payloadLength` upstream accepts for fixed-length mode.
+pub const MAX_FIXED_PAYLOAD_LEN: usize = 64;
 
+/// Errors from this crate. New variants and fields may be added in minor
+/// releases.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
-    InitFailed,
-    EncodeFailed,
-    DecodeFailed,
-    BufferTooSmall,
+    /// `ggwave_init` returned `code`, usually because of `parameters`.
+    InitFailed { code: c_int, parameters: Parameters },
+    /// `ggwave_encode` returned `code` for a `payload_len`-byte payload.
+    EncodeFailed {
+        code: c_int,
+        protocol: ProtocolId,
+        payload_len: usize,
+    },
+    /// The decoder returned `code`: -1 for a failed decode, or 0 when a
+    /// transmission that was expected to decode didn't.
+    DecodeFailed { code: c_int },
+    /// A buffer of `provided` bytes was too small. For decoded payloads the C
+    /// library drops the payload when this happens, so it can't be fetched
+    /// again; `needed_hint` is a size known to be enough, if there is one.
+    BufferTooSmall {
+        needed_hint: Option<usize>,
+        provided: usize,
+    },
     InvalidInput(&'static str),
+    /// The payload is longer than `protocol` carries with the instance's
+    /// parameters; see [`ProtocolId::max_payload_len`].
+    PayloadTooLarge {
+        len: usize,
+        max: usize,
+        protocol: ProtocolId,
+    },
+    /// Every slot in the C library's instance table is taken; drop an
+    /// instance before creating another. Inferred from `ggwave_init` failing
+    /// for parameters that pass the library's checks.
+    InstanceLimitReached { max: usize },
+    /// The instance wasn't created for this call, e.g. `encode` on an
+    /// `RX`-only instance; caught before reaching the C library.
+    WrongOperatingMode {
+        needed: OperatingMode,
+        actual: OperatingMode,
+    },
+    /// The worker thread behind a [`GgWaveHandle`] is gone.
+    WorkerStopped,
+    /// The call would change C-library global state, which the `strict`
+    /// feature forbids.
+    GlobalStateForbidden,
+    /// The sound device couldn't be opened or stopped working.
+    AudioDevice(String),
+    /// Reading or writing audio failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// A WAV file couldn't be read or written.
+    #[cfg(feature = "wav")]
+    Wav(hound::Error),
+    /// Resampling audio to the input sample rate failed.
+    #[cfg(feature = "rubato")]
+    Resample(rubato::ResampleError),
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Error::InitFailed => write!(f, "failed to initialize ggwave instance"),
-            Error::EncodeFailed => write!(f, "failed to encode payload"),
-            Error::DecodeFailed => write!(f, "failed to decode waveform"),
-            Error::BufferTooSmall => write!(f, "payload buffer too small"),
+            Error::InitFailed { code, parameters: p } => write!(
+                f,
+                "failed to initialize ggwave instance (code {code}; input {} Hz, output {} Hz, \
+                 {} samples per frame, payload length {})",
+                p.sampleRateInp, p.sampleRateOut, p.samplesPerFrame, p.payloadLength
+            ),
+            Error::EncodeFailed {
+                code,
+                protocol,
+                payload_len,
+            } => write!(
+                f,
+                "failed to encode {payload_len}-byte payload with {protocol} (code {code})"
+            ),
+            Error::DecodeFailed { code } => write!(f, "failed to decode waveform (code {code})"),
+            Error::BufferTooSmall {
+                needed_hint: Some(needed),
+                provided,
+            } => write!(
+                f,
+                "buffer too small; {needed} bytes needed, {provided} provided"
+            ),
+            Error::BufferTooSmall {
+                needed_hint: None,
+                provided,
+            } => write!(
+                f,
+                "payload buffer of {provided} bytes too small; the payload exceeds \
+                 {MAX_DATA_SIZE} bytes, the largest this crate supports"
+            ),
             Error::InvalidInput(msg) => write!(f, "{msg}"),
+            Error::PayloadTooLarge { len, max, protocol } => write!(
+                f,
+                "{len}-byte payload is too large; {protocol} carries at most {max} bytes with \
+                 these parameters"
+            ),
+            Error::InstanceLimitReached { max } => write!(
+                f,
+                "all {max} ggwave instance slots are in use; drop an instance first"
+            ),
+            Error::WrongOperatingMode { needed, actual } => write!(
+                f,
+                "this call needs operating mode {needed}, but the instance was created with \
+                 {actual}"
+            ),
+            Error::WorkerStopped => write!(f, "ggwave worker thread has stopped"),
+            Error::GlobalStateForbidden => {
+                write!(f, "changing ggwave global state is forbidden in strict mode")
+            }
+            Error::AudioDevice(msg) => write!(f, "audio device error: {msg}"),
+            #[cfg(feature = "std")]
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            #[cfg(feature = "wav")]
+            Error::Wav(e) => write!(f, "WAV file error: {e}"),
+            #[cfg(feature = "rubato")]
+            Error::Resample(e) => write!(f, "resampling failed: {e}"),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "wav")]
+impl From<hound::Error> for Error {
+    fn from(e: hound::Error) -> Self {
+        Error::Wav(e)
+    }
+}
+
+#[cfg(feature = "rubato")]
+impl From<rubato::ResampleError> for Error {
+    fn from(e: rubato::ResampleError) -> Self {
+        Error::Resample(e)
+    }
+}
+
+/// Per-call settings for [`GgWave::encode_with`].
+///
+/// ```
+/// # use ggwave_rs::EncodeOptions;
+/// let options = EncodeOptions::new().volume(50).freq_shift(3_000.0);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeOptions {
+    volume: i32,
+    freq_shift: f32,
+}
+
+#[cfg(feature = "std")]
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            volume: 25,
+            freq_shift: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl EncodeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Volume (0-100); defaults to 25.
+    pub fn volume(mut self, volume: i32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Moves the generated waveform up by `hz` (down if negative) in the Rust
+    /// layer, selecting a band per message without touching the C library's
+    /// global protocol tables the way `txProtocolSetFreqStart` does.
+    ///
+    /// Receivers must shift captures back with [`Waveform::freq_shifted`]
+    /// before decoding. Needs F32 output.
+    pub fn freq_shift(mut self, hz: f32) -> Self {
+        self.freq_shift = hz;
+        self
+    }
+}
+
 /// A ggwave encoder/decoder instance.
 ///
 /// # Thread Safety
@@ -44,83 +307,443 @@ impl std::error::Error for Error {}
 /// all instances in a global array (`g_instances[GGWAVE_MAX_INSTANCES]`) without
 /// any synchronization. Concurrent access from multiple threads would cause data
 /// races and undefined behavior. Create and use each instance on a single thread.
+///
+/// With the `send` feature, every call into the C library holds a
+/// process-wide lock and `GgWave` is `Send` (but still `!Sync`), so it can be
+/// moved into another thread or task. Calling the raw [`ffi`] functions
+/// directly bypasses the lock.
 pub struct GgWave {
     instance: ffi::ggwave_Instance,
     parameters: Parameters,
-    // PhantomData<Rc<()>> makes this type !Send and !Sync.
-    _not_send_sync: PhantomData<Rc<()>>,
+    /// Payload length, protocol and size of the last
+    /// [`encoded_size`](Self::encoded_size) query.
+    last_encoded_size: Cell<Option<(usize, ProtocolId, usize)>>,
+    _not_send_sync: ThreadMarker,
+}
+
+// PhantomData<Rc<()>> makes GgWave !Send and !Sync; PhantomData<Cell<()>>
+// only !Sync.
+#[cfg(not(feature = "send"))]
+type ThreadMarker = PhantomData<alloc::rc::Rc<()>>;
+#[cfg(feature = "send")]
+type ThreadMarker = PhantomData<Cell<()>>;
+
+#[cfg(feature = "send")]
+static FFI_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(feature = "send")]
+type FfiGuard = std::sync::MutexGuard<'static, ()>;
+#[cfg(not(feature = "send"))]
+type FfiGuard = ();
+
+/// Taken around every call into the C library; only locks with `send`.
+pub(crate) fn ffi_lock() -> Option<FfiGuard> {
+    #[cfg(feature = "send")]
+    return Some(FFI_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
+    #[cfg(not(feature = "send"))]
+    return None;
 }
 
 impl GgWave {
     pub fn new(parameters: Parameters) -> Result<Self, Error> {
-        let instance = unsafe { ffi::ggwave_init(parameters) };
+        let instance = {
+            let _lock = ffi_lock();
+            unsafe { ffi::ggwave_init(parameters) }
+        };
         if instance < 0 {
-            return Err(Error::InitFailed);
+            // Upstream fails the same way for a full table and for bad
+            // parameters. The wrapper's own count misses instances created
+            // through `ffi`, so the parameters tell them apart instead.
+            if !init_rejects(&parameters) {
+                return Err(Error::InstanceLimitReached {
+                    max: ffi::GGWAVE_MAX_INSTANCES as usize,
+                });
+            }
+            return Err(Error::InitFailed {
+                code: instance,
+                parameters,
+            });
         }
+        instances::register();
 
         Ok(Self {
             instance,
             parameters,
+            last_encoded_size: Cell::new(None),
             _not_send_sync: PhantomData,
         })
     }
 
+    /// Wraps an instance created with `ggwave_init(parameters)`, such as
+    /// one from [`into_raw`](Self::into_raw), taking ownership: it's freed
+    /// when the wrapper is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `instance` must be live, must not be freed or used by anything else
+    /// while the wrapper owns it, and must have been created with
+    /// `parameters`, which the wrapper trusts for buffer sizes and mode
+    /// checks.
+    pub unsafe fn from_raw(instance: ffi::ggwave_Instance, parameters: Parameters) -> Self {
+        instances::register();
+        Self {
+            instance,
+            parameters,
+            last_encoded_size: Cell::new(None),
+            _not_send_sync: PhantomData,
+        }
+    }
+
+    /// The C library's handle, for passing to other C code. It stays owned
+    /// by `self` and is freed with it.
+    pub fn as_raw(&self) -> ffi::ggwave_Instance {
+        self.instance
+    }
+
+    /// Releases the instance without freeing it. The caller becomes
+    /// responsible for calling `ggwave_free`, or for handing it back with
+    /// [`from_raw`](Self::from_raw). It no longer counts towards
+    /// [`runtime::live_instances`].
+    pub fn into_raw(self) -> ffi::ggwave_Instance {
+        let instance = self.instance;
+        core::mem::forget(self);
+        instances::unregister();
+        instance
+    }
+
     pub fn parameters(&self) -> &Parameters {
         &self.parameters
     }
 
+    /// Re-creates the underlying instance with `parameters`, e.g. for a new
+    /// capture sample rate, without giving up its slot in the C library's
+    /// instance table. Any reception in progress is lost.
+    ///
+    /// If the library rejects `parameters`, the previous configuration is
+    /// restored and [`Error::InitFailed`] returned.
+    pub fn reconfigure(&mut self, parameters: Parameters) -> Result<(), Error> {
+        // Held across free and init so no other thread takes the slot.
+        let _lock = ffi_lock();
+        self.last_encoded_size.set(None);
+        unsafe { ffi::ggwave_free(self.instance) };
+        let instance = unsafe { ffi::ggwave_init(parameters) };
+        if instance >= 0 {
+            self.instance = instance;
+            self.parameters = parameters;
+            return Ok(());
+        }
+        // The old parameters were accepted before, so this only fails if
+        // something else took the slot; Drop then has nothing to free.
+        self.instance = unsafe { ffi::ggwave_init(self.parameters) };
+        Err(Error::InitFailed {
+            code: instance,
+            parameters,
+        })
+    }
+
+    pub fn uses_dss(&self) -> bool {
+        self.parameters.uses_dss()
+    }
+
+    /// Checks that an instance created with `peer` can receive what this one
+    /// sends and the other way round. Waveforms encoded with DSS only decode
+    /// with DSS, and vice versa, and nothing signals the mismatch otherwise.
+    pub fn check_peer(&self, peer: &Parameters) -> Result<(), Error> {
+        if self.uses_dss() != peer.uses_dss() {
+            return Err(Error::InvalidInput(
+                "both ends must agree on OperatingMode::USE_DSS",
+            ));
+        }
+        Ok(())
+    }
+
     pub fn encode(
         &self,
         payload: &[u8],
         protocol: ProtocolId,
         volume: i32,
     ) -> Result<Vec<u8>, Error> {
-        if !(0..=100).contains(&volume) {
-            return Err(Error::InvalidInput("volume must be between 0 and 100"));
+        check_volume(volume)?;
+        let size = self.encoded_size(payload.len(), protocol)?;
+        let mut waveform = vec![0u8; size];
+        let written = self.encode_unchecked(payload, protocol, volume, &mut waveform)?;
+        waveform.truncate(written);
+        Ok(waveform)
+    }
+
+    /// [`encode`](Self::encode) into a caller-provided buffer, without
+    /// allocating. Returns the number of bytes written.
+    ///
+    /// Fails with [`Error::BufferTooSmall`], carrying the size needed, when
+    /// `out` can't hold the waveform; [`encoded_size`](Self::encoded_size)
+    /// tells in advance.
+    pub fn encode_into(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+        out: &mut [u8],
+    ) -> Result<usize, Error> {
+        check_volume(volume)?;
+        let size = self.encoded_size(payload.len(), protocol)?;
+        if out.len() < size {
+            return Err(Error::BufferTooSmall {
+                needed_hint: Some(size),
+                provided: out.len(),
+            });
         }
+        self.encode_unchecked(payload, protocol, volume, out)
+    }
 
-        let payload_len = to_c_int(payload.len(), "payload too large")?;
+    /// Size in bytes of the waveform for a `payload_len`-byte payload.
+    ///
+    /// The size doesn't depend on the payload's content or the volume. The
+    /// library only reports it by setting up a transmission, so the last
+    /// answer is remembered: repeated encodes of same-length payloads with one
+    /// protocol ask the library once.
+    pub fn encoded_size(&self, payload_len: usize, protocol: ProtocolId) -> Result<usize, Error> {
+        if let Some((len, cached_protocol, size)) = self.last_encoded_size.get() {
+            if len == payload_len && cached_protocol == protocol {
+                return Ok(size);
+            }
+        }
+        self.require_mode(OperatingMode::TX)?;
+        let max = self.max_payload_len(protocol);
+        if payload_len > max {
+            return Err(Error::PayloadTooLarge {
+                len: payload_len,
+                max,
+                protocol,
+            });
+        }
+
+        let zeros = [0u8; MAX_DATA_SIZE];
+        let _lock = ffi_lock();
         let size = unsafe {
             ffi::ggwave_encode(
                 self.instance,
-                payload.as_ptr() as *const c_void,
-                payload_len,
-                protocol,
-                volume as c_int,
-                std::ptr::null_mut(),
+                zeros.as_ptr() as *const c_void,
+                payload_len as c_int,
+                protocol.into(),
+                25,
+                core::ptr::null_mut(),
                 1,
             )
         };
 
         if size <= 0 {
-            return Err(Error::EncodeFailed);
+            return Err(Error::EncodeFailed {
+                code: size,
+                protocol,
+                payload_len,
+            });
         }
+        let size = size as usize;
+        self.last_encoded_size.set(Some((payload_len, protocol, size)));
+        Ok(size)
+    }
 
-        let mut waveform = vec![0u8; size as usize];
+    /// Longest payload `protocol` carries with this instance's parameters.
+    pub fn max_payload_len(&self, protocol: ProtocolId) -> usize {
+        protocol.max_payload_len(&self.parameters)
+    }
+
+    /// How long the waveform for a `payload_len`-byte payload plays at the
+    /// configured output sample rate, without encoding it.
+    pub fn tx_duration(
+        &self,
+        payload_len: usize,
+        protocol: ProtocolId,
+    ) -> Result<core::time::Duration, Error> {
+        let sample_bytes =
+            SampleFormat::try_from(self.parameters.sampleFormatOut)?.bytes_per_sample();
+        if sample_bytes == 0 || self.parameters.sampleRateOut <= 0.0 {
+            return Err(Error::InvalidInput(
+                "output format and sample rate must be set",
+            ));
+        }
+        let samples = self.encoded_size(payload_len, protocol)? / sample_bytes;
+        Ok(core::time::Duration::from_secs_f64(
+            samples as f64 / self.parameters.sampleRateOut as f64,
+        ))
+    }
+
+    /// Encodes into `out`, which must be at least
+    /// [`encoded_size`](Self::encoded_size) bytes.
+    fn encode_unchecked(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+        out: &mut [u8],
+    ) -> Result<usize, Error> {
+        let _lock = ffi_lock();
         let written = unsafe {
             ffi::ggwave_encode(
                 self.instance,
                 payload.as_ptr() as *const c_void,
-                payload_len,
-                protocol,
+                payload.len() as c_int,
+                protocol.into(),
                 volume as c_int,
-                waveform.as_mut_ptr() as *mut c_void,
+                out.as_mut_ptr() as *mut c_void,
                 0,
             )
         };
 
         if written <= 0 {
-            return Err(Error::EncodeFailed);
+            return Err(Error::EncodeFailed {
+                code: written,
+                protocol,
+                payload_len: payload.len(),
+            });
         }
+        Ok(written as usize)
+    }
 
-        waveform.truncate(written as usize);
-        Ok(waveform)
+    /// [`encode`](Self::encode) with per-call [`EncodeOptions`].
+    #[cfg(feature = "std")]
+    pub fn encode_with(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        let waveform = self.encode(payload, protocol, options.volume)?;
+        if options.freq_shift == 0.0 {
+            return Ok(waveform);
+        }
+
+        expect_format(
+            self.parameters.sampleFormatOut,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            "freq_shift needs F32 output",
+        )?;
+        let rate = self.parameters.sampleRateOut;
+        if options.freq_shift.abs() >= rate / 2.0 {
+            return Err(Error::InvalidInput(
+                "freq_shift must be below half the output sample rate",
+            ));
+        }
+        Ok(Waveform::from_f32_bytes(&waveform, rate as u32)
+            .freq_shifted(options.freq_shift)
+            .to_f32_bytes())
+    }
+
+    /// [`encode`](Self::encode) for an instance whose output format is F32,
+    /// returning samples instead of bytes.
+    pub fn encode_f32(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Vec<f32>, Error> {
+        expect_format(
+            self.parameters.sampleFormatOut,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            "output format is not F32",
+        )?;
+        let waveform = self.encode(payload, protocol, volume)?;
+        Ok(waveform
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect())
     }
 
+    /// [`encode`](Self::encode) for an instance whose output format is I16,
+    /// returning samples instead of bytes.
+    pub fn encode_i16(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Vec<i16>, Error> {
+        expect_format(
+            self.parameters.sampleFormatOut,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_I16,
+            "output format is not I16",
+        )?;
+        let waveform = self.encode(payload, protocol, volume)?;
+        Ok(waveform
+            .chunks_exact(2)
+            .map(|c| i16::from_ne_bytes([c[0], c[1]]))
+            .collect())
+    }
+
+    /// Decodes a chunk of audio, returning a payload once one is complete.
+    ///
+    /// The payload buffer is always [`MAX_DATA_SIZE`], the largest payload
+    /// any protocol carries, because a payload that doesn't fit is lost
+    /// rather than left for a retry. [`Error::BufferTooSmall`] therefore only
+    /// occurs with a system library built with a larger limit.
     pub fn decode(&self, waveform: &[u8]) -> Result<Option<Vec<u8>>, Error> {
-        let waveform_len = to_c_int(waveform.len(), "waveform too large")?;
         let mut payload = vec![0u8; MAX_DATA_SIZE];
-        let decoded = unsafe {
+        Ok(self.decode_into(waveform, &mut payload)?.map(|len| {
+            payload.truncate(len);
+            payload
+        }))
+    }
+
+    /// [`decode`](Self::decode) into a caller-provided buffer, so a capture
+    /// callback can reuse one scratch buffer. Returns the payload length once
+    /// a payload is complete.
+    ///
+    /// A payload longer than `out` is lost, as described for `decode`; a
+    /// buffer of [`MAX_DATA_SIZE`] bytes always fits, and is the
+    /// `needed_hint` of the error for smaller ones.
+    pub fn decode_into(&self, waveform: &[u8], out: &mut [u8]) -> Result<Option<usize>, Error> {
+        self.require_mode(OperatingMode::RX)?;
+        let waveform_len = to_c_int(waveform.len(), "waveform too large")?;
+        let provided = out.len();
+        let out = &mut out[..provided.min(c_int::MAX as usize)];
+        match self.ndecode(waveform, waveform_len, out) {
+            0 => Ok(None),
+            -2 => Err(Error::BufferTooSmall {
+                needed_hint: (provided < MAX_DATA_SIZE).then_some(MAX_DATA_SIZE),
+                provided,
+            }),
+            n if n > 0 => Ok(Some(n as usize)),
+            code => Err(Error::DecodeFailed { code }),
+        }
+    }
+
+    /// [`decode`](Self::decode) for an instance whose input format is F32.
+    pub fn decode_f32(&self, samples: &[f32]) -> Result<Option<Vec<u8>>, Error> {
+        expect_format(
+            self.parameters.sampleFormatInp,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            "input format is not F32",
+        )?;
+        // The library reads native-endian samples, so the buffer is passed
+        // through as is.
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                samples.as_ptr() as *const u8,
+                core::mem::size_of_val(samples),
+            )
+        };
+        self.decode(bytes)
+    }
+
+    /// [`decode`](Self::decode) for an instance whose input format is I16.
+    pub fn decode_i16(&self, samples: &[i16]) -> Result<Option<Vec<u8>>, Error> {
+        expect_format(
+            self.parameters.sampleFormatInp,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_I16,
+            "input format is not I16",
+        )?;
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                samples.as_ptr() as *const u8,
+                core::mem::size_of_val(samples),
+            )
+        };
+        self.decode(bytes)
+    }
+
+    #[cfg(not(ggwave_no_ndecode))]
+    fn ndecode(&self, waveform: &[u8], waveform_len: c_int, payload: &mut [u8]) -> c_int {
+        let _lock = ffi_lock();
+        unsafe {
             ffi::ggwave_ndecode(
                 self.instance,
                 waveform.as_ptr() as *const c_void,
@@ -128,53 +751,317 @@ impl GgWave {
                 payload.as_mut_ptr() as *mut c_void,
                 payload.len() as c_int,
             )
-        };
+        }
+    }
 
-        match decoded {
-            0 => Ok(None),
-            -1 => Err(Error::DecodeFailed),
-            -2 => Err(Error::BufferTooSmall),
-            n if n > 0 => {
-                payload.truncate(n as usize);
-                Ok(Some(payload))
+    /// Fallback for system libraries older than `ggwave_ndecode`.
+    ///
+    /// `ggwave_decode` doesn't take the buffer size; it writes at most
+    /// `MAX_DATA_SIZE` bytes, so it gets a buffer of that size and the result
+    /// is copied out, mirroring `ggwave_ndecode`'s -2 when it doesn't fit.
+    #[cfg(ggwave_no_ndecode)]
+    fn ndecode(&self, waveform: &[u8], waveform_len: c_int, payload: &mut [u8]) -> c_int {
+        let mut buffer = [0u8; MAX_DATA_SIZE];
+        let _lock = ffi_lock();
+        let decoded = unsafe {
+            ffi::ggwave_decode(
+                self.instance,
+                waveform.as_ptr() as *const c_void,
+                waveform_len,
+                buffer.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if decoded > 0 {
+            let len = decoded as usize;
+            if len > payload.len() {
+                return -2;
             }
-            _ => Err(Error::DecodeFailed),
+            payload[..len].copy_from_slice(&buffer[..len]);
         }
+        decoded
+    }
+
+    /// [`rx_duration_frames`](Self::rx_duration_frames) as a duration, for
+    /// timeouts around reception. `None` if the library doesn't support the
+    /// query.
+    pub fn rx_duration(&self) -> Option<core::time::Duration> {
+        frames_to_duration(self.rx_duration_frames(), &self.parameters)
+    }
+
+    /// Fails unless the instance was created with every flag in `needed`.
+    pub(crate) fn require_mode(&self, needed: OperatingMode) -> Result<(), Error> {
+        require_mode(&self.parameters, needed)
     }
 
+    /// Returns -1 if the library doesn't support this query.
     pub fn rx_duration_frames(&self) -> i32 {
-        unsafe { ffi::ggwave_rxDurationFrames(self.instance) }
+        #[cfg(not(ggwave_no_rx_duration_frames))]
+        return {
+            let _lock = ffi_lock();
+            unsafe { ffi::ggwave_rxDurationFrames(self.instance) }
+        };
+        #[cfg(ggwave_no_rx_duration_frames)]
+        return -1;
     }
 }
 
 impl Drop for GgWave {
     fn drop(&mut self) {
-        unsafe { ffi::ggwave_free(self.instance) };
+        if self.instance >= 0 {
+            let _lock = ffi_lock();
+            unsafe { ffi::ggwave_free(self.instance) };
+        }
+        instances::unregister();
     }
 }
 
 pub fn default_parameters() -> Parameters {
+    let _lock = ffi_lock();
     unsafe { ffi::ggwave_getDefaultParameters() }
 }
 
-pub fn set_rx_protocol_enabled(protocol: ProtocolId, enabled: bool) {
-    unsafe { ffi::ggwave_rxToggleProtocol(protocol, if enabled { 1 } else { 0 }) };
+/// Enables or disables a protocol for reception in instances created afterwards.
+/// [`toggles::RxProtocolGuard`] does the same for a scope.
+///
+/// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+#[cfg(feature = "std")]
+pub fn set_rx_protocol_enabled(protocol: ProtocolId, enabled: bool) -> Result<(), Error> {
+    check_global_state()?;
+    toggles::set_rx(protocol, enabled);
+    Ok(())
+}
+
+/// Enables or disables a protocol for transmission in instances created afterwards.
+/// [`toggles::TxProtocolGuard`] does the same for a scope.
+///
+/// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+#[cfg(feature = "std")]
+pub fn set_tx_protocol_enabled(protocol: ProtocolId, enabled: bool) -> Result<(), Error> {
+    check_global_state()?;
+    toggles::set_tx(protocol, enabled);
+    Ok(())
+}
+
+/// Width of one tone bin in Hz: the library analyses frames of
+/// `samplesPerFrame` samples at its internal `sampleRate`, whatever the input
+/// and output rates are. `None` for unset parameters.
+pub fn hz_per_bin(parameters: &Parameters) -> Option<f32> {
+    if parameters.samplesPerFrame <= 0 || parameters.sampleRate <= 0.0 {
+        return None;
+    }
+    Some(parameters.sampleRate / parameters.samplesPerFrame as f32)
+}
+
+/// Bins below Nyquist in upstream's default 1024-sample frames.
+const MAX_BIN: i32 = 512;
+
+/// Nearest tone bin to `hz` with the bin width of `parameters`.
+#[cfg(feature = "std")]
+pub fn hz_to_bin(hz: f32, parameters: &Parameters) -> Option<i32> {
+    hz_per_bin(parameters).map(|width| (hz / width).round() as i32)
+}
+
+/// Frequency of tone bin `bin` with the bin width of `parameters`.
+pub fn bin_to_hz(bin: i32, parameters: &Parameters) -> Option<f32> {
+    hz_per_bin(parameters).map(|width| bin as f32 * width)
+}
+
+/// How long `frames` analysis frames last. Frames are `samplesPerFrame`
+/// samples at the library's internal `sampleRate`, whatever the input and
+/// output rates are. `None` for a negative count or unset parameters.
+pub fn frames_to_duration(frames: i32, parameters: &Parameters) -> Option<core::time::Duration> {
+    if frames < 0 || parameters.samplesPerFrame <= 0 || parameters.sampleRate <= 0.0 {
+        return None;
+    }
+    let seconds = frames as f64 * parameters.samplesPerFrame as f64 / parameters.sampleRate as f64;
+    Some(core::time::Duration::from_secs_f64(seconds))
+}
+
+/// Moves a protocol's lowest tone to `bin` for reception in instances
+/// created afterwards; see [`hz_to_bin`]. The protocol's tones must stay
+/// between bin 1 and bin 512 (24 kHz).
+///
+/// Fails with [`Error::GlobalStateForbidden`] when built with `strict`, and
+/// with [`Error::InvalidInput`] if the linked library lacks the setter.
+pub fn set_rx_protocol_freq_start(protocol: ProtocolId, bin: i32) -> Result<(), Error> {
+    check_global_state()?;
+    check_freq_start(protocol, bin)?;
+    #[cfg(not(ggwave_no_freq_start))]
+    return {
+        let _lock = ffi_lock();
+        unsafe { ffi::ggwave_rxProtocolSetFreqStart(protocol.into(), bin) };
+        Ok(())
+    };
+    #[cfg(ggwave_no_freq_start)]
+    return Err(NO_FREQ_START);
+}
+
+/// [`set_rx_protocol_freq_start`] for transmission.
+pub fn set_tx_protocol_freq_start(protocol: ProtocolId, bin: i32) -> Result<(), Error> {
+    check_global_state()?;
+    check_freq_start(protocol, bin)?;
+    #[cfg(not(ggwave_no_freq_start))]
+    return {
+        let _lock = ffi_lock();
+        unsafe { ffi::ggwave_txProtocolSetFreqStart(protocol.into(), bin) };
+        Ok(())
+    };
+    #[cfg(ggwave_no_freq_start)]
+    return Err(NO_FREQ_START);
+}
+
+#[cfg(ggwave_no_freq_start)]
+const NO_FREQ_START: Error =
+    Error::InvalidInput("the linked ggwave can't move protocol start frequencies");
+
+fn check_freq_start(protocol: ProtocolId, bin: i32) -> Result<(), Error> {
+    use ProtocolId::*;
+    // Two nibbles of 16 tones for each byte sent per frame.
+    let tone_bins = match protocol {
+        GGWAVE_PROTOCOL_DT_NORMAL
+        | GGWAVE_PROTOCOL_DT_FAST
+        | GGWAVE_PROTOCOL_DT_FASTEST
+        | GGWAVE_PROTOCOL_MT_NORMAL
+        | GGWAVE_PROTOCOL_MT_FAST
+        | GGWAVE_PROTOCOL_MT_FASTEST => 32,
+        _ => 96,
+    };
+    if bin < 1 || bin + tone_bins > MAX_BIN {
+        return Err(Error::InvalidInput(
+            "protocol tones must fit between bin 1 and bin 512",
+        ));
+    }
+    Ok(())
 }
 
-pub fn set_tx_protocol_enabled(protocol: ProtocolId, enabled: bool) {
-    unsafe { ffi::ggwave_txToggleProtocol(protocol, if enabled { 1 } else { 0 }) };
+/// Whether this build forbids changing the C library's process-wide state.
+pub const fn is_strict() -> bool {
+    cfg!(feature = "strict")
+}
+
+/// Guards every wrapper that mutates C-library global state.
+fn check_global_state() -> Result<(), Error> {
+    if is_strict() {
+        Err(Error::GlobalStateForbidden)
+    } else {
+        Ok(())
+    }
+}
+
+/// Fails unless `parameters` has every flag in `needed`.
+pub(crate) fn require_mode(parameters: &Parameters, needed: OperatingMode) -> Result<(), Error> {
+    let actual = OperatingMode::from_bits_truncate(parameters.operatingMode);
+    if actual.contains(needed) {
+        Ok(())
+    } else {
+        Err(Error::WrongOperatingMode { needed, actual })
+    }
+}
+
+pub(crate) fn check_volume(volume: i32) -> Result<(), Error> {
+    if !(0..=100).contains(&volume) {
+        return Err(Error::InvalidInput("volume must be between 0 and 100"));
+    }
+    Ok(())
+}
+
+/// Whether `ggwave_init` refuses `parameters` whatever the state of its
+/// instance table, repeating the backend's own checks.
+fn init_rejects(parameters: &Parameters) -> bool {
+    #[cfg(feature = "pure-rust")]
+    return modem::check_parameters(parameters).is_err();
+    #[cfg(not(feature = "pure-rust"))]
+    return {
+        // Upstream's limits in GGWave::prepare.
+        let rates = [
+            parameters.sampleRateInp,
+            parameters.sampleRateOut,
+            parameters.sampleRate,
+        ];
+        let known_format = |format| {
+            SampleFormat::try_from(format).is_ok_and(|f| f.bytes_per_sample() > 0)
+        };
+        !rates.iter().all(|rate| (1000.0..=96000.0).contains(rate))
+            || !(1..=1024).contains(&parameters.samplesPerFrame)
+            || parameters.payloadLength > MAX_FIXED_PAYLOAD_LEN as c_int
+            || !known_format(parameters.sampleFormatInp)
+            || !known_format(parameters.sampleFormatOut)
+    };
+}
+
+pub(crate) fn expect_format(
+    actual: ffi::ggwave_SampleFormat,
+    expected: SampleFormat,
+    error: &'static str,
+) -> Result<(), Error> {
+    if SampleFormat::try_from(actual)? != expected {
+        return Err(Error::InvalidInput(error));
+    }
+    Ok(())
 }
 
 fn to_c_int(value: usize, context: &'static str) -> Result<c_int, Error> {
     c_int::try_from(value).map_err(|_| Error::InvalidInput(context))
 }
 
+/// Serializes the unit tests that create instances: the C library's table
+/// holds only [`ffi::GGWAVE_MAX_INSTANCES`] of them, fewer than a parallel
+/// test run would keep alive.
+#[cfg(test)]
+pub(crate) fn serial() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn raw_handles_transfer_ownership() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let ggwave = GgWave::new(params).expect("init failed");
+        let raw = ggwave.as_raw();
+        assert_eq!(ggwave.into_raw(), raw);
+
+        let ggwave = unsafe { GgWave::from_raw(raw, params) };
+        assert_eq!(ggwave.as_raw(), raw);
+        assert_eq!(ggwave.parameters().samplesPerFrame, params.samplesPerFrame);
+    }
+
+    #[test]
+    fn reconfigure_keeps_the_wrapper() {
+        let _serial = crate::serial();
+        let mut ggwave = GgWave::new(default_parameters()).expect("init failed");
+        let mut params = default_parameters();
+        params.sampleRateInp = 44_100.0;
+        ggwave.reconfigure(params).unwrap();
+        assert_eq!(ggwave.parameters().sampleRateInp, 44_100.0);
+    }
+
+    #[test]
+    fn failed_reconfigure_restores_parameters() {
+        let _serial = crate::serial();
+        let mut ggwave = GgWave::new(default_parameters()).expect("init failed");
+        let mut params = default_parameters();
+        params.sampleRateInp = 10.0;
+        assert!(matches!(
+            ggwave.reconfigure(params),
+            Err(Error::InitFailed { .. })
+        ));
+        assert_eq!(
+            ggwave.parameters().sampleRateInp,
+            default_parameters().sampleRateInp
+        );
+        ggwave
+            .encode(b"still here", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+    }
+
     #[test]
     fn roundtrip_encode_decode() {
+        let _serial = crate::serial();
         let params = default_parameters();
         let tx = GgWave::new(params).expect("tx init failed");
         let waveform = tx
@@ -190,5 +1077,320 @@ mod tests {
         let decoded = decoded.expect("no payload decoded");
         assert_eq!(decoded, b"ping");
     }
+
+    #[cfg(feature = "send")]
+    #[test]
+    fn instances_move_between_threads() {
+        let _serial = crate::serial();
+        let tx = GgWave::new(default_parameters()).expect("tx init failed");
+        let tx = std::thread::spawn(move || tx).join().unwrap();
+        assert!(tx.rx_duration_frames() >= -1);
+    }
+
+    #[test]
+    fn calls_check_the_operating_mode() {
+        let _serial = crate::serial();
+        let rx_only = default_parameters()
+            .with_operating_mode(OperatingMode::RX)
+            .unwrap();
+        let rx = GgWave::new(rx_only).expect("rx init failed");
+        let err = rx
+            .encode(b"hi", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongOperatingMode {
+                needed: OperatingMode::TX,
+                actual: OperatingMode::RX
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "this call needs operating mode TX, but the instance was created with RX"
+        );
+        drop(rx);
+
+        let tx_only = default_parameters()
+            .with_operating_mode(OperatingMode::TX)
+            .unwrap();
+        let tx = GgWave::new(tx_only).expect("tx init failed");
+        assert!(matches!(
+            tx.decode(&[0; 1024]),
+            Err(Error::WrongOperatingMode { .. })
+        ));
+    }
+
+    #[test]
+    fn frames_convert_to_durations() {
+        let mut params = default_parameters();
+        params.samplesPerFrame = 1024;
+        params.sampleRate = 48_000.0;
+        params.sampleRateInp = 44_100.0;
+        assert_eq!(
+            frames_to_duration(75, &params),
+            Some(std::time::Duration::from_millis(1600))
+        );
+        assert_eq!(frames_to_duration(-1, &params), None);
+        params.sampleRate = 0.0;
+        assert_eq!(frames_to_duration(1, &params), None);
+    }
+
+    #[test]
+    #[cfg_attr(feature = "pure-rust", ignore = "the pure-Rust backend has no DSS")]
+    fn dss_roundtrip() {
+        let _serial = crate::serial();
+        let params = default_parameters().with_dss(true);
+        let tx = GgWave::new(params).expect("tx init failed");
+        let plain = GgWave::new(default_parameters().with_dss(false)).expect("rx init failed");
+        assert!(tx.check_peer(plain.parameters()).is_err());
+        drop(plain);
+
+        let rx = GgWave::new(params).expect("rx init failed");
+        tx.check_peer(rx.parameters()).unwrap();
+        let waveform = tx
+            .encode(b"spread", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .expect("encode failed");
+        let decoded = rx.decode(&waveform).expect("decode failed");
+        assert_eq!(decoded.as_deref(), Some(&b"spread"[..]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encode_into_reports_needed_size() {
+        let _serial = crate::serial();
+        let tx = GgWave::new(default_parameters()).expect("tx init failed");
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        let waveform = tx.encode(b"ping", protocol, 25).expect("encode failed");
+        assert_eq!(tx.encoded_size(4, protocol).unwrap(), waveform.len());
+        let rate = tx.parameters().sampleRateOut as u32;
+        assert_eq!(
+            tx.tx_duration(4, protocol).unwrap(),
+            Waveform::from_f32_bytes(&waveform, rate).duration()
+        );
+
+        let mut out = vec![0u8; waveform.len()];
+        assert!(matches!(
+            tx.encode_into(b"ping", protocol, 25, &mut out[1..]),
+            Err(Error::BufferTooSmall { needed_hint: Some(n), provided })
+                if n == waveform.len() && provided == n - 1
+        ));
+        let written = tx.encode_into(b"ping", protocol, 25, &mut out).unwrap();
+        assert_eq!(&out[..written], &waveform[..]);
+    }
+
+    #[test]
+    fn decode_into_reuses_the_buffer() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let tx = GgWave::new(params).expect("tx init failed");
+        let rx = GgWave::new(params).expect("rx init failed");
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST;
+        let mut scratch = [0u8; MAX_DATA_SIZE];
+        for payload in [&b"first"[..], b"2nd"] {
+            let waveform = tx.encode(payload, protocol, 25).expect("encode failed");
+            let len = rx
+                .decode_into(&waveform, &mut scratch)
+                .expect("decode failed")
+                .expect("nothing decoded");
+            assert_eq!(&scratch[..len], payload);
+        }
+
+        let waveform = tx.encode(b"too long", protocol, 25).expect("encode failed");
+        assert!(matches!(
+            rx.decode_into(&waveform, &mut scratch[..4]),
+            Err(Error::BufferTooSmall { provided: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn decode_into_fits_the_longest_payload() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let tx = GgWave::new(params).expect("tx init failed");
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST;
+        let payload: Vec<u8> = (0..MAX_VARIABLE_PAYLOAD_LEN as u8).collect();
+        let waveform = tx.encode(&payload, protocol, 25).expect("encode failed");
+
+        let rx = GgWave::new(params).expect("rx init failed");
+        let mut out = [0u8; MAX_DATA_SIZE];
+        let len = rx
+            .decode_into(&waveform, &mut out)
+            .expect("decode failed")
+            .expect("nothing decoded");
+        assert_eq!(&out[..len], &payload[..]);
+
+        let rx = GgWave::new(params).expect("rx init failed");
+        let provided = MAX_VARIABLE_PAYLOAD_LEN - 1;
+        assert!(matches!(
+            rx.decode_into(&waveform, &mut out[..provided]),
+            Err(Error::BufferTooSmall {
+                needed_hint: Some(MAX_DATA_SIZE),
+                provided: p,
+            }) if p == provided
+        ));
+    }
+
+    #[test]
+    fn encoded_size_rejects_oversized_payloads() {
+        let _serial = crate::serial();
+        let tx = GgWave::new(default_parameters()).expect("tx init failed");
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        assert!(matches!(
+            tx.encoded_size(MAX_VARIABLE_PAYLOAD_LEN + 1, protocol),
+            Err(Error::PayloadTooLarge { len: 141, max: 140, .. })
+        ));
+        let err = tx.encode(&[0; 200], protocol, 25).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "200-byte payload is too large; audible-fast carries at most 140 bytes with these \
+             parameters"
+        );
+    }
+
+    #[test]
+    fn typed_encode_checks_output_format() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let tx = GgWave::new(params).expect("tx init failed");
+        assert!(matches!(
+            tx.encode_i16(b"ping", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25),
+            Err(Error::InvalidInput("output format is not I16"))
+        ));
+    }
+
+    #[test]
+    fn typed_decode_checks_input_format() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let rx = GgWave::new(params).expect("rx init failed");
+        assert!(matches!(
+            rx.decode_i16(&[0; 1024]),
+            Err(Error::InvalidInput("input format is not I16"))
+        ));
+    }
+
+    #[test]
+    fn longest_variable_length_payload_roundtrips() {
+        let _serial = crate::serial();
+        // Upstream caps variable-length payloads at 140 bytes.
+        let params = default_parameters();
+        let tx = GgWave::new(params).expect("tx init failed");
+        let payload: Vec<u8> = (0..140u8).collect();
+        let waveform = tx
+            .encode(&payload, ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST, 25)
+            .expect("encode failed");
+        assert!(tx
+            .encode(&[0; 141], ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST, 25)
+            .is_err());
+
+        let rx = GgWave::new(params).expect("rx init failed");
+        assert_eq!(rx.decode(&waveform).expect("decode failed"), Some(payload));
+    }
+
+    #[test]
+    fn buffer_too_small_reports_hint() {
+        let exact = Error::BufferTooSmall {
+            needed_hint: Some(140),
+            provided: 100,
+        };
+        assert_eq!(
+            exact.to_string(),
+            "buffer too small; 140 bytes needed, 100 provided"
+        );
+        let unknown = Error::BufferTooSmall {
+            needed_hint: None,
+            provided: 256,
+        };
+        assert!(unknown.to_string().contains("exceeds 256 bytes"));
+    }
+
+    #[test]
+    fn errors_carry_context() {
+        let _serial = crate::serial();
+        let encode = Error::EncodeFailed {
+            code: -1,
+            protocol: ProtocolId::GGWAVE_PROTOCOL_DT_FAST,
+            payload_len: 200,
+        };
+        assert_eq!(
+            encode.to_string(),
+            "failed to encode 200-byte payload with dt-fast (code -1)"
+        );
+
+        let mut params = default_parameters();
+        // Below upstream's minimum input sample rate.
+        params.sampleRateInp = 10.0;
+        match GgWave::new(params) {
+            Err(Error::InitFailed { code, parameters }) => {
+                assert!(code < 0);
+                assert_eq!(parameters.sampleRateInp, 10.0);
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("10 Hz input accepted"),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn freq_shifted_roundtrip() {
+        let _serial = crate::serial();
+        let params = default_parameters();
+        let options = EncodeOptions::new().freq_shift(2_000.0);
+        let tx = GgWave::new(params).expect("tx init failed");
+        let shifted = tx
+            .encode_with(b"ping", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, &options)
+            .expect("encode failed");
+
+        let capture = Waveform::from_f32_bytes(&shifted, params.sampleRateOut as u32);
+        let rx = GgWave::new(params).expect("rx init failed");
+        let decoded = rx
+            .decode(&capture.freq_shifted(-2_000.0).to_f32_bytes())
+            .expect("decode failed");
+        assert_eq!(decoded.as_deref(), Some(&b"ping"[..]));
+
+        let too_far = EncodeOptions::new().freq_shift(params.sampleRateOut);
+        assert!(tx
+            .encode_with(b"ping", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, &too_far)
+            .is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn freq_start_is_validated() {
+        let params = default_parameters();
+        assert_eq!(hz_to_bin(1875.0, &params), Some(40));
+        assert_eq!(bin_to_hz(320, &params), Some(15_000.0));
+        let mut small_frames = params;
+        small_frames.samplesPerFrame = 512;
+        assert_eq!(bin_to_hz(40, &small_frames), Some(3750.0));
+        small_frames.samplesPerFrame = 0;
+        assert_eq!(hz_to_bin(1875.0, &small_frames), None);
+
+        let audible = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL;
+        let mt = ProtocolId::GGWAVE_PROTOCOL_MT_NORMAL;
+        assert!(check_freq_start(audible, 416).is_ok());
+        assert!(check_freq_start(audible, 417).is_err());
+        assert!(check_freq_start(mt, 480).is_ok());
+        assert!(check_freq_start(mt, 0).is_err());
+        if is_strict() {
+            assert!(matches!(
+                set_tx_protocol_freq_start(audible, 40),
+                Err(Error::GlobalStateForbidden)
+            ));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn toggles_respect_strict_mode() {
+        let result = set_rx_protocol_enabled(ProtocolId::GGWAVE_PROTOCOL_DT_FAST, true);
+        if is_strict() {
+            assert!(matches!(result, Err(Error::GlobalStateForbidden)));
+        } else {
+            assert!(result.is_ok());
+        }
+    }
 }
 
@@ -0,0 +1,690 @@
+//! The C API implemented in Rust (`pure-rust` feature), for targets where
+//! building upstream's C++ is impractical.
+//!
+//! [`ffi`](crate::ffi) re-exports these functions in place of the C
+//! library's, so the rest of the crate runs unchanged on top of them. They
+//! follow upstream's C API: an instance table of
+//! [`GGWAVE_MAX_INSTANCES`], process-wide protocol toggles picked up by
+//! instances created afterwards, and the same return codes.
+//!
+//! The modulation follows upstream's audible and ultrasound protocols and
+//! the custom slots: markers, tone layout and Reed-Solomon coding. Not
+//! supported: the DT and MT protocols (encoding them fails and the receiver
+//! ignores them), [`OperatingMode::USE_DSS`] and
+//! [`OperatingMode::TX_ONLY_TONES`] (`ggwave_init` fails).
+//!
+//! [`OperatingMode::USE_DSS`]: crate::OperatingMode::USE_DSS
+//! [`OperatingMode::TX_ONLY_TONES`]: crate::OperatingMode::TX_ONLY_TONES
+
+// Named like, and with the same contracts as, the C functions they stand
+// in for.
+#![allow(non_snake_case)]
+#![allow(clippy::missing_safety_doc)]
+
+mod rs;
+mod rx;
+mod tx;
+
+use std::ffi::{c_int, c_void};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::dsp::{from_bytes, to_bytes};
+use crate::ffi::*;
+use crate::{MAX_DATA_SIZE, MAX_FIXED_PAYLOAD_LEN, MAX_VARIABLE_PAYLOAD_LEN};
+use rx::Receiver;
+
+const COUNT: usize = ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 as usize;
+const MARKER_FRAMES: usize = 16;
+/// Tone pairs in a marker.
+const MARKER_BITS: usize = 16;
+/// The length byte and its two parity bytes, before the payload.
+const LENGTH_BYTES: usize = 3;
+const MAX_SAMPLES_PER_FRAME: c_int = 1024;
+const MIN_SAMPLES_PER_FRAME: c_int = 64;
+const SAMPLE_RATES: std::ops::RangeInclusive<f32> = 1000.0..=96000.0;
+
+/// Parity bytes protecting a `len`-byte payload.
+fn ecc_len(len: usize) -> usize {
+    if len < 4 {
+        2
+    } else {
+        (2 * (len / 5)).max(4)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Protocol {
+    /// Bin of the first tone.
+    freq_start: i32,
+    frames_per_tx: usize,
+    bytes_per_tx: usize,
+    enabled: bool,
+}
+
+impl Protocol {
+    /// Bins the protocol's tones span.
+    fn bins(&self) -> usize {
+        32 * self.bytes_per_tx
+    }
+}
+
+/// Upstream's protocol table; `None` for the DT and MT protocols, which
+/// this backend doesn't implement.
+fn default_protocol(id: usize) -> Option<Protocol> {
+    use ggwave_ProtocolId as P;
+    let (freq_start, frames_per_tx) = match ggwave_ProtocolId(id as c_int) {
+        P::GGWAVE_PROTOCOL_AUDIBLE_NORMAL => (40, 9),
+        P::GGWAVE_PROTOCOL_AUDIBLE_FAST => (40, 6),
+        P::GGWAVE_PROTOCOL_AUDIBLE_FASTEST => (40, 3),
+        P::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL => (320, 9),
+        P::GGWAVE_PROTOCOL_ULTRASOUND_FAST => (320, 6),
+        P::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST => (320, 3),
+        id if id.0 >= P::GGWAVE_PROTOCOL_CUSTOM_0.0 && id.0 < P::GGWAVE_PROTOCOL_COUNT.0 => {
+            // Custom slots start out like the audible normal protocol,
+            // disabled.
+            return Some(Protocol {
+                freq_start: 40,
+                frames_per_tx: 9,
+                bytes_per_tx: 3,
+                enabled: false,
+            });
+        }
+        _ => return None,
+    };
+    Some(Protocol {
+        freq_start,
+        frames_per_tx,
+        bytes_per_tx: 3,
+        enabled: true,
+    })
+}
+
+type Table = [Option<Protocol>; COUNT];
+
+struct Globals {
+    rx: Table,
+    tx: Table,
+    log: LogTarget,
+}
+
+enum LogTarget {
+    Stderr,
+    Off,
+    /// A `FILE *` from `ggwave_setLogFile`.
+    File(usize),
+}
+
+static GLOBALS: Mutex<Option<Globals>> = Mutex::new(None);
+
+fn globals() -> MutexGuard<'static, Option<Globals>> {
+    let mut globals = GLOBALS.lock().unwrap_or_else(|e| e.into_inner());
+    globals.get_or_insert_with(|| {
+        let table: Table = std::array::from_fn(default_protocol);
+        Globals {
+            rx: table,
+            tx: table,
+            log: LogTarget::Stderr,
+        }
+    });
+    globals
+}
+
+fn log(message: &str) {
+    let globals = globals();
+    match globals.as_ref().map(|g| &g.log) {
+        Some(LogTarget::Off) => {}
+        Some(LogTarget::File(stream)) => write_line(*stream, message),
+        _ => eprintln!("{message}"),
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn write_line(stream: usize, message: &str) {
+    let line = format!("{message}\n\0");
+    unsafe { libc::fputs(line.as_ptr().cast(), stream as *mut libc::FILE) };
+}
+
+/// wasm32-unknown-unknown has no C streams, so nothing can have passed one.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn write_line(_: usize, message: &str) {
+    eprintln!("{message}");
+}
+
+struct Instance {
+    parameters: ggwave_Parameters,
+    tx: Table,
+    receiver: Option<Receiver>,
+    input: Resampler,
+    /// Bytes of a sample split across `ggwave_decode` calls.
+    partial_sample: Vec<u8>,
+}
+
+struct Instances {
+    next_id: ggwave_Instance,
+    live: Vec<(ggwave_Instance, Arc<Mutex<Instance>>)>,
+}
+
+static INSTANCES: Mutex<Instances> = Mutex::new(Instances {
+    next_id: 0,
+    live: Vec::new(),
+});
+
+fn instances() -> MutexGuard<'static, Instances> {
+    INSTANCES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn instance(id: ggwave_Instance) -> Option<Arc<Mutex<Instance>>> {
+    let found = instances()
+        .live
+        .iter()
+        .find(|(live, _)| *live == id)
+        .map(|(_, instance)| instance.clone());
+    if found.is_none() {
+        log(&format!("Invalid or expired instance id: {id}"));
+    }
+    found
+}
+
+pub unsafe fn ggwave_setLogFile(fptr: *mut c_void) {
+    let mut globals = globals();
+    if let Some(globals) = globals.as_mut() {
+        globals.log = if fptr.is_null() {
+            LogTarget::Off
+        } else {
+            LogTarget::File(fptr as usize)
+        };
+    }
+}
+
+pub unsafe fn ggwave_getDefaultParameters() -> ggwave_Parameters {
+    ggwave_Parameters {
+        payloadLength: -1,
+        sampleRateInp: 48000.0,
+        sampleRateOut: 48000.0,
+        sampleRate: 48000.0,
+        samplesPerFrame: 1024,
+        soundMarkerThreshold: 3.0,
+        sampleFormatInp: ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+        sampleFormatOut: ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+        operatingMode: GGWAVE_OPERATING_MODE_RX_AND_TX,
+    }
+}
+
+pub unsafe fn ggwave_init(parameters: ggwave_Parameters) -> ggwave_Instance {
+    if let Err(message) = check_parameters(&parameters) {
+        log(message);
+        return -1;
+    }
+    let (rx, tx) = {
+        let globals = globals();
+        let globals = globals.as_ref().expect("initialized by globals()");
+        (globals.rx, globals.tx)
+    };
+
+    let p = &parameters;
+    let samples_per_frame = p.samplesPerFrame as usize;
+    let receiver = (p.operatingMode & GGWAVE_OPERATING_MODE_RX != 0).then(|| {
+        let protocols = rx
+            .iter()
+            .flatten()
+            .filter(|protocol| protocol.enabled && fits(protocol, samples_per_frame))
+            .copied()
+            .collect();
+        let fixed_len = (p.payloadLength > 0).then_some(p.payloadLength as usize);
+        Receiver::new(
+            samples_per_frame,
+            p.soundMarkerThreshold,
+            protocols,
+            fixed_len,
+        )
+    });
+    let instance = Instance {
+        parameters,
+        tx,
+        receiver,
+        input: Resampler::new(p.sampleRateInp, p.sampleRate),
+        partial_sample: Vec::new(),
+    };
+
+    let mut instances = instances();
+    if instances.live.len() >= GGWAVE_MAX_INSTANCES as usize {
+        drop(instances);
+        log("Failed to create GGWave instance - reached maximum number of instances");
+        return -1;
+    }
+    let id = instances.next_id;
+    instances.next_id += 1;
+    instances.live.push((id, Arc::new(Mutex::new(instance))));
+    id
+}
+
+pub(crate) fn check_parameters(p: &ggwave_Parameters) -> Result<(), &'static str> {
+    let rates = [p.sampleRateInp, p.sampleRateOut, p.sampleRate];
+    if !rates.iter().all(|rate| SAMPLE_RATES.contains(rate)) {
+        return Err("Invalid sample rate - must be between 1000 and 96000 Hz");
+    }
+    let frame = p.samplesPerFrame;
+    if !(MIN_SAMPLES_PER_FRAME..=MAX_SAMPLES_PER_FRAME).contains(&frame)
+        || !(frame as u32).is_power_of_two()
+    {
+        return Err("Invalid samples per frame - must be a power of two from 64 to 1024");
+    }
+    if p.payloadLength > MAX_FIXED_PAYLOAD_LEN as c_int {
+        return Err("Invalid payload length - fixed-length payloads are at most 64 bytes");
+    }
+    if p.soundMarkerThreshold.is_nan() || p.soundMarkerThreshold <= 0.0 {
+        return Err("Invalid sound marker threshold");
+    }
+    let known = GGWAVE_OPERATING_MODE_RX_AND_TX;
+    if p.operatingMode & !known != 0 {
+        return Err("Invalid operating mode - the pure-Rust backend has no DSS or tone output");
+    }
+    let rx = p.operatingMode & GGWAVE_OPERATING_MODE_RX != 0;
+    let tx = p.operatingMode & GGWAVE_OPERATING_MODE_TX != 0;
+    if rx && sample_bytes(p.sampleFormatInp).is_none() {
+        return Err("Invalid input sample format");
+    }
+    if tx && sample_bytes(p.sampleFormatOut).is_none() {
+        return Err("Invalid output sample format");
+    }
+    Ok(())
+}
+
+/// Whether every tone of `protocol` is below half the frame.
+fn fits(protocol: &Protocol, samples_per_frame: usize) -> bool {
+    protocol.freq_start > 0
+        && protocol.freq_start as usize + protocol.bins() < samples_per_frame / 2
+}
+
+pub unsafe fn ggwave_free(instance: ggwave_Instance) {
+    instances().live.retain(|(id, _)| *id != instance);
+}
+
+pub unsafe fn ggwave_encode(
+    instance: ggwave_Instance,
+    payloadBuffer: *const c_void,
+    payloadSize: c_int,
+    protocolId: ggwave_ProtocolId,
+    volume: c_int,
+    waveformBuffer: *mut c_void,
+    query: c_int,
+) -> c_int {
+    let Some(instance) = self::instance(instance) else {
+        return -1;
+    };
+    let instance = instance.lock().unwrap_or_else(|e| e.into_inner());
+    let payload = match usize::try_from(payloadSize) {
+        Ok(len) if !payloadBuffer.is_null() => {
+            std::slice::from_raw_parts(payloadBuffer as *const u8, len)
+        }
+        _ => {
+            log("Invalid payload");
+            return -1;
+        }
+    };
+    let waveform = match instance.encode(payload, protocolId, volume) {
+        Ok(waveform) => waveform,
+        Err(message) => {
+            log(message);
+            return -1;
+        }
+    };
+    let Ok(size) = c_int::try_from(waveform.len()) else {
+        log("Encoded waveform too large");
+        return -1;
+    };
+    if query == 0 {
+        if waveformBuffer.is_null() {
+            log("Invalid waveform buffer");
+            return -1;
+        }
+        std::ptr::copy_nonoverlapping(waveform.as_ptr(), waveformBuffer as *mut u8, waveform.len());
+    }
+    size
+}
+
+impl Instance {
+    fn encode(
+        &self,
+        payload: &[u8],
+        protocol: ggwave_ProtocolId,
+        volume: c_int,
+    ) -> Result<Vec<u8>, &'static str> {
+        let p = &self.parameters;
+        if p.operatingMode & GGWAVE_OPERATING_MODE_TX == 0 {
+            return Err("Instance was not created with TX mode");
+        }
+        if !(0..=100).contains(&volume) {
+            return Err("Invalid volume - must be between 0 and 100");
+        }
+        let entry = usize::try_from(protocol.0)
+            .ok()
+            .and_then(|id| self.tx.get(id).copied())
+            .ok_or("Invalid protocol id")?;
+        let protocol = entry.ok_or("Protocol not supported by the pure-Rust backend")?;
+        if !protocol.enabled {
+            return Err("Protocol is not enabled for transmission");
+        }
+        let samples_per_frame = p.samplesPerFrame as usize;
+        if !fits(&protocol, samples_per_frame) {
+            return Err("Protocol tones don't fit in the frame");
+        }
+
+        let encoded = if p.payloadLength > 0 {
+            if payload.len() != p.payloadLength as usize {
+                return Err("Invalid payload size - must equal the fixed payload length");
+            }
+            let parity = rs::encode(payload, ecc_len(payload.len()));
+            [payload, &parity].concat()
+        } else {
+            if payload.is_empty() || payload.len() > MAX_VARIABLE_PAYLOAD_LEN {
+                return Err("Invalid payload size - must be from 1 to 140 bytes");
+            }
+            let len = [payload.len() as u8];
+            let header = rs::encode(&len, LENGTH_BYTES - 1);
+            let parity = rs::encode(payload, ecc_len(payload.len()));
+            [&len[..], &header, payload, &parity].concat()
+        };
+
+        let samples = tx::synthesize(
+            &encoded,
+            &protocol,
+            p.payloadLength <= 0,
+            volume as f32 / 100.0,
+            samples_per_frame,
+        );
+        let samples = resample(&samples, p.sampleRate, p.sampleRateOut);
+        Ok(to_bytes(&samples, p.sampleFormatOut))
+    }
+}
+
+pub unsafe fn ggwave_decode(
+    instance: ggwave_Instance,
+    waveformBuffer: *const c_void,
+    waveformSize: c_int,
+    payloadBuffer: *mut c_void,
+) -> c_int {
+    ggwave_ndecode(
+        instance,
+        waveformBuffer,
+        waveformSize,
+        payloadBuffer,
+        MAX_DATA_SIZE as c_int,
+    )
+}
+
+pub unsafe fn ggwave_ndecode(
+    instance: ggwave_Instance,
+    waveformBuffer: *const c_void,
+    waveformSize: c_int,
+    payloadBuffer: *mut c_void,
+    payloadSize: c_int,
+) -> c_int {
+    let Some(instance) = self::instance(instance) else {
+        return -1;
+    };
+    let mut instance = instance.lock().unwrap_or_else(|e| e.into_inner());
+    let waveform = match usize::try_from(waveformSize) {
+        Ok(len) if !waveformBuffer.is_null() => {
+            std::slice::from_raw_parts(waveformBuffer as *const u8, len)
+        }
+        _ => {
+            log("Invalid waveform");
+            return -1;
+        }
+    };
+    let payload = match instance.decode(waveform) {
+        Ok(Some(payload)) => payload,
+        Ok(None) => return 0,
+        Err(message) => {
+            log(message);
+            return -1;
+        }
+    };
+    if payload.len() > usize::try_from(payloadSize).unwrap_or(0) {
+        log("Payload buffer too small");
+        return -2;
+    }
+    std::ptr::copy_nonoverlapping(payload.as_ptr(), payloadBuffer as *mut u8, payload.len());
+    payload.len() as c_int
+}
+
+impl Instance {
+    fn decode(&mut self, waveform: &[u8]) -> Result<Option<Vec<u8>>, &'static str> {
+        let Some(receiver) = self.receiver.as_mut() else {
+            return Err("Instance was not created with RX mode");
+        };
+        let format = self.parameters.sampleFormatInp;
+        let sample_bytes = sample_bytes(format).expect("checked by ggwave_init");
+        self.partial_sample.extend_from_slice(waveform);
+        let whole = self.partial_sample.len() / sample_bytes * sample_bytes;
+        let samples = from_bytes(&self.partial_sample[..whole], format);
+        self.partial_sample.drain(..whole);
+        let samples = self.input.process(&samples);
+        Ok(receiver.feed(&samples))
+    }
+}
+
+fn set_protocol(rx: bool, protocol: ggwave_ProtocolId, update: impl FnOnce(&mut Protocol)) {
+    let valid = {
+        let mut globals = globals();
+        let globals = globals.as_mut().expect("initialized by globals()");
+        let table = if rx { &mut globals.rx } else { &mut globals.tx };
+        match usize::try_from(protocol.0)
+            .ok()
+            .and_then(|id| table.get_mut(id))
+        {
+            Some(entry) => {
+                // Protocols this backend doesn't implement accept and
+                // ignore every setting.
+                if let Some(entry) = entry {
+                    update(entry);
+                }
+                true
+            }
+            None => false,
+        }
+    };
+    if !valid {
+        log("Invalid protocol id");
+    }
+}
+
+pub unsafe fn ggwave_rxToggleProtocol(protocolId: ggwave_ProtocolId, state: c_int) {
+    set_protocol(true, protocolId, |p| p.enabled = state != 0);
+}
+
+pub unsafe fn ggwave_txToggleProtocol(protocolId: ggwave_ProtocolId, state: c_int) {
+    set_protocol(false, protocolId, |p| p.enabled = state != 0);
+}
+
+pub unsafe fn ggwave_rxProtocolSetFreqStart(protocolId: ggwave_ProtocolId, freqStart: c_int) {
+    set_protocol(true, protocolId, |p| p.freq_start = freqStart);
+}
+
+pub unsafe fn ggwave_txProtocolSetFreqStart(protocolId: ggwave_ProtocolId, freqStart: c_int) {
+    set_protocol(false, protocolId, |p| p.freq_start = freqStart);
+}
+
+pub unsafe fn ggwave_rxDurationFrames(instance: ggwave_Instance) -> c_int {
+    let Some(instance) = self::instance(instance) else {
+        return -1;
+    };
+    let instance = instance.lock().unwrap_or_else(|e| e.into_inner());
+    instance
+        .receiver
+        .as_ref()
+        .map_or(0, |receiver| receiver.duration_frames() as c_int)
+}
+
+fn sample_bytes(format: ggwave_SampleFormat) -> Option<usize> {
+    use ggwave_SampleFormat as F;
+    match format {
+        F::GGWAVE_SAMPLE_FORMAT_U8 | F::GGWAVE_SAMPLE_FORMAT_I8 => Some(1),
+        F::GGWAVE_SAMPLE_FORMAT_U16 | F::GGWAVE_SAMPLE_FORMAT_I16 => Some(2),
+        F::GGWAVE_SAMPLE_FORMAT_F32 => Some(4),
+        _ => None,
+    }
+}
+
+/// Linear interpolation from one sample rate to another, for a whole
+/// waveform.
+fn resample(samples: &[f32], from: f32, to: f32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from as f64 / to as f64;
+    let len = ((samples.len() - 1) as f64 / step) as usize + 1;
+    (0..len)
+        .map(|j| {
+            let position = j as f64 * step;
+            let i = position as usize;
+            let frac = (position - i as f64) as f32;
+            let next = samples.get(i + 1).copied().unwrap_or(samples[i]);
+            samples[i] + (next - samples[i]) * frac
+        })
+        .collect()
+}
+
+/// [`resample`] for a stream fed in chunks.
+struct Resampler {
+    step: f64,
+    /// Position of the next output sample, counted from `previous`.
+    position: f64,
+    /// The last sample of the previous chunk.
+    previous: f32,
+}
+
+impl Resampler {
+    fn new(from: f32, to: f32) -> Self {
+        Self {
+            step: from as f64 / to as f64,
+            position: 1.0,
+            previous: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.step == 1.0 || input.is_empty() {
+            return input.to_vec();
+        }
+        // Index 0 is `previous`, index k is `input[k - 1]`.
+        let at = |k: usize| if k == 0 { self.previous } else { input[k - 1] };
+        let mut out = Vec::with_capacity((input.len() as f64 / self.step) as usize + 1);
+        while self.position <= input.len() as f64 {
+            let i = self.position as usize;
+            let frac = (self.position - i as f64) as f32;
+            let next = if i < input.len() { at(i + 1) } else { at(i) };
+            out.push(at(i) + (next - at(i)) * frac);
+            self.position += self.step;
+        }
+        self.position -= input.len() as f64;
+        self.previous = input[input.len() - 1];
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(parameters: ggwave_Parameters) -> Instance {
+        let rx: Table = std::array::from_fn(default_protocol);
+        let samples_per_frame = parameters.samplesPerFrame as usize;
+        let protocols = rx.iter().flatten().filter(|p| p.enabled).copied().collect();
+        Instance {
+            parameters,
+            tx: rx,
+            receiver: Some(Receiver::new(samples_per_frame, 3.0, protocols, None)),
+            input: Resampler::new(parameters.sampleRateInp, parameters.sampleRate),
+            partial_sample: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn every_protocol_roundtrips_in_chunks() {
+        let _serial = crate::serial();
+        let parameters = unsafe { ggwave_getDefaultParameters() };
+        for id in 0..6 {
+            let protocol = ggwave_ProtocolId(id);
+            let mut tx = instance(parameters);
+            let waveform = tx.encode(b"pure rust", protocol, 50).unwrap();
+
+            // Silence around the waveform, fed in odd-sized chunks.
+            let silence = vec![0u8; 4 * 777];
+            let stream = [&silence[..], &waveform, &silence].concat();
+            let mut decoded = None;
+            for chunk in stream.chunks(4 * 501) {
+                decoded = decoded.or(tx.decode(chunk).unwrap());
+            }
+            assert_eq!(decoded.as_deref(), Some(&b"pure rust"[..]), "protocol {id}");
+        }
+    }
+
+    #[test]
+    fn formats_and_rates_roundtrip() {
+        let _serial = crate::serial();
+        let mut parameters = unsafe { ggwave_getDefaultParameters() };
+        parameters.sampleRateInp = 44100.0;
+        parameters.sampleRateOut = 44100.0;
+        parameters.sampleFormatInp = ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_I16;
+        parameters.sampleFormatOut = ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_I16;
+        let mut ggwave = instance(parameters);
+        let protocol = ggwave_ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        let waveform = ggwave.encode(b"44.1 kHz", protocol, 25).unwrap();
+        assert_eq!(
+            ggwave.decode(&waveform).unwrap().as_deref(),
+            Some(&b"44.1 kHz"[..])
+        );
+    }
+
+    #[test]
+    fn fixed_length_roundtrip() {
+        let _serial = crate::serial();
+        let mut parameters = unsafe { ggwave_getDefaultParameters() };
+        parameters.payloadLength = 8;
+        let mut ggwave = instance(parameters);
+        ggwave.receiver = Some(Receiver::new(
+            1024,
+            3.0,
+            vec![default_protocol(1).unwrap()],
+            Some(8),
+        ));
+        let protocol = ggwave_ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        assert!(ggwave.encode(b"short", protocol, 50).is_err());
+        let waveform = ggwave.encode(b"fixed 8!", protocol, 50).unwrap();
+        let silence = vec![0u8; 4 * 1500];
+        let stream = [&silence[..], &waveform, &silence].concat();
+        let mut decoded = Vec::new();
+        for chunk in stream.chunks(4 * 1024) {
+            decoded.extend(ggwave.decode(chunk).unwrap());
+        }
+        assert_eq!(decoded, [b"fixed 8!".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_implement() {
+        let _serial = crate::serial();
+        let mut parameters = unsafe { ggwave_getDefaultParameters() };
+        let ggwave = instance(parameters);
+        let dt = ggwave_ProtocolId::GGWAVE_PROTOCOL_DT_FAST;
+        assert!(ggwave.encode(b"dt", dt, 50).is_err());
+        parameters.operatingMode |= GGWAVE_OPERATING_MODE_USE_DSS;
+        assert!(check_parameters(&parameters).is_err());
+    }
+
+    #[test]
+    fn resampler_streams_like_the_whole() {
+        let signal: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let whole = resample(&signal, 48000.0, 44100.0);
+        let mut resampler = Resampler::new(48000.0, 44100.0);
+        let streamed: Vec<f32> = signal
+            .chunks(77)
+            .flat_map(|chunk| resampler.process(chunk))
+            .collect();
+        assert_eq!(whole.len(), streamed.len());
+        for (a, b) in whole.iter().zip(&streamed) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
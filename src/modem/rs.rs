@@ -0,0 +1,235 @@
+//! Reed-Solomon over GF(2^8), the code upstream protects the length byte
+//! and the payload with.
+//!
+//! Primitive polynomial 0x11d, generator 2, first consecutive root 2^0.
+//! Code words are the message followed by the parity bytes, with the first
+//! byte as the highest-degree coefficient.
+
+const PRIMITIVE: u16 = 0x11d;
+
+struct Tables {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+const TABLES: Tables = {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= PRIMITIVE;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    Tables { exp, log }
+};
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    TABLES.exp[TABLES.log[a as usize] as usize + TABLES.log[b as usize] as usize]
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    debug_assert!(b != 0);
+    if a == 0 {
+        return 0;
+    }
+    TABLES.exp[TABLES.log[a as usize] as usize + 255 - TABLES.log[b as usize] as usize]
+}
+
+/// 2^power, for any power including negative ones.
+fn alpha(power: i32) -> u8 {
+    TABLES.exp[power.rem_euclid(255) as usize]
+}
+
+/// Evaluates a polynomial with ascending coefficients at `x`.
+fn eval_ascending(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// The generator polynomial (x - 2^0)(x - 2^1)..., highest degree first.
+fn generator(parity: usize) -> Vec<u8> {
+    let mut g = vec![1u8];
+    for i in 0..parity {
+        let root = alpha(i as i32);
+        let mut next = vec![0u8; g.len() + 1];
+        for (j, &c) in g.iter().enumerate() {
+            next[j] ^= c;
+            next[j + 1] ^= mul(c, root);
+        }
+        g = next;
+    }
+    g
+}
+
+/// Parity bytes for `message`, to be sent after it.
+pub(crate) fn encode(message: &[u8], parity: usize) -> Vec<u8> {
+    if parity == 0 {
+        return Vec::new();
+    }
+    let g = generator(parity);
+    let mut remainder = vec![0u8; parity];
+    for &byte in message {
+        let coef = byte ^ remainder[0];
+        remainder.rotate_left(1);
+        remainder[parity - 1] = 0;
+        if coef != 0 {
+            for (r, &gj) in remainder.iter_mut().zip(&g[1..]) {
+                *r ^= mul(gj, coef);
+            }
+        }
+    }
+    remainder
+}
+
+/// Corrects up to `parity / 2` byte errors in `codeword` in place. Returns
+/// `false`, leaving the code word in an unspecified state, if it has more
+/// errors than that.
+pub(crate) fn decode(codeword: &mut [u8], parity: usize) -> bool {
+    let n = codeword.len();
+    debug_assert!(parity < n && n <= 255);
+    // The byte at index k is the coefficient of x^(n - 1 - k).
+    let power_of = |k: usize| (n - 1 - k) as i32;
+
+    let syndromes = syndromes_of(codeword, parity);
+    if syndromes.iter().all(|&s| s == 0) {
+        return true;
+    }
+
+    // Berlekamp-Massey: the error locator, ascending, with roots at the
+    // inverses of the error positions.
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let mut errors = 0;
+    let mut shift = 1;
+    let mut last_discrepancy = 1u8;
+    for i in 0..parity {
+        let discrepancy = (1..=errors).fold(syndromes[i], |d, j| {
+            d ^ mul(*locator.get(j).unwrap_or(&0), syndromes[i - j])
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = div(discrepancy, last_discrepancy);
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (j, &p) in previous.iter().enumerate() {
+            next[j + shift] ^= mul(scale, p);
+        }
+        if 2 * errors <= i {
+            previous = std::mem::replace(&mut locator, next);
+            errors = i + 1 - errors;
+            last_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    if 2 * errors > parity || locator.iter().skip(errors + 1).any(|&c| c != 0) {
+        return false;
+    }
+    locator.truncate(errors + 1);
+
+    let positions: Vec<usize> = (0..n)
+        .filter(|&k| eval_ascending(&locator, alpha(-power_of(k))) == 0)
+        .collect();
+    if positions.len() != errors {
+        return false;
+    }
+
+    // Forney: the error evaluator is S(x) * locator(x) mod x^parity.
+    let mut evaluator = vec![0u8; parity];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate().take(parity - i) {
+            evaluator[i + j] ^= mul(s, l);
+        }
+    }
+    // Formal derivative: only odd powers survive in characteristic 2.
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(j, &l)| if j % 2 == 1 { l } else { 0 })
+        .collect();
+
+    for k in positions {
+        let x = alpha(power_of(k));
+        let x_inv = alpha(-power_of(k));
+        let denominator = eval_ascending(&derivative, x_inv);
+        if denominator == 0 {
+            return false;
+        }
+        codeword[k] ^= div(mul(x, eval_ascending(&evaluator, x_inv)), denominator);
+    }
+    syndromes_of(codeword, parity).iter().all(|&s| s == 0)
+}
+
+/// The code word evaluated at each root of the generator.
+fn syndromes_of(codeword: &[u8], parity: usize) -> Vec<u8> {
+    (0..parity)
+        .map(|j| {
+            let root = alpha(j as i32);
+            codeword.iter().fold(0, |acc, &c| mul(acc, root) ^ c)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrects_up_to_half_the_parity() {
+        let _serial = crate::serial();
+        let message: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37) ^ 0x5a).collect();
+        let parity = 16;
+        let clean: Vec<u8> = [message.clone(), encode(&message, parity)].concat();
+
+        let mut codeword = clean.clone();
+        assert!(decode(&mut codeword, parity));
+        assert_eq!(codeword, clean);
+
+        for errors in 1..=parity / 2 {
+            let mut codeword = clean.clone();
+            for e in 0..errors {
+                codeword[(e * 7 + 3) % clean.len()] ^= 0x80 | e as u8;
+            }
+            assert!(decode(&mut codeword, parity), "{errors} errors");
+            assert_eq!(codeword, clean, "{errors} errors");
+        }
+
+        let mut codeword = clean.clone();
+        for e in 0..=parity / 2 {
+            codeword[e * 5] ^= 0xff;
+        }
+        assert!(!decode(&mut codeword, parity) || codeword != clean);
+    }
+
+    #[test]
+    fn length_header() {
+        let _serial = crate::serial();
+        // Upstream's length byte: one data byte, two parity bytes.
+        let mut codeword = [42, 0, 0];
+        codeword[1..].copy_from_slice(&encode(&[42], 2));
+        let clean = codeword;
+        for k in 0..3 {
+            let mut damaged = clean;
+            damaged[k] ^= 0x11;
+            assert!(decode(&mut damaged, 2));
+            assert_eq!(damaged, clean);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Demodulation.
+//!
+//! The receiver takes a spectrum every quarter frame. In variable-length
+//! mode it waits for a start marker, keeps the spectra until the end marker,
+//! then looks for the alignment and protocol whose bytes pass the
+//! Reed-Solomon checks and whose length agrees with where the end marker
+//! was heard. In fixed-length mode there are no markers: every quarter frame
+//! it tries to decode the latest transmission-sized window.
+
+use std::collections::VecDeque;
+
+use super::tx::marker_offsets;
+use super::{ecc_len, rs, Protocol, LENGTH_BYTES, MARKER_FRAMES};
+use crate::{dsp, MAX_VARIABLE_PAYLOAD_LEN};
+
+/// Spectra per frame.
+const HOPS_PER_FRAME: usize = 4;
+const MARKER_HOPS: usize = MARKER_FRAMES * HOPS_PER_FRAME;
+/// Hops the data can start before the position implied by the first
+/// spectrum that matched the start marker, which is reached late when the
+/// marker's fade-in is buried in noise.
+const EARLY_START_HOPS: usize = 14;
+/// Hops it can start after that position, when windows only partly
+/// covering the marker already matched.
+const LATE_START_HOPS: usize = 4;
+/// Hops around the expected position where the end marker may be first
+/// matched: early when the data fades out, late in noise.
+const END_MARKER_SLACK: (usize, usize) = (6, 14);
+
+pub(crate) struct Receiver {
+    samples_per_frame: usize,
+    threshold: f32,
+    /// Enabled protocols this receiver can demodulate.
+    protocols: Vec<Protocol>,
+    fixed_len: Option<usize>,
+    /// Bins kept from each spectrum.
+    bins: std::ops::Range<usize>,
+    /// Samples not yet covered by a spectrum window.
+    samples: Vec<f32>,
+    spectra: VecDeque<Vec<f32>>,
+    /// Hop index of `spectra[0]`.
+    first_hop: usize,
+    state: State,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Listening,
+    Receiving {
+        start_hop: usize,
+        freq_start: i32,
+        /// Frames the transmission lasts, once the length byte is read.
+        total_frames: Option<usize>,
+    },
+}
+
+impl Receiver {
+    pub(crate) fn new(
+        samples_per_frame: usize,
+        threshold: f32,
+        protocols: Vec<Protocol>,
+        fixed_len: Option<usize>,
+    ) -> Self {
+        let lo = protocols.iter().map(|p| p.freq_start as usize).min();
+        let hi = protocols
+            .iter()
+            .map(|p| p.freq_start as usize + p.bins())
+            .max();
+        Self {
+            samples_per_frame,
+            threshold,
+            protocols,
+            fixed_len,
+            bins: lo.unwrap_or(0)..hi.unwrap_or(0),
+            samples: Vec::new(),
+            spectra: VecDeque::new(),
+            first_hop: 0,
+            state: State::Listening,
+            re: vec![0.0; samples_per_frame],
+            im: vec![0.0; samples_per_frame],
+        }
+    }
+
+    /// Feeds samples at the internal rate, returning the last payload they
+    /// completed.
+    pub(crate) fn feed(&mut self, samples: &[f32]) -> Option<Vec<u8>> {
+        if self.protocols.is_empty() {
+            return None;
+        }
+        self.samples.extend_from_slice(samples);
+        let n = self.samples_per_frame;
+        let hop = n / HOPS_PER_FRAME;
+        let mut payload = None;
+        let mut consumed = 0;
+        while self.samples.len() - consumed >= n {
+            self.push_spectrum(consumed);
+            consumed += hop;
+            if let Some(decoded) = self.step() {
+                payload = Some(decoded);
+            }
+        }
+        self.samples.drain(..consumed);
+        payload
+    }
+
+    /// Frames the transmission being received lasts: exact once its length
+    /// has been read, the longest possible before that, 0 when listening.
+    pub(crate) fn duration_frames(&self) -> usize {
+        match self.state {
+            State::Listening => 0,
+            State::Receiving {
+                total_frames: Some(frames),
+                ..
+            } => frames,
+            State::Receiving { freq_start, .. } => self.max_frames(freq_start),
+        }
+    }
+
+    fn max_frames(&self, freq_start: i32) -> usize {
+        self.candidates(freq_start)
+            .map(|p| 2 * MARKER_FRAMES + encoded_frames(p, MAX_VARIABLE_PAYLOAD_LEN))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn push_spectrum(&mut self, offset: usize) {
+        let n = self.samples_per_frame;
+        self.re.copy_from_slice(&self.samples[offset..offset + n]);
+        self.im.fill(0.0);
+        dsp::fft(&mut self.re, &mut self.im, false);
+        let spectrum = self
+            .bins
+            .clone()
+            .map(|k| self.re[k] * self.re[k] + self.im[k] * self.im[k])
+            .collect();
+        self.spectra.push_back(spectrum);
+    }
+
+    fn last_hop(&self) -> usize {
+        self.first_hop + self.spectra.len() - 1
+    }
+
+    fn spectrum(&self, hop: usize) -> Option<&[f32]> {
+        let index = hop.checked_sub(self.first_hop)?;
+        self.spectra.get(index).map(Vec::as_slice)
+    }
+
+    /// Drops the spectra before `hop`.
+    fn forget_before(&mut self, hop: usize) {
+        while self.first_hop < hop && !self.spectra.is_empty() {
+            self.spectra.pop_front();
+            self.first_hop += 1;
+        }
+    }
+
+    fn forget_all(&mut self) {
+        let next = self.first_hop + self.spectra.len();
+        self.forget_before(next);
+    }
+
+    fn candidates(&self, freq_start: i32) -> impl Iterator<Item = &Protocol> {
+        self.protocols
+            .iter()
+            .filter(move |p| p.freq_start == freq_start)
+    }
+
+    fn step(&mut self) -> Option<Vec<u8>> {
+        match self.fixed_len {
+            Some(len) => self.step_fixed(len),
+            None => self.step_variable(),
+        }
+    }
+
+    fn step_variable(&mut self) -> Option<Vec<u8>> {
+        let hop = self.last_hop();
+        match self.state {
+            State::Listening => {
+                let spectrum = self.spectrum(hop)?;
+                let found = self
+                    .protocols
+                    .iter()
+                    .map(|p| p.freq_start)
+                    .find(|&freq_start| self.matches_marker(spectrum, freq_start, true));
+                match found {
+                    Some(freq_start) => {
+                        self.state = State::Receiving {
+                            start_hop: hop,
+                            freq_start,
+                            total_frames: None,
+                        }
+                    }
+                    None => self.forget_before(hop + 1),
+                }
+                None
+            }
+            State::Receiving {
+                start_hop,
+                freq_start,
+                total_frames,
+            } => {
+                let elapsed = hop - start_hop;
+                if total_frames.is_none() {
+                    let total_frames = self.read_total_frames(start_hop, freq_start);
+                    if let State::Receiving {
+                        total_frames: slot, ..
+                    } = &mut self.state
+                    {
+                        *slot = total_frames;
+                    }
+                }
+                let data_started = elapsed > MARKER_HOPS + HOPS_PER_FRAME;
+                let spectrum = self.spectrum(hop)?;
+                if data_started && self.matches_marker(spectrum, freq_start, false) {
+                    let payload = self.analyse(start_hop, hop, freq_start);
+                    self.state = State::Listening;
+                    self.forget_all();
+                    return payload;
+                }
+                if elapsed > HOPS_PER_FRAME * self.max_frames(freq_start) + MARKER_HOPS {
+                    // The end marker never came.
+                    self.state = State::Listening;
+                    self.forget_all();
+                }
+                None
+            }
+        }
+    }
+
+    fn matches_marker(&self, spectrum: &[f32], freq_start: i32, start: bool) -> bool {
+        let base = freq_start as usize - self.bins.start;
+        marker_offsets(start).all(|offset| {
+            // The marker tone and the other bin of its pair.
+            let other = offset ^ 1;
+            spectrum[base + offset] > self.threshold * spectrum[base + other]
+        })
+    }
+
+    /// Data start hops to try, nearest the expected one first.
+    fn data_starts(start_hop: usize) -> impl Iterator<Item = usize> {
+        let expected = start_hop + MARKER_HOPS;
+        (0..=EARLY_START_HOPS).flat_map(move |back| {
+            let early = std::iter::once(expected - back);
+            let late = (1..=LATE_START_HOPS)
+                .contains(&back)
+                .then_some(expected + back);
+            early.chain(late)
+        })
+    }
+
+    /// Reads the length byte as soon as it has been received, for
+    /// [`duration_frames`](Self::duration_frames).
+    fn read_total_frames(&self, start_hop: usize, freq_start: i32) -> Option<usize> {
+        let data_start = start_hop + MARKER_HOPS;
+        self.candidates(freq_start).find_map(|protocol| {
+            let header_txs = LENGTH_BYTES.div_ceil(protocol.bytes_per_tx);
+            let mut reader = TxReader::new(self, protocol, data_start);
+            let len = reader.read_length(header_txs)?;
+            Some(2 * MARKER_FRAMES + encoded_frames(protocol, len))
+        })
+    }
+
+    fn analyse(&self, start_hop: usize, end_hop: usize, freq_start: i32) -> Option<Vec<u8>> {
+        for data_start in Self::data_starts(start_hop) {
+            for protocol in self.candidates(freq_start) {
+                let header_txs = LENGTH_BYTES.div_ceil(protocol.bytes_per_tx);
+                let mut reader = TxReader::new(self, protocol, data_start);
+                let Some(len) = reader.read_length(header_txs) else {
+                    continue;
+                };
+                let data_end = data_start + HOPS_PER_FRAME * encoded_frames(protocol, len);
+                let (early, late) = END_MARKER_SLACK;
+                if end_hop + early < data_end || end_hop > data_end + late {
+                    continue;
+                }
+                let parity = ecc_len(len);
+                let mut encoded = reader.read(LENGTH_BYTES + len + parity)?;
+                let data = &mut encoded[LENGTH_BYTES..];
+                if rs::decode(data, parity) {
+                    return Some(data[..len].to_vec());
+                }
+            }
+        }
+        None
+    }
+
+    fn step_fixed(&mut self, len: usize) -> Option<Vec<u8>> {
+        let hop = self.last_hop();
+        let parity = ecc_len(len);
+        let mut longest = 0;
+        for protocol in &self.protocols {
+            let frames = (len + parity).div_ceil(protocol.bytes_per_tx) * protocol.frames_per_tx;
+            longest = longest.max(frames);
+            // The window whose last frame is the newest spectrum.
+            let Some(data_start) = (hop + HOPS_PER_FRAME).checked_sub(HOPS_PER_FRAME * frames)
+            else {
+                continue;
+            };
+            let mut reader = TxReader::new(self, protocol, data_start);
+            reader.min_contrast = Some(self.threshold);
+            let Some(mut encoded) = reader.read(len + parity) else {
+                continue;
+            };
+            if rs::decode(&mut encoded, parity) {
+                encoded.truncate(len);
+                // Later windows would find the same transmission again.
+                self.forget_all();
+                return Some(encoded);
+            }
+        }
+        self.forget_before((hop + 1).saturating_sub(HOPS_PER_FRAME * longest));
+        None
+    }
+}
+
+/// Reads bytes from the spectra of one protocol starting at one hop.
+struct TxReader<'a> {
+    receiver: &'a Receiver,
+    protocol: &'a Protocol,
+    data_start: usize,
+    bytes: Vec<u8>,
+    /// When set, each tone must stand out from the rest of its nibble's
+    /// bins by this factor, so silence and noise aren't read as data.
+    min_contrast: Option<f32>,
+}
+
+impl<'a> TxReader<'a> {
+    fn new(receiver: &'a Receiver, protocol: &'a Protocol, data_start: usize) -> Self {
+        Self {
+            receiver,
+            protocol,
+            data_start,
+            bytes: Vec::new(),
+            min_contrast: None,
+        }
+    }
+
+    /// The length byte, if it is in range and its code word checks out.
+    fn read_length(&mut self, header_txs: usize) -> Option<usize> {
+        let mut header = self.read(header_txs * self.protocol.bytes_per_tx)?;
+        header.truncate(LENGTH_BYTES);
+        if !rs::decode(&mut header, LENGTH_BYTES - 1) {
+            return None;
+        }
+        let len = header[0] as usize;
+        (1..=MAX_VARIABLE_PAYLOAD_LEN).contains(&len).then_some(len)
+    }
+
+    /// The first `count` bytes, reading more transmissions as needed.
+    fn read(&mut self, count: usize) -> Option<Vec<u8>> {
+        while self.bytes.len() < count {
+            let tx = self.bytes.len() / self.protocol.bytes_per_tx;
+            self.read_tx(tx)?;
+        }
+        Some(self.bytes[..count].to_vec())
+    }
+
+    fn read_tx(&mut self, tx: usize) -> Option<()> {
+        let frames = self.protocol.frames_per_tx;
+        let bins = self.protocol.bins();
+        let base = self.protocol.freq_start as usize - self.receiver.bins.start;
+        let mut power = vec![0.0f32; bins];
+        for frame in 0..frames {
+            let hop = self.data_start + HOPS_PER_FRAME * (tx * frames + frame);
+            let spectrum = self.receiver.spectrum(hop)?;
+            for (p, s) in power.iter_mut().zip(&spectrum[base..base + bins]) {
+                *p += s;
+            }
+        }
+
+        let mut byte = 0u8;
+        for (nibble, group) in power.chunks_exact(16).enumerate() {
+            let (value, &max) = group
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .expect("16 bins");
+            if let Some(contrast) = self.min_contrast {
+                let rest = (group.iter().sum::<f32>() - max) / 15.0;
+                if max <= contrast * rest {
+                    return None;
+                }
+            }
+            if nibble % 2 == 0 {
+                byte = value as u8;
+            } else {
+                self.bytes.push(byte | (value as u8) << 4);
+            }
+        }
+        Some(())
+    }
+}
+
+/// Data frames of a variable-length transmission of `len` payload bytes.
+fn encoded_frames(protocol: &Protocol, len: usize) -> usize {
+    let bytes = LENGTH_BYTES + len + ecc_len(len);
+    bytes.div_ceil(protocol.bytes_per_tx) * protocol.frames_per_tx
+}
@@ -0,0 +1,105 @@
+//! Waveform synthesis.
+//!
+//! A transmission is a start marker, the encoded bytes a few at a time, and
+//! an end marker (markers are left out in fixed-length mode). Every tone is
+//! a whole number of cycles per frame, so a tone held over several frames
+//! is continuous.
+
+use std::f64::consts::PI;
+
+use super::{Protocol, MARKER_BITS, MARKER_FRAMES};
+
+/// Share of a tone's duration spent fading in, and again fading out.
+const FADE: f32 = 0.15;
+
+/// Bin offsets, from the protocol's first tone, of the marker tones: one of
+/// each pair of neighbouring bins. The end marker uses the other one.
+pub(crate) fn marker_offsets(start: bool) -> impl Iterator<Item = usize> {
+    (0..MARKER_BITS).map(move |i| 2 * i + usize::from((i % 2 == 1) == start))
+}
+
+/// Bin offsets of the tones carrying `bytes`: each nibble picks one of 16
+/// bins, two nibbles per byte.
+pub(crate) fn data_offsets(bytes: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    bytes.iter().enumerate().flat_map(|(j, &b)| {
+        [
+            32 * j + (b & 0x0f) as usize,
+            32 * j + 16 + (b >> 4) as usize,
+        ]
+    })
+}
+
+/// Frames the waveform for `encoded_len` bytes lasts.
+pub(crate) fn total_frames(encoded_len: usize, protocol: &Protocol, markers: bool) -> usize {
+    let data = encoded_len.div_ceil(protocol.bytes_per_tx) * protocol.frames_per_tx;
+    if markers {
+        data + 2 * MARKER_FRAMES
+    } else {
+        data
+    }
+}
+
+/// Synthesizes `encoded` at the internal sample rate, with frames of
+/// `samples_per_frame` samples and a peak level of about `volume` (0 to 1).
+pub(crate) fn synthesize(
+    encoded: &[u8],
+    protocol: &Protocol,
+    markers: bool,
+    volume: f32,
+    samples_per_frame: usize,
+) -> Vec<f32> {
+    let n = samples_per_frame;
+    let frames = total_frames(encoded.len(), protocol, markers);
+    let mut out = vec![0.0f32; frames * n];
+    let marker_frames = if markers { MARKER_FRAMES } else { 0 };
+    let data_frames = frames - 2 * marker_frames;
+
+    let mut offsets = Vec::with_capacity(32 * protocol.bytes_per_tx);
+    for (frame, samples) in out.chunks_exact_mut(n).enumerate() {
+        offsets.clear();
+        let (cycle, cycle_len) = if frame < marker_frames {
+            offsets.extend(marker_offsets(true));
+            (frame, MARKER_FRAMES)
+        } else if frame < marker_frames + data_frames {
+            let frame = frame - marker_frames;
+            let tx = frame / protocol.frames_per_tx;
+            let start = (tx * protocol.bytes_per_tx).min(encoded.len());
+            let end = (start + protocol.bytes_per_tx).min(encoded.len());
+            // The last group is padded with zero bytes.
+            let mut group = encoded[start..end].to_vec();
+            group.resize(protocol.bytes_per_tx, 0);
+            offsets.extend(data_offsets(&group));
+            (frame % protocol.frames_per_tx, protocol.frames_per_tx)
+        } else {
+            offsets.extend(marker_offsets(false));
+            (frame - marker_frames - data_frames, MARKER_FRAMES)
+        };
+
+        let scale = volume / offsets.len() as f32;
+        let n_tones = 32 * protocol.bytes_per_tx;
+        for &offset in &offsets {
+            let bin = protocol.freq_start as usize + offset;
+            // Spread the phases so the tones don't all peak together.
+            let phase = PI * offset as f64 / n_tones as f64;
+            for (i, sample) in samples.iter_mut().enumerate() {
+                let cycles = ((i * bin) % n) as f64 / n as f64;
+                let envelope = envelope(cycle * n + i, cycle_len * n);
+                *sample += scale * envelope * (2.0 * PI * cycles + phase).sin() as f32;
+            }
+        }
+    }
+    out
+}
+
+/// Gain at sample `k` of a tone `len` samples long.
+fn envelope(k: usize, len: usize) -> f32 {
+    let fade = FADE * len as f32;
+    let k = k as f32;
+    if k < fade {
+        k / fade
+    } else if k > len as f32 - fade {
+        (len as f32 - k) / fade
+    } else {
+        1.0
+    }
+}
@@ -0,0 +1,320 @@
+//! Background noise profiles and spectral subtraction.
+//!
+//! In consistently noisy places (machinery, ventilation) the noise spectrum
+//! changes little over time. A [`NoiseLearner`] averages the spectrum of a few
+//! seconds of ambient audio into a [`NoiseProfile`], and a [`Denoiser`]
+//! subtracts that floor from captured audio before it reaches the decoder.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::dsp::{fft, sqrt_hann};
+
+/// Default analysis frame length in samples.
+pub const DEFAULT_FRAME_LEN: usize = 1024;
+
+/// Fraction of each bin's magnitude that is always kept, so that bins
+/// dominated by noise are attenuated rather than zeroed out.
+const SPECTRAL_FLOOR: f32 = 0.05;
+
+/// Average magnitude per frequency bin of ambient noise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseProfile {
+    frame_len: usize,
+    floor: Vec<f32>,
+}
+
+impl NoiseProfile {
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Noise magnitude for bins `0..=frame_len / 2`.
+    pub fn floor(&self) -> &[f32] {
+        &self.floor
+    }
+
+    /// Denoises a complete recording; the output has the same length.
+    pub fn denoise(&self, samples: &[f32]) -> Vec<f32> {
+        let mut denoiser = Denoiser::new(self.clone());
+        let mut out = denoiser.process(samples);
+        out.extend(denoiser.process(&vec![0.0; self.frame_len]));
+        out.truncate(samples.len());
+        out
+    }
+
+    /// Saves the profile as `key=value` lines.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let floor: Vec<String> = self.floor.iter().map(f32::to_string).collect();
+        fs::write(
+            path,
+            format!("frame_len={}\nfloor={}\n", self.frame_len, floor.join(",")),
+        )
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut frame_len = None;
+        let mut floor = None;
+        for line in fs::read_to_string(path)?.lines() {
+            match line.split_once('=') {
+                Some(("frame_len", value)) => {
+                    frame_len = Some(
+                        value
+                            .trim()
+                            .parse::<usize>()
+                            .map_err(|_| invalid("invalid frame_len in noise profile"))?,
+                    );
+                }
+                Some(("floor", values)) => {
+                    floor = Some(
+                        values
+                            .split(',')
+                            .map(|v| v.trim().parse::<f32>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| invalid("invalid floor in noise profile"))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        let frame_len = frame_len.ok_or_else(|| invalid("noise profile has no frame_len"))?;
+        let floor = floor.ok_or_else(|| invalid("noise profile has no floor"))?;
+        if !frame_len.is_power_of_two() || frame_len < 4 {
+            return Err(invalid(
+                "noise profile frame_len isn't a power of two of at least 4",
+            ));
+        }
+        if floor.len() != frame_len / 2 + 1 {
+            return Err(invalid("noise profile floor doesn't match frame_len"));
+        }
+        Ok(Self { frame_len, floor })
+    }
+}
+
+/// Accumulates ambient audio into a [`NoiseProfile`].
+pub struct NoiseLearner {
+    frames: Frames,
+    sums: Vec<f64>,
+    count: usize,
+}
+
+impl NoiseLearner {
+    /// `frame_len` must be a power of two of at least 4; larger frames give
+    /// finer frequency resolution.
+    pub fn new(frame_len: usize) -> Self {
+        Self {
+            frames: Frames::new(frame_len),
+            sums: vec![0.0; frame_len / 2 + 1],
+            count: 0,
+        }
+    }
+
+    pub fn feed(&mut self, samples: &[f32]) {
+        let sums = &mut self.sums;
+        let count = &mut self.count;
+        self.frames.feed(samples, |re, im, _| {
+            for (sum, (r, i)) in sums.iter_mut().zip(re.iter().zip(im.iter())) {
+                *sum += r.hypot(*i) as f64;
+            }
+            *count += 1;
+        });
+    }
+
+    /// Number of frames learned so far.
+    pub fn frames(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the learned profile, or `None` if not even one frame was fed.
+    pub fn finish(&self) -> Option<NoiseProfile> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(NoiseProfile {
+            frame_len: self.frames.frame_len(),
+            floor: self
+                .sums
+                .iter()
+                .map(|sum| (sum / self.count as f64) as f32)
+                .collect(),
+        })
+    }
+}
+
+/// Streaming spectral subtraction of a [`NoiseProfile`].
+///
+/// Output is produced in steps of half a frame, so a call may return fewer
+/// samples than it was given; the rest comes out with later input.
+pub struct Denoiser {
+    profile: NoiseProfile,
+    frames: Frames,
+    overlap: Vec<f32>,
+}
+
+impl Denoiser {
+    pub fn new(profile: NoiseProfile) -> Self {
+        let frame_len = profile.frame_len;
+        Self {
+            profile,
+            frames: Frames::new(frame_len),
+            overlap: vec![0.0; frame_len / 2],
+        }
+    }
+
+    pub fn profile(&self) -> &NoiseProfile {
+        &self.profile
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let n = self.profile.frame_len;
+        let hop = n / 2;
+        let floor = &self.profile.floor;
+        let overlap = &mut self.overlap;
+        let mut out = Vec::with_capacity(samples.len() + hop);
+
+        self.frames.feed(samples, |re, im, window| {
+            for k in 0..=hop {
+                let magnitude = re[k].hypot(im[k]);
+                if magnitude <= 0.0 {
+                    continue;
+                }
+                let kept = (magnitude - floor[k]).max(SPECTRAL_FLOOR * magnitude);
+                let scale = kept / magnitude;
+                re[k] *= scale;
+                im[k] *= scale;
+                // Keep the spectrum conjugate-symmetric so the output is real.
+                if k != 0 && k != hop {
+                    re[n - k] *= scale;
+                    im[n - k] *= scale;
+                }
+            }
+            fft(re, im, true);
+
+            for i in 0..hop {
+                out.push(overlap[i] + re[i] * window[i]);
+                overlap[i] = re[hop + i] * window[hop + i];
+            }
+        });
+        out
+    }
+}
+
+/// Splits a stream into windowed, half-overlapping frames and transforms them.
+struct Frames {
+    window: Vec<f32>,
+    pending: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl Frames {
+    fn new(frame_len: usize) -> Self {
+        assert!(
+            frame_len.is_power_of_two() && frame_len >= 4,
+            "frame length must be a power of two"
+        );
+        Self {
+            window: sqrt_hann(frame_len),
+            pending: Vec::new(),
+            re: vec![0.0; frame_len],
+            im: vec![0.0; frame_len],
+        }
+    }
+
+    fn frame_len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Calls `on_frame` with the spectrum of every complete frame.
+    /// The window is passed along for synthesis.
+    fn feed(&mut self, samples: &[f32], mut on_frame: impl FnMut(&mut [f32], &mut [f32], &[f32])) {
+        let n = self.frame_len();
+        self.pending.extend_from_slice(samples);
+        while self.pending.len() >= n {
+            for i in 0..n {
+                self.re[i] = self.pending[i] * self.window[i];
+                self.im[i] = 0.0;
+            }
+            fft(&mut self.re, &mut self.im, false);
+            on_frame(&mut self.re, &mut self.im, &self.window);
+            self.pending.drain(..n / 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Rng;
+    use std::f32::consts::PI;
+
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut rng = Rng::new(seed);
+        (0..len)
+            .map(|_| (rng.unit() as f32 * 2.0 - 1.0) * 0.1)
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn silent_profile_passes_audio_through() {
+        let profile = NoiseProfile {
+            frame_len: 64,
+            floor: vec![0.0; 33],
+        };
+        let input = noise(1000, 1);
+        let output = profile.denoise(&input);
+        assert_eq!(output.len(), input.len());
+        // The first half frame fades in; the rest reconstructs exactly.
+        for (a, b) in output[32..].iter().zip(&input[32..]) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn learned_noise_is_suppressed_and_tone_kept() {
+        let mut learner = NoiseLearner::new(256);
+        assert!(learner.finish().is_none());
+        learner.feed(&noise(48_000, 7));
+        let profile = learner.finish().unwrap();
+        assert!(learner.frames() > 300);
+
+        let noisy = noise(8192, 99);
+        let denoised = profile.denoise(&noisy);
+        assert!(energy(&denoised) < energy(&noisy) * 0.25);
+
+        let tone: Vec<f32> = (0..8192)
+            .map(|i| 0.5 * (2.0 * PI * 1000.0 * i as f32 / 48_000.0).sin())
+            .collect();
+        let mixed: Vec<f32> = tone.iter().zip(&noisy).map(|(t, n)| t + n).collect();
+        let denoised = profile.denoise(&mixed);
+        assert!(energy(&denoised[256..]) > energy(&tone[256..]) * 0.8);
+    }
+
+    #[test]
+    fn profile_roundtrip() {
+        let mut learner = NoiseLearner::new(64);
+        learner.feed(&noise(640, 3));
+        let profile = learner.finish().unwrap();
+
+        let path = std::env::temp_dir().join(format!("ggwave_noise_{}", std::process::id()));
+        profile.save(&path).unwrap();
+        assert_eq!(NoiseProfile::load(&path).unwrap(), profile);
+
+        fs::write(&path, "frame_len=64\nfloor=1,2,3\n").unwrap();
+        assert!(NoiseProfile::load(&path).is_err());
+        for frame_len in [1, 2] {
+            let floor = vec!["0"; frame_len / 2 + 1].join(",");
+            fs::write(&path, format!("frame_len={frame_len}\nfloor={floor}\n")).unwrap();
+            let e = NoiseProfile::load(&path).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+        let _ = fs::remove_file(&path);
+    }
+}
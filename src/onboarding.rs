@@ -0,0 +1,594 @@
+//! Wi-Fi provisioning of headless devices over sound.
+//!
+//! Credentials use the `WIFI:` schema that phones already understand from QR
+//! codes (`WIFI:T:WPA;S:<ssid>;P:<psk>;;`). The text is split into tagged
+//! wire-format chunks that fit one transmission each. A [`Delivery`] cycles
+//! through the sequence until the device answers with an acknowledgement
+//! frame; the receiving [`Reassembler`] fills in whatever chunks it missed on
+//! earlier passes and produces that acknowledgement once the credentials are
+//! complete.
+//!
+//! With the `encryption` feature, [`Delivery::sealed`] encrypts the payload
+//! with a [`DeviceKey`] shared with the device (ChaCha20-Poly1305, a fresh
+//! random nonce per delivery), and the acknowledgement is authenticated with
+//! the same key. Without a key the passphrase is sent in the clear: anyone
+//! within earshot can recover it, so only provision in a trusted space.
+
+use std::fmt;
+
+use crate::wire::{Frame, FrameKind, WireError};
+
+/// Application tag of onboarding frames ("WI").
+pub const ONBOARDING_TAG: u16 = 0x5749;
+
+/// Application tag of the device's acknowledgement ("WA").
+pub const ONBOARDING_ACK_TAG: u16 = 0x5741;
+
+/// First byte of a sealed payload; plaintext payloads start with `WIFI:`.
+const SEALED_VERSION: u8 = 0x01;
+
+/// Longest payload a variable-length ggwave transmission carries.
+const MAX_FRAME_LEN: usize = crate::MAX_VARIABLE_PAYLOAD_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Wpa,
+    Wep,
+    Open,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WifiCredentials {
+    pub ssid: String,
+    pub security: Security,
+    /// Passphrase; ignored for open networks.
+    pub psk: Option<String>,
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnboardingError {
+    /// The text isn't a `WIFI:` payload or lacks the SSID.
+    InvalidPayload,
+    /// A frame couldn't be parsed or built.
+    Wire(WireError),
+    /// The credentials don't fit in 255 chunks.
+    TooLong,
+    /// The payload is sealed but the receiver has no device key.
+    KeyRequired,
+    /// The receiver has a device key but the payload was sent in the clear.
+    Unsealed,
+    /// The sealed payload failed authentication: wrong key or corrupted.
+    Decryption,
+}
+
+impl fmt::Display for OnboardingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OnboardingError::InvalidPayload => write!(f, "invalid Wi-Fi payload"),
+            OnboardingError::Wire(e) => write!(f, "{e}"),
+            OnboardingError::TooLong => write!(f, "Wi-Fi credentials are too long"),
+            OnboardingError::KeyRequired => {
+                write!(f, "onboarding payload is sealed; a device key is required")
+            }
+            OnboardingError::Unsealed => write!(f, "onboarding payload isn't sealed"),
+            OnboardingError::Decryption => {
+                write!(f, "onboarding payload failed authentication")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OnboardingError {}
+
+impl From<WireError> for OnboardingError {
+    fn from(e: WireError) -> Self {
+        OnboardingError::Wire(e)
+    }
+}
+
+impl WifiCredentials {
+    pub fn wpa(ssid: impl Into<String>, psk: impl Into<String>) -> Self {
+        Self {
+            ssid: ssid.into(),
+            security: Security::Wpa,
+            psk: Some(psk.into()),
+            hidden: false,
+        }
+    }
+
+    /// Formats the credentials in the `WIFI:` schema.
+    pub fn to_payload(&self) -> String {
+        let security = match self.security {
+            Security::Wpa => "WPA",
+            Security::Wep => "WEP",
+            Security::Open => "nopass",
+        };
+        let mut out = format!("WIFI:T:{security};S:{};", escape(&self.ssid));
+        if let (Some(psk), false) = (&self.psk, self.security == Security::Open) {
+            out.push_str(&format!("P:{};", escape(psk)));
+        }
+        if self.hidden {
+            out.push_str("H:true;");
+        }
+        out.push(';');
+        out
+    }
+
+    /// Parses a `WIFI:` payload.
+    pub fn parse(payload: &str) -> Result<Self, OnboardingError> {
+        let fields = payload
+            .strip_prefix("WIFI:")
+            .ok_or(OnboardingError::InvalidPayload)?;
+
+        let mut ssid = None;
+        let mut psk = None;
+        let mut security = Security::Wpa;
+        let mut hidden = false;
+        for field in split_unescaped(fields) {
+            let Some((key, value)) = field.split_once(':') else {
+                continue;
+            };
+            let value = unescape(value);
+            match key {
+                "S" => ssid = Some(value),
+                "P" => psk = Some(value),
+                "T" => {
+                    security = match value.as_str() {
+                        "WEP" => Security::Wep,
+                        "nopass" | "" => Security::Open,
+                        _ => Security::Wpa,
+                    }
+                }
+                "H" => hidden = value == "true",
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            ssid: ssid.ok_or(OnboardingError::InvalidPayload)?,
+            psk: psk.filter(|_| security != Security::Open),
+            security,
+            hidden,
+        })
+    }
+
+    /// Encoded frames carrying the credentials in the clear, in order; each
+    /// fits in one transmission.
+    pub fn frames(&self) -> Result<Vec<Vec<u8>>, OnboardingError> {
+        chunk_frames(self.to_payload().as_bytes())
+    }
+}
+
+/// A 32-byte key provisioned on the device (e.g. printed on its label) that
+/// seals the credentials in transit.
+#[cfg(feature = "encryption")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DeviceKey([u8; 32]);
+
+#[cfg(feature = "encryption")]
+impl DeviceKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl fmt::Debug for DeviceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeviceKey(..)")
+    }
+}
+
+/// Sender side of an onboarding: the frames to play and the receipt that
+/// confirms the device got them.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    frames: Vec<Vec<u8>>,
+    next: usize,
+    receipt: Vec<u8>,
+    confirmed: bool,
+}
+
+impl Delivery {
+    /// Sends the credentials in the clear.
+    pub fn new(creds: &WifiCredentials) -> Result<Self, OnboardingError> {
+        let payload = creds.to_payload().into_bytes();
+        Ok(Self::with_receipt(
+            chunk_frames(&payload)?,
+            plain_receipt(&payload),
+        ))
+    }
+
+    /// Seals the credentials with the device's key.
+    #[cfg(feature = "encryption")]
+    pub fn sealed(creds: &WifiCredentials, key: &DeviceKey) -> Result<Self, OnboardingError> {
+        let payload = sealed::seal(key, creds.to_payload().as_bytes())?;
+        Ok(Self::with_receipt(
+            chunk_frames(&payload)?,
+            sealed::receipt(key, &payload)?,
+        ))
+    }
+
+    fn with_receipt(frames: Vec<Vec<u8>>, receipt: Vec<u8>) -> Self {
+        Self {
+            frames,
+            next: 0,
+            receipt,
+            confirmed: false,
+        }
+    }
+
+    /// Every frame of the sequence, in order.
+    pub fn frames(&self) -> &[Vec<u8>] {
+        &self.frames
+    }
+
+    /// The next frame to transmit, cycling through the sequence, or `None`
+    /// once the device has confirmed.
+    pub fn next_frame(&mut self) -> Option<&[u8]> {
+        if self.confirmed || self.frames.is_empty() {
+            return None;
+        }
+        let frame = &self.frames[self.next];
+        self.next = (self.next + 1) % self.frames.len();
+        Some(frame)
+    }
+
+    /// Feeds a payload heard from the device. Returns `true` if it's the
+    /// acknowledgement of this delivery.
+    pub fn feed(&mut self, payload: &[u8]) -> bool {
+        let acked = matches!(
+            Frame::decode_for(payload, ONBOARDING_ACK_TAG),
+            Ok(Some(frame)) if frame.body == self.receipt
+        );
+        self.confirmed |= acked;
+        acked
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+}
+
+/// Collects onboarding chunks until the credentials are complete.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    chunks: Vec<Option<Vec<u8>>>,
+    #[cfg(feature = "encryption")]
+    key: Option<DeviceKey>,
+    ack: Option<Vec<u8>>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accepts payloads sealed with `key`.
+    #[cfg(feature = "encryption")]
+    pub fn with_key(key: DeviceKey) -> Self {
+        Self {
+            key: Some(key),
+            ..Self::default()
+        }
+    }
+
+    /// Feeds a decoded payload. Frames from other applications are ignored;
+    /// duplicates are harmless. Returns the credentials once every chunk has
+    /// arrived; [`ack_frame`](Self::ack_frame) then holds the reply for the
+    /// sender.
+    pub fn feed(&mut self, payload: &[u8]) -> Result<Option<WifiCredentials>, OnboardingError> {
+        let Some(frame) = Frame::decode_for(payload, ONBOARDING_TAG)? else {
+            return Ok(None);
+        };
+        let (index, count) = match frame.kind {
+            FrameKind::Chunk { index, count } => (index as usize, count as usize),
+            FrameKind::Message => (0, 1),
+        };
+        // A different chunk count means a new sequence has started.
+        if self.chunks.len() != count {
+            self.chunks = vec![None; count];
+        }
+        self.chunks[index] = Some(frame.body);
+
+        if self.chunks.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+        let payload: Vec<u8> = self.chunks.drain(..).flatten().flatten().collect();
+        let (text, receipt) = self.open(payload)?;
+        let text = String::from_utf8(text).map_err(|_| OnboardingError::InvalidPayload)?;
+        let creds = WifiCredentials::parse(&text)?;
+        self.ack = Some(
+            Frame::message(receipt)
+                .with_tag(ONBOARDING_ACK_TAG)
+                .encode()?,
+        );
+        Ok(Some(creds))
+    }
+
+    /// Acknowledgement to transmit back to the sender, once the credentials
+    /// are complete.
+    pub fn ack_frame(&self) -> Option<&[u8]> {
+        self.ack.as_deref()
+    }
+
+    /// Returns the plaintext payload and its receipt.
+    fn open(&self, payload: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), OnboardingError> {
+        let is_sealed = payload.first() == Some(&SEALED_VERSION);
+        #[cfg(feature = "encryption")]
+        match (&self.key, is_sealed) {
+            (Some(key), true) => {
+                return Ok((
+                    sealed::open(key, &payload)?,
+                    sealed::receipt(key, &payload)?,
+                ))
+            }
+            (Some(_), false) => return Err(OnboardingError::Unsealed),
+            (None, _) => {}
+        }
+        if is_sealed {
+            return Err(OnboardingError::KeyRequired);
+        }
+        let receipt = plain_receipt(&payload);
+        Ok((payload, receipt))
+    }
+
+    /// Number of chunks received of the current sequence, and its length.
+    pub fn progress(&self) -> (usize, usize) {
+        let received = self.chunks.iter().filter(|c| c.is_some()).count();
+        (received, self.chunks.len())
+    }
+}
+
+/// Splits a payload into tagged chunk frames that fit one transmission each.
+fn chunk_frames(payload: &[u8]) -> Result<Vec<Vec<u8>>, OnboardingError> {
+    let overhead = Frame::chunk(0, 1, Vec::new())
+        .with_tag(ONBOARDING_TAG)
+        .overhead();
+    let chunks: Vec<&[u8]> = payload.chunks(MAX_FRAME_LEN - overhead).collect();
+    let count = u8::try_from(chunks.len()).map_err(|_| OnboardingError::TooLong)?;
+
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, body)| {
+            Ok(Frame::chunk(index as u8, count, body.to_vec())
+                .with_tag(ONBOARDING_TAG)
+                .encode()?)
+        })
+        .collect()
+}
+
+/// Receipt of a cleartext payload: its 64-bit FNV-1a hash. It only tells
+/// deliveries apart; anyone who heard the payload can forge it.
+fn plain_receipt(payload: &[u8]) -> Vec<u8> {
+    let hash = payload.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash.to_le_bytes().to_vec()
+}
+
+/// Sealed payloads are `SEALED_VERSION || nonce || ciphertext`.
+#[cfg(feature = "encryption")]
+mod sealed {
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    use super::{DeviceKey, OnboardingError, SEALED_VERSION};
+
+    const NONCE_LEN: usize = 12;
+    const PAYLOAD_AAD: &[u8] = b"ggwave-onboarding";
+    const ACK_AAD: &[u8] = b"ggwave-onboarding-ack";
+
+    fn cipher(key: &DeviceKey) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&key.0))
+    }
+
+    fn nonce(sealed: &[u8]) -> Result<[u8; NONCE_LEN], OnboardingError> {
+        sealed
+            .get(1..1 + NONCE_LEN)
+            .and_then(|n| n.try_into().ok())
+            .ok_or(OnboardingError::Decryption)
+    }
+
+    pub(super) fn seal(key: &DeviceKey, text: &[u8]) -> Result<Vec<u8>, OnboardingError> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher(key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: text,
+                    aad: PAYLOAD_AAD,
+                },
+            )
+            .map_err(|_| OnboardingError::TooLong)?;
+        let mut out = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        out.push(SEALED_VERSION);
+        out.extend_from_slice(&nonce);
+        out.extend(ciphertext);
+        Ok(out)
+    }
+
+    pub(super) fn open(key: &DeviceKey, sealed: &[u8]) -> Result<Vec<u8>, OnboardingError> {
+        let nonce = nonce(sealed)?;
+        cipher(key)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &sealed[1 + NONCE_LEN..],
+                    aad: PAYLOAD_AAD,
+                },
+            )
+            .map_err(|_| OnboardingError::Decryption)
+    }
+
+    /// An authentication tag over nothing, under a nonce derived from the
+    /// payload's: only a holder of the key who saw this payload can make it.
+    pub(super) fn receipt(key: &DeviceKey, sealed: &[u8]) -> Result<Vec<u8>, OnboardingError> {
+        let mut nonce = nonce(sealed)?;
+        nonce[0] ^= 0x80;
+        cipher(key)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &[],
+                    aad: ACK_AAD,
+                },
+            )
+            .map_err(|_| OnboardingError::Decryption)
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Splits on `;` that isn't escaped.
+fn split_unescaped(fields: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in fields.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                out.push(&fields[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&fields[start..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_roundtrip_with_escaping() {
+        let creds = WifiCredentials {
+            ssid: "Cafe;Guest".into(),
+            security: Security::Wpa,
+            psk: Some(r"p\a:ss,word".into()),
+            hidden: true,
+        };
+        let payload = creds.to_payload();
+        assert_eq!(
+            payload,
+            r"WIFI:T:WPA;S:Cafe\;Guest;P:p\\a\:ss\,word;H:true;;"
+        );
+        assert_eq!(WifiCredentials::parse(&payload), Ok(creds));
+    }
+
+    #[test]
+    fn open_network_has_no_psk() {
+        let creds = WifiCredentials::parse("WIFI:T:nopass;S:Lobby;;").unwrap();
+        assert_eq!(creds.security, Security::Open);
+        assert_eq!(creds.psk, None);
+        assert_eq!(
+            WifiCredentials::parse("WIFI:T:WPA;P:x;;"),
+            Err(OnboardingError::InvalidPayload)
+        );
+    }
+
+    #[test]
+    fn reassembles_chunks_out_of_order_with_other_traffic() {
+        let _serial = crate::serial();
+        // Escaping doubles the longest legal SSID, pushing it past one frame.
+        let creds = WifiCredentials::wpa(";".repeat(32), "y".repeat(63));
+        let frames = creds.frames().unwrap();
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|f| f.len() <= MAX_FRAME_LEN));
+
+        let mut reassembler = Reassembler::new();
+        assert_eq!(
+            reassembler.feed(&Frame::message(b"hi".to_vec()).encode().unwrap()),
+            Ok(None)
+        );
+        for frame in frames.iter().rev().skip(1) {
+            assert_eq!(reassembler.feed(frame), Ok(None));
+            assert_eq!(reassembler.feed(frame), Ok(None));
+        }
+        assert_eq!(reassembler.progress(), (frames.len() - 1, frames.len()));
+        assert_eq!(reassembler.feed(frames.last().unwrap()), Ok(Some(creds)));
+    }
+
+    #[test]
+    fn delivery_stops_once_acknowledged() {
+        let _serial = crate::serial();
+        let creds = WifiCredentials::wpa("HomeNet", "correct horse");
+        let mut delivery = Delivery::new(&creds).unwrap();
+        let mut reassembler = Reassembler::new();
+        assert_eq!(reassembler.ack_frame(), None);
+
+        let frame = delivery.next_frame().unwrap().to_vec();
+        assert_eq!(reassembler.feed(&frame), Ok(Some(creds.clone())));
+        // Another delivery's ack doesn't confirm this one.
+        let other = Delivery::new(&WifiCredentials::wpa("HomeNet", "wrong")).unwrap();
+        let mut stale = Reassembler::new();
+        stale.feed(&other.frames()[0]).unwrap();
+        assert!(!delivery.feed(stale.ack_frame().unwrap()));
+        assert!(delivery.next_frame().is_some());
+
+        assert!(delivery.feed(reassembler.ack_frame().unwrap()));
+        assert!(delivery.is_confirmed());
+        assert_eq!(delivery.next_frame(), None);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn sealed_delivery_needs_the_device_key() {
+        let _serial = crate::serial();
+        let creds = WifiCredentials::wpa("HomeNet", "correct horse");
+        let key = DeviceKey::new([7; 32]);
+        let delivery = Delivery::sealed(&creds, &key).unwrap();
+        let feed_all = |reassembler: &mut Reassembler| {
+            let mut result = Ok(None);
+            for frame in delivery.frames() {
+                result = reassembler.feed(frame);
+            }
+            result
+        };
+
+        assert_eq!(
+            feed_all(&mut Reassembler::new()),
+            Err(OnboardingError::KeyRequired)
+        );
+        assert_eq!(
+            feed_all(&mut Reassembler::with_key(DeviceKey::new([8; 32]))),
+            Err(OnboardingError::Decryption)
+        );
+        let mut keyed = Reassembler::with_key(key.clone());
+        assert_eq!(
+            keyed.feed(&Delivery::new(&creds).unwrap().frames()[0]),
+            Err(OnboardingError::Unsealed)
+        );
+        assert_eq!(feed_all(&mut keyed), Ok(Some(creds)));
+
+        let mut delivery = delivery;
+        assert!(delivery.feed(keyed.ack_frame().unwrap()));
+    }
+}
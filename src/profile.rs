@@ -0,0 +1,122 @@
+//! Listening profiles trading decode coverage for power use.
+
+use crate::{set_rx_protocol_enabled, Error, Parameters, ProtocolId};
+
+const AUDIBLE: [ProtocolId; 3] = [
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+    ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST,
+];
+
+/// How aggressively a receiver spends CPU (and battery) on listening.
+///
+/// A profile bundles three knobs that are meant to change together:
+///
+/// - the set of protocols the decoder searches for,
+/// - the capture sample rate (`PowerSaver` decimates to 16 kHz, which still
+///   covers the audible protocols' band),
+/// - an energy gate that lets the receiver skip decoding near-silent chunks.
+///
+/// [`Receiver::set_profile`](crate::receiver::Receiver::set_profile) switches
+/// all three at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListeningProfile {
+    Performance,
+    Balanced,
+    PowerSaver,
+}
+
+impl ListeningProfile {
+    /// Whether the decoder searches for `protocol`. Custom protocols are
+    /// left as the application configured them.
+    pub fn receives(self, protocol: ProtocolId) -> bool {
+        match self {
+            ListeningProfile::Performance => !protocol.is_custom(),
+            ListeningProfile::Balanced => AUDIBLE.contains(&protocol) || protocol.is_ultrasound(),
+            ListeningProfile::PowerSaver => AUDIBLE.contains(&protocol),
+        }
+    }
+
+    pub fn rx_protocols(self) -> Vec<ProtocolId> {
+        ProtocolId::all().filter(|&p| self.receives(p)).collect()
+    }
+
+    /// Sample rate to capture audio at.
+    pub fn capture_sample_rate(self) -> f32 {
+        match self {
+            ListeningProfile::Performance | ListeningProfile::Balanced => 48000.0,
+            ListeningProfile::PowerSaver => 16000.0,
+        }
+    }
+
+    /// RMS level (linear, full scale = 1.0) below which a chunk is skipped.
+    pub fn gate_threshold(self) -> f32 {
+        match self {
+            ListeningProfile::Performance => 0.0,
+            ListeningProfile::Balanced => 0.001,
+            ListeningProfile::PowerSaver => 0.003,
+        }
+    }
+
+    /// Sets the input sample rate of parameters for a receiver using this profile.
+    pub fn apply(self, params: &mut Parameters) {
+        params.sampleRateInp = self.capture_sample_rate();
+    }
+
+    /// Enables exactly this profile's protocols for reception.
+    ///
+    /// The toggles are process-global and are picked up by instances created
+    /// afterwards, so this fails in `strict` builds.
+    pub fn enable_rx_protocols(self) -> Result<(), Error> {
+        for protocol in ProtocolId::all().filter(|p| !p.is_custom()) {
+            set_rx_protocol_enabled(protocol, self.receives(protocol))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a captured chunk of F32 samples is loud enough to decode.
+    pub fn admits(self, samples: &[f32]) -> bool {
+        let threshold = self.gate_threshold();
+        if threshold <= 0.0 {
+            return true;
+        }
+        if samples.is_empty() {
+            return false;
+        }
+        let mean_square =
+            samples.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / samples.len() as f64;
+        mean_square.sqrt() as f32 >= threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_sets_shrink_with_power() {
+        let performance = ListeningProfile::Performance.rx_protocols();
+        let balanced = ListeningProfile::Balanced.rx_protocols();
+        let saver = ListeningProfile::PowerSaver.rx_protocols();
+        assert!(balanced.iter().all(|p| performance.contains(p)));
+        assert!(saver.iter().all(|p| balanced.contains(p)));
+        assert!(saver.len() < balanced.len() && balanced.len() < performance.len());
+    }
+
+    #[test]
+    fn gate_skips_quiet_chunks() {
+        let quiet = vec![0.0005f32; 256];
+        let loud = vec![0.1f32; 256];
+        assert!(ListeningProfile::Performance.admits(&quiet));
+        assert!(!ListeningProfile::Balanced.admits(&quiet));
+        assert!(ListeningProfile::Balanced.admits(&loud));
+        assert!(!ListeningProfile::PowerSaver.admits(&[]));
+    }
+
+    #[test]
+    fn apply_sets_capture_rate() {
+        let mut params = crate::default_parameters();
+        ListeningProfile::PowerSaver.apply(&mut params);
+        assert_eq!(params.sampleRateInp, 16000.0);
+    }
+}
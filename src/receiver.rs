@@ -0,0 +1,529 @@
+//! F32 receiver with settings tuned to the acoustic environment.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::noise::{Denoiser, NoiseProfile};
+use crate::profile::ListeningProfile;
+use crate::toggles::{ProtocolToggles, RxProtocolGuard};
+use crate::{Error, GgWave, Parameters, ProtocolId, SampleFormat};
+
+/// Peak level calibration aims the recording at before decoding.
+const TARGET_PEAK: f32 = 0.5;
+const MIN_GAIN: f32 = 0.25;
+const MAX_GAIN: f32 = 16.0;
+
+/// Marker thresholds tried during calibration, lowest first.
+const MARKER_THRESHOLDS: [f32; 10] = [1.5, 2.0, 2.5, 3.0, 3.5, 4.0, 5.0, 6.0, 7.0, 8.0];
+
+/// Level at or above which a captured sample counts as clipped.
+const CLIP_LEVEL: f32 = 0.999;
+/// Consecutive full-scale samples that indicate clipping rather than a peak.
+const CLIP_RUN: usize = 3;
+/// Share of clipped samples in a window that counts as sustained clipping.
+const CLIP_RATIO: f32 = 0.001;
+
+/// Problems with the captured audio noticed while decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverWarning {
+    /// The input is overloaded; the microphone gain should be reduced.
+    /// Clipped ultrasound usually fails to decode without any other sign.
+    InputClipping,
+}
+
+/// Detects sustained clipping in a capture stream.
+///
+/// Clipping is judged over half-second windows, counting only runs of
+/// consecutive full-scale samples, so an isolated peak doesn't trigger it.
+#[derive(Debug, Clone)]
+pub struct ClipDetector {
+    window: usize,
+    seen: usize,
+    clipped: usize,
+    run: usize,
+    clipping: bool,
+}
+
+impl ClipDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            window: (sample_rate as usize / 2).max(1),
+            seen: 0,
+            clipped: 0,
+            run: 0,
+            clipping: false,
+        }
+    }
+
+    /// Feeds captured samples, returning whether the last complete window
+    /// was clipping.
+    pub fn feed(&mut self, samples: &[f32]) -> bool {
+        self.feed_any(samples);
+        self.clipping
+    }
+
+    /// Feeds captured samples, returning whether any window completed
+    /// during the call was clipping; for judging a whole recording at once.
+    pub fn feed_any(&mut self, samples: &[f32]) -> bool {
+        let mut any = false;
+        for &sample in samples {
+            if sample.abs() >= CLIP_LEVEL {
+                self.run += 1;
+                match self.run {
+                    CLIP_RUN => self.clipped += CLIP_RUN,
+                    run if run > CLIP_RUN => self.clipped += 1,
+                    _ => {}
+                }
+            } else {
+                self.run = 0;
+            }
+
+            self.seen += 1;
+            if self.seen == self.window {
+                self.clipping = self.clipped as f32 >= self.window as f32 * CLIP_RATIO;
+                any |= self.clipping;
+                self.seen = 0;
+                self.clipped = 0;
+            }
+        }
+        any
+    }
+
+    pub fn is_clipping(&self) -> bool {
+        self.clipping
+    }
+}
+
+/// Settings found by [`Receiver::calibrate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tuning {
+    /// Linear gain applied to captured samples before decoding.
+    pub gain: f32,
+    pub marker_threshold: f32,
+}
+
+impl Tuning {
+    /// Saves the tuning as `key=value` lines, to be reloaded in later sessions.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(
+            path,
+            format!(
+                "gain={}\nmarker_threshold={}\n",
+                self.gain, self.marker_threshold
+            ),
+        )
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut gain = None;
+        let mut marker_threshold = None;
+        for line in fs::read_to_string(path)?.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value: f32 = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid("invalid number in tuning file"))?;
+            match key.trim() {
+                "gain" => gain = Some(value),
+                "marker_threshold" => marker_threshold = Some(value),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            gain: gain.ok_or_else(|| invalid("tuning file has no gain"))?,
+            marker_threshold: marker_threshold
+                .ok_or_else(|| invalid("tuning file has no marker_threshold"))?,
+        })
+    }
+}
+
+/// Averages groups of `factor` samples, carrying a partial group over to the
+/// next chunk.
+#[derive(Debug, Clone)]
+struct Decimator {
+    factor: usize,
+    sum: f32,
+    count: usize,
+}
+
+impl Decimator {
+    fn new(factor: usize) -> Self {
+        Self {
+            factor,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let mut out = Vec::with_capacity(samples.len() / self.factor + 1);
+        for &sample in samples {
+            self.sum += sample;
+            self.count += 1;
+            if self.count == self.factor {
+                out.push(self.sum / self.factor as f32);
+                self.sum = 0.0;
+                self.count = 0;
+            }
+        }
+        out
+    }
+}
+
+/// Check a decoded payload must pass before [`Receiver::decode`] returns it;
+/// the error explains the rejection.
+pub type Validator = Box<dyn Fn(&[u8]) -> Result<(), String>>;
+
+/// Decodes captured F32 audio, applying a [`Tuning`].
+pub struct Receiver {
+    /// Parameters at the capture rate, before any profile is applied.
+    parameters: Parameters,
+    tuning: Tuning,
+    profile: Option<ListeningProfile>,
+    decimator: Option<Decimator>,
+    denoiser: Option<Denoiser>,
+    clip_detector: ClipDetector,
+    validators: Vec<Validator>,
+    rejected: u64,
+    last_rejection: Option<String>,
+    ggwave: GgWave,
+}
+
+impl Receiver {
+    /// Creates a receiver for F32 input; `parameters`' marker threshold is the
+    /// starting tuning, with unity gain.
+    pub fn new(mut parameters: Parameters) -> Result<Self, Error> {
+        parameters.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let tuning = Tuning {
+            gain: 1.0,
+            marker_threshold: parameters.soundMarkerThreshold,
+        };
+        Self::with_tuning(parameters, tuning)
+    }
+
+    /// Creates a receiver with a previously saved tuning.
+    pub fn with_tuning(mut parameters: Parameters, tuning: Tuning) -> Result<Self, Error> {
+        parameters.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        parameters.soundMarkerThreshold = tuning.marker_threshold;
+        Ok(Self {
+            parameters,
+            tuning,
+            profile: None,
+            decimator: None,
+            denoiser: None,
+            clip_detector: ClipDetector::new(parameters.sampleRateInp as u32),
+            validators: Vec::new(),
+            rejected: 0,
+            last_rejection: None,
+            ggwave: GgWave::new(parameters)?,
+        })
+    }
+
+    pub fn tuning(&self) -> Tuning {
+        self.tuning
+    }
+
+    /// Subtracts a learned background noise profile from captured audio
+    /// before decoding (and before calibrating).
+    pub fn set_noise_profile(&mut self, profile: Option<NoiseProfile>) {
+        self.denoiser = profile.map(Denoiser::new);
+    }
+
+    pub fn noise_profile(&self) -> Option<&NoiseProfile> {
+        self.denoiser.as_ref().map(Denoiser::profile)
+    }
+
+    /// Switches to a [`ListeningProfile`], e.g. to `PowerSaver` when the
+    /// battery runs low. From the next chunk on, quiet chunks are skipped,
+    /// input is decimated by the largest whole factor that keeps it at or
+    /// above the profile's capture rate, and only the profile's protocols
+    /// are searched for.
+    ///
+    /// The decoder is recreated, dropping any transmission in progress.
+    /// Narrowing the protocol set goes through the process-global toggles
+    /// (restored once the decoder exists), so it fails with
+    /// [`Error::GlobalStateForbidden`] in `strict` builds unless the set is
+    /// unchanged.
+    pub fn set_profile(&mut self, profile: ListeningProfile) -> Result<(), Error> {
+        let factor = (self.parameters.sampleRateInp / profile.capture_sample_rate())
+            .floor()
+            .max(1.0) as usize;
+        let mut parameters = self.parameters;
+        parameters.sampleRateInp /= factor as f32;
+        self.ggwave = with_rx_protocols(&profile.rx_protocols(), || GgWave::new(parameters))?;
+        self.decimator = (factor > 1).then(|| Decimator::new(factor));
+        self.profile = Some(profile);
+        Ok(())
+    }
+
+    pub fn profile(&self) -> Option<ListeningProfile> {
+        self.profile
+    }
+
+    /// Decodes a chunk of captured audio. Decoder state carries over between
+    /// calls, so a transmission may span several chunks.
+    ///
+    /// Check [`warning`](Self::warning) when nothing decodes. Payloads that
+    /// fail a validator are counted in [`rejected`](Self::rejected) and not
+    /// returned.
+    pub fn decode(&mut self, samples: &[f32]) -> Result<Option<Vec<u8>>, Error> {
+        self.clip_detector.feed(samples);
+        if self.profile.is_some_and(|profile| !profile.admits(samples)) {
+            return Ok(None);
+        }
+        let denoised;
+        let samples = match &mut self.denoiser {
+            Some(denoiser) => {
+                denoised = denoiser.process(samples);
+                &denoised
+            }
+            None => samples,
+        };
+        let decimated;
+        let samples = match &mut self.decimator {
+            Some(decimator) => {
+                decimated = decimator.process(samples);
+                &decimated
+            }
+            None => samples,
+        };
+        let waveform = amplified_bytes(samples, self.tuning.gain);
+        let Some(payload) = self.ggwave.decode(&waveform)? else {
+            return Ok(None);
+        };
+        if let Some(reason) = self.validators.iter().find_map(|v| v(&payload).err()) {
+            self.rejected += 1;
+            self.last_rejection = Some(reason);
+            return Ok(None);
+        }
+        Ok(Some(payload))
+    }
+
+    /// Adds a check every decoded payload must pass, e.g. a schema check that
+    /// keeps malformed or hostile input away from downstream systems.
+    /// Validators run in the order they were added.
+    pub fn add_validator(&mut self, validator: impl Fn(&[u8]) -> Result<(), String> + 'static) {
+        self.validators.push(Box::new(validator));
+    }
+
+    pub fn clear_validators(&mut self) {
+        self.validators.clear();
+    }
+
+    /// Number of decoded payloads rejected by a validator.
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Why the most recent payload was rejected.
+    pub fn last_rejection(&self) -> Option<&str> {
+        self.last_rejection.as_deref()
+    }
+
+    /// Problem with the recently captured audio, if any.
+    pub fn warning(&self) -> Option<ReceiverWarning> {
+        self.clip_detector
+            .is_clipping()
+            .then_some(ReceiverWarning::InputClipping)
+    }
+
+    /// Tunes gain and marker threshold from a recording of a known
+    /// transmission made in the current environment.
+    ///
+    /// The gain brings the recording's peak to a fixed level; the marker
+    /// threshold is the highest one that still decodes `expected`, backed off
+    /// one step for margin, since higher thresholds reject more noise.
+    /// Returns [`Error::DecodeFailed`] if `expected` can't be decoded at all,
+    /// leaving the current tuning in place.
+    pub fn calibrate(&mut self, recording: &[f32], expected: &[u8]) -> Result<Tuning, Error> {
+        let denoised;
+        let recording = match self.noise_profile() {
+            Some(profile) => {
+                denoised = profile.denoise(recording);
+                &denoised
+            }
+            None => recording,
+        };
+        let gain = calibration_gain(recording);
+        let waveform = amplified_bytes(recording, gain);
+
+        let mut decoded_at = Vec::new();
+        for threshold in MARKER_THRESHOLDS {
+            let mut params = self.parameters;
+            params.soundMarkerThreshold = threshold;
+            if GgWave::new(params)?.decode(&waveform)?.as_deref() == Some(expected) {
+                decoded_at.push(threshold);
+            }
+        }
+
+        let marker_threshold = match decoded_at.as_slice() {
+            [] => return Err(Error::DecodeFailed { code: 0 }),
+            [only] => *only,
+            [.., below, _] => *below,
+        };
+        let tuning = Tuning {
+            gain,
+            marker_threshold,
+        };
+        self.parameters.soundMarkerThreshold = marker_threshold;
+        match self.profile {
+            Some(profile) => self.set_profile(profile)?,
+            None => self.ggwave = GgWave::new(self.parameters)?,
+        }
+        self.tuning = tuning;
+        self.set_noise_profile(self.noise_profile().cloned());
+        Ok(tuning)
+    }
+}
+
+/// Runs `f` with exactly `protocols` (among the standard ones) enabled for
+/// reception, touching the global toggles only where they differ.
+fn with_rx_protocols<T>(
+    protocols: &[ProtocolId],
+    f: impl FnOnce() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let current = ProtocolToggles::snapshot();
+    let (on, off): (Vec<ProtocolId>, Vec<ProtocolId>) = ProtocolId::all()
+        .filter(|p| !p.is_custom() && protocols.contains(p) != current.rx_enabled(*p))
+        .partition(|p| protocols.contains(p));
+    let _on = (!on.is_empty())
+        .then(|| RxProtocolGuard::new(&on, true))
+        .transpose()?;
+    let _off = (!off.is_empty())
+        .then(|| RxProtocolGuard::new(&off, false))
+        .transpose()?;
+    f()
+}
+
+fn calibration_gain(recording: &[f32]) -> f32 {
+    let peak = recording.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 0.0 {
+        (TARGET_PEAK / peak).clamp(MIN_GAIN, MAX_GAIN)
+    } else {
+        1.0
+    }
+}
+
+fn amplified_bytes(samples: &[f32], gain: f32) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&s| (s * gain).clamp(-1.0, 1.0).to_ne_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning_roundtrip() {
+        let path = std::env::temp_dir().join(format!("ggwave_tuning_{}", std::process::id()));
+        let tuning = Tuning {
+            gain: 2.5,
+            marker_threshold: 3.5,
+        };
+        tuning.save(&path).unwrap();
+        assert_eq!(Tuning::load(&path).unwrap(), tuning);
+
+        fs::write(&path, "gain=2.5\n").unwrap();
+        assert!(Tuning::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn clip_detector_ignores_isolated_peaks() {
+        let mut detector = ClipDetector::new(1000);
+        let mut window = vec![0.0f32; 500];
+        window[10] = 1.0;
+        window[20] = -1.0;
+        assert!(!detector.feed(&window));
+
+        for s in &mut window[100..103] {
+            *s = 1.0;
+        }
+        // Not judged until the window is complete.
+        assert!(!detector.feed(&window[..250]));
+        assert!(detector.feed(&window[250..]));
+        assert!(!detector.feed(&[0.0; 500]));
+
+        let recording = [&window[..], &[0.0; 1000]].concat();
+        assert!(!detector.clone().feed(&recording));
+        assert!(detector.feed_any(&recording));
+    }
+
+    #[test]
+    fn validators_reject_payloads() {
+        let _serial = crate::serial();
+        let mut params = crate::default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let encoded = GgWave::new(params)
+            .unwrap()
+            .encode(
+                b"plain",
+                crate::ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+                25,
+            )
+            .unwrap();
+        let samples: Vec<f32> = encoded
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let mut receiver = Receiver::new(params).unwrap();
+        receiver.add_validator(|payload| {
+            payload
+                .starts_with(b"{")
+                .then_some(())
+                .ok_or_else(|| "not a JSON object".to_string())
+        });
+        assert_eq!(receiver.decode(&samples).unwrap(), None);
+        assert_eq!(receiver.rejected(), 1);
+        assert_eq!(receiver.last_rejection(), Some("not a JSON object"));
+    }
+
+    #[test]
+    fn power_saver_decimates_and_gates() {
+        let _serial = crate::serial();
+        let mut params = crate::default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let encoded = GgWave::new(params)
+            .unwrap()
+            .encode(b"saver", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .unwrap();
+        let samples: Vec<f32> = encoded
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let mut receiver = Receiver::new(params).unwrap();
+        let switched = receiver.set_profile(ListeningProfile::PowerSaver);
+        if crate::is_strict() {
+            assert!(matches!(switched, Err(Error::GlobalStateForbidden)));
+            return;
+        }
+        switched.unwrap();
+        assert_eq!(receiver.ggwave.parameters().sampleRateInp, 16000.0);
+        assert_eq!(ProtocolToggles::snapshot(), ProtocolToggles::DEFAULT);
+        // Quiet chunks never reach the decoder.
+        assert_eq!(receiver.decode(&[0.0005; 4096]).unwrap(), None);
+
+        let mut decoded = None;
+        // Odd chunk sizes exercise the decimator's carry-over.
+        for chunk in samples.chunks(1000).chain([&[0.0; 16384][..]]) {
+            decoded = decoded.or(receiver.decode(chunk).unwrap());
+        }
+        assert_eq!(decoded.as_deref(), Some(&b"saver"[..]));
+    }
+
+    #[test]
+    fn calibration_gain_targets_peak() {
+        assert_eq!(calibration_gain(&[0.1, -0.25]), 2.0);
+        assert_eq!(calibration_gain(&[0.001]), MAX_GAIN);
+        assert_eq!(calibration_gain(&[1.0]), 0.5);
+        assert_eq!(calibration_gain(&[]), 1.0);
+    }
+}
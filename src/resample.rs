@@ -0,0 +1,101 @@
+//! Decoding audio captured at another sample rate (`rubato` feature).
+
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+use crate::{dsp, Error, GgWave};
+
+/// Input frames per resampler call.
+const CHUNK: usize = 1024;
+
+/// Converts mono `samples` from `from` Hz to `to` Hz, keeping them aligned:
+/// the resampler's delay is trimmed and the tail flushed, so the result is
+/// as long as the input at the new rate.
+fn resample(samples: &[f32], from: u32, to: u32) -> Result<Vec<f32>, Error> {
+    if from == 0 || to == 0 {
+        return Err(Error::InvalidInput("sample rates must be positive"));
+    }
+    let parameters = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        oversampling_factor: 128,
+        interpolation: SincInterpolationType::Linear,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let mut resampler =
+        SincFixedIn::<f32>::new(f64::from(to) / f64::from(from), 1.0, parameters, CHUNK, 1)
+            .map_err(|_| Error::InvalidInput("can't resample between these sample rates"))?;
+
+    let delay = resampler.output_delay();
+    let expected = (samples.len() as u64 * u64::from(to)).div_ceil(u64::from(from)) as usize;
+    let mut resampled = Vec::with_capacity(delay + expected + CHUNK);
+    let mut chunks = samples.chunks_exact(CHUNK);
+    for chunk in &mut chunks {
+        resampled.extend_from_slice(&resampler.process(&[chunk], None)?[0]);
+    }
+    let mut tail = Some([chunks.remainder()]).filter(|[r]| !r.is_empty());
+    while resampled.len() < delay + expected {
+        let out = resampler.process_partial(tail.take().as_ref().map(|t| &t[..]), None)?;
+        resampled.extend_from_slice(&out[0]);
+    }
+    resampled.drain(..delay);
+    resampled.truncate(expected);
+    Ok(resampled)
+}
+
+impl GgWave {
+    /// Decodes mono audio captured at `input_rate` Hz, resampling it to the
+    /// instance's `sampleRateInp` first; for recordings whose rate isn't
+    /// known until they arrive.
+    ///
+    /// Audio already at `sampleRateInp` is decoded as is.
+    pub fn decode_resampled(
+        &self,
+        samples: &[f32],
+        input_rate: u32,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let expected_rate = self.parameters().sampleRateInp as u32;
+        let format = self.parameters().sampleFormatInp;
+        if input_rate == expected_rate {
+            return self.decode(&dsp::to_bytes(samples, format));
+        }
+        let resampled = resample(samples, input_rate, expected_rate)?;
+        self.decode(&dsp::to_bytes(&resampled, format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, ProtocolId, SampleFormat};
+
+    #[test]
+    fn decodes_audio_at_other_rates() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let ggwave = GgWave::new(params).unwrap();
+        let waveform = ggwave
+            .encode_f32(b"resampled", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .unwrap();
+        let rate = params.sampleRateOut as u32;
+
+        for input_rate in [rate, 44100, 22050, 37_123] {
+            let captured = resample(&waveform, rate, input_rate).unwrap();
+            assert_eq!(
+                captured.len(),
+                (waveform.len() as u64 * u64::from(input_rate)).div_ceil(u64::from(rate)) as usize
+            );
+            assert_eq!(
+                ggwave
+                    .decode_resampled(&captured, input_rate)
+                    .unwrap()
+                    .as_deref(),
+                Some(&b"resampled"[..]),
+                "{input_rate} Hz"
+            );
+        }
+        assert!(resample(&waveform, 0, rate).is_err());
+    }
+}
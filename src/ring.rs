@@ -0,0 +1,194 @@
+//! Handing captured audio from a real-time callback to a decode thread
+//! (`ringbuf` feature).
+//!
+//! Audio callbacks mustn't block or allocate, which rules out feeding a
+//! [`Decoder`] or sending on a channel from them. [`channel`] returns the
+//! two ends of a lock-free ring of F32 samples: the callback pushes into the
+//! [`RtProducer`], and a thread of its own drains the [`RtConsumer`] into a
+//! decoder. When the decode thread falls behind and the ring fills up, the
+//! samples that don't fit are dropped and counted in [`OverrunStats`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+
+use crate::{Decoder, Error};
+
+/// Samples fed to the decoder at a time.
+const DRAIN_CHUNK: usize = 4096;
+
+/// The two ends of a ring holding `capacity` samples.
+///
+/// The ring should hold a few frames' worth of audio more than the decode
+/// thread lags behind: a second of audio at the input rate is plenty.
+pub fn channel(capacity: usize) -> (RtProducer, RtConsumer) {
+    let (ring, drain) = HeapRb::new(capacity).split();
+    let stats = Arc::new(Stats::default());
+    (
+        RtProducer {
+            ring,
+            stats: stats.clone(),
+        },
+        RtConsumer {
+            ring: drain,
+            stats,
+            scratch: vec![0.0; DRAIN_CHUNK],
+        },
+    )
+}
+
+#[derive(Default)]
+struct Stats {
+    pushed: AtomicU64,
+    dropped: AtomicU64,
+    overruns: AtomicU64,
+}
+
+/// Counts kept by the producer, for spotting a decode thread that can't
+/// keep up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OverrunStats {
+    /// Samples that made it into the ring.
+    pub pushed: u64,
+    /// Samples dropped because the ring was full.
+    pub dropped: u64,
+    /// Pushes that dropped samples.
+    pub overruns: u64,
+}
+
+impl Stats {
+    fn snapshot(&self) -> OverrunStats {
+        OverrunStats {
+            pushed: self.pushed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The audio callback's end of the ring.
+pub struct RtProducer {
+    ring: HeapProd<f32>,
+    stats: Arc<Stats>,
+}
+
+impl RtProducer {
+    /// Pushes as many of `samples` (mono, at the decoder's input rate) as
+    /// fit, without blocking or allocating, and returns how many did. The
+    /// rest are dropped.
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let pushed = self.ring.push_slice(samples);
+        self.stats
+            .pushed
+            .fetch_add(pushed as u64, Ordering::Relaxed);
+        if pushed < samples.len() {
+            let dropped = (samples.len() - pushed) as u64;
+            self.stats.dropped.fetch_add(dropped, Ordering::Relaxed);
+            self.stats.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        pushed
+    }
+
+    pub fn stats(&self) -> OverrunStats {
+        self.stats.snapshot()
+    }
+}
+
+/// The decode thread's end of the ring.
+pub struct RtConsumer {
+    ring: HeapCons<f32>,
+    stats: Arc<Stats>,
+    scratch: Vec<f32>,
+}
+
+impl RtConsumer {
+    /// Feeds everything in the ring to `decoder`, whose input format must be
+    /// F32, calling `on_payload` with every payload completed. Returns the
+    /// number of samples drained.
+    ///
+    /// Call it periodically, e.g. every frame's duration.
+    pub fn drain_into<F>(
+        &mut self,
+        decoder: &mut Decoder,
+        mut on_payload: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(Vec<u8>),
+    {
+        let mut drained = 0;
+        loop {
+            let n = self.ring.pop_slice(&mut self.scratch);
+            if n == 0 {
+                return Ok(drained);
+            }
+            drained += n;
+            let mut decoded = decoder.feed_f32(&self.scratch[..n])?;
+            // Audio after a payload stays in the decoder and may hold another.
+            while let Some(payload) = decoded {
+                on_payload(payload);
+                decoded = decoder.feed(&[])?;
+            }
+        }
+    }
+
+    /// Samples waiting in the ring.
+    pub fn pending(&self) -> usize {
+        self.ring.occupied_len()
+    }
+
+    pub fn stats(&self) -> OverrunStats {
+        self.stats.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, GgWave, ProtocolId, SampleFormat};
+
+    #[test]
+    fn drains_into_a_decoder_and_counts_overruns() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let waveform = GgWave::new(params)
+            .unwrap()
+            .encode_f32(b"ring", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST, 25)
+            .unwrap();
+        let silence = vec![0.0f32; params.samplesPerFrame as usize * 8];
+        let audio = [waveform, silence].concat();
+
+        let (mut producer, mut consumer) = channel(8192);
+        let mut decoder = Decoder::new(params).unwrap();
+        let mut payloads = Vec::new();
+        for chunk in audio.chunks(512) {
+            assert_eq!(producer.push(chunk), chunk.len());
+            if consumer.pending() >= 4096 {
+                consumer
+                    .drain_into(&mut decoder, |p| payloads.push(p))
+                    .unwrap();
+            }
+        }
+        consumer
+            .drain_into(&mut decoder, |p| payloads.push(p))
+            .unwrap();
+        assert_eq!(payloads, [b"ring"]);
+        assert_eq!(consumer.pending(), 0);
+        assert_eq!(
+            producer.stats(),
+            OverrunStats {
+                pushed: audio.len() as u64,
+                dropped: 0,
+                overruns: 0,
+            }
+        );
+
+        assert_eq!(producer.push(&[0.0; 10_000]), 8192);
+        assert_eq!(producer.push(&[0.0; 10]), 0);
+        let stats = consumer.stats();
+        assert_eq!((stats.dropped, stats.overruns), (1818, 2));
+    }
+}
@@ -0,0 +1,184 @@
+//! Dispatching received payloads to per-type handlers.
+//!
+//! The payload type is carried in the wire frame's application tag, so a
+//! receiver that handles several kinds of traffic registers one handler per
+//! [`PayloadType`] instead of sniffing payloads itself. Untagged frames and
+//! raw payloads that aren't frames at all go to an optional fallback.
+
+use std::collections::HashMap;
+
+use crate::onboarding::ONBOARDING_TAG;
+use crate::wire::Frame;
+
+/// Application tag of URL frames ("UR").
+pub const URL_TAG: u16 = 0x5552;
+/// Application tag of one-time token frames ("TK").
+pub const TOKEN_TAG: u16 = 0x544b;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadType {
+    /// UTF-8 URL.
+    Url,
+    /// Wi-Fi credentials, see [`crate::onboarding`].
+    Wifi,
+    /// One-time password token, as sent by the `totp` module's
+    /// `TotpSender`.
+    Token,
+    /// Any other application tag.
+    Custom(u16),
+}
+
+impl PayloadType {
+    pub fn from_tag(tag: u16) -> Self {
+        match tag {
+            URL_TAG => PayloadType::Url,
+            ONBOARDING_TAG => PayloadType::Wifi,
+            TOKEN_TAG => PayloadType::Token,
+            tag => PayloadType::Custom(tag),
+        }
+    }
+
+    pub fn tag(self) -> u16 {
+        match self {
+            PayloadType::Url => URL_TAG,
+            PayloadType::Wifi => ONBOARDING_TAG,
+            PayloadType::Token => TOKEN_TAG,
+            PayloadType::Custom(tag) => tag,
+        }
+    }
+}
+
+type Handler<'a> = Box<dyn FnMut(&Frame) + 'a>;
+type Fallback<'a> = Box<dyn FnMut(&[u8]) + 'a>;
+
+/// What [`Router::route`] did with a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Routed {
+    /// Passed to the handler for this type.
+    Handled(PayloadType),
+    /// Passed to the fallback handler.
+    Fallback,
+    /// No handler was registered for it.
+    Dropped,
+}
+
+#[derive(Default)]
+pub struct Router<'a> {
+    handlers: HashMap<PayloadType, Handler<'a>>,
+    fallback: Option<Fallback<'a>>,
+}
+
+impl<'a> Router<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler for `kind`, replacing any previous one.
+    pub fn on(&mut self, kind: PayloadType, handler: impl FnMut(&Frame) + 'a) -> &mut Self {
+        self.handlers.insert(kind, Box::new(handler));
+        self
+    }
+
+    /// Registers the handler for payloads without a type: untagged frames and
+    /// bytes that don't parse as a frame.
+    pub fn fallback(&mut self, handler: impl FnMut(&[u8]) + 'a) -> &mut Self {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Dispatches one decoded payload.
+    pub fn route(&mut self, payload: &[u8]) -> Routed {
+        let tagged = Frame::decode(payload)
+            .ok()
+            .and_then(|frame| Some((PayloadType::from_tag(frame.tag?), frame)));
+        match tagged {
+            Some((kind, frame)) => match self.handlers.get_mut(&kind) {
+                Some(handler) => {
+                    handler(&frame);
+                    Routed::Handled(kind)
+                }
+                None => Routed::Dropped,
+            },
+            None => match &mut self.fallback {
+                Some(handler) => {
+                    handler(payload);
+                    Routed::Fallback
+                }
+                None => Routed::Dropped,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged(tag: u16, body: &[u8]) -> Vec<u8> {
+        Frame::message(body.to_vec())
+            .with_tag(tag)
+            .encode()
+            .unwrap()
+    }
+
+    #[test]
+    fn tags_map_to_types() {
+        for kind in [
+            PayloadType::Url,
+            PayloadType::Wifi,
+            PayloadType::Token,
+            PayloadType::Custom(7),
+        ] {
+            assert_eq!(PayloadType::from_tag(kind.tag()), kind);
+        }
+    }
+
+    #[test]
+    fn routes_by_type() {
+        let _serial = crate::serial();
+        let mut urls = Vec::new();
+        let mut raw = Vec::new();
+        {
+            let mut router = Router::new();
+            router
+                .on(PayloadType::Url, |frame| urls.push(frame.body.clone()))
+                .fallback(|payload| raw.push(payload.to_vec()));
+
+            assert_eq!(
+                router.route(&tagged(URL_TAG, b"https://example.com")),
+                Routed::Handled(PayloadType::Url)
+            );
+            assert_eq!(router.route(&tagged(TOKEN_TAG, b"123")), Routed::Dropped);
+            assert_eq!(router.route(b"plain text"), Routed::Fallback);
+            let untagged = Frame::message(b"hi".to_vec()).encode().unwrap();
+            assert_eq!(router.route(&untagged), Routed::Fallback);
+        }
+        assert_eq!(urls, [b"https://example.com".to_vec()]);
+        assert_eq!(raw.len(), 2);
+        assert_eq!(raw[0], b"plain text");
+    }
+
+    #[cfg(feature = "totp")]
+    #[test]
+    fn routes_totp_tokens() {
+        let _serial = crate::serial();
+        use crate::totp::{Token, TotpConfig, TotpSender, TotpVerifier};
+
+        let config = TotpConfig::new("door-1", b"secret".to_vec()).unwrap();
+        let mut verifier = TotpVerifier::new(config.clone(), 1);
+        let now = 1_700_000_000;
+        let mut verified = Vec::new();
+        let mut router = Router::new();
+        router
+            .on(PayloadType::Token, |frame| {
+                let token = Token::decode(&frame.body).unwrap();
+                verified.push(verifier.verify_token(&token, now));
+            })
+            .fallback(|_| panic!("token routed to the fallback"));
+
+        let payload = TotpSender::new(config).token_at(now);
+        assert_eq!(router.route(&payload), Routed::Handled(PayloadType::Token));
+        drop(router);
+        assert_eq!(verified, [Ok(())]);
+    }
+}
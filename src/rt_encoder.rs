@@ -0,0 +1,163 @@
+//! Encoding from latency-sensitive contexts.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{check_volume, Error, GgWave, Parameters, ProtocolId};
+
+/// An encoder for one protocol that does all its allocating and size
+/// queries up front.
+///
+/// [`GgWave::encode_into`] asks the library for the waveform size whenever
+/// the payload length or protocol changes. `RtEncoder` asks for every
+/// payload length it will encode when it's created, and keeps a buffer for
+/// the longest waveform, so neither [`encode`](Self::encode) nor
+/// [`encode_into`](Self::encode_into) allocates or makes a size query: each
+/// is one call into the library's encoder. That makes them suitable for an
+/// audio callback or another thread that mustn't stall. (The C++ encoder
+/// works in buffers allocated with the instance; the `pure-rust` backend's
+/// does allocate.)
+pub struct RtEncoder {
+    ggwave: GgWave,
+    protocol: ProtocolId,
+    /// Waveform size by payload length; 0 for lengths that can't be encoded.
+    sizes: Vec<usize>,
+    buffer: Vec<u8>,
+}
+
+impl RtEncoder {
+    /// An encoder for payloads of up to `max_payload_len` bytes with
+    /// `protocol`. In fixed-length mode (`payloadLength` set) only that
+    /// length is prepared, and `max_payload_len` must cover it.
+    pub fn new(
+        parameters: Parameters,
+        protocol: ProtocolId,
+        max_payload_len: usize,
+    ) -> Result<Self, Error> {
+        let ggwave = GgWave::new(parameters)?;
+        let lengths = match usize::try_from(parameters.payloadLength) {
+            Ok(fixed) if fixed > 0 => fixed..=fixed,
+            _ => 1..=max_payload_len,
+        };
+        if *lengths.end() > max_payload_len {
+            return Err(Error::InvalidInput(
+                "max_payload_len is shorter than the fixed payload length",
+            ));
+        }
+
+        let mut sizes = vec![0; max_payload_len + 1];
+        for len in lengths {
+            sizes[len] = ggwave.encoded_size(len, protocol)?;
+        }
+        let longest = sizes.iter().copied().max().unwrap_or(0);
+        Ok(Self {
+            ggwave,
+            protocol,
+            sizes,
+            buffer: vec![0; longest],
+        })
+    }
+
+    /// Encodes `payload` into the encoder's own buffer and returns the
+    /// waveform, valid until the next call.
+    pub fn encode(&mut self, payload: &[u8], volume: i32) -> Result<&[u8], Error> {
+        let mut buffer = core::mem::take(&mut self.buffer);
+        let written = self.encode_into(payload, volume, &mut buffer);
+        self.buffer = buffer;
+        Ok(&self.buffer[..written?])
+    }
+
+    /// Encodes `payload` into `out`, returning the number of bytes written.
+    ///
+    /// Fails with [`Error::BufferTooSmall`] when `out` can't hold the
+    /// waveform; [`max_waveform_len`](Self::max_waveform_len) always fits.
+    pub fn encode_into(&self, payload: &[u8], volume: i32, out: &mut [u8]) -> Result<usize, Error> {
+        check_volume(volume)?;
+        let size = match self.sizes.get(payload.len()) {
+            Some(&size) if size > 0 => size,
+            Some(_) => {
+                return Err(Error::InvalidInput(
+                    "payload length can't be encoded with these parameters",
+                ))
+            }
+            None => {
+                return Err(Error::PayloadTooLarge {
+                    len: payload.len(),
+                    max: self.sizes.len() - 1,
+                    protocol: self.protocol,
+                })
+            }
+        };
+        if out.len() < size {
+            return Err(Error::BufferTooSmall {
+                needed_hint: Some(size),
+                provided: out.len(),
+            });
+        }
+        self.ggwave
+            .encode_unchecked(payload, self.protocol, volume, out)
+    }
+
+    /// Size of the waveform for a `payload_len`-byte payload, if the
+    /// encoder was prepared for that length.
+    pub fn encoded_size(&self, payload_len: usize) -> Option<usize> {
+        self.sizes
+            .get(payload_len)
+            .copied()
+            .filter(|&size| size > 0)
+    }
+
+    /// Size of the longest waveform the encoder produces.
+    pub fn max_waveform_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn protocol(&self) -> ProtocolId {
+        self.protocol
+    }
+
+    pub fn ggwave(&self) -> &GgWave {
+        &self.ggwave
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+
+    #[test]
+    fn prepares_every_length_up_front() {
+        let _serial = crate::serial();
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        let mut encoder = RtEncoder::new(default_parameters(), protocol, 16).unwrap();
+        let ggwave = GgWave::new(default_parameters()).unwrap();
+        for len in 1..=16 {
+            assert_eq!(
+                encoder.encoded_size(len),
+                Some(ggwave.encoded_size(len, protocol).unwrap())
+            );
+        }
+        assert_eq!(encoder.encoded_size(0), None);
+        assert_eq!(encoder.encoded_size(17), None);
+        assert_eq!(
+            encoder.max_waveform_len(),
+            ggwave.encoded_size(16, protocol).unwrap()
+        );
+
+        let waveform = encoder.encode(b"realtime", 25).unwrap().to_vec();
+        assert_eq!(waveform, ggwave.encode(b"realtime", protocol, 25).unwrap());
+        assert!(matches!(
+            encoder.encode(&[0; 17], 25),
+            Err(Error::PayloadTooLarge {
+                len: 17,
+                max: 16,
+                ..
+            })
+        ));
+        assert!(matches!(
+            encoder.encode_into(b"hi", 25, &mut [0; 16]),
+            Err(Error::BufferTooSmall { provided: 16, .. })
+        ));
+    }
+}
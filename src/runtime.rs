@@ -0,0 +1,274 @@
+//! Process-wide setup and teardown of the C library's global state.
+//!
+//! The C library keeps its log destination, protocol toggles and instance
+//! table in globals. [`init`] configures them once for the whole process, and
+//! [`shutdown`] puts the toggles back to their defaults once every instance
+//! has been dropped.
+
+use std::ffi::c_void;
+use std::fs::File;
+use std::sync::Mutex;
+
+use crate::{check_global_state, ffi, ffi_lock, set_rx_protocol_enabled, set_tx_protocol_enabled};
+use crate::{instances, Error, ProtocolId};
+
+static CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+static LOG_STREAM: Mutex<Option<LogStream>> = Mutex::new(None);
+
+/// A C stream opened by [`set_log_output`], closed once it's replaced.
+struct LogStream(*mut c_void);
+
+// Only touched while LOG_STREAM is locked.
+unsafe impl Send for LogStream {}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        // Streams are only ever opened on Unix.
+        #[cfg(unix)]
+        unsafe {
+            libc::fclose(self.0.cast());
+        }
+    }
+}
+
+/// Global settings applied by [`init`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Stop the C library from logging to stderr.
+    pub silence_log: bool,
+    /// Enable exactly these protocols for reception; `None` keeps the current
+    /// toggles.
+    pub rx_protocols: Option<Vec<ProtocolId>>,
+    /// Enable exactly these protocols for transmission; `None` keeps the
+    /// current toggles.
+    pub tx_protocols: Option<Vec<ProtocolId>>,
+}
+
+impl Config {
+    fn mutates_global_state(&self) -> bool {
+        self.silence_log || self.rx_protocols.is_some() || self.tx_protocols.is_some()
+    }
+}
+
+/// Applies `config` to the C library's global state.
+///
+/// Calling it again with the same config is a no-op; a different config
+/// fails until [`shutdown`] is called. In `strict` builds any config that
+/// would change global state fails with [`Error::GlobalStateForbidden`].
+pub fn init(config: Config) -> Result<(), Error> {
+    let mut current = CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+    match current.as_ref() {
+        Some(existing) if *existing == config => return Ok(()),
+        Some(_) => {
+            return Err(Error::InvalidInput(
+                "ggwave runtime already initialized with a different config",
+            ))
+        }
+        None => {}
+    }
+
+    if config.mutates_global_state() {
+        check_global_state()?;
+    }
+    if config.silence_log {
+        set_log_output(None)?;
+    }
+    if let Some(enabled) = &config.rx_protocols {
+        for protocol in ProtocolId::all() {
+            set_rx_protocol_enabled(protocol, enabled.contains(&protocol))?;
+        }
+    }
+    if let Some(enabled) = &config.tx_protocols {
+        for protocol in ProtocolId::all() {
+            set_tx_protocol_enabled(protocol, enabled.contains(&protocol))?;
+        }
+    }
+
+    *current = Some(config);
+    Ok(())
+}
+
+/// Sends the C library's diagnostic log, which goes to stderr by default,
+/// to `output`, or discards it with `None`.
+///
+/// The file stays open until the log is redirected again. To go back to
+/// stderr, pass a duplicate of it:
+/// `File::from(std::io::stderr().as_fd().try_clone_to_owned()?)`. Other
+/// threads shouldn't be using ggwave at the time, unless the `send` feature
+/// serializes the calls. Redirecting to a file needs a Unix platform.
+///
+/// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+pub fn set_log_output(output: Option<File>) -> Result<(), Error> {
+    check_global_state()?;
+    let stream = output.map(open_stream).transpose()?;
+    let mut current = LOG_STREAM.lock().unwrap_or_else(|e| e.into_inner());
+    let _lock = ffi_lock();
+    let raw = stream.as_ref().map_or(std::ptr::null_mut(), |s| s.0);
+    unsafe { ffi::ggwave_setLogFile(raw) };
+    // Closes the previous stream, if any.
+    *current = stream;
+    Ok(())
+}
+
+/// Discards the C library's diagnostic log; `set_log_output(None)`.
+pub fn disable_logging() -> Result<(), Error> {
+    set_log_output(None)
+}
+
+/// Re-emits the C library's diagnostic log through the `log` crate, with
+/// the `ggwave` target: failures at warn level, everything else at debug.
+///
+/// The log goes through a pipe read by a background thread, which ends when
+/// the log is redirected again. Fails like [`set_log_output`].
+#[cfg(all(feature = "log", unix))]
+pub fn forward_log() -> Result<(), Error> {
+    use std::io::{BufRead, BufReader};
+    use std::os::fd::OwnedFd;
+
+    check_global_state()?;
+    let (reader, writer) = std::io::pipe()
+        .map_err(|_| Error::InvalidInput("can't create a pipe for the ggwave log"))?;
+    std::thread::Builder::new()
+        .name("ggwave-log".into())
+        .spawn(move || {
+            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                let line = line.trim_end();
+                if !line.is_empty() {
+                    log::log!(target: "ggwave", log_level(line), "{line}");
+                }
+            }
+        })
+        .map_err(|_| Error::InvalidInput("can't start the ggwave log thread"))?;
+    set_log_output(Some(File::from(OwnedFd::from(writer))))
+}
+
+/// Upstream has no levels; failures are the lines worth surfacing.
+#[cfg(all(feature = "log", unix))]
+fn log_level(line: &str) -> log::Level {
+    let line = line.to_ascii_lowercase();
+    if ["fail", "error", "invalid"]
+        .iter()
+        .any(|word| line.contains(word))
+    {
+        log::Level::Warn
+    } else {
+        log::Level::Debug
+    }
+}
+
+#[cfg(unix)]
+fn open_stream(file: File) -> Result<LogStream, Error> {
+    use std::os::fd::IntoRawFd;
+
+    let fd = file.into_raw_fd();
+    let stream = unsafe { libc::fdopen(fd, c"a".as_ptr()) };
+    if stream.is_null() {
+        unsafe { libc::close(fd) };
+        return Err(Error::InvalidInput("can't open the log file as a C stream"));
+    }
+    // Line buffering, so log lines show up as they're written.
+    unsafe { libc::setvbuf(stream, std::ptr::null_mut(), libc::_IOLBF, 0) };
+    Ok(LogStream(stream.cast()))
+}
+
+#[cfg(not(unix))]
+fn open_stream(_: File) -> Result<LogStream, Error> {
+    Err(Error::InvalidInput(
+        "redirecting the ggwave log needs a Unix platform",
+    ))
+}
+
+/// Whether [`init`] has been called since the last [`shutdown`].
+pub fn is_initialized() -> bool {
+    CONFIG.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Restores the default protocol toggles (standard protocols on, custom ones
+/// off) so that [`init`] can be called again.
+///
+/// Fails while any `GgWave` instance is alive. Log silencing is not undone,
+/// as the C library offers no way to restore its default destination.
+///
+/// The check doesn't stop other threads from creating instances: one created
+/// while `shutdown` runs may start with the toggles `init` set, the
+/// defaults, or a mix of both. Call it once the threads that use ggwave have finished.
+pub fn shutdown() -> Result<(), Error> {
+    let mut current = CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+    if live_instances() > 0 {
+        return Err(Error::InvalidInput(
+            "ggwave instances are still alive at shutdown",
+        ));
+    }
+    if let Some(config) = current.take() {
+        if config.rx_protocols.is_some() || config.tx_protocols.is_some() {
+            for protocol in ProtocolId::all() {
+                let standard = protocol.as_raw() < ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0.as_raw();
+                if config.rx_protocols.is_some() {
+                    set_rx_protocol_enabled(protocol, standard)?;
+                }
+                if config.tx_protocols.is_some() {
+                    set_tx_protocol_enabled(protocol, standard)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Number of `GgWave` instances currently alive in this process.
+pub fn live_instances() -> usize {
+    instances::live()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_strict;
+
+    /// The log tests replace the same global stream.
+    static LOG_TESTS: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn log_output_can_be_redirected_and_disabled() {
+        let _serial = LOG_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let path = std::env::temp_dir().join(format!("ggwave-log-{}.txt", std::process::id()));
+        let file = File::create(&path).unwrap();
+        if is_strict() {
+            assert!(matches!(
+                set_log_output(Some(file)),
+                Err(Error::GlobalStateForbidden)
+            ));
+            return;
+        }
+
+        set_log_output(Some(file)).unwrap();
+        // Replacing the stream closes the previous one.
+        set_log_output(Some(File::create(&path).unwrap())).unwrap();
+        disable_logging().unwrap();
+        assert!(LOG_STREAM.lock().unwrap().is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(all(feature = "log", unix))]
+    #[test]
+    fn log_forwarding() {
+        let _serial = LOG_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(
+            log_level("Failed to create GGWave instance"),
+            log::Level::Warn
+        );
+        assert_eq!(log_level("Invalid capture sample rate"), log::Level::Warn);
+        assert_eq!(
+            log_level("Received sound data successfully"),
+            log::Level::Debug
+        );
+
+        if is_strict() {
+            assert!(matches!(forward_log(), Err(Error::GlobalStateForbidden)));
+            return;
+        }
+        forward_log().unwrap();
+        assert!(LOG_STREAM.lock().unwrap().is_some());
+        disable_logging().unwrap();
+    }
+}
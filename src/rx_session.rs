@@ -0,0 +1,190 @@
+//! Event-driven reception.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::{Decoder, Error, Parameters};
+
+type MarkerHandler<'a> = Box<dyn FnMut() + 'a>;
+type MessageHandler<'a> = Box<dyn FnMut(&[u8]) + 'a>;
+type ErrorHandler<'a> = Box<dyn FnMut(Error) + 'a>;
+
+/// A [`Decoder`] that reports what it hears through callbacks instead of
+/// return values, for GUIs, games and other event-driven applications.
+///
+/// ```no_run
+/// use ggwave_rs::{default_parameters, RxSession};
+///
+/// let mut session = RxSession::new(default_parameters())?;
+/// session
+///     .on_marker_detected(|| println!("receiving…"))
+///     .on_message(|payload| println!("received {payload:?}"))
+///     .on_error(|e| eprintln!("decoding failed: {e}"));
+/// // in the capture callback:
+/// # let captured = [0u8; 4096];
+/// session.feed(&captured);
+/// # Ok::<(), ggwave_rs::Error>(())
+/// ```
+///
+/// Callbacks run inside [`feed`](Self::feed), on the caller's thread. Errors
+/// with no `on_error` callback registered are dropped.
+pub struct RxSession<'a> {
+    decoder: Decoder,
+    /// Whether `on_marker_detected` has fired for the transmission being
+    /// received.
+    receiving: bool,
+    on_marker: Option<MarkerHandler<'a>>,
+    on_message: Option<MessageHandler<'a>>,
+    on_error: Option<ErrorHandler<'a>>,
+}
+
+impl<'a> RxSession<'a> {
+    pub fn new(parameters: Parameters) -> Result<Self, Error> {
+        Ok(Self {
+            decoder: Decoder::new(parameters)?,
+            receiving: false,
+            on_marker: None,
+            on_message: None,
+            on_error: None,
+        })
+    }
+
+    /// Called when a transmission's start marker is detected.
+    ///
+    /// Detection relies on [`Decoder::status`]; when the linked library
+    /// can't report reception state, it fires just before `on_message`.
+    pub fn on_marker_detected(&mut self, handler: impl FnMut() + 'a) -> &mut Self {
+        self.on_marker = Some(Box::new(handler));
+        self
+    }
+
+    /// Called with every payload received.
+    pub fn on_message(&mut self, handler: impl FnMut(&[u8]) + 'a) -> &mut Self {
+        self.on_message = Some(Box::new(handler));
+        self
+    }
+
+    /// Called when decoding fails.
+    pub fn on_error(&mut self, handler: impl FnMut(Error) + 'a) -> &mut Self {
+        self.on_error = Some(Box::new(handler));
+        self
+    }
+
+    /// Feeds captured audio in the instance's input format, calling the
+    /// callbacks for everything it completes.
+    pub fn feed(&mut self, samples: &[u8]) {
+        let decoded = self.decoder.feed(samples);
+        self.deliver(decoded);
+    }
+
+    /// [`feed`](Self::feed) for an instance whose input format is F32.
+    pub fn feed_f32(&mut self, samples: &[f32]) {
+        let decoded = self.decoder.feed_f32(samples);
+        self.deliver(decoded);
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    fn deliver(&mut self, mut decoded: Result<Option<Vec<u8>>, Error>) {
+        loop {
+            match decoded {
+                Ok(Some(payload)) => {
+                    self.marker_detected();
+                    self.receiving = false;
+                    if let Some(handler) = &mut self.on_message {
+                        handler(&payload);
+                    }
+                    // Audio after a payload stays in the decoder and may
+                    // hold another.
+                    decoded = self.decoder.feed(&[]);
+                }
+                Ok(None) => {
+                    if self.decoder.is_receiving() {
+                        self.marker_detected();
+                    } else {
+                        self.receiving = false;
+                    }
+                    return;
+                }
+                Err(e) => {
+                    self.receiving = false;
+                    if let Some(handler) = &mut self.on_error {
+                        handler(e);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    fn marker_detected(&mut self) {
+        if !self.receiving {
+            self.receiving = true;
+            if let Some(handler) = &mut self.on_marker {
+                handler();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default_parameters, GgWave, ProtocolId, SampleFormat};
+    use core::cell::RefCell;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Marker,
+        Message(Vec<u8>),
+        Error,
+    }
+
+    #[test]
+    fn reports_markers_messages_and_errors() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let ggwave = GgWave::new(params).unwrap();
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        let silence = vec![0.0f32; params.samplesPerFrame as usize * 8];
+        let audio = [
+            ggwave.encode_f32(b"first", protocol, 25).unwrap(),
+            silence.clone(),
+            ggwave.encode_f32(b"second", protocol, 25).unwrap(),
+            silence,
+        ]
+        .concat();
+
+        let events = RefCell::new(Vec::new());
+        let mut session = RxSession::new(params).unwrap();
+        session
+            .on_marker_detected(|| events.borrow_mut().push(Event::Marker))
+            .on_message(|p| events.borrow_mut().push(Event::Message(p.to_vec())))
+            .on_error(|_| events.borrow_mut().push(Event::Error));
+        for chunk in audio.chunks(1000) {
+            session.feed_f32(chunk);
+        }
+        drop(session);
+        assert_eq!(
+            events.take(),
+            [
+                Event::Marker,
+                Event::Message(b"first".to_vec()),
+                Event::Marker,
+                Event::Message(b"second".to_vec()),
+            ]
+        );
+
+        // F32 samples for an I16 decoder.
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_I16.into();
+        let mut session = RxSession::new(params).unwrap();
+        session.on_error(|_| events.borrow_mut().push(Event::Error));
+        session.feed_f32(&[0.0; 16]);
+        drop(session);
+        assert_eq!(events.take(), [Event::Error]);
+    }
+}
@@ -0,0 +1,292 @@
+//! Finding transmissions in long recordings.
+//!
+//! A recording is first split into segments of activity (frames whose level
+//! stands out from the recording's noise floor), then each segment is decoded
+//! on its own. Segments that don't decode are dropped.
+//!
+//! [`GgWave::scan`] instead runs one instance over the whole waveform a frame
+//! at a time, which needs no activity detection but only suits recordings
+//! already in the instance's input format.
+
+use crate::cancel::{CancellationToken, Outcome, Progress};
+use crate::{Error, GgWave, OperatingMode, Parameters, SampleFormat, MAX_DATA_SIZE};
+
+/// Samples per analysis frame, matching the library's default frame size.
+const FRAME_LEN: usize = 1024;
+/// Silence shorter than this doesn't end a segment.
+const MAX_GAP_SECS: f32 = 0.3;
+/// Margin added on both sides of a segment.
+const PAD_SECS: f32 = 0.1;
+/// A frame is active when its RMS exceeds the noise floor by this factor.
+const ACTIVITY_RATIO: f32 = 4.0;
+/// Lower bound for the activity threshold, for digitally silent recordings.
+const MIN_THRESHOLD: f32 = 1e-4;
+
+/// Half-open range of sample indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Segment {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Start time in seconds at `sample_rate`.
+    pub fn start_secs(&self, sample_rate: f32) -> f64 {
+        self.start as f64 / sample_rate as f64
+    }
+
+    /// End time in seconds at `sample_rate`.
+    pub fn end_secs(&self, sample_rate: f32) -> f64 {
+        self.end as f64 / sample_rate as f64
+    }
+}
+
+/// A decoded transmission and where it is in the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    pub segment: Segment,
+    pub payload: Vec<u8>,
+}
+
+/// A payload found by [`GgWave::scan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanHit {
+    /// Sample index just past the frame that completed the payload; the
+    /// transmission ends shortly before it.
+    pub offset: usize,
+    pub payload: Vec<u8>,
+}
+
+impl GgWave {
+    /// Decodes every payload in `waveform`, given in the instance's input
+    /// format, feeding it to the library one frame at a time. Where
+    /// [`decode`](GgWave::decode) returns the first payload only, this
+    /// yields each one with its position.
+    ///
+    /// Fails up front if the instance can't decode or its input format or
+    /// frame size isn't set; frames the library rejects are skipped.
+    pub fn scan<'a>(
+        &'a self,
+        waveform: &'a [u8],
+    ) -> Result<impl Iterator<Item = ScanHit> + 'a, Error> {
+        self.require_mode(OperatingMode::RX)?;
+        let parameters = self.parameters();
+        let sample_bytes = SampleFormat::try_from(parameters.sampleFormatInp)?.bytes_per_sample();
+        let samples_per_frame = usize::try_from(parameters.samplesPerFrame).unwrap_or(0);
+        if sample_bytes == 0 || samples_per_frame == 0 {
+            return Err(Error::InvalidInput(
+                "scan needs an input format and samplesPerFrame",
+            ));
+        }
+        let frame_bytes = sample_bytes * samples_per_frame;
+
+        let mut payload = [0u8; MAX_DATA_SIZE];
+        Ok(waveform
+            .chunks(frame_bytes)
+            .enumerate()
+            .filter_map(move |(i, frame)| {
+                let len = self.decode_into(frame, &mut payload).ok()??;
+                Some(ScanHit {
+                    offset: (i * frame_bytes + frame.len()) / sample_bytes,
+                    payload: payload[..len].to_vec(),
+                })
+            }))
+    }
+}
+
+/// Finds the segments of `samples` with activity above the noise floor.
+pub fn active_segments(samples: &[f32], sample_rate: f32) -> Vec<Segment> {
+    let levels: Vec<f32> = samples
+        .chunks(FRAME_LEN)
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt())
+        .collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    // The 10th percentile frame level stands in for the noise floor.
+    let mut sorted = levels.clone();
+    sorted.sort_by(f32::total_cmp);
+    let threshold = (sorted[sorted.len() / 10] * ACTIVITY_RATIO).max(MIN_THRESHOLD);
+
+    let max_gap = (MAX_GAP_SECS * sample_rate) as usize / FRAME_LEN;
+    let pad = (PAD_SECS * sample_rate) as usize;
+
+    let mut frames: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in levels.iter().enumerate().filter(|(_, &l)| l > threshold) {
+        match frames.last_mut() {
+            Some((_, last)) if i - *last <= max_gap + 1 => *last = i,
+            _ => frames.push((i, i)),
+        }
+    }
+
+    frames
+        .into_iter()
+        .map(|(first, last)| Segment {
+            start: (first * FRAME_LEN).saturating_sub(pad),
+            end: ((last + 1) * FRAME_LEN + pad).min(samples.len()),
+        })
+        .collect()
+}
+
+/// Decodes every transmission in a recording of F32 samples.
+///
+/// `parameters` should describe the recording (in particular its input
+/// sample rate); the input format is set to F32.
+pub fn scan(parameters: Parameters, samples: &[f32]) -> Result<Vec<Detection>, Error> {
+    scan_cancellable(parameters, samples, &CancellationToken::new(), |_| {})
+        .map(Outcome::into_inner)
+}
+
+/// Like [`scan`], but checks `token` before each segment and reports
+/// progress (in segments) to `on_progress` after each one. Once cancelled it
+/// returns the detections found so far.
+pub fn scan_cancellable(
+    mut parameters: Parameters,
+    samples: &[f32],
+    token: &CancellationToken,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Outcome<Vec<Detection>>, Error> {
+    parameters.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+    let silence = vec![0u8; (PAD_SECS * parameters.sampleRateInp) as usize * 4];
+
+    let segments = active_segments(samples, parameters.sampleRateInp);
+    let mut progress = Progress {
+        done: 0,
+        total: segments.len(),
+    };
+    let mut detections = Vec::new();
+    for segment in segments {
+        if token.is_cancelled() {
+            return Ok(Outcome::Cancelled {
+                partial: detections,
+                progress,
+            });
+        }
+
+        // Each segment gets a fresh decoder, with silence around it so the end
+        // marker is seen even when the segment is cut tight.
+        let mut waveform = silence.clone();
+        waveform.extend(
+            samples[segment.start..segment.end]
+                .iter()
+                .flat_map(|s| s.to_ne_bytes()),
+        );
+        waveform.extend_from_slice(&silence);
+
+        if let Some(payload) = GgWave::new(parameters)?.decode(&waveform)? {
+            detections.push(Detection { segment, payload });
+        }
+        progress.done += 1;
+        on_progress(progress);
+    }
+    Ok(Outcome::Complete(detections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burst(len: usize) -> Vec<f32> {
+        (0..len).map(|i| 0.3 * (i as f32 * 0.2).sin()).collect()
+    }
+
+    #[test]
+    fn cancelled_scan_stops_before_decoding() {
+        let _serial = crate::serial();
+        let mut samples = vec![0.0f32; 48_000];
+        samples.extend(burst(24_000));
+        samples.extend(vec![0.0; 48_000]);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut reported = Vec::new();
+        let outcome = scan_cancellable(crate::default_parameters(), &samples, &token, |p| {
+            reported.push(p)
+        })
+        .unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Cancelled {
+                partial: Vec::new(),
+                progress: Progress { done: 0, total: 1 },
+            }
+        );
+        assert!(reported.is_empty());
+    }
+
+    #[test]
+    fn scan_finds_every_payload() {
+        let _serial = crate::serial();
+        let params = crate::default_parameters();
+        let tx = GgWave::new(params).expect("tx init failed");
+        let protocol = crate::ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST;
+        let silence = vec![0u8; 48_000 * 2];
+        let mut waveform = silence.clone();
+        for payload in [&b"one"[..], b"two"] {
+            waveform.extend(tx.encode(payload, protocol, 25).expect("encode failed"));
+            waveform.extend_from_slice(&silence);
+        }
+
+        let rx = GgWave::new(params).expect("rx init failed");
+        let hits: Vec<ScanHit> = rx.scan(&waveform).unwrap().collect();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].payload, b"one");
+        assert_eq!(hits[1].payload, b"two");
+        assert!(hits[0].offset < hits[1].offset);
+    }
+
+    #[test]
+    fn scan_needs_an_rx_instance() {
+        let _serial = crate::serial();
+        let params = crate::default_parameters()
+            .with_operating_mode(OperatingMode::TX)
+            .unwrap();
+        let tx = GgWave::new(params).expect("tx init failed");
+        assert!(matches!(
+            tx.scan(&[0; 4096]).map(|hits| hits.count()),
+            Err(Error::WrongOperatingMode { .. })
+        ));
+    }
+
+    #[test]
+    fn finds_separate_bursts() {
+        let rate = 48_000.0;
+        let mut samples = vec![0.0f32; 48_000];
+        samples.extend(burst(24_000));
+        samples.extend(vec![0.0; 48_000]);
+        samples.extend(burst(12_000));
+        samples.extend(vec![0.0; 48_000]);
+
+        let segments = active_segments(&samples, rate);
+        assert_eq!(segments.len(), 2);
+        let pad = (PAD_SECS * rate) as usize;
+        assert!(segments[0].start <= 48_000 && segments[0].start >= 48_000 - pad - FRAME_LEN);
+        assert!(segments[0].end >= 72_000 && segments[0].end <= 72_000 + pad + FRAME_LEN);
+        assert!(segments[1].start >= 120_000 - pad - FRAME_LEN);
+        assert!((segments[0].start_secs(rate) - 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn short_gaps_dont_split() {
+        let mut samples = burst(10_000);
+        samples.extend(vec![0.0; 4_000]);
+        samples.extend(burst(10_000));
+        samples.extend(vec![0.0; 96_000]);
+        assert_eq!(active_segments(&samples, 48_000.0).len(), 1);
+    }
+
+    #[test]
+    fn silence_has_no_segments() {
+        assert!(active_segments(&[], 48_000.0).is_empty());
+        assert!(active_segments(&vec![0.0; 10_000], 48_000.0).is_empty());
+    }
+}
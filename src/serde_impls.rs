@@ -0,0 +1,226 @@
+//! `Serialize`/`Deserialize` for parameters and the C enums (`serde`
+//! feature), for keeping encoder/decoder settings in config files.
+//!
+//! Protocols are written as their stable [`ProtocolId::id`] and read back
+//! from any spelling `FromStr` accepts. Sample formats and filters use short
+//! lowercase names (`"f32"`, `"hann"`). Parameters use snake_case field
+//! names; missing fields take the library's defaults.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::{self, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::{default_parameters, Filter, OperatingMode, Parameters, ProtocolId, SampleFormat};
+
+impl Serialize for ProtocolId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtocolId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor("a protocol name", |s| s.parse().ok()))
+    }
+}
+
+const SAMPLE_FORMATS: [(SampleFormat, &str); 6] = [
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_UNDEFINED, "undefined"),
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_U8, "u8"),
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_I8, "i8"),
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_U16, "u16"),
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_I16, "i16"),
+    (SampleFormat::GGWAVE_SAMPLE_FORMAT_F32, "f32"),
+];
+
+const FILTERS: [(Filter, &str); 3] = [
+    (Filter::GGWAVE_FILTER_HANN, "hann"),
+    (Filter::GGWAVE_FILTER_HAMMING, "hamming"),
+    (
+        Filter::GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS,
+        "first_order_high_pass",
+    ),
+];
+
+fn name_of<T: PartialEq>(table: &[(T, &'static str)], value: T) -> &'static str {
+    table
+        .iter()
+        .find(|(v, _)| *v == value)
+        .map(|(_, name)| *name)
+        .expect("every variant is in the table")
+}
+
+fn from_name<T: Copy>(table: &[(T, &'static str)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|(v, _)| *v)
+}
+
+impl Serialize for SampleFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(name_of(&SAMPLE_FORMATS, *self))
+    }
+}
+
+impl<'de> Deserialize<'de> for SampleFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor("a sample format", |s| {
+            from_name(&SAMPLE_FORMATS, s)
+        }))
+    }
+}
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(name_of(&FILTERS, *self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NameVisitor("a filter", |s| from_name(&FILTERS, s)))
+    }
+}
+
+struct NameVisitor<T>(&'static str, fn(&str) -> Option<T>);
+
+impl<T> Visitor<'_> for NameVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        (self.1)(s).ok_or_else(|| E::invalid_value(de::Unexpected::Str(s), &self))
+    }
+}
+
+/// The serialized form of [`Parameters`].
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ParametersDef {
+    payload_length: i32,
+    sample_rate_inp: f32,
+    sample_rate_out: f32,
+    sample_rate: f32,
+    samples_per_frame: i32,
+    sound_marker_threshold: f32,
+    sample_format_inp: SampleFormat,
+    sample_format_out: SampleFormat,
+    operating_mode: OperatingMode,
+}
+
+impl Default for ParametersDef {
+    fn default() -> Self {
+        let defaults = default_parameters();
+        ParametersDef::try_from(&defaults).expect("library defaults are known values")
+    }
+}
+
+impl TryFrom<&Parameters> for ParametersDef {
+    type Error = &'static str;
+
+    fn try_from(p: &Parameters) -> Result<Self, &'static str> {
+        Ok(ParametersDef {
+            payload_length: p.payloadLength,
+            sample_rate_inp: p.sampleRateInp,
+            sample_rate_out: p.sampleRateOut,
+            sample_rate: p.sampleRate,
+            samples_per_frame: p.samplesPerFrame,
+            sound_marker_threshold: p.soundMarkerThreshold,
+            sample_format_inp: SampleFormat::from_raw(p.sampleFormatInp.0)
+                .ok_or("unknown input sample format")?,
+            sample_format_out: SampleFormat::from_raw(p.sampleFormatOut.0)
+                .ok_or("unknown output sample format")?,
+            operating_mode: p.operating_mode().ok_or("unknown operating mode bits")?,
+        })
+    }
+}
+
+impl Serialize for Parameters {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ParametersDef::try_from(self)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Parameters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let def = ParametersDef::deserialize(deserializer)?;
+        let params = Parameters {
+            payloadLength: def.payload_length,
+            sampleRateInp: def.sample_rate_inp,
+            sampleRateOut: def.sample_rate_out,
+            sampleRate: def.sample_rate,
+            samplesPerFrame: def.samples_per_frame,
+            soundMarkerThreshold: def.sound_marker_threshold,
+            sampleFormatInp: def.sample_format_inp.into(),
+            sampleFormatOut: def.sample_format_out.into(),
+            operatingMode: 0,
+        };
+        params
+            .with_operating_mode(def.operating_mode)
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_roundtrip() {
+        let params = default_parameters()
+            .with_operating_mode(OperatingMode::RX | OperatingMode::USE_DSS)
+            .unwrap();
+        let json = serde_json::to_string(&params).unwrap();
+        assert!(json.contains(r#""operating_mode":"RX | USE_DSS""#), "{json}");
+        let back: Parameters = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        assert!(back.uses_dss());
+
+        let partial: Parameters =
+            serde_json::from_str(r#"{"sample_rate_inp": 44100.0, "sample_format_inp": "i16"}"#)
+                .unwrap();
+        assert_eq!(partial.sampleRateInp, 44100.0);
+        assert_eq!(
+            partial.sampleFormatInp,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_I16.into()
+        );
+        assert_eq!(
+            partial.samplesPerFrame,
+            default_parameters().samplesPerFrame
+        );
+
+        assert!(serde_json::from_str::<Parameters>(r#"{"sample_rte": 1.0}"#).is_err());
+        assert!(serde_json::from_str::<Parameters>(r#"{"operating_mode": ""}"#).is_err());
+    }
+
+    #[test]
+    fn enums_use_names() {
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST;
+        assert_eq!(
+            serde_json::to_string(&protocol).unwrap(),
+            r#""ultrasound.fast""#
+        );
+        assert_eq!(
+            serde_json::from_str::<ProtocolId>(r#""u-fast""#).unwrap(),
+            protocol
+        );
+        assert!(serde_json::from_str::<ProtocolId>("4").is_err());
+
+        for (format, _) in SAMPLE_FORMATS {
+            let json = serde_json::to_string(&format).unwrap();
+            assert_eq!(serde_json::from_str::<SampleFormat>(&json).unwrap(), format);
+        }
+        assert_eq!(
+            serde_json::from_str::<Filter>(r#""Hamming""#).unwrap(),
+            Filter::GGWAVE_FILTER_HAMMING
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Adapters between payloads and waveform chunks for futures pipelines
+//! (`futures` feature).
+//!
+//! [`PayloadStream`] turns a `Stream` of captured audio chunks into a
+//! `Stream` of decoded payloads; [`PayloadSink`] is a `Sink` of payloads
+//! that hands each encoded waveform to a `Sink` of audio chunks. Chunks are
+//! PCM in the instance's input or output format. Neither needs a particular
+//! runtime; encoding and decoding happen inside the poll calls.
+
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use crate::cancel::CancellationToken;
+use crate::{Decoder, Error, GgWave, Parameters, ProtocolId};
+
+/// A `Stream` of payloads decoded from a `Stream` of audio chunks.
+///
+/// Chunks can be of any size; see [`Decoder::feed`]. A decoding error is
+/// yielded as an item and decoding carries on with the next chunk. The
+/// stream ends with the chunk stream, or once the token given to
+/// [`new_cancellable`](Self::new_cancellable) is cancelled.
+pub struct PayloadStream<S> {
+    chunks: S,
+    decoder: Decoder,
+    token: CancellationToken,
+    /// Audio after the last payload may hold another one.
+    drain: bool,
+}
+
+impl<S> PayloadStream<S>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<[u8]>,
+{
+    pub fn new(parameters: Parameters, chunks: S) -> Result<Self, Error> {
+        Self::new_cancellable(parameters, chunks, &CancellationToken::new())
+    }
+
+    /// Like [`new`](Self::new), but checks `token` before each chunk is
+    /// decoded.
+    pub fn new_cancellable(
+        parameters: Parameters,
+        chunks: S,
+        token: &CancellationToken,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            chunks,
+            decoder: Decoder::new(parameters)?,
+            token: token.clone(),
+            drain: false,
+        })
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    pub fn into_inner(self) -> S {
+        self.chunks
+    }
+}
+
+impl<S> Stream for PayloadStream<S>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<[u8]>,
+{
+    type Item = Result<Vec<u8>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.token.is_cancelled() {
+                return Poll::Ready(None);
+            }
+            let decoded = if this.drain {
+                this.decoder.feed(&[])
+            } else {
+                match ready!(Pin::new(&mut this.chunks).poll_next(cx)) {
+                    Some(chunk) => this.decoder.feed(chunk.as_ref()),
+                    None => return Poll::Ready(None),
+                }
+            };
+            this.drain = matches!(decoded, Ok(Some(_)));
+            if let Some(result) = decoded.transpose() {
+                return Poll::Ready(Some(result));
+            }
+        }
+    }
+}
+
+/// A `Sink` of payloads that sends each one's waveform, as one chunk, into
+/// a `Sink` of audio chunks.
+///
+/// Errors from the chunk sink are converted into [`Error`]; `std::io::Error`
+/// already is.
+pub struct PayloadSink<S> {
+    chunks: S,
+    ggwave: GgWave,
+    protocol: ProtocolId,
+    volume: i32,
+}
+
+impl<S> PayloadSink<S>
+where
+    S: Sink<Vec<u8>> + Unpin,
+    Error: From<S::Error>,
+{
+    /// A sink that encodes with `protocol` at `volume` (0 to 100).
+    pub fn new(
+        parameters: Parameters,
+        chunks: S,
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            chunks,
+            ggwave: GgWave::new(parameters)?,
+            protocol,
+            volume,
+        })
+    }
+
+    pub fn ggwave(&self) -> &GgWave {
+        &self.ggwave
+    }
+
+    pub fn into_inner(self) -> S {
+        self.chunks
+    }
+}
+
+impl<S, P> Sink<P> for PayloadSink<S>
+where
+    S: Sink<Vec<u8>> + Unpin,
+    Error: From<S::Error>,
+    P: AsRef<[u8]>,
+{
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(ready!(Pin::new(&mut self.chunks).poll_ready(cx))?))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, payload: P) -> Result<(), Error> {
+        let waveform = self
+            .ggwave
+            .encode(payload.as_ref(), self.protocol, self.volume)?;
+        Ok(Pin::new(&mut self.chunks).start_send(waveform)?)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(ready!(Pin::new(&mut self.chunks).poll_flush(cx))?))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(ready!(Pin::new(&mut self.chunks).poll_close(cx))?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+    use std::task::Waker;
+
+    /// Collects the chunks sent into it.
+    struct Chunks(Vec<Vec<u8>>);
+
+    impl Sink<Vec<u8>> for Chunks {
+        type Error = std::io::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, chunk: Vec<u8>) -> std::io::Result<()> {
+            self.0.push(chunk);
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Yields slices of a buffer, always ready.
+    struct Slices<'a>(std::slice::Chunks<'a, u8>);
+
+    impl<'a> Stream for Slices<'a> {
+        type Item = &'a [u8];
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<&'a [u8]>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    #[test]
+    fn payloads_roundtrip_through_chunks() {
+        let _serial = crate::serial();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut params = default_parameters();
+        params.sampleFormatInp = params.sampleFormatOut;
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+
+        let mut sink = PayloadSink::new(params, Chunks(Vec::new()), protocol, 25).unwrap();
+        for payload in [&b"one"[..], b"two"] {
+            let mut sink = Pin::new(&mut sink);
+            let ready = Sink::<&[u8]>::poll_ready(sink.as_mut(), &mut cx);
+            assert!(matches!(ready, Poll::Ready(Ok(()))));
+            sink.as_mut().start_send(payload).unwrap();
+        }
+        let closed = Sink::<&[u8]>::poll_close(Pin::new(&mut sink), &mut cx);
+        assert!(matches!(closed, Poll::Ready(Ok(()))));
+        let mut audio = sink.into_inner().0.concat();
+        audio.resize(audio.len() + 64 * 1024, 0);
+
+        let mut stream = PayloadStream::new(params, Slices(audio.chunks(3000))).unwrap();
+        let mut payloads = Vec::new();
+        while let Poll::Ready(Some(payload)) = Pin::new(&mut stream).poll_next(&mut cx) {
+            payloads.push(payload.unwrap());
+        }
+        assert_eq!(payloads, [b"one", b"two"]);
+
+        let token = CancellationToken::new();
+        let mut stream =
+            PayloadStream::new_cancellable(params, Slices(audio.chunks(3000)), &token).unwrap();
+        token.cancel();
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+        assert!(stream.into_inner().0.next().is_some());
+    }
+}
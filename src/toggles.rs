@@ -0,0 +1,202 @@
+//! Scoped changes to the C library's protocol toggles.
+//!
+//! The library can enable and disable protocols but can't report which are
+//! enabled, so every change made through this crate is mirrored here. Changes
+//! made by calling [`ffi`](crate::ffi) directly are not seen.
+
+use std::sync::Mutex;
+
+use crate::{check_global_state, ffi, ffi_lock, Error, ProtocolId};
+
+const COUNT: usize = ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 as usize;
+
+static CURRENT: Mutex<ProtocolToggles> = Mutex::new(ProtocolToggles::DEFAULT);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Rx,
+    Tx,
+}
+
+/// Which protocols are enabled for reception and transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolToggles {
+    rx: [bool; COUNT],
+    tx: [bool; COUNT],
+}
+
+impl ProtocolToggles {
+    /// The library's state at startup: standard protocols on, custom ones
+    /// off.
+    pub const DEFAULT: Self = {
+        let mut standard = [false; COUNT];
+        let mut i = 0;
+        while i < ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0.as_raw() as usize {
+            standard[i] = true;
+            i += 1;
+        }
+        Self {
+            rx: standard,
+            tx: standard,
+        }
+    };
+
+    /// The toggles as currently set.
+    pub fn snapshot() -> Self {
+        *CURRENT.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Puts the toggles back to this snapshot, e.g. at the end of a test.
+    ///
+    /// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+    pub fn restore(&self) -> Result<(), Error> {
+        check_global_state()?;
+        for protocol in ProtocolId::all() {
+            set(Direction::Rx, protocol, self.rx_enabled(protocol));
+            set(Direction::Tx, protocol, self.tx_enabled(protocol));
+        }
+        Ok(())
+    }
+
+    pub fn rx_enabled(&self, protocol: ProtocolId) -> bool {
+        self.rx[protocol.as_raw() as usize]
+    }
+
+    pub fn tx_enabled(&self, protocol: ProtocolId) -> bool {
+        self.tx[protocol.as_raw() as usize]
+    }
+
+    fn get(&self, direction: Direction, protocol: ProtocolId) -> bool {
+        match direction {
+            Direction::Rx => self.rx_enabled(protocol),
+            Direction::Tx => self.tx_enabled(protocol),
+        }
+    }
+}
+
+/// Toggles a protocol in the library and in the mirrored state.
+pub(crate) fn set_rx(protocol: ProtocolId, enabled: bool) {
+    set(Direction::Rx, protocol, enabled);
+}
+
+pub(crate) fn set_tx(protocol: ProtocolId, enabled: bool) {
+    set(Direction::Tx, protocol, enabled);
+}
+
+fn set(direction: Direction, protocol: ProtocolId, enabled: bool) {
+    let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+    let state = if enabled { 1 } else { 0 };
+    let _lock = ffi_lock();
+    match direction {
+        Direction::Rx => {
+            unsafe { ffi::ggwave_rxToggleProtocol(protocol.into(), state) };
+            current.rx[protocol.as_raw() as usize] = enabled;
+        }
+        Direction::Tx => {
+            unsafe { ffi::ggwave_txToggleProtocol(protocol.into(), state) };
+            current.tx[protocol.as_raw() as usize] = enabled;
+        }
+    }
+}
+
+/// Previous states to put back on drop.
+#[derive(Debug)]
+struct Restore {
+    direction: Direction,
+    previous: Vec<(ProtocolId, bool)>,
+}
+
+impl Restore {
+    fn apply(direction: Direction, protocols: &[ProtocolId], enabled: bool) -> Result<Self, Error> {
+        check_global_state()?;
+        let snapshot = ProtocolToggles::snapshot();
+        let previous = protocols
+            .iter()
+            .map(|&protocol| (protocol, snapshot.get(direction, protocol)))
+            .collect();
+        for &protocol in protocols {
+            set(direction, protocol, enabled);
+        }
+        Ok(Self {
+            direction,
+            previous,
+        })
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        // Undo in reverse so a protocol listed twice ends up as it started.
+        for &(protocol, enabled) in self.previous.iter().rev() {
+            set(self.direction, protocol, enabled);
+        }
+    }
+}
+
+/// Enables or disables protocols for reception until dropped, then puts back
+/// their previous states.
+///
+/// Guards that overlap on a protocol must be dropped in reverse order of
+/// creation, like any nested scope.
+#[derive(Debug)]
+#[must_use = "the toggles are restored when the guard is dropped"]
+pub struct RxProtocolGuard {
+    _restore: Restore,
+}
+
+impl RxProtocolGuard {
+    /// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+    pub fn new(protocols: &[ProtocolId], enabled: bool) -> Result<Self, Error> {
+        Restore::apply(Direction::Rx, protocols, enabled).map(|_restore| Self { _restore })
+    }
+}
+
+/// [`RxProtocolGuard`] for transmission.
+#[derive(Debug)]
+#[must_use = "the toggles are restored when the guard is dropped"]
+pub struct TxProtocolGuard {
+    _restore: Restore,
+}
+
+impl TxProtocolGuard {
+    /// Fails with [`Error::GlobalStateForbidden`] when built with `strict`.
+    pub fn new(protocols: &[ProtocolId], enabled: bool) -> Result<Self, Error> {
+        Restore::apply(Direction::Tx, protocols, enabled).map(|_restore| Self { _restore })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::is_strict;
+
+    #[test]
+    fn guards_restore_previous_state() {
+        // No other test touches the TX toggles of this protocol.
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST;
+        if is_strict() {
+            assert!(matches!(
+                TxProtocolGuard::new(&[protocol], false),
+                Err(Error::GlobalStateForbidden)
+            ));
+            return;
+        }
+
+        let before = ProtocolToggles::snapshot();
+        assert!(before.tx_enabled(protocol));
+        {
+            let _outer = TxProtocolGuard::new(&[protocol], false).unwrap();
+            assert!(!ProtocolToggles::snapshot().tx_enabled(protocol));
+            {
+                let _inner = TxProtocolGuard::new(&[protocol, protocol], true).unwrap();
+                assert!(ProtocolToggles::snapshot().tx_enabled(protocol));
+            }
+            assert!(!ProtocolToggles::snapshot().tx_enabled(protocol));
+        }
+        assert!(ProtocolToggles::snapshot().tx_enabled(protocol));
+
+        crate::set_tx_protocol_enabled(protocol, false).unwrap();
+        before.restore().unwrap();
+        assert!(ProtocolToggles::snapshot().tx_enabled(protocol));
+    }
+}
@@ -0,0 +1,100 @@
+//! Tone sequences for instances in `TX_ONLY_TONES` mode.
+//!
+//! In that mode the library doesn't synthesize audio: each byte of the
+//! encoder's output is a tone, given as a bin offset from the protocol's
+//! first tone, held for the protocol's frames per transmission. Devices that
+//! can only play a square wave (buzzers, simple DACs) play these directly.
+
+use crate::{Error, GgWave, OperatingMode, Parameters, ProtocolId, Speed};
+
+const UNKNOWN_LAYOUT: Error = Error::InvalidInput("custom protocols have no known tone layout");
+
+/// One tone of a transmission.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    pub freq_hz: f32,
+    pub duration_ms: f32,
+}
+
+impl GgWave {
+    /// Encodes `payload` as a tone sequence. The instance must have been
+    /// created with [`OperatingMode::TX_ONLY_TONES`].
+    ///
+    /// Frequencies assume the protocol's default start; they're wrong after
+    /// moving it with [`set_tx_protocol_freq_start`](crate::set_tx_protocol_freq_start).
+    pub fn encode_tones(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Vec<Tone>, Error> {
+        self.require_mode(OperatingMode::TX | OperatingMode::TX_ONLY_TONES)?;
+        let tones = self.encode(payload, protocol, volume)?;
+        tones_from(&tones, protocol, self.parameters())
+    }
+}
+
+fn tones_from(
+    bins: &[u8],
+    protocol: ProtocolId,
+    parameters: &Parameters,
+) -> Result<Vec<Tone>, Error> {
+    let (start_hz, _) = protocol.freq_range_hz(parameters).ok_or(UNKNOWN_LAYOUT)?;
+    let frames_per_tx = match protocol.speed() {
+        Some(Speed::Normal) => 9,
+        Some(Speed::Fast) => 6,
+        Some(Speed::Fastest) => 3,
+        None => return Err(UNKNOWN_LAYOUT),
+    };
+    // freq_range_hz succeeding means the bin width is known.
+    let hz_per_bin = crate::hz_per_bin(parameters).ok_or(UNKNOWN_LAYOUT)?;
+    let duration_ms =
+        frames_per_tx as f32 * parameters.samplesPerFrame as f32 * 1000.0 / parameters.sampleRate;
+    Ok(bins
+        .iter()
+        .map(|&bin| Tone {
+            freq_hz: start_hz + bin as f32 * hz_per_bin,
+            duration_ms,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+
+    #[test]
+    fn tone_bins_become_frequencies() {
+        let mut params = default_parameters();
+        params.samplesPerFrame = 1024;
+        let tones = tones_from(&[0, 2], ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST, &params).unwrap();
+        assert_eq!(
+            tones,
+            [
+                Tone {
+                    freq_hz: 1125.0,
+                    duration_ms: 64.0
+                },
+                Tone {
+                    freq_hz: 1218.75,
+                    duration_ms: 64.0
+                },
+            ]
+        );
+        assert!(tones_from(&[0], ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0, &params).is_err());
+    }
+
+    #[test]
+    fn encode_tones_needs_tones_mode() {
+        let _serial = crate::serial();
+        let params = default_parameters()
+            .with_operating_mode(OperatingMode::TX)
+            .unwrap();
+        let tx = GgWave::new(params).expect("tx init failed");
+        assert!(matches!(
+            tx.encode_tones(b"hi", ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST, 25),
+            Err(Error::WrongOperatingMode { .. })
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+//! RFC 6238 time-based one-time passwords sent over sound.
+//!
+//! A [`TotpSender`] builds a short payload carrying its device id, the time
+//! step and the code for that step, sent as a wire frame tagged
+//! [`TOKEN_TAG`] so a [`Router`](crate::router::Router) hands it to its
+//! [`PayloadType::Token`](crate::router::PayloadType::Token) handler; a
+//! [`TotpVerifier`] holding the same
+//! secret accepts it if the step is within its window of the current time and
+//! newer than the last accepted one, so a recorded transmission can't be
+//! replayed. The code is computed over the device id as well as the step, so
+//! a token captured from one device doesn't pass for another's.
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+pub use crate::router::TOKEN_TAG;
+use crate::wire::{Frame, FrameKind, WireError, HEADER_LEN, TAG_LEN};
+use crate::MAX_VARIABLE_PAYLOAD_LEN;
+
+/// Default time step in seconds (RFC 6238 recommends 30).
+pub const DEFAULT_STEP: u64 = 30;
+/// Default number of digits.
+pub const DEFAULT_DIGITS: u32 = 6;
+/// Most digits a code can have; 10^10 doesn't fit the 31-bit value.
+pub const MAX_DIGITS: u32 = 9;
+
+const STEP_LEN: usize = 8;
+const CODE_LEN: usize = 4;
+
+/// Longest device id whose token frame still fits a transmission.
+pub const MAX_DEVICE_ID_LEN: usize =
+    MAX_VARIABLE_PAYLOAD_LEN - HEADER_LEN - TAG_LEN - 1 - STEP_LEN - CODE_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpError {
+    /// The payload isn't a token frame.
+    Malformed,
+    /// The token was issued by a different device.
+    WrongDevice,
+    /// The token's time step is too far from the verifier's clock.
+    OutsideWindow,
+    /// The token's time step was already used (or is older than one that was).
+    Replayed,
+    /// The code doesn't match.
+    InvalidCode,
+    /// A [`TotpConfig`] setting is out of range.
+    InvalidConfig(&'static str),
+}
+
+impl fmt::Display for TotpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TotpError::Malformed => write!(f, "malformed token"),
+            TotpError::WrongDevice => write!(f, "token is for a different device"),
+            TotpError::OutsideWindow => write!(f, "token is outside the accepted time window"),
+            TotpError::Replayed => write!(f, "token was already used"),
+            TotpError::InvalidCode => write!(f, "invalid code"),
+            TotpError::InvalidConfig(msg) => write!(f, "invalid TOTP configuration: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TotpError {}
+
+/// RFC 4226 HOTP value for `counter`, using HMAC-SHA1.
+///
+/// # Panics
+///
+/// If `digits` is more than [`MAX_DIGITS`].
+pub fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    truncate(&hmac_sha1(secret, &[&counter.to_be_bytes()]), digits)
+}
+
+/// RFC 6238 TOTP value at `unix_time`.
+///
+/// # Panics
+///
+/// If `step` is 0 or `digits` is more than [`MAX_DIGITS`].
+pub fn totp(secret: &[u8], unix_time: u64, step: u64, digits: u32) -> u32 {
+    hotp(secret, unix_time / step, digits)
+}
+
+/// The code a [`TotpSender`] sends: HOTP with the device id (length-prefixed,
+/// as in the token) in front of the counter in the HMAC input.
+fn device_code(secret: &[u8], device_id: &[u8], step: u64, digits: u32) -> u32 {
+    let hash = hmac_sha1(
+        secret,
+        &[&[device_id.len() as u8], device_id, &step.to_be_bytes()],
+    );
+    truncate(&hash, digits)
+}
+
+fn hmac_sha1(secret: &[u8], message: &[&[u8]]) -> [u8; 20] {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    for part in message {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// RFC 4226 dynamic truncation of an HMAC-SHA1 value to `digits` digits.
+fn truncate(hash: &[u8; 20], digits: u32) -> u32 {
+    assert!(digits <= MAX_DIGITS, "at most {MAX_DIGITS} digits");
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    binary % 10u32.pow(digits)
+}
+
+/// Parsed token: `device_len u8 | device_id | step u64 | code u32`, sent as
+/// the body of a message frame tagged [`TOKEN_TAG`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub device_id: Vec<u8>,
+    pub step: u64,
+    pub code: u32,
+}
+
+impl Token {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(1 + self.device_id.len() + STEP_LEN + CODE_LEN);
+        out.push(self.device_id.len() as u8);
+        out.extend_from_slice(&self.device_id);
+        out.extend_from_slice(&self.step.to_be_bytes());
+        out.extend_from_slice(&self.code.to_be_bytes());
+        out
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self, TotpError> {
+        let (&device_len, rest) = payload.split_first().ok_or(TotpError::Malformed)?;
+        let device_len = device_len as usize;
+        if rest.len() != device_len + STEP_LEN + CODE_LEN {
+            return Err(TotpError::Malformed);
+        }
+        let (device_id, rest) = rest.split_at(device_len);
+        let (step, code) = rest.split_at(STEP_LEN);
+        Ok(Self {
+            device_id: device_id.to_vec(),
+            step: u64::from_be_bytes(step.try_into().expect("split at STEP_LEN")),
+            code: u32::from_be_bytes(code.try_into().expect("remaining CODE_LEN bytes")),
+        })
+    }
+
+    /// The token as a frame tagged [`TOKEN_TAG`], the payload a
+    /// [`TotpSender`] transmits.
+    pub fn to_frame(&self) -> Result<Vec<u8>, WireError> {
+        Frame::message(self.encode()).with_tag(TOKEN_TAG).encode()
+    }
+
+    /// Parses a received payload made by [`to_frame`](Self::to_frame).
+    pub fn from_frame(payload: &[u8]) -> Result<Self, TotpError> {
+        match Frame::decode(payload) {
+            Ok(frame) if frame.kind == FrameKind::Message && frame.tag == Some(TOKEN_TAG) => {
+                Self::decode(&frame.body)
+            }
+            _ => Err(TotpError::Malformed),
+        }
+    }
+}
+
+/// Shared settings of a sender and its verifier.
+#[derive(Debug, Clone)]
+pub struct TotpConfig {
+    device_id: Vec<u8>,
+    secret: Vec<u8>,
+    step: u64,
+    digits: u32,
+}
+
+impl TotpConfig {
+    /// Settings with the default step and digits. The device id is at most
+    /// [`MAX_DEVICE_ID_LEN`] bytes.
+    pub fn new(
+        device_id: impl Into<Vec<u8>>,
+        secret: impl Into<Vec<u8>>,
+    ) -> Result<Self, TotpError> {
+        Self::with_timing(device_id, secret, DEFAULT_STEP, DEFAULT_DIGITS)
+    }
+
+    /// Settings with a `step` of at least one second and 1 to
+    /// [`MAX_DIGITS`] `digits`.
+    pub fn with_timing(
+        device_id: impl Into<Vec<u8>>,
+        secret: impl Into<Vec<u8>>,
+        step: u64,
+        digits: u32,
+    ) -> Result<Self, TotpError> {
+        let device_id = device_id.into();
+        if device_id.len() > MAX_DEVICE_ID_LEN {
+            return Err(TotpError::InvalidConfig(
+                "device id is too long for a token frame",
+            ));
+        }
+        if step == 0 {
+            return Err(TotpError::InvalidConfig("step must be at least one second"));
+        }
+        if !(1..=MAX_DIGITS).contains(&digits) {
+            return Err(TotpError::InvalidConfig("digits must be between 1 and 9"));
+        }
+        Ok(Self {
+            device_id,
+            secret: secret.into(),
+            step,
+            digits,
+        })
+    }
+
+    pub fn device_id(&self) -> &[u8] {
+        &self.device_id
+    }
+
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    fn code(&self, step: u64) -> u32 {
+        device_code(&self.secret, &self.device_id, step, self.digits)
+    }
+}
+
+pub struct TotpSender {
+    config: TotpConfig,
+}
+
+impl TotpSender {
+    pub fn new(config: TotpConfig) -> Self {
+        Self { config }
+    }
+
+    /// Payload to transmit at `unix_time`: the token as a frame tagged
+    /// [`TOKEN_TAG`].
+    pub fn token_at(&self, unix_time: u64) -> Vec<u8> {
+        let step = unix_time / self.config.step;
+        Token {
+            device_id: self.config.device_id.clone(),
+            step,
+            code: self.config.code(step),
+        }
+        .to_frame()
+        .expect("TotpConfig keeps the device id short enough for a frame")
+    }
+}
+
+pub struct TotpVerifier {
+    config: TotpConfig,
+    /// Steps of clock skew tolerated in either direction.
+    window: u64,
+    last_accepted: Option<u64>,
+}
+
+impl TotpVerifier {
+    /// Accepts tokens up to `window` steps away from the verifier's clock.
+    pub fn new(config: TotpConfig, window: u64) -> Self {
+        Self {
+            config,
+            window,
+            last_accepted: None,
+        }
+    }
+
+    /// Checks a received payload against the clock at `unix_time`. Each time
+    /// step is accepted at most once.
+    pub fn verify(&mut self, payload: &[u8], unix_time: u64) -> Result<(), TotpError> {
+        self.verify_token(&Token::from_frame(payload)?, unix_time)
+    }
+
+    /// [`verify`](Self::verify) for a token already taken out of its frame,
+    /// e.g. by a [`Router`](crate::router::Router) handler.
+    pub fn verify_token(&mut self, token: &Token, unix_time: u64) -> Result<(), TotpError> {
+        if token.device_id != self.config.device_id {
+            return Err(TotpError::WrongDevice);
+        }
+        let now = unix_time / self.config.step;
+        if token.step.abs_diff(now) > self.window {
+            return Err(TotpError::OutsideWindow);
+        }
+        if self.last_accepted.is_some_and(|last| token.step <= last) {
+            return Err(TotpError::Replayed);
+        }
+        let expected = self.config.code(token.step);
+        // Compare without an early exit on the first differing byte.
+        let diff = expected
+            .to_be_bytes()
+            .iter()
+            .zip(token.code.to_be_bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            return Err(TotpError::InvalidCode);
+        }
+        self.last_accepted = Some(token.step);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn rfc6238_sha1_vectors() {
+        let vectors = [
+            (59, 94287082),
+            (1111111109, 7081804),
+            (1111111111, 14050471),
+            (1234567890, 89005924),
+            (2000000000, 69279037),
+            (20000000000, 65353130),
+        ];
+        for (time, code) in vectors {
+            assert_eq!(totp(RFC_SECRET, time, 30, 8), code, "T = {time}");
+        }
+    }
+
+    #[test]
+    fn token_roundtrip() {
+        let _serial = crate::serial();
+        let token = Token {
+            device_id: b"door-1".to_vec(),
+            step: 42,
+            code: 123456,
+        };
+        assert_eq!(Token::decode(&token.encode()), Ok(token.clone()));
+        assert_eq!(Token::decode(&[]), Err(TotpError::Malformed));
+        assert_eq!(Token::decode(&[5, 1, 2]), Err(TotpError::Malformed));
+
+        let frame = token.to_frame().unwrap();
+        assert_eq!(Token::from_frame(&frame), Ok(token.clone()));
+        // A bare token, or one in a frame with another tag, isn't accepted.
+        assert_eq!(
+            Token::from_frame(&token.encode()),
+            Err(TotpError::Malformed)
+        );
+        let untagged = Frame::message(token.encode()).encode().unwrap();
+        assert_eq!(Token::from_frame(&untagged), Err(TotpError::Malformed));
+    }
+
+    #[test]
+    fn verifier_checks_window_device_and_replay() {
+        let _serial = crate::serial();
+        let config = TotpConfig::new("door-1", RFC_SECRET).unwrap();
+        let sender = TotpSender::new(config.clone());
+        let mut verifier = TotpVerifier::new(config, 1);
+
+        let now = 1_700_000_000;
+        let token = sender.token_at(now);
+        assert_eq!(verifier.verify(&token, now + 20), Ok(()));
+        assert_eq!(verifier.verify(&token, now + 20), Err(TotpError::Replayed));
+
+        let stale = sender.token_at(now - 120);
+        assert_eq!(verifier.verify(&stale, now), Err(TotpError::OutsideWindow));
+
+        let next = sender.token_at(now + 30);
+        let mut forged = Token::from_frame(&next).unwrap();
+        forged.code = (forged.code + 1) % 1_000_000;
+        assert_eq!(
+            verifier.verify(&forged.to_frame().unwrap(), now + 30),
+            Err(TotpError::InvalidCode)
+        );
+        assert_eq!(verifier.verify(&next, now + 30), Ok(()));
+
+        let other = TotpSender::new(TotpConfig::new("door-2", RFC_SECRET).unwrap());
+        assert_eq!(
+            verifier.verify(&other.token_at(now + 60), now + 60),
+            Err(TotpError::WrongDevice)
+        );
+    }
+
+    #[test]
+    fn codes_are_bound_to_the_device() {
+        let _serial = crate::serial();
+        let door_1 = TotpConfig::new("door-1", RFC_SECRET).unwrap();
+        let door_2 = TotpConfig::new("door-2", RFC_SECRET).unwrap();
+        let mut verifier = TotpVerifier::new(door_2.clone(), 1);
+
+        // A token captured from door-1, relabelled as door-2's.
+        let now = 1_700_000_000;
+        let mut swapped = Token::from_frame(&TotpSender::new(door_1).token_at(now)).unwrap();
+        swapped.device_id = b"door-2".to_vec();
+        assert_eq!(
+            verifier.verify(&swapped.to_frame().unwrap(), now),
+            Err(TotpError::InvalidCode)
+        );
+        assert_eq!(
+            verifier.verify(&TotpSender::new(door_2).token_at(now), now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn config_rejects_out_of_range_settings() {
+        let _serial = crate::serial();
+        let longest = TotpConfig::new(vec![0; MAX_DEVICE_ID_LEN], RFC_SECRET).unwrap();
+        let frame = TotpSender::new(longest).token_at(0);
+        assert_eq!(frame.len(), MAX_VARIABLE_PAYLOAD_LEN);
+        assert!(TotpConfig::new(vec![0; MAX_DEVICE_ID_LEN + 1], RFC_SECRET).is_err());
+        assert!(TotpConfig::with_timing("door-1", RFC_SECRET, 0, 6).is_err());
+        assert!(TotpConfig::with_timing("door-1", RFC_SECRET, 30, 0).is_err());
+        assert!(TotpConfig::with_timing("door-1", RFC_SECRET, 30, 10).is_err());
+        let config = TotpConfig::with_timing("door-1", RFC_SECRET, 60, 9).unwrap();
+        assert_eq!((config.step(), config.digits()), (60, 9));
+    }
+}
@@ -0,0 +1,140 @@
+//! Handing a waveform to an audio output one frame at a time.
+
+use alloc::vec::Vec;
+
+use crate::{Error, GgWave, ProtocolId, SampleFormat};
+
+/// Silence in the unsigned 16-bit format, in the byte order the library
+/// writes.
+const U16_SILENCE: [u8; 2] = 0x8000u16.to_ne_bytes();
+
+/// One sample of silence in `format`: the midpoint for the unsigned ones.
+fn silence(format: SampleFormat) -> &'static [u8] {
+    match format {
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_UNDEFINED => &[],
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_U8 => &[0x80],
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_I8 => &[0],
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_U16 => &U16_SILENCE,
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_I16 => &[0; 2],
+        SampleFormat::GGWAVE_SAMPLE_FORMAT_F32 => &[0; 4],
+    }
+}
+
+/// An encoded waveform cut into frames of `samplesPerFrame` samples, for
+/// output callbacks that ask for that many at a time; see
+/// [`GgWave::tx_session`].
+///
+/// The last frame is padded with silence. [`next_frame`](Self::next_frame)
+/// lends each frame without allocating; as an `Iterator` the session yields
+/// copies.
+pub struct TxSession {
+    waveform: Vec<u8>,
+    frame_len: usize,
+    position: usize,
+}
+
+impl GgWave {
+    /// Encodes `payload` and returns the waveform as a [`TxSession`].
+    pub fn tx_session(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<TxSession, Error> {
+        let format = SampleFormat::try_from(self.parameters().sampleFormatOut)?;
+        let samples_per_frame = usize::try_from(self.parameters().samplesPerFrame).unwrap_or(0);
+        let frame_len = samples_per_frame * format.bytes_per_sample();
+        if frame_len == 0 {
+            return Err(Error::InvalidInput(
+                "tx session needs an output format and samplesPerFrame",
+            ));
+        }
+
+        let mut waveform = self.encode(payload, protocol, volume)?;
+        let padded = waveform.len().div_ceil(frame_len) * frame_len;
+        let silence = silence(format);
+        while waveform.len() < padded {
+            waveform.extend_from_slice(silence);
+        }
+        Ok(TxSession {
+            waveform,
+            frame_len,
+            position: 0,
+        })
+    }
+}
+
+impl TxSession {
+    /// The next frame, or `None` once the waveform has been played.
+    pub fn next_frame(&mut self) -> Option<&[u8]> {
+        let frame = self
+            .waveform
+            .get(self.position..self.position + self.frame_len)?;
+        self.position += self.frame_len;
+        Some(frame)
+    }
+
+    /// Frames not handed out yet, for progress reporting.
+    pub fn remaining_frames(&self) -> usize {
+        (self.waveform.len() - self.position) / self.frame_len
+    }
+
+    pub fn total_frames(&self) -> usize {
+        self.waveform.len() / self.frame_len
+    }
+
+    /// Size of a frame in bytes, in the instance's output format.
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+}
+
+impl Iterator for TxSession {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        self.next_frame().map(<[u8]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining_frames();
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for TxSession {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_parameters;
+
+    #[test]
+    fn frames_cover_the_waveform_and_pad_with_silence() {
+        let _serial = crate::serial();
+        let mut params = default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_U8.into();
+        // Resampled, so the waveform doesn't end on a frame boundary.
+        params.sampleRateOut = 44100.0;
+        let ggwave = GgWave::new(params).unwrap();
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        let waveform = ggwave.encode(b"frames", protocol, 25).unwrap();
+
+        let mut session = ggwave.tx_session(b"frames", protocol, 25).unwrap();
+        let frame_len = params.samplesPerFrame as usize;
+        assert_eq!(session.frame_len(), frame_len);
+        let total = waveform.len().div_ceil(frame_len);
+        assert_eq!(session.total_frames(), total);
+
+        let mut played = Vec::new();
+        while let Some(frame) = session.next_frame() {
+            assert_eq!(frame.len(), frame_len);
+            played.extend_from_slice(frame);
+            assert_eq!(session.remaining_frames(), total - played.len() / frame_len);
+        }
+        assert_eq!(played[..waveform.len()], waveform[..]);
+        assert!(played.len() > waveform.len());
+        assert!(played[waveform.len()..].iter().all(|&b| b == 0x80));
+        assert_eq!(session.len(), 0);
+    }
+}
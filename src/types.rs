@@ -0,0 +1,632 @@
+//! Safe Rust enums for the C library's enumerations.
+//!
+//! The FFI layer represents these as `c_int` newtypes; converting an FFI value
+//! with `from_raw`/`TryFrom` fails for values this crate doesn't know about.
+
+use alloc::string::{String, ToString};
+use core::ffi::c_int;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{ffi, Error};
+
+macro_rules! c_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident => ffi::$raw:ident, $unknown:literal {
+            $($variant:ident = $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant = $value,)*
+        }
+
+        impl $name {
+            /// Converts a value reported by the C library, returning `None`
+            /// for values this crate doesn't know about.
+            pub fn from_raw(raw: c_int) -> Option<Self> {
+                match raw {
+                    $($value => Some($name::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub const fn as_raw(self) -> c_int {
+                self as c_int
+            }
+        }
+
+        impl From<$name> for ffi::$raw {
+            fn from(value: $name) -> Self {
+                ffi::$raw(value.as_raw())
+            }
+        }
+
+        impl TryFrom<ffi::$raw> for $name {
+            type Error = Error;
+
+            fn try_from(raw: ffi::$raw) -> Result<Self, Error> {
+                $name::from_raw(raw.0).ok_or(Error::InvalidInput($unknown))
+            }
+        }
+
+        impl From<$name> for c_int {
+            fn from(value: $name) -> Self {
+                value.as_raw()
+            }
+        }
+
+        impl TryFrom<c_int> for $name {
+            type Error = Error;
+
+            fn try_from(raw: c_int) -> Result<Self, Error> {
+                $name::from_raw(raw).ok_or(Error::InvalidInput($unknown))
+            }
+        }
+    };
+}
+
+c_enum! {
+    /// Sample format of waveforms passed to and from the library.
+    SampleFormat => ffi::ggwave_SampleFormat, "unknown sample format" {
+        GGWAVE_SAMPLE_FORMAT_UNDEFINED = 0,
+        GGWAVE_SAMPLE_FORMAT_U8 = 1,
+        GGWAVE_SAMPLE_FORMAT_I8 = 2,
+        GGWAVE_SAMPLE_FORMAT_U16 = 3,
+        GGWAVE_SAMPLE_FORMAT_I16 = 4,
+        GGWAVE_SAMPLE_FORMAT_F32 = 5,
+    }
+}
+
+impl SampleFormat {
+    /// Size of one sample, or 0 for `GGWAVE_SAMPLE_FORMAT_UNDEFINED`.
+    pub const fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_UNDEFINED => 0,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_U8 | SampleFormat::GGWAVE_SAMPLE_FORMAT_I8 => 1,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_U16 | SampleFormat::GGWAVE_SAMPLE_FORMAT_I16 => 2,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32 => 4,
+        }
+    }
+}
+
+c_enum! {
+    /// Transmission protocol. `GGWAVE_PROTOCOL_COUNT` is not a protocol and
+    /// only exists at the FFI layer.
+    ProtocolId => ffi::ggwave_ProtocolId, "unknown protocol id" {
+        GGWAVE_PROTOCOL_AUDIBLE_NORMAL = 0,
+        GGWAVE_PROTOCOL_AUDIBLE_FAST = 1,
+        GGWAVE_PROTOCOL_AUDIBLE_FASTEST = 2,
+        GGWAVE_PROTOCOL_ULTRASOUND_NORMAL = 3,
+        GGWAVE_PROTOCOL_ULTRASOUND_FAST = 4,
+        GGWAVE_PROTOCOL_ULTRASOUND_FASTEST = 5,
+        GGWAVE_PROTOCOL_DT_NORMAL = 6,
+        GGWAVE_PROTOCOL_DT_FAST = 7,
+        GGWAVE_PROTOCOL_DT_FASTEST = 8,
+        GGWAVE_PROTOCOL_MT_NORMAL = 9,
+        GGWAVE_PROTOCOL_MT_FAST = 10,
+        GGWAVE_PROTOCOL_MT_FASTEST = 11,
+        GGWAVE_PROTOCOL_CUSTOM_0 = 12,
+        GGWAVE_PROTOCOL_CUSTOM_1 = 13,
+        GGWAVE_PROTOCOL_CUSTOM_2 = 14,
+        GGWAVE_PROTOCOL_CUSTOM_3 = 15,
+        GGWAVE_PROTOCOL_CUSTOM_4 = 16,
+        GGWAVE_PROTOCOL_CUSTOM_5 = 17,
+        GGWAVE_PROTOCOL_CUSTOM_6 = 18,
+        GGWAVE_PROTOCOL_CUSTOM_7 = 19,
+        GGWAVE_PROTOCOL_CUSTOM_8 = 20,
+        GGWAVE_PROTOCOL_CUSTOM_9 = 21,
+    }
+}
+
+impl ProtocolId {
+    /// Every protocol in numeric order, custom slots included, for
+    /// auto-detection loops and protocol pickers.
+    pub fn all() -> impl Iterator<Item = ProtocolId> + Clone {
+        (0..).map_while(ProtocolId::from_raw)
+    }
+
+    /// Stable identifier for configs, the CLI and other serialized forms, e.g.
+    /// `"audible.fast"` or `"custom.3"`. Unlike the numeric value it doesn't
+    /// change if upstream reorders its protocol enum.
+    pub const fn id(self) -> &'static str {
+        match self {
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL => "audible.normal",
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST => "audible.fast",
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST => "audible.fastest",
+            ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL => "ultrasound.normal",
+            ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST => "ultrasound.fast",
+            ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST => "ultrasound.fastest",
+            ProtocolId::GGWAVE_PROTOCOL_DT_NORMAL => "dt.normal",
+            ProtocolId::GGWAVE_PROTOCOL_DT_FAST => "dt.fast",
+            ProtocolId::GGWAVE_PROTOCOL_DT_FASTEST => "dt.fastest",
+            ProtocolId::GGWAVE_PROTOCOL_MT_NORMAL => "mt.normal",
+            ProtocolId::GGWAVE_PROTOCOL_MT_FAST => "mt.fast",
+            ProtocolId::GGWAVE_PROTOCOL_MT_FASTEST => "mt.fastest",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0 => "custom.0",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_1 => "custom.1",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_2 => "custom.2",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_3 => "custom.3",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_4 => "custom.4",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_5 => "custom.5",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_6 => "custom.6",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_7 => "custom.7",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_8 => "custom.8",
+            ProtocolId::GGWAVE_PROTOCOL_CUSTOM_9 => "custom.9",
+        }
+    }
+
+    /// Upstream's display name, e.g. `"[U] Fast"`.
+    pub const fn name(self) -> &'static str {
+        use ProtocolId::*;
+        match self {
+            GGWAVE_PROTOCOL_AUDIBLE_NORMAL => "Normal",
+            GGWAVE_PROTOCOL_AUDIBLE_FAST => "Fast",
+            GGWAVE_PROTOCOL_AUDIBLE_FASTEST => "Fastest",
+            GGWAVE_PROTOCOL_ULTRASOUND_NORMAL => "[U] Normal",
+            GGWAVE_PROTOCOL_ULTRASOUND_FAST => "[U] Fast",
+            GGWAVE_PROTOCOL_ULTRASOUND_FASTEST => "[U] Fastest",
+            GGWAVE_PROTOCOL_DT_NORMAL => "[DT] Normal",
+            GGWAVE_PROTOCOL_DT_FAST => "[DT] Fast",
+            GGWAVE_PROTOCOL_DT_FASTEST => "[DT] Fastest",
+            GGWAVE_PROTOCOL_MT_NORMAL => "[MT] Normal",
+            GGWAVE_PROTOCOL_MT_FAST => "[MT] Fast",
+            GGWAVE_PROTOCOL_MT_FASTEST => "[MT] Fastest",
+            GGWAVE_PROTOCOL_CUSTOM_0 => "Custom 0",
+            GGWAVE_PROTOCOL_CUSTOM_1 => "Custom 1",
+            GGWAVE_PROTOCOL_CUSTOM_2 => "Custom 2",
+            GGWAVE_PROTOCOL_CUSTOM_3 => "Custom 3",
+            GGWAVE_PROTOCOL_CUSTOM_4 => "Custom 4",
+            GGWAVE_PROTOCOL_CUSTOM_5 => "Custom 5",
+            GGWAVE_PROTOCOL_CUSTOM_6 => "Custom 6",
+            GGWAVE_PROTOCOL_CUSTOM_7 => "Custom 7",
+            GGWAVE_PROTOCOL_CUSTOM_8 => "Custom 8",
+            GGWAVE_PROTOCOL_CUSTOM_9 => "Custom 9",
+        }
+    }
+
+    /// Speed class, or `None` for custom protocols.
+    pub const fn speed(self) -> Option<Speed> {
+        use ProtocolId::*;
+        match self {
+            GGWAVE_PROTOCOL_AUDIBLE_NORMAL
+            | GGWAVE_PROTOCOL_ULTRASOUND_NORMAL
+            | GGWAVE_PROTOCOL_DT_NORMAL
+            | GGWAVE_PROTOCOL_MT_NORMAL => Some(Speed::Normal),
+            GGWAVE_PROTOCOL_AUDIBLE_FAST
+            | GGWAVE_PROTOCOL_ULTRASOUND_FAST
+            | GGWAVE_PROTOCOL_DT_FAST
+            | GGWAVE_PROTOCOL_MT_FAST => Some(Speed::Fast),
+            GGWAVE_PROTOCOL_AUDIBLE_FASTEST
+            | GGWAVE_PROTOCOL_ULTRASOUND_FASTEST
+            | GGWAVE_PROTOCOL_DT_FASTEST
+            | GGWAVE_PROTOCOL_MT_FASTEST => Some(Speed::Fastest),
+            _ => None,
+        }
+    }
+
+    pub const fn is_ultrasound(self) -> bool {
+        matches!(
+            self,
+            ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_NORMAL
+                | ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FAST
+                | ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST
+        )
+    }
+
+    pub const fn is_custom(self) -> bool {
+        self.as_raw() >= ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0.as_raw()
+    }
+
+    /// Lowest and highest tone frequency in Hz, from upstream's protocol
+    /// table (first tone bin, bytes per frame) and the bin width of
+    /// `parameters` (see [`hz_per_bin`](crate::hz_per_bin)). `None` for
+    /// custom protocols, whose start can be moved, and for unset parameters.
+    ///
+    /// Tones above half the output sample rate can't be played at that rate.
+    pub fn freq_range_hz(self, parameters: &ffi::ggwave_Parameters) -> Option<(f32, f32)> {
+        use ProtocolId::*;
+        let (first_bin, bytes_per_frame) = match self {
+            GGWAVE_PROTOCOL_AUDIBLE_NORMAL
+            | GGWAVE_PROTOCOL_AUDIBLE_FAST
+            | GGWAVE_PROTOCOL_AUDIBLE_FASTEST => (40, 3),
+            GGWAVE_PROTOCOL_ULTRASOUND_NORMAL
+            | GGWAVE_PROTOCOL_ULTRASOUND_FAST
+            | GGWAVE_PROTOCOL_ULTRASOUND_FASTEST => (320, 3),
+            GGWAVE_PROTOCOL_DT_NORMAL
+            | GGWAVE_PROTOCOL_DT_FAST
+            | GGWAVE_PROTOCOL_DT_FASTEST
+            | GGWAVE_PROTOCOL_MT_NORMAL
+            | GGWAVE_PROTOCOL_MT_FAST
+            | GGWAVE_PROTOCOL_MT_FASTEST => (24, 1),
+            _ => return None,
+        };
+        let hz_per_bin = crate::hz_per_bin(parameters)?;
+        // Two nibbles of 16 tones for each byte.
+        let last_bin = first_bin + 32 * bytes_per_frame - 1;
+        Some((first_bin as f32 * hz_per_bin, last_bin as f32 * hz_per_bin))
+    }
+
+    /// Longest payload this protocol carries with `parameters`:
+    /// [`MAX_VARIABLE_PAYLOAD_LEN`](crate::MAX_VARIABLE_PAYLOAD_LEN) bytes in
+    /// variable-length mode, `payloadLength` in fixed-length mode. Upstream
+    /// applies the same limits to every protocol at the moment.
+    pub fn max_payload_len(self, parameters: &ffi::ggwave_Parameters) -> usize {
+        match parameters.payloadLength {
+            len if len > 0 => (len as usize).min(crate::MAX_FIXED_PAYLOAD_LEN),
+            _ => crate::MAX_VARIABLE_PAYLOAD_LEN,
+        }
+    }
+
+    /// Parses a stable identifier. Older spellings are accepted too: the CLI's
+    /// `audible-fast`, `audible_fast` and the C name `GGWAVE_PROTOCOL_AUDIBLE_FAST`,
+    /// case-insensitively.
+    pub fn from_id(id: &str) -> Option<Self> {
+        let id = id.to_ascii_lowercase().replace(['-', '_'], ".");
+        let id = id.strip_prefix("ggwave.protocol.").unwrap_or(&id);
+        ProtocolId::all().find(|protocol| protocol.id() == id)
+    }
+
+    /// Shorthands accepted by the `FromStr` impl.
+    fn from_alias(alias: &str) -> Option<Self> {
+        use ProtocolId::*;
+        const FAMILIES: [(&[&str], [ProtocolId; 3]); 4] = [
+            (
+                &["", "a", "audible"],
+                [
+                    GGWAVE_PROTOCOL_AUDIBLE_NORMAL,
+                    GGWAVE_PROTOCOL_AUDIBLE_FAST,
+                    GGWAVE_PROTOCOL_AUDIBLE_FASTEST,
+                ],
+            ),
+            (
+                &["u", "us", "ultra", "ultrasound"],
+                [
+                    GGWAVE_PROTOCOL_ULTRASOUND_NORMAL,
+                    GGWAVE_PROTOCOL_ULTRASOUND_FAST,
+                    GGWAVE_PROTOCOL_ULTRASOUND_FASTEST,
+                ],
+            ),
+            (
+                &["dt"],
+                [
+                    GGWAVE_PROTOCOL_DT_NORMAL,
+                    GGWAVE_PROTOCOL_DT_FAST,
+                    GGWAVE_PROTOCOL_DT_FASTEST,
+                ],
+            ),
+            (
+                &["mt"],
+                [
+                    GGWAVE_PROTOCOL_MT_NORMAL,
+                    GGWAVE_PROTOCOL_MT_FAST,
+                    GGWAVE_PROTOCOL_MT_FASTEST,
+                ],
+            ),
+        ];
+
+        let alias = alias.trim().to_ascii_lowercase();
+        let (family, speed) = if let Some(tagged) = alias.strip_prefix('[') {
+            // Upstream's display names: "[U] Fast", "[DT] Normal", or "[U2]"
+            // with the speed as an index.
+            let (tag, speed) = tagged.split_once(']')?;
+            match tag.char_indices().last() {
+                Some((i, digit @ '0'..='2')) if speed.trim().is_empty() => {
+                    (tag[..i].to_string(), digit.to_string())
+                }
+                _ => (tag.to_string(), speed.trim().to_string()),
+            }
+        } else {
+            match alias.split_once(['-', '_', '.', ' ']) {
+                Some((family, speed)) => (family.to_string(), speed.to_string()),
+                // Upstream names the audible protocols by speed alone.
+                None if matches!(alias.as_str(), "normal" | "fast" | "fastest") => {
+                    (String::new(), alias)
+                }
+                None => (alias, String::new()),
+            }
+        };
+        if family.is_empty() && speed.is_empty() {
+            return None;
+        }
+
+        let speed = match speed.as_str() {
+            "" | "n" | "normal" | "0" => 0,
+            "f" | "fast" | "1" => 1,
+            "ff" | "fastest" | "2" => 2,
+            _ => return None,
+        };
+        FAMILIES
+            .iter()
+            .find(|(names, _)| names.contains(&family.as_str()))
+            .map(|(_, protocols)| protocols[speed])
+    }
+}
+
+/// Accepts every spelling [`ProtocolId::from_id`] does, plus shorthands:
+/// a family on its own for its normal speed (`audible`, `ultrasound`, `dt`),
+/// abbreviated families and speeds (`u-fast`, `a-ff`), upstream's display
+/// names (`Fastest`, `[U] Fast`, `[MT] Normal`) and tags with a speed index
+/// (`[U2]` for ultrasound fastest).
+impl FromStr for ProtocolId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        ProtocolId::from_id(s)
+            .or_else(|| ProtocolId::from_alias(s))
+            .ok_or(Error::InvalidInput("unknown protocol"))
+    }
+}
+
+/// The CLI's spelling, e.g. `audible-fast` or `custom-3`, which `FromStr`
+/// parses back. Use [`ProtocolId::id`] for configs and [`ProtocolId::name`]
+/// for upstream's display name.
+impl fmt::Display for ProtocolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (family, rest) = self.id().split_once('.').expect("ids have a family");
+        write!(f, "{family}-{rest}")
+    }
+}
+
+/// Speed class of a built-in protocol: fewer frames per transmission is
+/// faster but less robust.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Speed {
+    Normal,
+    Fast,
+    Fastest,
+}
+
+c_enum! {
+    /// Window/filter applied to captured audio.
+    Filter => ffi::ggwave_Filter, "unknown filter" {
+        GGWAVE_FILTER_HANN = 0,
+        GGWAVE_FILTER_HAMMING = 1,
+        GGWAVE_FILTER_FIRST_ORDER_HIGH_PASS = 2,
+    }
+}
+
+bitflags::bitflags! {
+    /// What an instance is set up for, as stored in
+    /// `Parameters::operatingMode`.
+    ///
+    /// Use [`Parameters::with_operating_mode`] to set it; it rejects modes the
+    /// C library can't do anything useful with.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct OperatingMode: c_int {
+        const RX = ffi::GGWAVE_OPERATING_MODE_RX;
+        const TX = ffi::GGWAVE_OPERATING_MODE_TX;
+        const RX_AND_TX = ffi::GGWAVE_OPERATING_MODE_RX_AND_TX;
+        /// Generate only the tone sequence, without the markers and
+        /// envelope. Requires `TX`.
+        const TX_ONLY_TONES = ffi::GGWAVE_OPERATING_MODE_TX_ONLY_TONES;
+        /// Direct-sequence spread spectrum.
+        const USE_DSS = ffi::GGWAVE_OPERATING_MODE_USE_DSS;
+    }
+}
+
+impl OperatingMode {
+    /// Checks the combination: at least one of `RX` and `TX`, and
+    /// `TX_ONLY_TONES` only together with `TX`.
+    pub fn validate(self) -> Result<Self, Error> {
+        if !self.intersects(OperatingMode::RX_AND_TX) {
+            return Err(Error::InvalidInput("operating mode needs RX or TX"));
+        }
+        if self.contains(OperatingMode::TX_ONLY_TONES) && !self.contains(OperatingMode::TX) {
+            return Err(Error::InvalidInput("TX_ONLY_TONES requires TX"));
+        }
+        Ok(self)
+    }
+}
+
+/// The flag names joined by ` | `, e.g. `RX | USE_DSS`.
+impl fmt::Display for OperatingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("(none)");
+        }
+        bitflags::parser::to_writer(self, f)
+    }
+}
+
+impl ffi::ggwave_Parameters {
+    /// The operating mode, or `None` if it has bits this crate doesn't know.
+    pub fn operating_mode(&self) -> Option<OperatingMode> {
+        OperatingMode::from_bits(self.operatingMode)
+    }
+
+    /// Sets the operating mode after [validating](OperatingMode::validate) it.
+    pub fn with_operating_mode(mut self, mode: OperatingMode) -> Result<Self, Error> {
+        self.operatingMode = mode.validate()?.bits();
+        Ok(self)
+    }
+
+    /// Turns direct-sequence spread spectrum on or off, keeping the rest of
+    /// the operating mode. Both ends of a transmission must agree on it.
+    pub fn with_dss(mut self, enabled: bool) -> Self {
+        if enabled {
+            self.operatingMode |= OperatingMode::USE_DSS.bits();
+        } else {
+            self.operatingMode &= !OperatingMode::USE_DSS.bits();
+        }
+        self
+    }
+
+    pub fn uses_dss(&self) -> bool {
+        self.operatingMode & OperatingMode::USE_DSS.bits() != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_id_raw_roundtrip() {
+        for raw in 0..ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 {
+            let protocol = ProtocolId::from_raw(raw).expect("known protocol");
+            assert_eq!(protocol.as_raw(), raw);
+            assert_eq!(ffi::ggwave_ProtocolId::from(protocol).0, raw);
+        }
+        assert_eq!(
+            ProtocolId::from_raw(ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0),
+            None
+        );
+        assert_eq!(ProtocolId::from_raw(-1), None);
+        assert_eq!(ProtocolId::from_raw(1000), None);
+
+        let all: Vec<_> = ProtocolId::all().collect();
+        assert_eq!(
+            all.len(),
+            ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 as usize
+        );
+        assert_eq!(all[0], ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_NORMAL);
+        assert_eq!(all.last(), Some(&ProtocolId::GGWAVE_PROTOCOL_CUSTOM_9));
+        assert_eq!(i32::from(ProtocolId::GGWAVE_PROTOCOL_MT_FAST), 10);
+        assert_eq!(
+            ProtocolId::try_from(10).unwrap(),
+            ProtocolId::GGWAVE_PROTOCOL_MT_FAST
+        );
+        assert!(ProtocolId::try_from(22).is_err());
+    }
+
+    #[test]
+    fn protocol_ids_are_stable() {
+        assert_eq!(
+            ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST.id(),
+            "audible.fast"
+        );
+        assert_eq!(ProtocolId::GGWAVE_PROTOCOL_CUSTOM_3.id(), "custom.3");
+        for raw in 0..ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_COUNT.0 {
+            let protocol = ProtocolId::from_raw(raw).unwrap();
+            assert_eq!(ProtocolId::from_id(protocol.id()), Some(protocol));
+        }
+    }
+
+    #[test]
+    fn protocol_ids_accept_old_names() {
+        let expected = Some(ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST);
+        assert_eq!(ProtocolId::from_id("ultrasound-fastest"), expected);
+        assert_eq!(ProtocolId::from_id("ultrasound_fastest"), expected);
+        assert_eq!(
+            ProtocolId::from_id("GGWAVE_PROTOCOL_ULTRASOUND_FASTEST"),
+            expected
+        );
+        assert_eq!(
+            ProtocolId::from_id("custom-9"),
+            Some(ProtocolId::GGWAVE_PROTOCOL_CUSTOM_9)
+        );
+        assert_eq!(ProtocolId::from_id("audible"), None);
+        assert_eq!(ProtocolId::from_id("count"), None);
+    }
+
+    #[test]
+    fn protocol_aliases() {
+        use ProtocolId::*;
+        for (alias, expected) in [
+            ("audible-fast", GGWAVE_PROTOCOL_AUDIBLE_FAST),
+            ("u-fast", GGWAVE_PROTOCOL_ULTRASOUND_FAST),
+            ("U_FF", GGWAVE_PROTOCOL_ULTRASOUND_FASTEST),
+            ("audible", GGWAVE_PROTOCOL_AUDIBLE_NORMAL),
+            ("dt", GGWAVE_PROTOCOL_DT_NORMAL),
+            ("Fastest", GGWAVE_PROTOCOL_AUDIBLE_FASTEST),
+            ("[U] Fast", GGWAVE_PROTOCOL_ULTRASOUND_FAST),
+            ("[MT]normal", GGWAVE_PROTOCOL_MT_NORMAL),
+            ("[U2]", GGWAVE_PROTOCOL_ULTRASOUND_FASTEST),
+            ("[dt1]", GGWAVE_PROTOCOL_DT_FAST),
+            ("custom.4", GGWAVE_PROTOCOL_CUSTOM_4),
+        ] {
+            assert_eq!(alias.parse::<ProtocolId>().unwrap(), expected, "{alias}");
+        }
+        for protocol in ProtocolId::all() {
+            assert_eq!(
+                protocol.to_string().parse::<ProtocolId>().unwrap(),
+                protocol
+            );
+        }
+        assert_eq!(
+            GGWAVE_PROTOCOL_ULTRASOUND_NORMAL.to_string(),
+            "ultrasound-normal"
+        );
+        for bad in ["", "[]", "[U3]", "[U2] fast", "x-fast", "u-slow", "count"] {
+            assert!(bad.parse::<ProtocolId>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn unknown_ffi_values_are_rejected() {
+        assert_eq!(
+            SampleFormat::try_from(ffi::ggwave_SampleFormat::GGWAVE_SAMPLE_FORMAT_F32).unwrap(),
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32
+        );
+        assert!(SampleFormat::try_from(ffi::ggwave_SampleFormat(42)).is_err());
+        assert!(Filter::try_from(ffi::ggwave_Filter(3)).is_err());
+    }
+
+    #[test]
+    fn operating_mode_combinations() {
+        let tones = OperatingMode::TX | OperatingMode::TX_ONLY_TONES;
+        assert_eq!(tones.validate().unwrap(), tones);
+        assert!((OperatingMode::RX | OperatingMode::USE_DSS)
+            .validate()
+            .is_ok());
+        assert!(OperatingMode::USE_DSS.validate().is_err());
+        assert!((OperatingMode::RX | OperatingMode::TX_ONLY_TONES)
+            .validate()
+            .is_err());
+
+        let params = crate::default_parameters()
+            .with_operating_mode(OperatingMode::RX)
+            .unwrap();
+        assert_eq!(params.operatingMode, ffi::GGWAVE_OPERATING_MODE_RX);
+        assert_eq!(params.operating_mode(), Some(OperatingMode::RX));
+        assert!(crate::default_parameters()
+            .with_operating_mode(OperatingMode::empty())
+            .is_err());
+
+        let dss = params.with_dss(true);
+        assert!(dss.uses_dss());
+        assert_eq!(
+            dss.operating_mode(),
+            Some(OperatingMode::RX | OperatingMode::USE_DSS)
+        );
+        assert!(!dss.with_dss(false).uses_dss());
+    }
+
+    #[test]
+    fn protocol_metadata() {
+        let params = crate::default_parameters();
+        let fast = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+        assert_eq!(fast.name(), "Fast");
+        assert_eq!(fast.name().parse::<ProtocolId>().unwrap(), fast);
+        assert_eq!(fast.speed(), Some(Speed::Fast));
+        assert!(!fast.is_ultrasound());
+        assert_eq!(fast.freq_range_hz(&params), Some((1875.0, 6328.125)));
+
+        let ultrasound = ProtocolId::GGWAVE_PROTOCOL_ULTRASOUND_FASTEST;
+        assert!(ultrasound.is_ultrasound());
+        assert_eq!(ultrasound.freq_range_hz(&params).unwrap().0, 15_000.0);
+
+        let custom = ProtocolId::GGWAVE_PROTOCOL_CUSTOM_4;
+        assert!(custom.is_custom());
+        assert_eq!(
+            (custom.speed(), custom.freq_range_hz(&params)),
+            (None, None)
+        );
+        for raw in 0..ffi::ggwave_ProtocolId::GGWAVE_PROTOCOL_CUSTOM_0.0 {
+            let protocol = ProtocolId::from_raw(raw).unwrap();
+            assert_eq!(protocol.name().parse::<ProtocolId>().unwrap(), protocol);
+        }
+
+        let mut fixed = params;
+        assert_eq!(fast.max_payload_len(&params), 140);
+        fixed.payloadLength = 16;
+        assert_eq!(custom.max_payload_len(&fixed), 16);
+        fixed.payloadLength = 100;
+        assert_eq!(fast.max_payload_len(&fixed), 64);
+    }
+}
@@ -0,0 +1,86 @@
+//! Encoding and decoding in a browser through Web Audio (`wasm` feature).
+//!
+//! Web Audio hands out and takes mono audio as `Float32Array`s at the
+//! `AudioContext`'s sample rate. [`to_float32_array`] and
+//! [`from_float32_array`] move samples across the JavaScript boundary, and
+//! [`WebGgWave`] is a `GgWave` exported to JavaScript that speaks them
+//! directly:
+//!
+//! ```js
+//! const ggwave = new WebGgWave(context.sampleRate);
+//! const samples = ggwave.encode(new TextEncoder().encode("hi"), 1, 50);
+//! const buffer = context.createBuffer(1, samples.length, context.sampleRate);
+//! buffer.copyToChannel(samples, 0);
+//! ```
+
+use js_sys::Float32Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{default_parameters, GgWave, ProtocolId, SampleFormat, Waveform};
+
+/// Copies `samples` into a new `Float32Array`, e.g. for
+/// `AudioBuffer.copyToChannel`.
+pub fn to_float32_array(samples: &[f32]) -> Float32Array {
+    Float32Array::from(samples)
+}
+
+/// Copies the samples out of `array`, e.g. one filled by
+/// `AudioBuffer.copyFromChannel` or an `AudioWorklet`'s input.
+pub fn from_float32_array(array: &Float32Array) -> Vec<f32> {
+    array.to_vec()
+}
+
+impl Waveform {
+    /// The samples as a `Float32Array`.
+    pub fn to_float32_array(&self) -> Float32Array {
+        to_float32_array(&self.samples)
+    }
+
+    /// Wraps the samples of `array`, recorded at `sample_rate`.
+    pub fn from_float32_array(array: &Float32Array, sample_rate: u32) -> Self {
+        Self::new(from_float32_array(array), sample_rate)
+    }
+}
+
+/// A [`GgWave`] with F32 input and output at one sample rate, for
+/// JavaScript.
+#[wasm_bindgen]
+pub struct WebGgWave {
+    inner: GgWave,
+}
+
+#[wasm_bindgen]
+impl WebGgWave {
+    /// An instance that encodes and decodes at `sample_rate`, normally the
+    /// `AudioContext`'s.
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate: f32) -> Result<WebGgWave, JsError> {
+        let mut parameters = default_parameters();
+        parameters.sampleRateInp = sample_rate;
+        parameters.sampleRateOut = sample_rate;
+        parameters.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        parameters.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        Ok(Self {
+            inner: GgWave::new(parameters)?,
+        })
+    }
+
+    /// Encodes `payload` with the protocol whose id is `protocol` (see
+    /// [`ProtocolId`]) at `volume` (0 to 100).
+    pub fn encode(
+        &self,
+        payload: &[u8],
+        protocol: i32,
+        volume: i32,
+    ) -> Result<Float32Array, JsError> {
+        let protocol = ProtocolId::try_from(protocol)?;
+        let samples = self.inner.encode_f32(payload, protocol, volume)?;
+        Ok(to_float32_array(&samples))
+    }
+
+    /// Feeds a chunk of captured audio to the receiver, returning a payload
+    /// once one is complete.
+    pub fn decode(&self, samples: &Float32Array) -> Result<Option<Vec<u8>>, JsError> {
+        Ok(self.inner.decode_f32(&from_float32_array(samples))?)
+    }
+}
@@ -0,0 +1,133 @@
+//! Reading and writing WAV files (`wav` feature).
+
+use std::path::Path;
+
+use hound::{SampleFormat as WavFormat, WavReader, WavSpec, WavWriter};
+
+use crate::{Error, SampleFormat, Waveform};
+
+impl Waveform {
+    /// Writes a mono WAV file in the waveform's format: 32-bit float for F32,
+    /// 16-bit for I16 and U16, 8-bit for U8 and I8.
+    pub fn to_wav(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let (bits_per_sample, sample_format) = match self.format {
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_U8 | SampleFormat::GGWAVE_SAMPLE_FORMAT_I8 => {
+                (8, WavFormat::Int)
+            }
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_U16 | SampleFormat::GGWAVE_SAMPLE_FORMAT_I16 => {
+                (16, WavFormat::Int)
+            }
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32
+            | SampleFormat::GGWAVE_SAMPLE_FORMAT_UNDEFINED => (32, WavFormat::Float),
+        };
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample,
+            sample_format,
+        };
+
+        let mut writer = WavWriter::create(path, spec)?;
+        let scaled = |s: f32, max: f32| (s.clamp(-1.0, 1.0) * max).round();
+        for &s in &self.samples {
+            match bits_per_sample {
+                8 => writer.write_sample(scaled(s, 127.0) as i8)?,
+                16 => writer.write_sample(scaled(s, 32767.0) as i16)?,
+                _ => writer.write_sample(s)?,
+            }
+        }
+        writer.finalize()?;
+        Ok(())
+    }
+
+    /// Reads a WAV file of float or integer samples (up to 32 bits), mixing
+    /// multichannel audio down to mono.
+    ///
+    /// The format is the file's where the library has it (F32, I16, U8 for
+    /// 8-bit files) and F32 otherwise.
+    pub fn from_wav(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut reader = WavReader::open(path)?;
+        let spec = reader.spec();
+        let (samples, format) = match (spec.sample_format, spec.bits_per_sample) {
+            (WavFormat::Float, 32) => (
+                reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+                SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            ),
+            (WavFormat::Int, bits @ 1..=32) => {
+                let full_scale = (1u64 << (bits - 1)) as f32;
+                let samples = reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 / full_scale))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let format = match bits {
+                    8 => SampleFormat::GGWAVE_SAMPLE_FORMAT_U8,
+                    16 => SampleFormat::GGWAVE_SAMPLE_FORMAT_I16,
+                    _ => SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+                };
+                (samples, format)
+            }
+            _ => return Err(hound::Error::Unsupported.into()),
+        };
+
+        let channels = usize::from(spec.channels);
+        let samples = if channels > 1 {
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                .collect()
+        } else {
+            samples
+        };
+        Ok(Self {
+            samples,
+            sample_rate: spec.sample_rate,
+            format,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_and_mixes_down() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("ggwave_rs_wav_{}.wav", std::process::id()));
+
+        for format in [
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_I16,
+            SampleFormat::GGWAVE_SAMPLE_FORMAT_U8,
+        ] {
+            let waveform = Waveform {
+                format,
+                ..Waveform::new(vec![0.0, 0.5, -0.5], 8000)
+            };
+            waveform.to_wav(&path).unwrap();
+            let read = Waveform::from_wav(&path).unwrap();
+            assert_eq!((read.format, read.sample_rate), (format, 8000));
+            for (a, b) in read.samples.iter().zip(&waveform.samples) {
+                assert!((a - b).abs() < 0.01, "{format:?}: {a} vs {b}");
+            }
+        }
+
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: WavFormat::Int,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for s in [i32::MAX, 0, i32::MIN, i32::MIN] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+        let read = Waveform::from_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.format, SampleFormat::GGWAVE_SAMPLE_FORMAT_F32);
+        assert_eq!(read.samples.len(), 2);
+        assert!((read.samples[0] - 0.5).abs() < 1e-6);
+        assert_eq!(read.samples[1], -1.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! Encoded audio together with its sample rate and format.
+
+use std::time::Duration;
+
+use crate::{dsp, Error, GgWave, ProtocolId, SampleFormat};
+
+/// Mono audio at a known sample rate.
+///
+/// Samples are kept as F32 for processing; `format` is the sample format
+/// they were converted from and that [`to_bytes`](Self::to_bytes) converts
+/// them back to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waveform {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub format: SampleFormat,
+}
+
+impl Waveform {
+    /// Wraps F32 samples.
+    pub fn new(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            sample_rate,
+            format: SampleFormat::GGWAVE_SAMPLE_FORMAT_F32,
+        }
+    }
+
+    /// Converts native-endian PCM in `format`, as the library reads and
+    /// writes it.
+    pub fn from_bytes(bytes: &[u8], format: SampleFormat, sample_rate: u32) -> Self {
+        Self {
+            samples: dsp::from_bytes(bytes, format.into()),
+            sample_rate,
+            format,
+        }
+    }
+
+    /// Wraps the raw output of `GgWave::encode` for an instance whose output
+    /// format is F32.
+    pub fn from_f32_bytes(bytes: &[u8], sample_rate: u32) -> Self {
+        Self::from_bytes(bytes, SampleFormat::GGWAVE_SAMPLE_FORMAT_F32, sample_rate)
+    }
+
+    pub fn as_f32(&self) -> &[f32] {
+        &self.samples
+    }
+
+    /// The samples as native-endian PCM in `format`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        dsp::to_bytes(&self.samples, self.format.into())
+    }
+
+    /// Panics if the sample rate is 0.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate as f64)
+    }
+
+    /// Sample index of `at`, rounded to the nearest sample.
+    pub fn offset_of(&self, at: Duration) -> usize {
+        (at.as_secs_f64() * self.sample_rate as f64).round() as usize
+    }
+
+    /// Mixes this waveform, scaled by `gain`, into `carrier` (audio at the
+    /// same sample rate) starting at the sample nearest to `at`.
+    ///
+    /// The carrier is added to rather than overwritten, so program material
+    /// keeps playing underneath; check its levels afterwards if it was
+    /// already close to full scale. Fails if the waveform would run past the
+    /// end of the carrier.
+    pub fn insert_into(&self, carrier: &mut [f32], at: Duration, gain: f32) -> Result<(), Error> {
+        let start = self.offset_of(at);
+        let end = start
+            .checked_add(self.samples.len())
+            .filter(|&end| end <= carrier.len())
+            .ok_or(Error::InvalidInput(
+                "waveform doesn't fit in the carrier at that offset",
+            ))?;
+        for (out, &s) in carrier[start..end].iter_mut().zip(&self.samples) {
+            *out += s * gain;
+        }
+        Ok(())
+    }
+
+    /// Copy with every frequency moved by `hz` (down if negative).
+    ///
+    /// Moves a transmission to another band without changing the protocol
+    /// tables: the sender shifts its output up and the receiver shifts the
+    /// capture back down by the same amount before decoding. Components
+    /// pushed past 0 Hz or half the sample rate are lost.
+    pub fn freq_shifted(&self, hz: f32) -> Self {
+        Self {
+            samples: dsp::freq_shift(&self.samples, hz, self.sample_rate as f32),
+            ..*self
+        }
+    }
+
+    /// Raw native-endian F32 bytes, as `GgWave::decode` expects for an
+    /// instance whose input format is F32.
+    pub fn to_f32_bytes(&self) -> Vec<u8> {
+        dsp::to_bytes(&self.samples, SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into())
+    }
+
+    /// A mono `rodio` source playing this waveform, to append to a `Sink`
+    /// or mix with other sources. rodio resamples it to the device's rate.
+    ///
+    /// Panics if the sample rate is 0.
+    #[cfg(feature = "rodio")]
+    pub fn into_source(self) -> rodio::buffer::SamplesBuffer {
+        rodio::buffer::SamplesBuffer::new(1, self.sample_rate, self.samples)
+    }
+}
+
+impl GgWave {
+    /// [`encode`](Self::encode), returning the waveform with the output
+    /// sample rate and format.
+    pub fn encode_waveform(
+        &self,
+        payload: &[u8],
+        protocol: ProtocolId,
+        volume: i32,
+    ) -> Result<Waveform, Error> {
+        let format = SampleFormat::try_from(self.parameters().sampleFormatOut)?;
+        let bytes = self.encode(payload, protocol, volume)?;
+        Ok(Waveform::from_bytes(
+            &bytes,
+            format,
+            self.parameters().sampleRateOut as u32,
+        ))
+    }
+
+    /// [`decode`](Self::decode) for a waveform at the input sample rate, in
+    /// any format: it's converted to the input format first.
+    pub fn decode_waveform(&self, waveform: &Waveform) -> Result<Option<Vec<u8>>, Error> {
+        if waveform.sample_rate != self.parameters().sampleRateInp as u32 {
+            return Err(Error::InvalidInput(
+                "waveform's sample rate differs from the input sample rate",
+            ));
+        }
+        self.decode(&dsp::to_bytes(
+            &waveform.samples,
+            self.parameters().sampleFormatInp,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_at_exact_sample() {
+        let waveform = Waveform::new(vec![1.0, 0.5], 1000);
+        assert_eq!(waveform.duration(), Duration::from_millis(2));
+
+        let mut carrier = vec![0.1; 10];
+        waveform
+            .insert_into(&mut carrier, Duration::from_micros(3_400), 0.5)
+            .unwrap();
+        assert_eq!(carrier, [0.1, 0.1, 0.1, 0.6, 0.35, 0.1, 0.1, 0.1, 0.1, 0.1]);
+
+        assert!(waveform
+            .insert_into(&mut carrier, Duration::from_millis(9), 1.0)
+            .is_err());
+        waveform
+            .insert_into(&mut carrier, Duration::from_millis(8), 1.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn from_f32_bytes_roundtrip() {
+        let bytes: Vec<u8> = [0.25f32, -1.0]
+            .iter()
+            .flat_map(|s| s.to_ne_bytes())
+            .collect();
+        let waveform = Waveform::from_f32_bytes(&bytes, 48000);
+        assert_eq!(waveform.samples, [0.25, -1.0]);
+        assert_eq!(waveform.to_f32_bytes(), bytes);
+    }
+
+    #[test]
+    fn encodes_and_decodes_across_formats() {
+        let _serial = crate::serial();
+        let mut params = crate::default_parameters();
+        params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_I16.into();
+        params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+        let ggwave = GgWave::new(params).unwrap();
+        let protocol = ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST;
+
+        let waveform = ggwave.encode_waveform(b"meta", protocol, 25).unwrap();
+        let bytes = ggwave.encode(b"meta", protocol, 25).unwrap();
+        assert_eq!(waveform.format, SampleFormat::GGWAVE_SAMPLE_FORMAT_I16);
+        assert_eq!(waveform.sample_rate, 48000);
+        assert_eq!(waveform.as_f32().len(), bytes.len() / 2);
+        assert_eq!(waveform.to_bytes(), bytes);
+        assert_eq!(
+            waveform.duration(),
+            ggwave.tx_duration(4, protocol).unwrap()
+        );
+
+        assert_eq!(
+            ggwave.decode_waveform(&waveform).unwrap().as_deref(),
+            Some(&b"meta"[..])
+        );
+        let resampled = Waveform {
+            sample_rate: 44100,
+            ..waveform
+        };
+        assert!(ggwave.decode_waveform(&resampled).is_err());
+    }
+
+    #[cfg(feature = "rodio")]
+    #[test]
+    fn plays_as_a_rodio_source() {
+        use rodio::Source;
+
+        let source = Waveform::new(vec![0.5; 4800], 48000).into_source();
+        assert_eq!(source.sample_rate(), 48000);
+        assert_eq!(source.channels(), 1);
+        assert_eq!(source.total_duration(), Some(Duration::from_millis(100)));
+        assert!(source.eq([0.5; 4800]));
+    }
+}
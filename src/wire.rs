@@ -0,0 +1,418 @@
+//! Versioned wire format for payloads carried over ggwave.
+//!
+//! Every frame starts with a three byte header:
+//!
+//! | byte | field     |
+//! |------|-----------|
+//! | 0    | version   |
+//! | 1    | kind      |
+//! | 2    | flags     |
+//!
+//! followed by the optional fields below, in this order, and then the body:
+//!
+//! - `FLAG_ADDRESSED`: source and destination address, one byte each
+//! - kind `Chunk`: chunk index and chunk count, one byte each
+//!
+//! With `FLAG_TAGGED` set, the body is followed by a two byte big-endian
+//! application tag. Unrelated applications sharing the same acoustic space
+//! pick distinct tags and drop each other's frames with [`Frame::decode_for`].
+//!
+//! Parsers are strict: unknown versions, kinds and flag bits are rejected
+//! instead of being guessed at, so a receiver never misreads a frame produced
+//! by a newer revision of the format.
+
+use crate::MAX_VARIABLE_PAYLOAD_LEN;
+
+/// Wire format version produced and accepted by this crate.
+pub const WIRE_VERSION: u8 = 1;
+
+/// Size of the fixed frame header.
+pub const HEADER_LEN: usize = 3;
+
+pub const FLAG_ADDRESSED: u8 = 1 << 0;
+pub const FLAG_TAGGED: u8 = 1 << 1;
+
+const KNOWN_FLAGS: u8 = FLAG_ADDRESSED | FLAG_TAGGED;
+
+/// Size of the application tag trailer.
+pub const TAG_LEN: usize = 2;
+
+const KIND_MESSAGE: u8 = 0;
+const KIND_CHUNK: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A complete message.
+    Message,
+    /// One part of a message split across several transmissions.
+    Chunk { index: u8, count: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub source: u8,
+    pub destination: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub address: Option<Address>,
+    pub body: Vec<u8>,
+    /// Application-defined tag carried in the trailer.
+    pub tag: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    Truncated,
+    UnsupportedVersion(u8),
+    UnknownKind(u8),
+    ReservedFlags(u8),
+    InvalidChunk { index: u8, count: u8 },
+    TooLong(usize),
+}
+
+impl std::fmt::Display for WireError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WireError::Truncated => write!(f, "frame is truncated"),
+            WireError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported wire format version {v} (supported: {WIRE_VERSION})"
+            ),
+            WireError::UnknownKind(k) => write!(f, "unknown frame kind {k}"),
+            WireError::ReservedFlags(flags) => {
+                write!(f, "reserved frame flags set: {flags:#04x}")
+            }
+            WireError::InvalidChunk { index, count } => {
+                write!(f, "invalid chunk {index} of {count}")
+            }
+            WireError::TooLong(len) => write!(
+                f,
+                "frame is {len} bytes, more than the {MAX_VARIABLE_PAYLOAD_LEN} bytes a \
+                 transmission carries"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl WireError {
+    /// Stable machine-readable identifier, used by the conformance vectors.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WireError::Truncated => "truncated",
+            WireError::UnsupportedVersion(_) => "unsupported-version",
+            WireError::UnknownKind(_) => "unknown-kind",
+            WireError::ReservedFlags(_) => "reserved-flags",
+            WireError::InvalidChunk { .. } => "invalid-chunk",
+            WireError::TooLong(_) => "too-long",
+        }
+    }
+}
+
+impl Frame {
+    pub fn message(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kind: FrameKind::Message,
+            address: None,
+            body: body.into(),
+            tag: None,
+        }
+    }
+
+    pub fn chunk(index: u8, count: u8, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            kind: FrameKind::Chunk { index, count },
+            address: None,
+            body: body.into(),
+            tag: None,
+        }
+    }
+
+    pub fn with_address(mut self, source: u8, destination: u8) -> Self {
+        self.address = Some(Address {
+            source,
+            destination,
+        });
+        self
+    }
+
+    pub fn with_tag(mut self, tag: u16) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Number of bytes the frame adds on top of its body.
+    pub fn overhead(&self) -> usize {
+        let mut len = HEADER_LEN;
+        if self.address.is_some() {
+            len += 2;
+        }
+        if let FrameKind::Chunk { .. } = self.kind {
+            len += 2;
+        }
+        if self.tag.is_some() {
+            len += TAG_LEN;
+        }
+        len
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, WireError> {
+        let len = self.overhead() + self.body.len();
+        if len > MAX_VARIABLE_PAYLOAD_LEN {
+            return Err(WireError::TooLong(len));
+        }
+
+        let mut flags = 0;
+        if self.address.is_some() {
+            flags |= FLAG_ADDRESSED;
+        }
+        if self.tag.is_some() {
+            flags |= FLAG_TAGGED;
+        }
+        let kind = match self.kind {
+            FrameKind::Message => KIND_MESSAGE,
+            FrameKind::Chunk { index, count } => {
+                validate_chunk(index, count)?;
+                KIND_CHUNK
+            }
+        };
+
+        let mut out = Vec::with_capacity(len);
+        out.extend_from_slice(&[WIRE_VERSION, kind, flags]);
+        if let Some(address) = self.address {
+            out.extend_from_slice(&[address.source, address.destination]);
+        }
+        if let FrameKind::Chunk { index, count } = self.kind {
+            out.extend_from_slice(&[index, count]);
+        }
+        out.extend_from_slice(&self.body);
+        if let Some(tag) = self.tag {
+            out.extend_from_slice(&tag.to_be_bytes());
+        }
+        Ok(out)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, WireError> {
+        if bytes.len() > MAX_VARIABLE_PAYLOAD_LEN {
+            return Err(WireError::TooLong(bytes.len()));
+        }
+
+        let version = peek_version(bytes)?;
+        if version != WIRE_VERSION {
+            return Err(WireError::UnsupportedVersion(version));
+        }
+
+        let mut reader = Reader { bytes, pos: 1 };
+        let kind = reader.byte()?;
+        let flags = reader.byte()?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(WireError::ReservedFlags(flags & !KNOWN_FLAGS));
+        }
+
+        let address = if flags & FLAG_ADDRESSED != 0 {
+            Some(Address {
+                source: reader.byte()?,
+                destination: reader.byte()?,
+            })
+        } else {
+            None
+        };
+
+        let kind = match kind {
+            KIND_MESSAGE => FrameKind::Message,
+            KIND_CHUNK => {
+                let index = reader.byte()?;
+                let count = reader.byte()?;
+                validate_chunk(index, count)?;
+                FrameKind::Chunk { index, count }
+            }
+            other => return Err(WireError::UnknownKind(other)),
+        };
+
+        let rest = reader.rest();
+        let (body, tag) = if flags & FLAG_TAGGED != 0 {
+            if rest.len() < TAG_LEN {
+                return Err(WireError::Truncated);
+            }
+            let (body, trailer) = rest.split_at(rest.len() - TAG_LEN);
+            (body, Some(u16::from_be_bytes([trailer[0], trailer[1]])))
+        } else {
+            (rest, None)
+        };
+
+        Ok(Self {
+            kind,
+            address,
+            body: body.to_vec(),
+            tag,
+        })
+    }
+
+    /// Parses a frame addressed to the application using `tag`.
+    ///
+    /// Returns `Ok(None)` for well-formed frames that are untagged or carry a
+    /// different tag, so they can be ignored rather than treated as errors.
+    pub fn decode_for(bytes: &[u8], tag: u16) -> Result<Option<Self>, WireError> {
+        let frame = Self::decode(bytes)?;
+        Ok((frame.tag == Some(tag)).then_some(frame))
+    }
+}
+
+/// Returns the format version of a frame without parsing the rest of it.
+pub fn peek_version(bytes: &[u8]) -> Result<u8, WireError> {
+    bytes.first().copied().ok_or(WireError::Truncated)
+}
+
+fn validate_chunk(index: u8, count: u8) -> Result<(), WireError> {
+    if count == 0 || index >= count {
+        return Err(WireError::InvalidChunk { index, count });
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, WireError> {
+        let b = *self.bytes.get(self.pos).ok_or(WireError::Truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_roundtrip() {
+        let _serial = crate::serial();
+        let frame = Frame::message(b"hello".to_vec());
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes, [1, 0, 0, b'h', b'e', b'l', b'l', b'o']);
+        assert_eq!(Frame::decode(&bytes).unwrap(), frame);
+    }
+
+    #[test]
+    fn addressed_chunk_roundtrip() {
+        let _serial = crate::serial();
+        let frame = Frame::chunk(2, 5, b"xy".to_vec()).with_address(7, 9);
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes, [1, 1, FLAG_ADDRESSED, 7, 9, 2, 5, b'x', b'y']);
+        assert_eq!(frame.overhead(), 7);
+        assert_eq!(Frame::decode(&bytes).unwrap(), frame);
+    }
+
+    #[test]
+    fn tagged_roundtrip() {
+        let _serial = crate::serial();
+        let frame = Frame::message(b"hi".to_vec())
+            .with_address(1, 2)
+            .with_tag(0xbeef);
+        let bytes = frame.encode().unwrap();
+        assert_eq!(
+            bytes,
+            [
+                1,
+                0,
+                FLAG_ADDRESSED | FLAG_TAGGED,
+                1,
+                2,
+                b'h',
+                b'i',
+                0xbe,
+                0xef
+            ]
+        );
+        assert_eq!(frame.overhead(), 7);
+        assert_eq!(Frame::decode(&bytes).unwrap(), frame);
+    }
+
+    #[test]
+    fn decode_for_filters_by_tag() {
+        let _serial = crate::serial();
+        let ours = Frame::message(b"a".to_vec()).with_tag(7).encode().unwrap();
+        let theirs = Frame::message(b"a".to_vec()).with_tag(8).encode().unwrap();
+        let untagged = Frame::message(b"a".to_vec()).encode().unwrap();
+        assert_eq!(Frame::decode_for(&ours, 7).unwrap().unwrap().tag, Some(7));
+        assert_eq!(Frame::decode_for(&theirs, 7), Ok(None));
+        assert_eq!(Frame::decode_for(&untagged, 7), Ok(None));
+        assert_eq!(
+            Frame::decode_for(&[1, 0, FLAG_TAGGED, 7], 7),
+            Err(WireError::Truncated)
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let _serial = crate::serial();
+        // A future revision may change everything after the version byte, so
+        // the parser must refuse it rather than guess.
+        for version in [0, 2, 0xff] {
+            assert_eq!(
+                Frame::decode(&[version, 0, 0, b'a']),
+                Err(WireError::UnsupportedVersion(version))
+            );
+            assert_eq!(peek_version(&[version]), Ok(version));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_kinds_and_flags() {
+        let _serial = crate::serial();
+        assert_eq!(Frame::decode(&[1, 9, 0]), Err(WireError::UnknownKind(9)));
+        assert_eq!(
+            Frame::decode(&[1, 0, 0x80]),
+            Err(WireError::ReservedFlags(0x80))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_frames() {
+        let _serial = crate::serial();
+        assert_eq!(Frame::decode(&[]), Err(WireError::Truncated));
+        assert_eq!(Frame::decode(&[1, 0]), Err(WireError::Truncated));
+        assert_eq!(
+            Frame::decode(&[1, 0, FLAG_ADDRESSED, 7]),
+            Err(WireError::Truncated)
+        );
+        assert_eq!(Frame::decode(&[1, 1, 0, 0]), Err(WireError::Truncated));
+    }
+
+    #[test]
+    fn rejects_invalid_chunks() {
+        let _serial = crate::serial();
+        assert_eq!(
+            Frame::decode(&[1, 1, 0, 3, 3]),
+            Err(WireError::InvalidChunk { index: 3, count: 3 })
+        );
+        assert_eq!(
+            Frame::chunk(0, 0, Vec::new()).encode(),
+            Err(WireError::InvalidChunk { index: 0, count: 0 })
+        );
+    }
+
+    #[test]
+    fn enforces_size_limit() {
+        let _serial = crate::serial();
+        let body = vec![0u8; MAX_VARIABLE_PAYLOAD_LEN - HEADER_LEN];
+        assert!(Frame::message(body.clone()).encode().is_ok());
+        let mut too_long = body;
+        too_long.push(0);
+        assert_eq!(
+            Frame::message(too_long).encode(),
+            Err(WireError::TooLong(MAX_VARIABLE_PAYLOAD_LEN + 1))
+        );
+    }
+}
@@ -0,0 +1,39 @@
+#![allow(dead_code)]
+
+use ggwave_rs::{default_parameters, Parameters, ProtocolId, SampleFormat};
+
+/// Every built-in protocol.
+pub fn protocols() -> Vec<ProtocolId> {
+    ProtocolId::all().filter(|p| !p.is_custom()).collect()
+}
+
+pub fn f32_parameters() -> Parameters {
+    let mut params = default_parameters();
+    params.sampleFormatInp = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+    params.sampleFormatOut = SampleFormat::GGWAVE_SAMPLE_FORMAT_F32.into();
+    params
+}
+
+/// Resident set size of the current process, where the platform exposes it.
+pub fn rss_bytes() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * usize::try_from(page_size).ok()?)
+}
+
+pub fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Serializes tests within one test binary.
+///
+/// The C library keeps its instances in an unsynchronized global table, so
+/// tests that create instances must not run concurrently.
+pub fn serial() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,149 @@
+//! Instance churn and leak checks around `ggwave_free`.
+//!
+//! The churn tests only run with `GGWAVE_LEAK_CHECK=1` (iterations via
+//! `GGWAVE_LEAK_ITERATIONS`). They are meant to be run under a leak checker:
+//!
+//! ```sh
+//! GGWAVE_LEAK_CHECK=1 cargo test --test instance_churn --no-run
+//! GGWAVE_LEAK_CHECK=1 valgrind --leak-check=full --error-exitcode=1 \
+//!     target/debug/deps/instance_churn-<hash>
+//! ```
+//!
+//! or with AddressSanitizer on both the C++ and Rust side (nightly):
+//!
+//! ```sh
+//! CXXFLAGS=-fsanitize=address RUSTFLAGS=-Zsanitizer=address GGWAVE_LEAK_CHECK=1 \
+//!     cargo +nightly test --target x86_64-unknown-linux-gnu --test instance_churn
+//! ```
+
+mod common;
+
+use common::{env_or, f32_parameters, rss_bytes, serial};
+use ggwave_rs::ffi::GGWAVE_MAX_INSTANCES;
+use ggwave_rs::{
+    Error, GgWave, ProtocolId, GGWAVE_OPERATING_MODE_RX, GGWAVE_OPERATING_MODE_RX_AND_TX,
+    GGWAVE_OPERATING_MODE_TX, GGWAVE_OPERATING_MODE_TX_ONLY_TONES, GGWAVE_OPERATING_MODE_USE_DSS,
+};
+
+const MODES: [i32; 6] = [
+    GGWAVE_OPERATING_MODE_RX,
+    GGWAVE_OPERATING_MODE_TX,
+    GGWAVE_OPERATING_MODE_RX_AND_TX,
+    GGWAVE_OPERATING_MODE_TX | GGWAVE_OPERATING_MODE_TX_ONLY_TONES,
+    GGWAVE_OPERATING_MODE_RX_AND_TX | GGWAVE_OPERATING_MODE_USE_DSS,
+    GGWAVE_OPERATING_MODE_RX | GGWAVE_OPERATING_MODE_USE_DSS,
+];
+
+/// Allowed RSS growth across the whole churn run, after warm-up.
+const MAX_RSS_GROWTH: usize = 8 * 1024 * 1024;
+
+fn leak_check_enabled() -> bool {
+    if std::env::var_os("GGWAVE_LEAK_CHECK").is_none() {
+        eprintln!("skipping: set GGWAVE_LEAK_CHECK=1 to run instance churn checks");
+        return false;
+    }
+    true
+}
+
+fn churn(iterations: usize, work: impl Fn(usize)) {
+    // Warm up allocator pools and lazily initialized upstream state first.
+    for i in 0..iterations / 10 {
+        work(i);
+    }
+    let before = rss_bytes();
+    for i in 0..iterations {
+        work(i);
+    }
+    if let (Some(before), Some(after)) = (before, rss_bytes()) {
+        assert!(
+            after.saturating_sub(before) <= MAX_RSS_GROWTH,
+            "RSS grew from {before} to {after} bytes over {iterations} iterations"
+        );
+    }
+}
+
+#[test]
+fn churn_instances_across_operating_modes() {
+    if !leak_check_enabled() {
+        return;
+    }
+    let _serial = serial();
+    let iterations = env_or("GGWAVE_LEAK_ITERATIONS", 5000);
+
+    churn(iterations, |i| {
+        let mut params = f32_parameters();
+        params.operatingMode = MODES[i % MODES.len()];
+        let instance = GgWave::new(params).expect("init failed");
+        drop(instance);
+    });
+}
+
+#[test]
+fn churn_encode_decode() {
+    if !leak_check_enabled() {
+        return;
+    }
+    let _serial = serial();
+    let iterations = env_or("GGWAVE_LEAK_ITERATIONS", 5000) / 10;
+
+    churn(iterations, |_| {
+        let params = f32_parameters();
+        let ggwave = GgWave::new(params).expect("init failed");
+        let waveform = ggwave
+            .encode(b"leak", ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FASTEST, 25)
+            .expect("encode failed");
+        ggwave.decode(&waveform).expect("decode failed");
+    });
+}
+
+#[test]
+fn drop_order_releases_slots() {
+    let _serial = serial();
+    let params = f32_parameters();
+    let max = GGWAVE_MAX_INSTANCES as usize;
+
+    // Drop the full table in several orders and make sure every freed slot can
+    // be taken again, whichever position it was in.
+    let orders: [&[usize]; 3] = [&[0, 1, 2, 3], &[3, 2, 1, 0], &[1, 3, 0, 2]];
+    for order in orders {
+        let mut instances: Vec<Option<GgWave>> = (0..max)
+            .map(|_| Some(GgWave::new(params).expect("init failed")))
+            .collect();
+        for &i in order.iter().filter(|&&i| i < max) {
+            instances[i] = None;
+            instances[i] = Some(GgWave::new(params).expect("slot was not released"));
+            instances[i] = None;
+        }
+        assert!(instances.iter().all(Option::is_none));
+    }
+
+    // One more than the table holds fails with a distinct error.
+    let full: Vec<GgWave> = (0..max)
+        .map(|_| GgWave::new(params).expect("init failed"))
+        .collect();
+    assert!(matches!(
+        GgWave::new(params),
+        Err(Error::InstanceLimitReached { max: m }) if m == max
+    ));
+    drop(full);
+
+    // Slots taken through the raw API, which the wrapper doesn't count, are
+    // reported the same way.
+    let raw: Vec<_> = (0..max)
+        .map(|_| unsafe { ggwave_rs::ffi::ggwave_init(params) })
+        .collect();
+    assert!(raw.iter().all(|&instance| instance >= 0));
+    assert!(matches!(
+        GgWave::new(params),
+        Err(Error::InstanceLimitReached { max: m }) if m == max
+    ));
+    for instance in raw {
+        unsafe { ggwave_rs::ffi::ggwave_free(instance) };
+    }
+
+    // With every slot released, the whole table is available again.
+    let refill: Vec<GgWave> = (0..max)
+        .map(|_| GgWave::new(params).expect("init failed"))
+        .collect();
+    assert_eq!(refill.len(), max);
+}
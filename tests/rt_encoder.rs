@@ -0,0 +1,65 @@
+//! `RtEncoder` must not allocate once created.
+//!
+//! A counting global allocator records the allocations made by the test's
+//! thread while it encodes. The pure-Rust backend's encoder allocates by
+//! design, so the count is only checked against the C++ library.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use ggwave_rs::{default_parameters, ProtocolId, RtEncoder};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Not available while the thread is shutting down.
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn encoding_does_not_allocate() {
+    let mut encoder = RtEncoder::new(
+        default_parameters(),
+        ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST,
+        32,
+    )
+    .expect("encoder init failed");
+    let mut out = vec![0u8; encoder.max_waveform_len()];
+    let payloads: [&[u8]; 3] = [b"a", b"realtime", &[0x5a; 32]];
+
+    let before = allocations();
+    for payload in payloads {
+        let written = encoder.encode_into(payload, 25, &mut out).unwrap();
+        assert_eq!(Some(written), encoder.encoded_size(payload.len()));
+        let waveform = encoder.encode(payload, 50).unwrap();
+        assert_eq!(waveform.len(), written);
+    }
+    let allocated = allocations() - before;
+
+    if !cfg!(feature = "pure-rust") {
+        assert_eq!(allocated, 0, "encoding allocated {allocated} times");
+    }
+}
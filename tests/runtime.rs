@@ -0,0 +1,35 @@
+//! `runtime::init` and `shutdown`.
+//!
+//! They set the process-wide protocol toggles, which would change what the
+//! library's other tests decode while they run alongside, so they get a test
+//! binary of their own.
+
+use ggwave_rs::runtime::{init, is_initialized, live_instances, shutdown, Config};
+use ggwave_rs::{default_parameters, is_strict, Error, GgWave, ProtocolId};
+
+#[test]
+fn init_is_idempotent_and_shutdown_waits_for_instances() {
+    let config = Config {
+        rx_protocols: Some(vec![ProtocolId::GGWAVE_PROTOCOL_AUDIBLE_FAST]),
+        ..Config::default()
+    };
+    if is_strict() {
+        assert!(matches!(init(config), Err(Error::GlobalStateForbidden)));
+        return;
+    }
+
+    init(config.clone()).unwrap();
+    init(config).unwrap();
+    assert!(is_initialized());
+    assert!(init(Config::default()).is_err());
+
+    let instance = GgWave::new(default_parameters()).unwrap();
+    assert_eq!(live_instances(), 1);
+    assert!(shutdown().is_err());
+    drop(instance);
+
+    shutdown().unwrap();
+    assert!(!is_initialized());
+    init(Config::default()).unwrap();
+    shutdown().unwrap();
+}
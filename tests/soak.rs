@@ -0,0 +1,118 @@
+//! Long-running encode/decode soak test.
+//!
+//! Ignored by default; run it explicitly, e.g. for four hours:
+//!
+//! ```sh
+//! GGWAVE_SOAK_SECS=14400 cargo test --release --test soak -- --ignored --nocapture
+//! ```
+//!
+//! `GGWAVE_SOAK_SEED` replays a specific run and `GGWAVE_SOAK_REPORT_SECS`
+//! sets how often a progress window is reported.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{env_or, f32_parameters, protocols, rss_bytes};
+use ggwave_rs::testing::Rng;
+use ggwave_rs::GgWave;
+
+/// Allowed RSS growth between the first and the last window.
+const MAX_RSS_GROWTH: usize = 32 * 1024 * 1024;
+
+struct Window {
+    attempts: u64,
+    successes: u64,
+    rss: Option<usize>,
+}
+
+impl Window {
+    fn success_ratio(&self) -> f64 {
+        self.successes as f64 / self.attempts.max(1) as f64
+    }
+}
+
+#[test]
+#[ignore = "long-running; set GGWAVE_SOAK_SECS and run with --ignored"]
+fn soak_encode_decode() {
+    let duration = Duration::from_secs(env_or("GGWAVE_SOAK_SECS", 60));
+    let report_every = Duration::from_secs(env_or("GGWAVE_SOAK_REPORT_SECS", 10));
+    let seed = env_or("GGWAVE_SOAK_SEED", 0x5eed_u64);
+    let mut rng = Rng::new(seed);
+    println!("soak: seed={seed} duration={duration:?}");
+
+    let protocols = protocols();
+    let started = Instant::now();
+    let mut windows: Vec<Window> = Vec::new();
+    let mut current = Window {
+        attempts: 0,
+        successes: 0,
+        rss: None,
+    };
+    let mut window_started = Instant::now();
+
+    while started.elapsed() < duration {
+        let mut params = f32_parameters();
+        params.soundMarkerThreshold = [2.0, 3.0, 4.0][rng.range(0, 2)];
+        let protocol = protocols[rng.range(0, protocols.len() - 1)];
+        let volume = rng.range(10, 100) as i32;
+        let len = rng.range(1, 64);
+        let payload = rng.bytes(len);
+
+        // Fresh instances every iteration exercise the C side's slot reuse.
+        let tx = GgWave::new(params).expect("tx init failed");
+        let rx = GgWave::new(params).expect("rx init failed");
+        let waveform = tx
+            .encode(&payload, protocol, volume)
+            .expect("encode failed");
+        current.attempts += 1;
+        if rx.decode(&waveform).ok().flatten().as_deref() == Some(&payload[..]) {
+            current.successes += 1;
+        } else {
+            println!(
+                "soak: decode mismatch protocol={protocol:?} len={}",
+                payload.len()
+            );
+        }
+
+        if window_started.elapsed() >= report_every {
+            current.rss = rss_bytes();
+            println!(
+                "soak: t={:>6}s attempts={} success={:.4} rss={:?}",
+                started.elapsed().as_secs(),
+                current.attempts,
+                current.success_ratio(),
+                current.rss
+            );
+            windows.push(current);
+            current = Window {
+                attempts: 0,
+                successes: 0,
+                rss: None,
+            };
+            window_started = Instant::now();
+        }
+    }
+
+    let (Some(first), Some(last)) = (windows.first(), windows.last()) else {
+        panic!("soak ran for less than one report window");
+    };
+
+    let total: u64 = windows.iter().map(|w| w.attempts).sum();
+    let ok: u64 = windows.iter().map(|w| w.successes).sum();
+    let overall = ok as f64 / total as f64;
+    assert!(overall >= 0.99, "overall success ratio {overall:.4}");
+    assert!(
+        last.success_ratio() >= first.success_ratio() - 0.01,
+        "success ratio drifted from {:.4} to {:.4}",
+        first.success_ratio(),
+        last.success_ratio()
+    );
+
+    if let (Some(start), Some(end)) = (first.rss, last.rss) {
+        assert!(
+            end.saturating_sub(start) <= MAX_RSS_GROWTH,
+            "RSS grew from {start} to {end} bytes"
+        );
+    }
+}
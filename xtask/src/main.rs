@@ -0,0 +1,144 @@
+//! Development tasks, run with `cargo xtask <task>`.
+//!
+//! - `check-features [--system | --pure-rust]`: builds and runs the
+//!   encode/decode roundtrip with no optional feature, with each one on its
+//!   own and with all of them together, catching feature-gate breakage that
+//!   a single configuration misses, and builds the library without `std`.
+//!   Combinations use the vendored library unless another backend is given.
+
+use std::env;
+use std::iter;
+use std::process::{exit, Command};
+
+/// Optional library features, each checked on its own and all together.
+const LIB_FEATURES: &[&str] = &[
+    "strict",
+    "send",
+    "totp",
+    "encryption",
+    "log",
+    "serde",
+    "tokio",
+    "rodio",
+    "heapless",
+    "codec",
+    "futures",
+    "ringbuf",
+    "wav",
+    "rubato",
+    "cpal",
+    "wasm",
+];
+/// Left out of the all-features check: `wasm` swaps in the pure-Rust
+/// backend, which would hide the backend being checked.
+const NOT_COMBINED: &[&str] = &["wasm"];
+/// Optional CLI features, each checked on its own.
+const CLI_FEATURES: &[&str] = &["sqlite", "jsonschema", "cpal"];
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("check-features") => {
+            let backend = if args.iter().any(|a| a == "--system") {
+                "system"
+            } else if args.iter().any(|a| a == "--pure-rust") {
+                "pure-rust"
+            } else {
+                "vendored"
+            };
+            exit(check_features(backend));
+        }
+        _ => {
+            eprintln!("usage: cargo xtask check-features [--system | --pure-rust]");
+            exit(2);
+        }
+    }
+}
+
+/// Returns the process exit code.
+fn check_features(backend: &str) -> i32 {
+    let mut failed = Vec::new();
+
+    let combined: Vec<&str> = LIB_FEATURES
+        .iter()
+        .copied()
+        .filter(|f| !NOT_COMBINED.contains(f))
+        .collect();
+    let sets = iter::once(Vec::new())
+        .chain(LIB_FEATURES.iter().map(|f| vec![*f]))
+        .chain(iter::once(combined));
+    for set in sets {
+        let features = [backend, "std"]
+            .into_iter()
+            .chain(set)
+            .collect::<Vec<_>>()
+            .join(",");
+        let ok = cargo(&[
+            "build",
+            "-p",
+            "ggwave-rs",
+            "--no-default-features",
+            "--features",
+            &features,
+        ]) && cargo(&[
+            "test",
+            "-p",
+            "ggwave-rs",
+            "--no-default-features",
+            "--features",
+            &features,
+            "--lib",
+            "--",
+            "--exact",
+            "tests::roundtrip_encode_decode",
+        ]);
+        report(&format!("ggwave-rs [{features}]"), ok, &mut failed);
+    }
+
+    // The pure-Rust backend needs std, the C++ ones don't.
+    if backend != "pure-rust" {
+        for features in [backend.to_string(), format!("{backend},heapless")] {
+            let ok = cargo(&[
+                "build",
+                "-p",
+                "ggwave-rs",
+                "--no-default-features",
+                "--features",
+                &features,
+            ]);
+            report(&format!("ggwave-rs [{features}, no_std]"), ok, &mut failed);
+        }
+    }
+
+    for feature in CLI_FEATURES {
+        let features = format!("ggwave-rs/{backend},{feature}");
+        let ok = cargo(&["build", "-p", "ggwave-cli", "--features", &features]);
+        report(&format!("ggwave-cli [{features}]"), ok, &mut failed);
+    }
+
+    if failed.is_empty() {
+        0
+    } else {
+        eprintln!("\nfailed combinations:");
+        for name in &failed {
+            eprintln!("  {name}");
+        }
+        1
+    }
+}
+
+fn report(name: &str, ok: bool, failed: &mut Vec<String>) {
+    println!("{} {name}", if ok { "ok  " } else { "FAIL" });
+    if !ok {
+        failed.push(name.to_string());
+    }
+}
+
+fn cargo(args: &[&str]) -> bool {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    Command::new(cargo)
+        .args(args)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}